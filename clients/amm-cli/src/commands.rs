@@ -0,0 +1,1108 @@
+use amm_client::{
+    batch_address, dca_address, decode_config, decode_farm, decode_lp_lock, decode_stake_position,
+    farm_address, instructions, limit_order_address, lp_lock_address, metadata_address,
+    min_amount_with_slippage, stake_position_address, trader_tier_address, twamm_order_address,
+    Batch, CircuitBreakerParams, Dca, DynamicFeeParams, LimitOrder, OracleGuard, OraclePrice,
+    PoolAddresses, PoolState, TraderTier, TwammOrder, BPS,
+};
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::AccountDeserialize;
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::associated_token::spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+use anchor_spl::token::spl_token::native_mint;
+use anchor_spl::token::{self, Mint, TokenAccount};
+use anyhow::{anyhow, bail, Context, Result};
+use solana_sdk::clock::Clock;
+use solana_sdk::signer::Signer;
+use solana_sdk::sysvar;
+use solana_sdk::transaction::Transaction;
+
+use crate::{PoolArgs, Runtime};
+
+fn send(rt: &Runtime, instructions: &[Instruction]) -> Result<()> {
+    let blockhash = rt.rpc.get_latest_blockhash()?;
+    let tx = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&rt.payer.pubkey()),
+        &[&rt.payer],
+        blockhash,
+    );
+    let signature = rt.rpc.send_and_confirm_transaction(&tx)?;
+    println!("Signature: {signature}");
+    Ok(())
+}
+
+fn fetch<T: AccountDeserialize>(rt: &Runtime, address: &Pubkey) -> Result<T> {
+    let data = rt
+        .rpc
+        .get_account_data(address)
+        .with_context(|| format!("failed to fetch account {address}"))?;
+    T::try_deserialize(&mut data.as_slice())
+        .map_err(|err| anyhow!("failed to decode account {address}: {err}"))
+}
+
+/// Current price of `state`'s oracle feed as raw y per raw x, for quoting
+/// PMM pools.
+fn oracle_price(rt: &Runtime, state: &PoolState) -> Result<Option<u128>> {
+    let Some(guard) = state.config.oracle.filter(|_| state.config.pmm.is_some()) else {
+        return Ok(None);
+    };
+    let feed = rt
+        .rpc
+        .get_account(&guard.feed)
+        .with_context(|| format!("no oracle feed at {}", guard.feed))?;
+    let price = OraclePrice::parse(guard.kind, &feed.owner, &feed.data)
+        .map_err(|err| anyhow!("invalid oracle feed: {err}"))?;
+    let mint_x: Mint = fetch(rt, &state.config.mint_x)?;
+    let mint_y: Mint = fetch(rt, &state.config.mint_y)?;
+    let price = price
+        .to_q64(mint_x.decimals, mint_y.decimals, guard.invert)
+        .map_err(|err| anyhow!("cannot price the oracle feed: {err}"))?;
+    Ok(Some(price))
+}
+
+fn pool_state(rt: &Runtime, pool: &PoolAddresses) -> Result<PoolState> {
+    let data = rt
+        .rpc
+        .get_account_data(&pool.config)
+        .with_context(|| format!("no pool at {}", pool.config))?;
+    let config = decode_config(&data).map_err(|err| anyhow!("invalid pool config: {err}"))?;
+
+    let vault_x: TokenAccount = fetch(rt, &pool.vault_x)?;
+    let vault_y: TokenAccount = fetch(rt, &pool.vault_y)?;
+    let mint_lp: Mint = fetch(rt, &pool.mint_lp)?;
+    let clock: Clock = solana_sdk::account::from_account(&rt.rpc.get_account(&sysvar::clock::ID)?)
+        .context("failed to decode the clock sysvar")?;
+
+    Ok(PoolState::from_vaults(
+        config,
+        vault_x.amount,
+        vault_y.amount,
+        mint_lp.supply,
+        clock.unix_timestamp,
+    )?)
+}
+
+/// Idempotent ATA creation for `mints`, skipping the native mint when SOL is
+/// wrapped on-chain.
+fn create_atas(rt: &Runtime, mints: &[Pubkey]) -> Vec<Instruction> {
+    let owner = rt.payer.pubkey();
+    mints
+        .iter()
+        .filter(|mint| !(rt.native_sol && **mint == native_mint::ID))
+        .map(|mint| create_associated_token_account_idempotent(&owner, &owner, mint, &token::ID))
+        .collect()
+}
+
+fn max_amount_with_slippage(amount: u64, slippage_bps: u64) -> u64 {
+    (amount as u128 * (BPS + slippage_bps) as u128)
+        .div_ceil(BPS as u128)
+        .min(u64::MAX as u128) as u64
+}
+
+pub fn create_pool(
+    rt: &Runtime,
+    args: &PoolArgs,
+    fee_bps: u16,
+    authority: Option<Pubkey>,
+    lp_decimals: Option<u8>,
+) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    send(
+        rt,
+        &[instructions::initialize(
+            &pool,
+            &rt.payer.pubkey(),
+            fee_bps,
+            authority,
+            lp_decimals,
+        )],
+    )?;
+    println!("Pool: {}", pool.config);
+    println!("LP mint: {}", pool.mint_lp);
+    Ok(())
+}
+
+pub fn create_lp_metadata(rt: &Runtime, args: &PoolArgs) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    send(
+        rt,
+        &[instructions::create_lp_metadata(&pool, &rt.payer.pubkey())],
+    )?;
+    println!("LP metadata: {}", metadata_address(&pool.mint_lp));
+    Ok(())
+}
+
+pub fn migrate_config(rt: &Runtime, args: &PoolArgs) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    send(
+        rt,
+        &[instructions::migrate_config(&pool, &rt.payer.pubkey())],
+    )?;
+    println!("Migrated config: {}", pool.config);
+    Ok(())
+}
+
+pub fn deposit(
+    rt: &Runtime,
+    args: &PoolArgs,
+    lp: u64,
+    max_x: Option<u64>,
+    max_y: Option<u64>,
+    slippage_bps: u64,
+) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+
+    let (max_x, max_y) = match (max_x, max_y) {
+        (Some(x), Some(y)) => (x, y),
+        _ => {
+            let state = pool_state(rt, &pool)?;
+            if state.lp_supply == 0 {
+                bail!("the first deposit sets the price, pass both --max-x and --max-y");
+            }
+            let quote = state
+                .quote_deposit(lp)
+                .map_err(|err| anyhow!("cannot quote deposit: {err}"))?;
+            (
+                max_x.unwrap_or(max_amount_with_slippage(quote.x, slippage_bps)),
+                max_y.unwrap_or(max_amount_with_slippage(quote.y, slippage_bps)),
+            )
+        }
+    };
+
+    let deposit = match rt.native_sol {
+        true => instructions::deposit_native,
+        false => instructions::deposit,
+    };
+    let mut ixs = create_atas(rt, &[pool.mint_x, pool.mint_y, pool.mint_lp]);
+    ixs.push(deposit(&pool, &rt.payer.pubkey(), lp, max_x, max_y));
+    send(rt, &ixs)
+}
+
+pub fn withdraw(
+    rt: &Runtime,
+    args: &PoolArgs,
+    lp: u64,
+    min_x: Option<u64>,
+    min_y: Option<u64>,
+    slippage_bps: u64,
+) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+
+    let (min_x, min_y) = match (min_x, min_y) {
+        (Some(x), Some(y)) => (x, y),
+        _ => {
+            let quote = pool_state(rt, &pool)?
+                .quote_withdraw(lp)
+                .map_err(|err| anyhow!("cannot quote withdraw: {err}"))?;
+            let slip = |amount| min_amount_with_slippage(amount, slippage_bps);
+            (
+                min_x.map_or_else(|| slip(quote.x), Ok)?,
+                min_y.map_or_else(|| slip(quote.y), Ok)?,
+            )
+        }
+    };
+
+    let withdraw = match rt.native_sol {
+        true => instructions::withdraw_native,
+        false => instructions::withdraw,
+    };
+    send(rt, &[withdraw(&pool, &rt.payer.pubkey(), min_x, min_y, lp)])
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn swap(
+    rt: &Runtime,
+    args: &PoolArgs,
+    amount_in: u64,
+    is_x_in: bool,
+    min_out: Option<u64>,
+    slippage_bps: u64,
+    source: Option<Pubkey>,
+    recipient: Option<Pubkey>,
+    tier: bool,
+    referral: Option<(Pubkey, u16)>,
+) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    let owner = rt.payer.pubkey();
+    let tier = match tier {
+        true => {
+            let (address, _) = trader_tier_address(&pool.config, &owner);
+            Some(fetch::<TraderTier>(rt, &address).context("no trader tier for the keypair")?)
+        }
+        false => None,
+    };
+
+    let min_out = match min_out {
+        Some(min_out) => min_out,
+        None => {
+            let mut state = pool_state(rt, &pool)?;
+            if let Some(price) = oracle_price(rt, &state)? {
+                state = state.with_oracle_price(price);
+            }
+            if let Some(tier) = &tier {
+                state = state.with_tier(tier);
+            }
+            let quote = state
+                .quote_swap(amount_in, is_x_in)
+                .map_err(|err| anyhow!("cannot quote swap: {err}"))?;
+            println!(
+                "Quoted out: {} (fee {}, impact {} bps)",
+                quote.amount_out, quote.fee, quote.price_impact_bps
+            );
+            min_amount_with_slippage(quote.amount_out, slippage_bps)
+                .map_err(|err| anyhow!("invalid slippage: {err}"))?
+        }
+    };
+
+    let (in_mint, out_mint) = match is_x_in {
+        true => (pool.mint_x, pool.mint_y),
+        false => (pool.mint_y, pool.mint_x),
+    };
+    if let Some(guard) = pool_state(rt, &pool)?.config.oracle {
+        if referral.is_some()
+            || tier.is_some()
+            || source.is_some()
+            || recipient.is_some()
+            || rt.native_sol
+        {
+            bail!("swaps on an oracle guarded pool go between the keypair's token accounts");
+        }
+        let mut ixs = create_atas(rt, &[out_mint]);
+        ixs.push(instructions::swap_with_oracle(
+            &pool,
+            &owner,
+            &guard.feed,
+            amount_in,
+            min_out,
+            is_x_in,
+        ));
+        return send(rt, &ixs);
+    }
+    if let Some((referrer, referral_bps)) = referral {
+        let mut ixs = create_atas(rt, &[out_mint]);
+        ixs.push(instructions::swap_with_referral(
+            &pool,
+            &owner,
+            &referrer,
+            referral_bps,
+            amount_in,
+            min_out,
+            is_x_in,
+        ));
+        return send(rt, &ixs);
+    }
+    if tier.is_some() {
+        let mut ixs = create_atas(rt, &[out_mint]);
+        ixs.push(instructions::swap_with_tier(
+            &pool, &owner, amount_in, min_out, is_x_in,
+        ));
+        return send(rt, &ixs);
+    }
+    if source.is_none() && recipient.is_none() {
+        let swap = match rt.native_sol {
+            true => instructions::swap_native,
+            false => instructions::swap,
+        };
+        let mut ixs = create_atas(rt, &[out_mint]);
+        ixs.push(swap(&pool, &owner, amount_in, min_out, is_x_in));
+        return send(rt, &ixs);
+    }
+
+    // explicit token accounts are always used as given
+    let source = source.unwrap_or_else(|| get_associated_token_address(&owner, &in_mint));
+    let mut ixs = Vec::new();
+    let recipient = match recipient {
+        Some(recipient) => recipient,
+        None => {
+            ixs.push(create_associated_token_account_idempotent(
+                &owner,
+                &owner,
+                &out_mint,
+                &token::ID,
+            ));
+            get_associated_token_address(&owner, &out_mint)
+        }
+    };
+    ixs.push(instructions::swap_with_accounts(
+        &pool, &owner, &source, &recipient, amount_in, min_out, is_x_in,
+    ));
+    send(rt, &ixs)
+}
+
+pub fn show(rt: &Runtime, args: &PoolArgs) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    let state = pool_state(rt, &pool)?;
+    let mint_x: Mint = fetch(rt, &pool.mint_x)?;
+    let mint_y: Mint = fetch(rt, &pool.mint_y)?;
+
+    println!("Pool:       {}", pool.config);
+    println!("Mint X:     {}", pool.mint_x);
+    println!("Mint Y:     {}", pool.mint_y);
+    println!("LP mint:    {}", pool.mint_lp);
+    println!("Reserve X:  {}", state.reserve_x);
+    println!("Reserve Y:  {}", state.reserve_y);
+    println!("LP supply:  {}", state.lp_supply);
+    if state.reserve_x > 0 {
+        let price = (state.reserve_y as f64 / 10f64.powi(mint_y.decimals as i32))
+            / (state.reserve_x as f64 / 10f64.powi(mint_x.decimals as i32));
+        println!("Price:      {price} Y per X");
+    }
+    match state.config.fees_y_in {
+        Some(fees_y_in) => println!(
+            "Fee:        {} bps X to Y, {fees_y_in} bps Y to X",
+            state.config.fees
+        ),
+        None => println!("Fee:        {} bps", state.config.fees),
+    }
+    if let Some(dynamic_fee) = &state.config.dynamic_fee {
+        let params = &dynamic_fee.params;
+        println!(
+            "Dynamic fee: {} bps now X to Y, up to {} bps",
+            dynamic_fee.fee(state.config.fees),
+            params.max_fee
+        );
+        println!(
+            "  filter {}s, decay {}s, reduction {} bps, control {}, max volatility {} bps",
+            params.filter_period,
+            params.decay_period,
+            params.reduction_factor,
+            params.variable_fee_control,
+            params.max_volatility_accumulator
+        );
+    }
+    if let Some(circuit_breaker) = &state.config.circuit_breaker {
+        println!(
+            "Price band: {} bps per trade, {} bps per slot",
+            circuit_breaker.params.max_swap_move_bps, circuit_breaker.params.max_slot_move_bps
+        );
+    }
+    if let Some(pmm) = &state.config.pmm {
+        println!(
+            "Curve:      PMM, k {} bps, targets {} X / {} Y",
+            pmm.k, pmm.target_x, pmm.target_y
+        );
+    }
+    if let Some(guard) = &state.config.oracle {
+        println!(
+            "Oracle:     {:?} feed {}{}, within {} bps, at most {}s old",
+            guard.kind,
+            guard.feed,
+            if guard.invert { " (inverted)" } else { "" },
+            guard.max_deviation_bps,
+            guard.max_staleness
+        );
+    }
+    if let Some(window) = state.config.batch_window {
+        println!("Batches:    every {window}s");
+    }
+    if state.config.maker_fee_bps != state.config.fees {
+        println!("Maker fee:  {} bps", state.config.maker_fee(true));
+    }
+    if state.config.max_referral_bps > 0 {
+        println!(
+            "Referrals:  up to {} bps of the fee",
+            state.config.max_referral_bps
+        );
+    }
+    match state.config.authority {
+        Some(authority) => println!("Authority:  {authority}"),
+        None => println!("Authority:  none"),
+    }
+    Ok(())
+}
+
+/// Changes the given fields and keeps the rest of the config as it is.
+pub fn update_config(
+    rt: &Runtime,
+    args: &PoolArgs,
+    fee_bps: Option<u16>,
+    authority: Option<Option<Pubkey>>,
+) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    let config = pool_state(rt, &pool)?.config;
+
+    send(
+        rt,
+        &[instructions::update_config(
+            &pool,
+            &rt.payer.pubkey(),
+            fee_bps.unwrap_or(config.fees),
+            authority.unwrap_or(config.authority),
+        )],
+    )
+}
+
+pub fn set_dynamic_fee(
+    rt: &Runtime,
+    args: &PoolArgs,
+    params: Option<DynamicFeeParams>,
+) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    send(
+        rt,
+        &[instructions::set_dynamic_fee(
+            &pool,
+            &rt.payer.pubkey(),
+            params,
+        )],
+    )
+}
+
+pub fn set_circuit_breaker(
+    rt: &Runtime,
+    args: &PoolArgs,
+    params: Option<CircuitBreakerParams>,
+) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    send(
+        rt,
+        &[instructions::set_circuit_breaker(
+            &pool,
+            &rt.payer.pubkey(),
+            params,
+        )],
+    )
+}
+
+pub fn set_oracle(rt: &Runtime, args: &PoolArgs, guard: Option<OracleGuard>) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    send(
+        rt,
+        &[instructions::set_oracle(&pool, &rt.payer.pubkey(), guard)],
+    )
+}
+
+pub fn set_pmm(rt: &Runtime, args: &PoolArgs, k: Option<u16>) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    send(rt, &[instructions::set_pmm(&pool, &rt.payer.pubkey(), k)])
+}
+
+pub fn set_directional_fees(rt: &Runtime, args: &PoolArgs, fees_y_in: Option<u16>) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    send(
+        rt,
+        &[instructions::set_directional_fees(
+            &pool,
+            &rt.payer.pubkey(),
+            fees_y_in,
+        )],
+    )
+}
+
+pub fn set_trader_tier(rt: &Runtime, args: &PoolArgs, trader: &Pubkey, fee_bps: u16) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    send(
+        rt,
+        &[instructions::set_trader_tier(
+            &pool,
+            &rt.payer.pubkey(),
+            trader,
+            fee_bps,
+        )],
+    )
+}
+
+pub fn remove_trader_tier(rt: &Runtime, args: &PoolArgs, trader: &Pubkey) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    send(
+        rt,
+        &[instructions::remove_trader_tier(
+            &pool,
+            &rt.payer.pubkey(),
+            trader,
+        )],
+    )
+}
+
+pub fn set_max_referral(rt: &Runtime, args: &PoolArgs, max_referral_bps: u16) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    send(
+        rt,
+        &[instructions::set_max_referral(
+            &pool,
+            &rt.payer.pubkey(),
+            max_referral_bps,
+        )],
+    )
+}
+
+pub fn set_maker_fee(rt: &Runtime, args: &PoolArgs, maker_fee_bps: u16) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    send(
+        rt,
+        &[instructions::set_maker_fee(
+            &pool,
+            &rt.payer.pubkey(),
+            maker_fee_bps,
+        )],
+    )
+}
+
+pub fn set_batch_window(rt: &Runtime, args: &PoolArgs, batch_window: Option<i64>) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    send(
+        rt,
+        &[instructions::set_batch_window(
+            &pool,
+            &rt.payer.pubkey(),
+            batch_window,
+        )],
+    )
+}
+
+pub fn create_farm(rt: &Runtime, args: &PoolArgs) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    send(
+        rt,
+        &[instructions::initialize_farm(&pool, &rt.payer.pubkey())],
+    )?;
+    println!("Farm: {}", farm_address(&pool.config).0);
+    Ok(())
+}
+
+pub fn set_farm_reward(
+    rt: &Runtime,
+    args: &PoolArgs,
+    reward_mint: &Pubkey,
+    index: u8,
+    rate_per_second: u64,
+    start: i64,
+    end: i64,
+) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    send(
+        rt,
+        &[instructions::set_farm_reward(
+            &pool,
+            &rt.payer.pubkey(),
+            reward_mint,
+            index,
+            rate_per_second,
+            start,
+            end,
+        )],
+    )
+}
+
+/// Tops up a reward from the keypair's ATA of `reward_mint`.
+pub fn fund_farm_reward(
+    rt: &Runtime,
+    args: &PoolArgs,
+    reward_mint: &Pubkey,
+    amount: u64,
+) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    let owner = rt.payer.pubkey();
+    send(
+        rt,
+        &[instructions::fund_farm_reward(
+            &pool,
+            &owner,
+            reward_mint,
+            &get_associated_token_address(&owner, reward_mint),
+            amount,
+        )],
+    )
+}
+
+pub fn stake(rt: &Runtime, args: &PoolArgs, amount: u64) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    send(
+        rt,
+        &[instructions::stake(&pool, &rt.payer.pubkey(), amount)],
+    )
+}
+
+pub fn unstake(rt: &Runtime, args: &PoolArgs, amount: u64) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    send(
+        rt,
+        &[instructions::unstake(&pool, &rt.payer.pubkey(), amount)],
+    )
+}
+
+/// Claims every reward of the farm into the keypair's ATAs.
+pub fn claim_farm_rewards(rt: &Runtime, args: &PoolArgs) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    let (farm_address, _) = farm_address(&pool.config);
+    let data = rt
+        .rpc
+        .get_account_data(&farm_address)
+        .with_context(|| format!("no farm for pool {}", pool.config))?;
+    let farm = decode_farm(&data).map_err(|err| anyhow!("invalid farm: {err}"))?;
+
+    let owner = rt.payer.pubkey();
+    let mints: Vec<Pubkey> = farm
+        .rewards
+        .iter()
+        .filter(|reward| reward.is_active())
+        .map(|reward| reward.mint)
+        .collect();
+    let mut ixs = create_atas(rt, &mints);
+    for mint in &mints {
+        ixs.push(instructions::claim_farm_reward(
+            &pool,
+            &owner,
+            mint,
+            &get_associated_token_address(&owner, mint),
+        ));
+    }
+    send(rt, &ixs)
+}
+
+pub fn show_farm(rt: &Runtime, args: &PoolArgs) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    let (farm_address, _) = farm_address(&pool.config);
+    let data = rt
+        .rpc
+        .get_account_data(&farm_address)
+        .with_context(|| format!("no farm for pool {}", pool.config))?;
+    let farm = decode_farm(&data).map_err(|err| anyhow!("invalid farm: {err}"))?;
+
+    println!("Farm:       {farm_address}");
+    println!("Staked LP:  {}", farm.total_staked);
+    for (index, reward) in farm.rewards.iter().enumerate() {
+        if reward.is_active() {
+            println!(
+                "Reward {index}:   {} at {}/s from {} to {}",
+                reward.mint, reward.rate_per_second, reward.start, reward.end
+            );
+        }
+    }
+
+    let (position, _) = stake_position_address(&farm_address, &rt.payer.pubkey());
+    if let Ok(data) = rt.rpc.get_account_data(&position) {
+        let position =
+            decode_stake_position(&data).map_err(|err| anyhow!("invalid position: {err}"))?;
+        println!("Your stake: {}", position.amount);
+    }
+    Ok(())
+}
+
+pub fn lock_lp(
+    rt: &Runtime,
+    args: &PoolArgs,
+    lock_id: u64,
+    amount: u64,
+    unlock_at: i64,
+    vesting_end: Option<i64>,
+) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    let owner = rt.payer.pubkey();
+    send(
+        rt,
+        &[instructions::lock_lp(
+            &pool,
+            &owner,
+            lock_id,
+            amount,
+            unlock_at,
+            vesting_end,
+        )],
+    )?;
+    println!("Lock: {}", lp_lock_address(&pool.config, &owner, lock_id).0);
+    Ok(())
+}
+
+pub fn unlock_lp(rt: &Runtime, args: &PoolArgs, lock_id: u64) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    send(
+        rt,
+        &[instructions::unlock_lp(&pool, &rt.payer.pubkey(), lock_id)],
+    )
+}
+
+pub fn claim_locked_fees(rt: &Runtime, args: &PoolArgs, lock_id: u64) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    send(
+        rt,
+        &[instructions::claim_locked_fees(
+            &pool,
+            &rt.payer.pubkey(),
+            lock_id,
+        )],
+    )
+}
+
+/// Prints a lock, whoever owns it, so its terms can be checked.
+pub fn show_lock(rt: &Runtime, lock: &Pubkey) -> Result<()> {
+    let data = rt
+        .rpc
+        .get_account_data(lock)
+        .with_context(|| format!("no lock at {lock}"))?;
+    let lock_state = decode_lp_lock(&data).map_err(|err| anyhow!("invalid lock: {err}"))?;
+    let escrow: TokenAccount = fetch(
+        rt,
+        &get_associated_token_address(lock, &amm_client::lp_mint_address(&lock_state.config).0),
+    )?;
+
+    println!("Lock:       {lock}");
+    println!("Pool:       {}", lock_state.config);
+    println!("Owner:      {}", lock_state.owner);
+    println!("Locked LP:  {}", lock_state.amount);
+    println!("Withdrawn:  {}", lock_state.withdrawn);
+    println!("In escrow:  {}", escrow.amount);
+    println!("Unlocks at: {}", lock_state.unlock_at);
+    if lock_state.vesting_end > lock_state.unlock_at {
+        println!("Vested by:  {}", lock_state.vesting_end);
+    }
+    Ok(())
+}
+
+pub fn place_limit_order(
+    rt: &Runtime,
+    args: &PoolArgs,
+    order_id: u64,
+    is_x_in: bool,
+    amount_in: u64,
+    min_out: u64,
+) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    let owner = rt.payer.pubkey();
+    send(
+        rt,
+        &[instructions::place_limit_order(
+            &pool, &owner, order_id, is_x_in, amount_in, min_out,
+        )],
+    )?;
+    println!(
+        "Order: {}",
+        limit_order_address(&pool.config, &owner, order_id).0
+    );
+    Ok(())
+}
+
+pub fn cancel_limit_order(rt: &Runtime, args: &PoolArgs, order_id: u64) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    let owner = rt.payer.pubkey();
+    let order: LimitOrder = fetch(rt, &limit_order_address(&pool.config, &owner, order_id).0)?;
+    send(
+        rt,
+        &[instructions::cancel_limit_order(
+            &pool,
+            &owner,
+            order_id,
+            order.is_x_in,
+        )],
+    )
+}
+
+pub fn fill_limit_order(rt: &Runtime, args: &PoolArgs, order: &Pubkey) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    let order: LimitOrder = fetch(rt, order)?;
+    if order.config != pool.config {
+        bail!("the order rests on another pool");
+    }
+    let cranker = rt.payer.pubkey();
+    let ix = match pool_state(rt, &pool)?.config.oracle {
+        Some(guard) => {
+            instructions::fill_limit_order_with_oracle(&pool, &cranker, &order, &guard.feed)
+        }
+        None => instructions::fill_limit_order(&pool, &cranker, &order),
+    };
+    let mint_in = match order.is_x_in {
+        true => pool.mint_x,
+        false => pool.mint_y,
+    };
+    // the final fill returns anything else in the escrow to the owner
+    send(
+        rt,
+        &[
+            create_associated_token_account_idempotent(
+                &cranker,
+                &order.owner,
+                &mint_in,
+                &token::ID,
+            ),
+            ix,
+        ],
+    )
+}
+
+/// Prints an order, whoever placed it.
+pub fn show_limit_order(rt: &Runtime, address: &Pubkey) -> Result<()> {
+    let order: LimitOrder = fetch(rt, address)?;
+    let (sells, buys) = match order.is_x_in {
+        true => ("X", "Y"),
+        false => ("Y", "X"),
+    };
+
+    println!("Order:      {address}");
+    println!("Pool:       {}", order.config);
+    println!("Owner:      {}", order.owner);
+    println!(
+        "Sells:      {} {sells} for at least {} {buys}",
+        order.amount_in, order.min_out
+    );
+    println!("Unfilled:   {} {sells}", order.remaining);
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn open_dca(
+    rt: &Runtime,
+    args: &PoolArgs,
+    dca_id: u64,
+    is_x_in: bool,
+    total: u64,
+    amount_per_cycle: u64,
+    interval: i64,
+    min_out_per_cycle: u64,
+    tip_bps: u16,
+) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    let owner = rt.payer.pubkey();
+    send(
+        rt,
+        &[instructions::open_dca(
+            &pool,
+            &owner,
+            dca_id,
+            is_x_in,
+            total,
+            amount_per_cycle,
+            interval,
+            min_out_per_cycle,
+            tip_bps,
+        )],
+    )?;
+    println!("DCA: {}", dca_address(&pool.config, &owner, dca_id).0);
+    Ok(())
+}
+
+pub fn close_dca(rt: &Runtime, args: &PoolArgs, dca_id: u64) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    let owner = rt.payer.pubkey();
+    let dca: Dca = fetch(rt, &dca_address(&pool.config, &owner, dca_id).0)?;
+    send(
+        rt,
+        &[instructions::close_dca(&pool, &owner, dca_id, dca.is_x_in)],
+    )
+}
+
+pub fn execute_dca(rt: &Runtime, args: &PoolArgs, dca: &Pubkey) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    let dca: Dca = fetch(rt, dca)?;
+    if dca.config != pool.config {
+        bail!("the DCA sells into another pool");
+    }
+    let keeper = rt.payer.pubkey();
+    let mint_in = match dca.is_x_in {
+        true => pool.mint_x,
+        false => pool.mint_y,
+    };
+    let ix = match pool_state(rt, &pool)?.config.oracle {
+        Some(guard) => instructions::execute_dca_with_oracle(&pool, &keeper, &dca, &guard.feed),
+        None => instructions::execute_dca(&pool, &keeper, &dca),
+    };
+    send(
+        rt,
+        &[
+            create_associated_token_account_idempotent(&keeper, &keeper, &mint_in, &token::ID),
+            // the last slice returns anything else in the escrow to the owner
+            create_associated_token_account_idempotent(&keeper, &dca.owner, &mint_in, &token::ID),
+            ix,
+        ],
+    )
+}
+
+/// Prints a DCA, whoever opened it.
+pub fn show_dca(rt: &Runtime, address: &Pubkey) -> Result<()> {
+    let dca: Dca = fetch(rt, address)?;
+    let (sells, buys) = match dca.is_x_in {
+        true => ("X", "Y"),
+        false => ("Y", "X"),
+    };
+
+    println!("DCA:        {address}");
+    println!("Pool:       {}", dca.config);
+    println!("Owner:      {}", dca.owner);
+    println!(
+        "Sells:      {} {sells} every {}s for at least {} {buys}",
+        dca.amount_per_cycle, dca.interval, dca.min_out_per_cycle
+    );
+    println!("Unsold:     {} {sells}", dca.remaining);
+    println!("Next at:    {}", dca.next_at);
+    println!("Keeper tip: {} bps", dca.tip_bps);
+    Ok(())
+}
+
+pub fn place_twamm_order(
+    rt: &Runtime,
+    args: &PoolArgs,
+    order_id: u64,
+    is_x_in: bool,
+    amount: u64,
+    intervals: u32,
+) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    let owner = rt.payer.pubkey();
+    send(
+        rt,
+        &[instructions::place_twamm_order(
+            &pool, &owner, order_id, is_x_in, amount, intervals,
+        )],
+    )?;
+    let address = twamm_order_address(&pool.config, &owner, order_id).0;
+    let order: TwammOrder = fetch(rt, &address)?;
+    println!("Long-term order: {address}");
+    println!("Ends at:         {}", order.end);
+    Ok(())
+}
+
+/// End of `owner`'s long-term order `order_id` if its expiry has been
+/// archived, i.e. no slot of the pool holds it any more.
+fn twamm_archived_end(rt: &Runtime, pool: &PoolAddresses, order_id: u64) -> Result<Option<i64>> {
+    let address = twamm_order_address(&pool.config, &rt.payer.pubkey(), order_id).0;
+    let order: TwammOrder = fetch(rt, &address)?;
+    let state = pool_state(rt, pool)?;
+    let in_slot = state
+        .config
+        .twamm
+        .expiries
+        .iter()
+        .any(|expiry| expiry.orders > 0 && expiry.at == order.end);
+    Ok((!in_slot).then_some(order.end))
+}
+
+pub fn withdraw_twamm_proceeds(rt: &Runtime, args: &PoolArgs, order_id: u64) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    let archived_end = twamm_archived_end(rt, &pool, order_id)?;
+    send(
+        rt,
+        &[instructions::withdraw_twamm_proceeds(
+            &pool,
+            &rt.payer.pubkey(),
+            order_id,
+            archived_end,
+        )],
+    )
+}
+
+pub fn close_twamm_order(rt: &Runtime, args: &PoolArgs, order_id: u64) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    let archived_end = twamm_archived_end(rt, &pool, order_id)?;
+    send(
+        rt,
+        &[instructions::close_twamm_order(
+            &pool,
+            &rt.payer.pubkey(),
+            order_id,
+            archived_end,
+        )],
+    )
+}
+
+pub fn execute_twamm(rt: &Runtime, args: &PoolArgs) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    send(rt, &[instructions::execute_twamm(&pool)])
+}
+
+pub fn archive_twamm_expiry(rt: &Runtime, args: &PoolArgs, at: i64) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    send(
+        rt,
+        &[instructions::archive_twamm_expiry(
+            &pool,
+            &rt.payer.pubkey(),
+            at,
+        )],
+    )
+}
+
+/// Prints a long-term order, whoever placed it.
+pub fn show_twamm_order(rt: &Runtime, address: &Pubkey) -> Result<()> {
+    let order: TwammOrder = fetch(rt, address)?;
+    let sells = match order.is_x_in {
+        true => "X",
+        false => "Y",
+    };
+
+    println!("Long-term order: {address}");
+    println!("Pool:            {}", order.config);
+    println!("Owner:           {}", order.owner);
+    println!("Sells:           {} {sells}", order.amount);
+    println!("From:            {}", order.start);
+    println!("Until:           {}", order.end);
+    Ok(())
+}
+
+pub fn submit_swap_intent(
+    rt: &Runtime,
+    args: &PoolArgs,
+    is_x_in: bool,
+    amount_in: u64,
+    min_out: u64,
+) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    let state = pool_state(rt, &pool)?;
+    let Some(window) = state.config.batch_window else {
+        bail!("the pool does not run batch auctions");
+    };
+    let batch_id = (state.unix_timestamp / window) as u64;
+    send(
+        rt,
+        &[instructions::submit_swap_intent(
+            &pool,
+            &rt.payer.pubkey(),
+            batch_id,
+            amount_in,
+            min_out,
+            is_x_in,
+        )],
+    )?;
+    println!("Batch:     {batch_id}");
+    println!("Closes at: {}", (batch_id as i64 + 1) * window);
+    Ok(())
+}
+
+pub fn settle_batch(rt: &Runtime, args: &PoolArgs, batch_id: u64) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    let ix = match pool_state(rt, &pool)?.config.oracle {
+        Some(guard) => instructions::settle_batch_with_oracle(&pool, batch_id, &guard.feed),
+        None => instructions::settle_batch(&pool, batch_id),
+    };
+    send(rt, &[ix])
+}
+
+pub fn claim_swap_intents(rt: &Runtime, args: &PoolArgs, batch_id: u64) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    let batch: Batch = fetch(rt, &batch_address(&pool.config, batch_id).0)?;
+    send(
+        rt,
+        &[instructions::claim_swap_intents(
+            &pool,
+            &rt.payer.pubkey(),
+            &batch,
+        )],
+    )
+}
+
+pub fn show_batch(rt: &Runtime, args: &PoolArgs, batch_id: u64) -> Result<()> {
+    let pool = PoolAddresses::new(args.mint_x, args.mint_y);
+    let address = batch_address(&pool.config, batch_id).0;
+    let batch: Batch = fetch(rt, &address)?;
+
+    println!("Batch:     {address}");
+    println!("Closes at: {}", batch.closes_at);
+    println!("Settled:   {}", batch.settled);
+    for intent in batch.intents() {
+        let (sells, buys) = match intent.is_x_in {
+            true => ("X", "Y"),
+            false => ("Y", "X"),
+        };
+        print!(
+            "  {} sells {} {sells} for at least {} {buys}",
+            intent.owner, intent.amount_in, intent.min_out
+        );
+        match (batch.settled, intent.amount_out) {
+            (false, _) => println!(),
+            (true, 0) if intent.fee > 0 => println!(", refunded less a {} fee", intent.fee),
+            (true, 0) => println!(", refunded"),
+            (true, out) => println!(", got {out} {buys}"),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+const DEFAULT_URL: &str = "http://127.0.0.1:8899";
+
+/// The fields we need from a Solana CLI `config.yml`.
+#[derive(Default, Deserialize)]
+pub struct CliConfig {
+    pub json_rpc_url: Option<String>,
+    pub keypair_path: Option<String>,
+}
+
+fn solana_dir() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_default()
+        .join(".config")
+        .join("solana")
+}
+
+impl CliConfig {
+    /// Loads `path`, or the default Solana CLI config if it exists.
+    pub fn load(path: Option<&str>) -> Result<Self> {
+        let (path, required) = match path {
+            Some(path) => (PathBuf::from(path), true),
+            None => (solana_dir().join("cli").join("config.yml"), false),
+        };
+
+        if !required && !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let file = std::fs::File::open(&path)
+            .with_context(|| format!("failed to open config {}", path.display()))?;
+        serde_yaml::from_reader(file)
+            .with_context(|| format!("failed to parse config {}", path.display()))
+    }
+
+    /// RPC URL from the config, falling back to a local test validator.
+    pub fn url(&self) -> String {
+        self.json_rpc_url
+            .clone()
+            .unwrap_or_else(|| DEFAULT_URL.to_string())
+    }
+
+    /// Keypair path from the config, falling back to `~/.config/solana/id.json`.
+    pub fn keypair_path(&self) -> String {
+        self.keypair_path
+            .clone()
+            .unwrap_or_else(|| solana_dir().join("id.json").to_string_lossy().into_owned())
+    }
+}
@@ -0,0 +1,897 @@
+mod commands;
+mod config;
+
+use amm_client::{CircuitBreakerParams, DynamicFeeParams, OracleGuard, OracleKind};
+use anchor_lang::prelude::Pubkey;
+use anyhow::Result;
+use clap::{Args, Parser, Subcommand};
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::{read_keypair_file, Keypair};
+use solana_sdk::signer::Signer;
+
+#[derive(Parser)]
+#[command(name = "amm-cli", about = "Operate amm-contract pools")]
+struct Cli {
+    /// Solana CLI config file to read the RPC URL and keypair from
+    #[arg(short = 'C', long, global = true)]
+    config: Option<String>,
+
+    /// RPC URL, overrides the config file
+    #[arg(short = 'u', long, global = true)]
+    url: Option<String>,
+
+    /// Keypair file, overrides the config file
+    #[arg(short = 'k', long, global = true)]
+    keypair: Option<String>,
+
+    /// Use the keypair's WSOL account for SOL pools instead of paying and
+    /// receiving lamports
+    #[arg(long, global = true)]
+    wsol: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Args)]
+pub struct PoolArgs {
+    /// Mint of the pool's x token
+    #[arg(long)]
+    pub mint_x: Pubkey,
+
+    /// Mint of the pool's y token
+    #[arg(long)]
+    pub mint_y: Pubkey,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create a pool for a mint pair
+    CreatePool {
+        #[command(flatten)]
+        pool: PoolArgs,
+        /// Swap fee in basis points
+        #[arg(long)]
+        fee_bps: u16,
+        /// Pool authority, defaults to the keypair
+        #[arg(long, conflicts_with = "no_authority")]
+        authority: Option<Pubkey>,
+        /// Create the pool without an authority
+        #[arg(long)]
+        no_authority: bool,
+        /// LP mint decimals, at most 18, defaults to the mean of the pair's
+        #[arg(long)]
+        lp_decimals: Option<u8>,
+    },
+    /// Create token metadata for a pool's LP mint, named after the pair
+    CreateLpMetadata {
+        #[command(flatten)]
+        pool: PoolArgs,
+    },
+    /// Bring a pool created by an older program up to the current config
+    /// layout
+    MigrateConfig {
+        #[command(flatten)]
+        pool: PoolArgs,
+    },
+    /// Deposit x and y for LP tokens
+    Deposit {
+        #[command(flatten)]
+        pool: PoolArgs,
+        /// LP tokens to mint
+        #[arg(long)]
+        lp: u64,
+        /// Most x to pay, quoted from the pool when omitted
+        #[arg(long)]
+        max_x: Option<u64>,
+        /// Most y to pay, quoted from the pool when omitted
+        #[arg(long)]
+        max_y: Option<u64>,
+        #[arg(long, default_value_t = 50)]
+        slippage_bps: u64,
+    },
+    /// Burn LP tokens for x and y
+    Withdraw {
+        #[command(flatten)]
+        pool: PoolArgs,
+        /// LP tokens to burn
+        #[arg(long)]
+        lp: u64,
+        /// Least x to receive, quoted from the pool when omitted
+        #[arg(long)]
+        min_x: Option<u64>,
+        /// Least y to receive, quoted from the pool when omitted
+        #[arg(long)]
+        min_y: Option<u64>,
+        #[arg(long, default_value_t = 50)]
+        slippage_bps: u64,
+    },
+    /// Swap x for y, or y for x with --y-in
+    Swap {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[arg(long)]
+        amount_in: u64,
+        /// Pay y instead of x
+        #[arg(long)]
+        y_in: bool,
+        /// Least output to receive, quoted from the pool when omitted
+        #[arg(long)]
+        min_out: Option<u64>,
+        #[arg(long, default_value_t = 50)]
+        slippage_bps: u64,
+        /// Token account to pay from, defaults to the keypair's ATA
+        #[arg(long)]
+        source: Option<Pubkey>,
+        /// Token account to pay out to, defaults to the keypair's ATA
+        #[arg(long)]
+        recipient: Option<Pubkey>,
+        /// Swap at the keypair's trader tier fee, between its ATAs
+        #[arg(long, conflicts_with_all = ["source", "recipient"])]
+        tier: bool,
+        /// Input-token account paid a share of the fee, between the
+        /// keypair's ATAs
+        #[arg(long, requires = "referral_bps", conflicts_with_all = ["source", "recipient", "tier"])]
+        referrer: Option<Pubkey>,
+        /// Share of the fee paid to --referrer, in basis points of the fee
+        #[arg(long, requires = "referrer")]
+        referral_bps: Option<u16>,
+    },
+    /// Print reserves, price, LP supply, fee and authority
+    Show {
+        #[command(flatten)]
+        pool: PoolArgs,
+    },
+    /// Authority-only pool operations
+    #[command(subcommand)]
+    Admin(AdminCommand),
+    /// Stake LP for liquidity-mining rewards
+    #[command(subcommand)]
+    Farm(FarmCommand),
+    /// Time-lock LP tokens
+    #[command(subcommand)]
+    Lock(LockCommand),
+    /// Rest limit orders filled against the pool
+    #[command(subcommand)]
+    Order(OrderCommand),
+    /// Sell into the pool on a schedule
+    #[command(subcommand)]
+    Dca(DcaCommand),
+    /// Sell into the pool evenly over time
+    #[command(subcommand)]
+    Twamm(TwammCommand),
+    /// Swap through the pool's batch auctions
+    #[command(subcommand)]
+    Batch(BatchCommand),
+}
+
+#[derive(Subcommand)]
+enum BatchCommand {
+    /// Submit a swap of x (or y with --y-in) to the current batch
+    Submit {
+        #[command(flatten)]
+        pool: PoolArgs,
+        /// Sell y instead of x
+        #[arg(long)]
+        y_in: bool,
+        #[arg(long)]
+        amount_in: u64,
+        /// Least output; the intent is refunded less the fee if the clearing
+        /// price misses it
+        #[arg(long, default_value_t = 0)]
+        min_out: u64,
+    },
+    /// Settle a closed batch at its clearing price
+    Settle {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[arg(long)]
+        batch_id: u64,
+    },
+    /// Claim the outputs and refunds of the keypair's intents in a batch
+    Claim {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[arg(long)]
+        batch_id: u64,
+    },
+    /// Print a batch's intents
+    Show {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[arg(long)]
+        batch_id: u64,
+    },
+}
+
+#[derive(Subcommand)]
+enum DcaCommand {
+    /// Escrow x (or y with --y-in) to sell a slice per interval
+    Open {
+        #[command(flatten)]
+        pool: PoolArgs,
+        /// Number telling the keypair's DCAs on this pool apart
+        #[arg(long, default_value_t = 0)]
+        dca_id: u64,
+        /// Sell y instead of x
+        #[arg(long)]
+        y_in: bool,
+        /// Everything to sell
+        #[arg(long)]
+        total: u64,
+        /// Sold per interval
+        #[arg(long)]
+        amount_per_cycle: u64,
+        /// Seconds between slices
+        #[arg(long)]
+        interval: i64,
+        /// Least output per full slice, setting the price bound
+        #[arg(long, default_value_t = 0)]
+        min_out_per_cycle: u64,
+        /// Keeper tip in basis points of each slice
+        #[arg(long, default_value_t = 10)]
+        tip_bps: u16,
+    },
+    /// Stop a DCA and refund what is unsold
+    Close {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[arg(long, default_value_t = 0)]
+        dca_id: u64,
+    },
+    /// Sell a DCA's due slice, collecting the keeper tip
+    Execute {
+        #[command(flatten)]
+        pool: PoolArgs,
+        /// DCA account address
+        #[arg(long)]
+        dca: Pubkey,
+    },
+    /// Print a DCA's schedule and progress
+    Show {
+        /// DCA account address
+        dca: Pubkey,
+    },
+}
+
+#[derive(Subcommand)]
+enum TwammCommand {
+    /// Sell x (or y with --y-in) evenly until a future interval boundary
+    Place {
+        #[command(flatten)]
+        pool: PoolArgs,
+        /// Number telling the keypair's long-term orders on this pool apart
+        #[arg(long, default_value_t = 0)]
+        order_id: u64,
+        /// Sell y instead of x
+        #[arg(long)]
+        y_in: bool,
+        #[arg(long)]
+        amount: u64,
+        /// Hourly interval boundaries until the order ends
+        #[arg(long)]
+        intervals: u32,
+    },
+    /// Withdraw what a long-term order has bought so far
+    Withdraw {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[arg(long, default_value_t = 0)]
+        order_id: u64,
+    },
+    /// Close a long-term order, cancelling it if still running
+    Close {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[arg(long, default_value_t = 0)]
+        order_id: u64,
+    },
+    /// Execute a pool's long-term orders up to now
+    Execute {
+        #[command(flatten)]
+        pool: PoolArgs,
+    },
+    /// Free the slot of orders that have ended, once all slots are taken
+    Archive {
+        #[command(flatten)]
+        pool: PoolArgs,
+        /// End time of the orders, as shown by `twamm show`
+        #[arg(long)]
+        at: i64,
+    },
+    /// Print a long-term order's schedule
+    Show {
+        /// Long-term order account address
+        order: Pubkey,
+    },
+}
+
+#[derive(Subcommand)]
+enum OrderCommand {
+    /// Escrow x (or y with --y-in) to sell at a limit price
+    Place {
+        #[command(flatten)]
+        pool: PoolArgs,
+        /// Number telling the keypair's orders on this pool apart
+        #[arg(long, default_value_t = 0)]
+        order_id: u64,
+        /// Sell y instead of x
+        #[arg(long)]
+        y_in: bool,
+        #[arg(long)]
+        amount_in: u64,
+        /// Least output for all of --amount-in, setting the limit price
+        #[arg(long)]
+        min_out: u64,
+    },
+    /// Refund the unfilled part of an order and close it
+    Cancel {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[arg(long, default_value_t = 0)]
+        order_id: u64,
+    },
+    /// Fill any order whose limit price the pool has reached
+    Fill {
+        #[command(flatten)]
+        pool: PoolArgs,
+        /// Order account address
+        #[arg(long)]
+        order: Pubkey,
+    },
+    /// Print an order's terms and fill progress
+    Show {
+        /// Order account address
+        order: Pubkey,
+    },
+}
+
+#[derive(Subcommand)]
+enum LockCommand {
+    /// Escrow LP until a timestamp, optionally vesting linearly after it
+    Create {
+        #[command(flatten)]
+        pool: PoolArgs,
+        /// Number telling the keypair's locks on this pool apart
+        #[arg(long, default_value_t = 0)]
+        lock_id: u64,
+        #[arg(long)]
+        amount: u64,
+        /// Unix timestamp the LP starts unlocking at
+        #[arg(long)]
+        unlock_at: i64,
+        /// Unix timestamp all LP is unlocked by, released linearly from
+        /// --unlock-at
+        #[arg(long)]
+        vesting_end: Option<i64>,
+    },
+    /// Withdraw the LP released so far
+    Unlock {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[arg(long, default_value_t = 0)]
+        lock_id: u64,
+    },
+    /// Collect the swap fees earned by the locked LP since the last claim
+    ClaimFees {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[arg(long, default_value_t = 0)]
+        lock_id: u64,
+    },
+    /// Print a lock's terms and escrow balance
+    Show {
+        /// Lock account address
+        lock: Pubkey,
+    },
+}
+
+#[derive(Subcommand)]
+enum FarmCommand {
+    /// Open the pool's farm (authority only)
+    Create {
+        #[command(flatten)]
+        pool: PoolArgs,
+    },
+    /// Add a reward or change its schedule (authority only)
+    SetReward {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[arg(long)]
+        reward_mint: Pubkey,
+        /// Reward slot, from 0
+        #[arg(long)]
+        index: u8,
+        /// Reward tokens emitted per second, in base units
+        #[arg(long)]
+        rate: u64,
+        /// Unix timestamp emission starts at
+        #[arg(long)]
+        start: i64,
+        /// Unix timestamp emission ends at
+        #[arg(long)]
+        end: i64,
+    },
+    /// Top up a reward from the keypair's tokens (authority only)
+    Fund {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[arg(long)]
+        reward_mint: Pubkey,
+        #[arg(long)]
+        amount: u64,
+    },
+    /// Stake LP tokens
+    Stake {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[arg(long)]
+        amount: u64,
+    },
+    /// Withdraw staked LP tokens
+    Unstake {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[arg(long)]
+        amount: u64,
+    },
+    /// Claim every earned reward
+    Claim {
+        #[command(flatten)]
+        pool: PoolArgs,
+    },
+    /// Print the farm's rewards and the keypair's stake
+    Show {
+        #[command(flatten)]
+        pool: PoolArgs,
+    },
+}
+
+#[derive(Subcommand)]
+enum AdminCommand {
+    /// Change the swap fee, which applies to X to Y swaps and, unless a
+    /// separate Y to X fee is set, to Y to X swaps too
+    SetFees {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[arg(long)]
+        fee_bps: u16,
+    },
+    /// Hand the pool to a new authority, or drop it with --none
+    SetAuthority {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[arg(long, required_unless_present = "none")]
+        new_authority: Option<Pubkey>,
+        #[arg(long)]
+        none: bool,
+    },
+    /// Let swaps pay up to this share of their fee to a referrer
+    SetMaxReferral {
+        #[command(flatten)]
+        pool: PoolArgs,
+        /// Basis points of the fee, 0 turns referrals off
+        #[arg(long)]
+        max_referral_bps: u16,
+    },
+    /// Settle swaps in batch auctions, or execute them right away again
+    SetBatchWindow {
+        #[command(flatten)]
+        pool: PoolArgs,
+        /// Seconds per batch; omit to turn batch auctions off
+        #[arg(long)]
+        window: Option<i64>,
+    },
+    /// Charge filled limit orders this fee instead of the swap fee, at least
+    /// half of it
+    SetMakerFee {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[arg(long)]
+        fee_bps: u16,
+    },
+    /// Cap a trader's swap fee, e.g. for market-making wallets
+    SetTraderTier {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[arg(long)]
+        trader: Pubkey,
+        #[arg(long)]
+        fee_bps: u16,
+    },
+    /// Return a trader to the pool fee
+    RemoveTraderTier {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[arg(long)]
+        trader: Pubkey,
+    },
+    /// Charge a different fee on Y to X swaps, or the same fee both ways
+    /// with --symmetric
+    SetYInFee {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[arg(long, required_unless_present = "symmetric")]
+        fee_bps: Option<u16>,
+        #[arg(long)]
+        symmetric: bool,
+    },
+    /// Charge the fee plus a volatility surcharge, or go back to the flat
+    /// fee with --disable
+    DynamicFee {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[command(flatten)]
+        params: DynamicFeeArgs,
+        #[arg(long)]
+        disable: bool,
+    },
+    /// Reject trades moving the price too far at once or within a slot, or
+    /// stop rejecting them with --disable
+    CircuitBreaker {
+        #[command(flatten)]
+        pool: PoolArgs,
+        /// Most one trade may move the price, in basis points
+        #[arg(long, required_unless_present = "disable")]
+        max_swap_move_bps: Option<u16>,
+        /// Most the price may move within a slot, in basis points
+        #[arg(long, required_unless_present = "disable")]
+        max_slot_move_bps: Option<u16>,
+        #[arg(long)]
+        disable: bool,
+    },
+    /// Quote swaps on the PMM curve around the oracle's price, or on the
+    /// constant product again with --disable
+    SetPmm {
+        #[command(flatten)]
+        pool: PoolArgs,
+        /// Slope in basis points; 10000 trades like the constant product
+        #[arg(long, required_unless_present = "disable")]
+        k_bps: Option<u16>,
+        #[arg(long)]
+        disable: bool,
+    },
+    /// Check swaps against a Pyth price feed, or a Switchboard one with
+    /// --switchboard, or stop checking them with --disable
+    SetOracle {
+        #[command(flatten)]
+        pool: PoolArgs,
+        /// Price account quoting X in Y
+        #[arg(long, required_unless_present = "disable")]
+        feed: Option<Pubkey>,
+        #[arg(long)]
+        switchboard: bool,
+        /// The feed quotes Y in X instead
+        #[arg(long)]
+        invert: bool,
+        /// Most a swap's price may stray from the feed's, in basis points
+        #[arg(long, default_value_t = 200)]
+        max_deviation_bps: u16,
+        /// Oldest the feed's price may be, in seconds
+        #[arg(long, default_value_t = 60)]
+        max_staleness: u32,
+        #[arg(long)]
+        disable: bool,
+    },
+}
+
+#[derive(Args)]
+pub struct DynamicFeeArgs {
+    /// Seconds within which swaps share a reference price
+    #[arg(long, default_value_t = 30)]
+    pub filter_period: u32,
+    /// Seconds without swaps after which volatility is forgotten
+    #[arg(long, default_value_t = 600)]
+    pub decay_period: u32,
+    /// Share of volatility kept between windows, in basis points
+    #[arg(long, default_value_t = 5_000)]
+    pub reduction_bps: u16,
+    /// Variable fee is control * volatility² / 1e8 basis points
+    #[arg(long, default_value_t = 4_000)]
+    pub variable_fee_control: u32,
+    /// Cap on volatility, in basis points of price movement
+    #[arg(long, default_value_t = 3_500)]
+    pub max_volatility_bps: u32,
+    /// Cap on the total fee, in basis points
+    #[arg(long, default_value_t = 500)]
+    pub max_fee_bps: u16,
+}
+
+impl From<DynamicFeeArgs> for DynamicFeeParams {
+    fn from(args: DynamicFeeArgs) -> Self {
+        Self {
+            filter_period: args.filter_period,
+            decay_period: args.decay_period,
+            reduction_factor: args.reduction_bps,
+            variable_fee_control: args.variable_fee_control,
+            max_volatility_accumulator: args.max_volatility_bps,
+            max_fee: args.max_fee_bps,
+        }
+    }
+}
+
+/// RPC connection and fee payer shared by every command.
+pub struct Runtime {
+    pub rpc: RpcClient,
+    pub payer: Keypair,
+    /// Wrap and unwrap native SOL sides on-chain
+    pub native_sol: bool,
+}
+
+fn runtime(cli: &Cli) -> Result<Runtime> {
+    let config = config::CliConfig::load(cli.config.as_deref())?;
+
+    let url = cli.url.clone().unwrap_or_else(|| config.url());
+    let keypair_path = cli.keypair.clone().unwrap_or_else(|| config.keypair_path());
+    let payer = read_keypair_file(&keypair_path)
+        .map_err(|err| anyhow::anyhow!("failed to read keypair {keypair_path}: {err}"))?;
+
+    Ok(Runtime {
+        rpc: RpcClient::new_with_commitment(url, CommitmentConfig::confirmed()),
+        payer,
+        native_sol: !cli.wsol,
+    })
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let rt = runtime(&cli)?;
+
+    match cli.command {
+        Command::CreatePool {
+            pool,
+            fee_bps,
+            authority,
+            no_authority,
+            lp_decimals,
+        } => {
+            let authority = match no_authority {
+                true => None,
+                false => Some(authority.unwrap_or(rt.payer.pubkey())),
+            };
+            commands::create_pool(&rt, &pool, fee_bps, authority, lp_decimals)
+        }
+        Command::CreateLpMetadata { pool } => commands::create_lp_metadata(&rt, &pool),
+        Command::MigrateConfig { pool } => commands::migrate_config(&rt, &pool),
+        Command::Deposit {
+            pool,
+            lp,
+            max_x,
+            max_y,
+            slippage_bps,
+        } => commands::deposit(&rt, &pool, lp, max_x, max_y, slippage_bps),
+        Command::Withdraw {
+            pool,
+            lp,
+            min_x,
+            min_y,
+            slippage_bps,
+        } => commands::withdraw(&rt, &pool, lp, min_x, min_y, slippage_bps),
+        Command::Swap {
+            pool,
+            amount_in,
+            y_in,
+            min_out,
+            slippage_bps,
+            source,
+            recipient,
+            tier,
+            referrer,
+            referral_bps,
+        } => commands::swap(
+            &rt,
+            &pool,
+            amount_in,
+            !y_in,
+            min_out,
+            slippage_bps,
+            source,
+            recipient,
+            tier,
+            referrer.zip(referral_bps),
+        ),
+        Command::Show { pool } => commands::show(&rt, &pool),
+        Command::Lock(LockCommand::Create {
+            pool,
+            lock_id,
+            amount,
+            unlock_at,
+            vesting_end,
+        }) => commands::lock_lp(&rt, &pool, lock_id, amount, unlock_at, vesting_end),
+        Command::Lock(LockCommand::Unlock { pool, lock_id }) => {
+            commands::unlock_lp(&rt, &pool, lock_id)
+        }
+        Command::Lock(LockCommand::ClaimFees { pool, lock_id }) => {
+            commands::claim_locked_fees(&rt, &pool, lock_id)
+        }
+        Command::Lock(LockCommand::Show { lock }) => commands::show_lock(&rt, &lock),
+        Command::Order(OrderCommand::Place {
+            pool,
+            order_id,
+            y_in,
+            amount_in,
+            min_out,
+        }) => commands::place_limit_order(&rt, &pool, order_id, !y_in, amount_in, min_out),
+        Command::Order(OrderCommand::Cancel { pool, order_id }) => {
+            commands::cancel_limit_order(&rt, &pool, order_id)
+        }
+        Command::Order(OrderCommand::Fill { pool, order }) => {
+            commands::fill_limit_order(&rt, &pool, &order)
+        }
+        Command::Order(OrderCommand::Show { order }) => commands::show_limit_order(&rt, &order),
+        Command::Dca(DcaCommand::Open {
+            pool,
+            dca_id,
+            y_in,
+            total,
+            amount_per_cycle,
+            interval,
+            min_out_per_cycle,
+            tip_bps,
+        }) => commands::open_dca(
+            &rt,
+            &pool,
+            dca_id,
+            !y_in,
+            total,
+            amount_per_cycle,
+            interval,
+            min_out_per_cycle,
+            tip_bps,
+        ),
+        Command::Dca(DcaCommand::Close { pool, dca_id }) => commands::close_dca(&rt, &pool, dca_id),
+        Command::Dca(DcaCommand::Execute { pool, dca }) => commands::execute_dca(&rt, &pool, &dca),
+        Command::Dca(DcaCommand::Show { dca }) => commands::show_dca(&rt, &dca),
+        Command::Batch(BatchCommand::Submit {
+            pool,
+            y_in,
+            amount_in,
+            min_out,
+        }) => commands::submit_swap_intent(&rt, &pool, !y_in, amount_in, min_out),
+        Command::Batch(BatchCommand::Settle { pool, batch_id }) => {
+            commands::settle_batch(&rt, &pool, batch_id)
+        }
+        Command::Batch(BatchCommand::Claim { pool, batch_id }) => {
+            commands::claim_swap_intents(&rt, &pool, batch_id)
+        }
+        Command::Batch(BatchCommand::Show { pool, batch_id }) => {
+            commands::show_batch(&rt, &pool, batch_id)
+        }
+        Command::Twamm(TwammCommand::Place {
+            pool,
+            order_id,
+            y_in,
+            amount,
+            intervals,
+        }) => commands::place_twamm_order(&rt, &pool, order_id, !y_in, amount, intervals),
+        Command::Twamm(TwammCommand::Withdraw { pool, order_id }) => {
+            commands::withdraw_twamm_proceeds(&rt, &pool, order_id)
+        }
+        Command::Twamm(TwammCommand::Close { pool, order_id }) => {
+            commands::close_twamm_order(&rt, &pool, order_id)
+        }
+        Command::Twamm(TwammCommand::Execute { pool }) => commands::execute_twamm(&rt, &pool),
+        Command::Twamm(TwammCommand::Archive { pool, at }) => {
+            commands::archive_twamm_expiry(&rt, &pool, at)
+        }
+        Command::Twamm(TwammCommand::Show { order }) => commands::show_twamm_order(&rt, &order),
+        Command::Farm(FarmCommand::Create { pool }) => commands::create_farm(&rt, &pool),
+        Command::Farm(FarmCommand::SetReward {
+            pool,
+            reward_mint,
+            index,
+            rate,
+            start,
+            end,
+        }) => commands::set_farm_reward(&rt, &pool, &reward_mint, index, rate, start, end),
+        Command::Farm(FarmCommand::Fund {
+            pool,
+            reward_mint,
+            amount,
+        }) => commands::fund_farm_reward(&rt, &pool, &reward_mint, amount),
+        Command::Farm(FarmCommand::Stake { pool, amount }) => commands::stake(&rt, &pool, amount),
+        Command::Farm(FarmCommand::Unstake { pool, amount }) => {
+            commands::unstake(&rt, &pool, amount)
+        }
+        Command::Farm(FarmCommand::Claim { pool }) => commands::claim_farm_rewards(&rt, &pool),
+        Command::Farm(FarmCommand::Show { pool }) => commands::show_farm(&rt, &pool),
+        Command::Admin(AdminCommand::SetFees { pool, fee_bps }) => {
+            commands::update_config(&rt, &pool, Some(fee_bps), None)
+        }
+        Command::Admin(AdminCommand::SetAuthority {
+            pool,
+            new_authority,
+            none,
+        }) => {
+            let new_authority = match none {
+                true => None,
+                false => new_authority,
+            };
+            commands::update_config(&rt, &pool, None, Some(new_authority))
+        }
+        Command::Admin(AdminCommand::SetMaxReferral {
+            pool,
+            max_referral_bps,
+        }) => commands::set_max_referral(&rt, &pool, max_referral_bps),
+        Command::Admin(AdminCommand::SetBatchWindow { pool, window }) => {
+            commands::set_batch_window(&rt, &pool, window)
+        }
+        Command::Admin(AdminCommand::SetMakerFee { pool, fee_bps }) => {
+            commands::set_maker_fee(&rt, &pool, fee_bps)
+        }
+        Command::Admin(AdminCommand::SetTraderTier {
+            pool,
+            trader,
+            fee_bps,
+        }) => commands::set_trader_tier(&rt, &pool, &trader, fee_bps),
+        Command::Admin(AdminCommand::RemoveTraderTier { pool, trader }) => {
+            commands::remove_trader_tier(&rt, &pool, &trader)
+        }
+        Command::Admin(AdminCommand::SetYInFee {
+            pool,
+            fee_bps,
+            symmetric,
+        }) => {
+            let fee_bps = match symmetric {
+                true => None,
+                false => fee_bps,
+            };
+            commands::set_directional_fees(&rt, &pool, fee_bps)
+        }
+        Command::Admin(AdminCommand::DynamicFee {
+            pool,
+            params,
+            disable,
+        }) => {
+            let params = match disable {
+                true => None,
+                false => Some(params.into()),
+            };
+            commands::set_dynamic_fee(&rt, &pool, params)
+        }
+        Command::Admin(AdminCommand::CircuitBreaker {
+            pool,
+            max_swap_move_bps,
+            max_slot_move_bps,
+            disable,
+        }) => {
+            let params = match (disable, max_swap_move_bps, max_slot_move_bps) {
+                (false, Some(max_swap_move_bps), Some(max_slot_move_bps)) => {
+                    Some(CircuitBreakerParams {
+                        max_swap_move_bps,
+                        max_slot_move_bps,
+                    })
+                }
+                _ => None,
+            };
+            commands::set_circuit_breaker(&rt, &pool, params)
+        }
+        Command::Admin(AdminCommand::SetPmm {
+            pool,
+            k_bps,
+            disable,
+        }) => commands::set_pmm(&rt, &pool, k_bps.filter(|_| !disable)),
+        Command::Admin(AdminCommand::SetOracle {
+            pool,
+            feed,
+            switchboard,
+            invert,
+            max_deviation_bps,
+            max_staleness,
+            disable,
+        }) => {
+            let guard = feed.filter(|_| !disable).map(|feed| OracleGuard {
+                kind: match switchboard {
+                    true => OracleKind::Switchboard,
+                    false => OracleKind::Pyth,
+                },
+                feed,
+                invert,
+                max_deviation_bps,
+                max_staleness,
+            });
+            commands::set_oracle(&rt, &pool, guard)
+        }
+    }
+}
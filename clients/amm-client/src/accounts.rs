@@ -0,0 +1,47 @@
+use amm_contract::states::{
+    Batch, Config, Dca, Farm, LimitOrder, LpLock, StakePosition, TwammOrder,
+};
+use anchor_lang::{AccountDeserialize, Result, Space};
+
+/// Decodes a `Config` account, checking its discriminator. Configs from
+/// before `migrate_config` read as it would leave them.
+pub fn decode_config(data: &[u8]) -> Result<Config> {
+    let mut data = data.to_vec();
+    data.resize(data.len().max(8 + Config::INIT_SPACE), 0);
+    Config::try_deserialize(&mut data.as_slice())
+}
+
+/// Decodes a `Farm` account, checking its discriminator.
+pub fn decode_farm(mut data: &[u8]) -> Result<Farm> {
+    Farm::try_deserialize(&mut data)
+}
+
+/// Decodes a `StakePosition` account, checking its discriminator.
+pub fn decode_stake_position(mut data: &[u8]) -> Result<StakePosition> {
+    StakePosition::try_deserialize(&mut data)
+}
+
+/// Decodes an `LpLock` account, checking its discriminator.
+pub fn decode_lp_lock(mut data: &[u8]) -> Result<LpLock> {
+    LpLock::try_deserialize(&mut data)
+}
+
+/// Decodes a `LimitOrder` account, checking its discriminator.
+pub fn decode_limit_order(mut data: &[u8]) -> Result<LimitOrder> {
+    LimitOrder::try_deserialize(&mut data)
+}
+
+/// Decodes a `Dca` account, checking its discriminator.
+pub fn decode_dca(mut data: &[u8]) -> Result<Dca> {
+    Dca::try_deserialize(&mut data)
+}
+
+/// Decodes a `TwammOrder` account, checking its discriminator.
+pub fn decode_twamm_order(mut data: &[u8]) -> Result<TwammOrder> {
+    TwammOrder::try_deserialize(&mut data)
+}
+
+/// Decodes a `Batch` account, checking its discriminator.
+pub fn decode_batch(mut data: &[u8]) -> Result<Batch> {
+    Batch::try_deserialize(&mut data)
+}
@@ -0,0 +1,1318 @@
+use amm_contract::states::{
+    Batch, CircuitBreakerParams, Dca, DynamicFeeParams, LimitOrder, OracleGuard,
+};
+use amm_contract::{accounts, instruction};
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::{system_program, InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::spl_token::native_mint;
+use anchor_spl::{associated_token, token};
+
+use crate::pda::{
+    batch_address, dca_address, farm_address, limit_order_address, lp_lock_address,
+    metadata_address, stake_position_address, temp_wsol_address, trader_tier_address,
+    twamm_archive_address, twamm_order_address, PoolAddresses,
+};
+
+fn build(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: amm_contract::ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+/// `owner`'s associated token account for `mint`, or `None` for the native
+/// mint so the program moves lamports instead.
+fn token_account_or_native(owner: &Pubkey, mint: &Pubkey) -> Option<Pubkey> {
+    (*mint != native_mint::ID).then(|| get_associated_token_address(owner, mint))
+}
+
+/// Creates the pool config, LP mint and both vaults for `pool`. The LP mint
+/// gets `lp_decimals`, or the program's default for the pair when `None`.
+pub fn initialize(
+    pool: &PoolAddresses,
+    initializer: &Pubkey,
+    fees: u16,
+    authority: Option<Pubkey>,
+    lp_decimals: Option<u8>,
+) -> Instruction {
+    build(
+        accounts::InitializeConfig {
+            initializer: *initializer,
+            mint_x: pool.mint_x,
+            mint_y: pool.mint_y,
+            mint_lp: pool.mint_lp,
+            vault_x: pool.vault_x,
+            vault_y: pool.vault_y,
+            config: pool.config,
+            token_program: token::ID,
+            system_program: system_program::ID,
+            associated_token_program: associated_token::ID,
+        },
+        instruction::Initialize {
+            fees,
+            authority,
+            _lp_decimals: lp_decimals,
+        },
+    )
+}
+
+/// Mints `amount_lp` LP to `user`, paying at most `max_x` / `max_y`.
+pub fn deposit(
+    pool: &PoolAddresses,
+    user: &Pubkey,
+    amount_lp: u64,
+    max_x: u64,
+    max_y: u64,
+) -> Instruction {
+    let (user_x, user_y, _) = pool.user_accounts(user);
+    deposit_from(
+        pool,
+        user,
+        Some(user_x),
+        Some(user_y),
+        amount_lp,
+        max_x,
+        max_y,
+    )
+}
+
+/// Like `deposit`, paying a native SOL side in lamports instead of WSOL.
+pub fn deposit_native(
+    pool: &PoolAddresses,
+    user: &Pubkey,
+    amount_lp: u64,
+    max_x: u64,
+    max_y: u64,
+) -> Instruction {
+    deposit_from(
+        pool,
+        user,
+        token_account_or_native(user, &pool.mint_x),
+        token_account_or_native(user, &pool.mint_y),
+        amount_lp,
+        max_x,
+        max_y,
+    )
+}
+
+fn deposit_from(
+    pool: &PoolAddresses,
+    user: &Pubkey,
+    user_x: Option<Pubkey>,
+    user_y: Option<Pubkey>,
+    amount_lp: u64,
+    max_x: u64,
+    max_y: u64,
+) -> Instruction {
+    let (_, _, user_lp) = pool.user_accounts(user);
+
+    build(
+        accounts::Deposit {
+            user: *user,
+            mint_x: pool.mint_x,
+            mint_y: pool.mint_y,
+            mint_lp: pool.mint_lp,
+            vault_x: pool.vault_x,
+            vault_y: pool.vault_y,
+            user_x,
+            user_y,
+            user_lp,
+            config: pool.config,
+            token_program: token::ID,
+            system_program: system_program::ID,
+            associated_token_program: associated_token::ID,
+        },
+        instruction::Deposit {
+            amount_lp,
+            max_x,
+            max_y,
+        },
+    )
+}
+
+/// Swaps `amount_in` of x (or y when `is_x_in` is false) for at least `min_out`,
+/// between `user`'s associated token accounts.
+pub fn swap(
+    pool: &PoolAddresses,
+    user: &Pubkey,
+    amount_in: u64,
+    min_out: u64,
+    is_x_in: bool,
+) -> Instruction {
+    let (user_x, user_y, _) = pool.user_accounts(user);
+    let (source, recipient) = match is_x_in {
+        true => (user_x, user_y),
+        false => (user_y, user_x),
+    };
+
+    swap_with_accounts(pool, user, &source, &recipient, amount_in, min_out, is_x_in)
+}
+
+/// Like `swap`, spending from `source`, which `user` owns or is a delegate of,
+/// and paying out to `recipient`.
+pub fn swap_with_accounts(
+    pool: &PoolAddresses,
+    user: &Pubkey,
+    source: &Pubkey,
+    recipient: &Pubkey,
+    amount_in: u64,
+    min_out: u64,
+    is_x_in: bool,
+) -> Instruction {
+    swap_between(
+        pool,
+        user,
+        Some(*source),
+        Some(*recipient),
+        None,
+        None,
+        None,
+        amount_in,
+        min_out,
+        is_x_in,
+    )
+}
+
+/// Like `swap`, paying `referral_bps` of the fee to `referrer`, a token
+/// account of the input mint.
+pub fn swap_with_referral(
+    pool: &PoolAddresses,
+    user: &Pubkey,
+    referrer: &Pubkey,
+    referral_bps: u16,
+    amount_in: u64,
+    min_out: u64,
+    is_x_in: bool,
+) -> Instruction {
+    let (user_x, user_y, _) = pool.user_accounts(user);
+    let (source, recipient) = match is_x_in {
+        true => (user_x, user_y),
+        false => (user_y, user_x),
+    };
+
+    swap_between(
+        pool,
+        user,
+        Some(source),
+        Some(recipient),
+        None,
+        Some((*referrer, referral_bps)),
+        None,
+        amount_in,
+        min_out,
+        is_x_in,
+    )
+}
+
+/// Like `swap`, at the reduced fee of `user`'s trader tier, which must exist.
+pub fn swap_with_tier(
+    pool: &PoolAddresses,
+    user: &Pubkey,
+    amount_in: u64,
+    min_out: u64,
+    is_x_in: bool,
+) -> Instruction {
+    let (user_x, user_y, _) = pool.user_accounts(user);
+    let (source, recipient) = match is_x_in {
+        true => (user_x, user_y),
+        false => (user_y, user_x),
+    };
+
+    swap_between(
+        pool,
+        user,
+        Some(source),
+        Some(recipient),
+        Some(trader_tier_address(&pool.config, user).0),
+        None,
+        None,
+        amount_in,
+        min_out,
+        is_x_in,
+    )
+}
+
+/// Like `swap`, on a pool guarded by the price account `oracle`.
+pub fn swap_with_oracle(
+    pool: &PoolAddresses,
+    user: &Pubkey,
+    oracle: &Pubkey,
+    amount_in: u64,
+    min_out: u64,
+    is_x_in: bool,
+) -> Instruction {
+    let (user_x, user_y, _) = pool.user_accounts(user);
+    let (source, recipient) = match is_x_in {
+        true => (user_x, user_y),
+        false => (user_y, user_x),
+    };
+
+    swap_between(
+        pool,
+        user,
+        Some(source),
+        Some(recipient),
+        None,
+        None,
+        Some(*oracle),
+        amount_in,
+        min_out,
+        is_x_in,
+    )
+}
+
+/// Like `swap`, paying in and out native SOL as lamports instead of WSOL.
+pub fn swap_native(
+    pool: &PoolAddresses,
+    user: &Pubkey,
+    amount_in: u64,
+    min_out: u64,
+    is_x_in: bool,
+) -> Instruction {
+    let (mint_in, mint_out) = match is_x_in {
+        true => (pool.mint_x, pool.mint_y),
+        false => (pool.mint_y, pool.mint_x),
+    };
+
+    swap_between(
+        pool,
+        user,
+        token_account_or_native(user, &mint_in),
+        token_account_or_native(user, &mint_out),
+        None,
+        None,
+        None,
+        amount_in,
+        min_out,
+        is_x_in,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn swap_between(
+    pool: &PoolAddresses,
+    user: &Pubkey,
+    source: Option<Pubkey>,
+    recipient: Option<Pubkey>,
+    trader_tier: Option<Pubkey>,
+    referral: Option<(Pubkey, u16)>,
+    oracle: Option<Pubkey>,
+    amount_in: u64,
+    min_out: u64,
+    is_x_in: bool,
+) -> Instruction {
+    build(
+        accounts::Swap {
+            user: *user,
+            config: pool.config,
+            mint_x: pool.mint_x,
+            mint_y: pool.mint_y,
+            vault_x: pool.vault_x,
+            vault_y: pool.vault_y,
+            source,
+            referrer: referral.map(|(referrer, _)| referrer),
+            trader_tier,
+            oracle,
+            temp_wsol: recipient
+                .is_none()
+                .then(|| temp_wsol_address(&pool.config).0),
+            recipient,
+            token_program: token::ID,
+            system_program: system_program::ID,
+        },
+        instruction::Swap {
+            amount_in,
+            min_out,
+            is_x_in,
+            referral_bps: referral.map_or(0, |(_, referral_bps)| referral_bps),
+        },
+    )
+}
+
+/// Burns `lp_amount` LP from `user`, receiving at least `min_x` / `min_y`.
+pub fn withdraw(
+    pool: &PoolAddresses,
+    user: &Pubkey,
+    min_x: u64,
+    min_y: u64,
+    lp_amount: u64,
+) -> Instruction {
+    let (user_x, user_y, _) = pool.user_accounts(user);
+    withdraw_to(
+        pool,
+        user,
+        Some(user_x),
+        Some(user_y),
+        min_x,
+        min_y,
+        lp_amount,
+    )
+}
+
+/// Like `withdraw`, receiving a native SOL side as lamports instead of WSOL.
+pub fn withdraw_native(
+    pool: &PoolAddresses,
+    user: &Pubkey,
+    min_x: u64,
+    min_y: u64,
+    lp_amount: u64,
+) -> Instruction {
+    withdraw_to(
+        pool,
+        user,
+        token_account_or_native(user, &pool.mint_x),
+        token_account_or_native(user, &pool.mint_y),
+        min_x,
+        min_y,
+        lp_amount,
+    )
+}
+
+fn withdraw_to(
+    pool: &PoolAddresses,
+    user: &Pubkey,
+    user_x: Option<Pubkey>,
+    user_y: Option<Pubkey>,
+    min_x: u64,
+    min_y: u64,
+    lp_amount: u64,
+) -> Instruction {
+    let (_, _, user_lp) = pool.user_accounts(user);
+    let temp_wsol =
+        (user_x.is_none() || user_y.is_none()).then(|| temp_wsol_address(&pool.config).0);
+
+    build(
+        accounts::Withdraw {
+            user: *user,
+            mint_x: pool.mint_x,
+            mint_y: pool.mint_y,
+            mint_lp: pool.mint_lp,
+            vault_x: pool.vault_x,
+            vault_y: pool.vault_y,
+            user_x,
+            user_y,
+            user_lp,
+            config: pool.config,
+            temp_wsol,
+            token_program: token::ID,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+        },
+        instruction::Withdraw {
+            min_x,
+            min_y,
+            lp_amount,
+        },
+    )
+}
+
+/// Sets the pool fee and authority. Must be signed by the current authority.
+pub fn update_config(
+    pool: &PoolAddresses,
+    authority: &Pubkey,
+    fees: u16,
+    new_authority: Option<Pubkey>,
+) -> Instruction {
+    build(
+        accounts::UpdateConfig {
+            authority: *authority,
+            config: pool.config,
+        },
+        instruction::UpdateConfig {
+            fees,
+            authority: new_authority,
+        },
+    )
+}
+
+/// Turns on volatility-driven fees with `params`, or off with `None`. Must be
+/// signed by the current authority.
+pub fn set_dynamic_fee(
+    pool: &PoolAddresses,
+    authority: &Pubkey,
+    params: Option<DynamicFeeParams>,
+) -> Instruction {
+    build(
+        accounts::UpdateConfig {
+            authority: *authority,
+            config: pool.config,
+        },
+        instruction::SetDynamicFee { params },
+    )
+}
+
+/// Bounds how far trades on `pool` may move its price, per trade and per
+/// slot, or lifts the bounds with `None`. Must be signed by the current
+/// authority.
+pub fn set_circuit_breaker(
+    pool: &PoolAddresses,
+    authority: &Pubkey,
+    params: Option<CircuitBreakerParams>,
+) -> Instruction {
+    build(
+        accounts::UpdateConfig {
+            authority: *authority,
+            config: pool.config,
+        },
+        instruction::SetCircuitBreaker { params },
+    )
+}
+
+/// Holds swaps on `pool` near the price feed in `guard`, or stops checking
+/// them against one with `None`. Must be signed by the current authority.
+pub fn set_oracle(
+    pool: &PoolAddresses,
+    authority: &Pubkey,
+    guard: Option<OracleGuard>,
+) -> Instruction {
+    build(
+        accounts::UpdateConfig {
+            authority: *authority,
+            config: pool.config,
+        },
+        instruction::SetOracle { guard },
+    )
+}
+
+/// Trades swaps on `pool` on the PMM curve with slope `k` bps around its
+/// oracle's price, or back on the constant product with `None`. Must be
+/// signed by the current authority.
+pub fn set_pmm(pool: &PoolAddresses, authority: &Pubkey, k: Option<u16>) -> Instruction {
+    build(
+        accounts::UpdateConfig {
+            authority: *authority,
+            config: pool.config,
+        },
+        instruction::SetPmm { k },
+    )
+}
+
+/// Charges `fees_y_in` on y to x swaps, or the pool fee both ways with `None`.
+/// Must be signed by the current authority.
+pub fn set_directional_fees(
+    pool: &PoolAddresses,
+    authority: &Pubkey,
+    fees_y_in: Option<u16>,
+) -> Instruction {
+    build(
+        accounts::UpdateConfig {
+            authority: *authority,
+            config: pool.config,
+        },
+        instruction::SetDirectionalFees { fees_y_in },
+    )
+}
+
+/// Lets swaps on `pool` pay up to `max_referral_bps` of their fee to a
+/// referrer. Must be signed by the current authority.
+pub fn set_max_referral(
+    pool: &PoolAddresses,
+    authority: &Pubkey,
+    max_referral_bps: u16,
+) -> Instruction {
+    build(
+        accounts::UpdateConfig {
+            authority: *authority,
+            config: pool.config,
+        },
+        instruction::SetMaxReferral { max_referral_bps },
+    )
+}
+
+/// Charges limit orders filled on `pool` `maker_fee_bps` instead of the swap
+/// fee, at least half of it. Must be signed by the current authority.
+pub fn set_maker_fee(pool: &PoolAddresses, authority: &Pubkey, maker_fee_bps: u16) -> Instruction {
+    build(
+        accounts::UpdateConfig {
+            authority: *authority,
+            config: pool.config,
+        },
+        instruction::SetMakerFee { maker_fee_bps },
+    )
+}
+
+/// Batches `pool`'s swaps over windows of `batch_window` seconds, or lets
+/// them execute right away again with `None`. Must be signed by the current
+/// authority.
+pub fn set_batch_window(
+    pool: &PoolAddresses,
+    authority: &Pubkey,
+    batch_window: Option<i64>,
+) -> Instruction {
+    build(
+        accounts::UpdateConfig {
+            authority: *authority,
+            config: pool.config,
+        },
+        instruction::SetBatchWindow { batch_window },
+    )
+}
+
+/// Caps `trader`'s swap fee on `pool` at `fee_bps`. Must be signed by the
+/// current authority, who pays for the tier account.
+pub fn set_trader_tier(
+    pool: &PoolAddresses,
+    authority: &Pubkey,
+    trader: &Pubkey,
+    fee_bps: u16,
+) -> Instruction {
+    build(
+        accounts::SetTraderTier {
+            authority: *authority,
+            config: pool.config,
+            trader_tier: trader_tier_address(&pool.config, trader).0,
+            system_program: system_program::ID,
+        },
+        instruction::SetTraderTier {
+            trader: *trader,
+            fee_bps,
+        },
+    )
+}
+
+/// Returns `trader` to the pool fee, refunding the tier's rent to `authority`.
+pub fn remove_trader_tier(
+    pool: &PoolAddresses,
+    authority: &Pubkey,
+    trader: &Pubkey,
+) -> Instruction {
+    build(
+        accounts::RemoveTraderTier {
+            authority: *authority,
+            config: pool.config,
+            trader_tier: trader_tier_address(&pool.config, trader).0,
+        },
+        instruction::RemoveTraderTier {},
+    )
+}
+
+/// Opens the LP farm of `pool`. Must be signed by the pool authority, who
+/// pays for the farm.
+pub fn initialize_farm(pool: &PoolAddresses, authority: &Pubkey) -> Instruction {
+    let (farm, _) = farm_address(&pool.config);
+    build(
+        accounts::InitializeFarm {
+            authority: *authority,
+            config: pool.config,
+            mint_lp: pool.mint_lp,
+            farm,
+            lp_vault: get_associated_token_address(&farm, &pool.mint_lp),
+            token_program: token::ID,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+        },
+        instruction::InitializeFarm {},
+    )
+}
+
+/// Emits `rate_per_second` of `reward_mint` between `start` and `end` from
+/// reward slot `index` of `pool`'s farm. Must be signed by the pool authority.
+pub fn set_farm_reward(
+    pool: &PoolAddresses,
+    authority: &Pubkey,
+    reward_mint: &Pubkey,
+    index: u8,
+    rate_per_second: u64,
+    start: i64,
+    end: i64,
+) -> Instruction {
+    let (farm, _) = farm_address(&pool.config);
+    build(
+        accounts::SetFarmReward {
+            authority: *authority,
+            config: pool.config,
+            farm,
+            mint_lp: pool.mint_lp,
+            reward_mint: *reward_mint,
+            reward_vault: get_associated_token_address(&farm, reward_mint),
+            token_program: token::ID,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+        },
+        instruction::SetFarmReward {
+            index,
+            rate_per_second,
+            start,
+            end,
+        },
+    )
+}
+
+/// Moves `amount` of `reward_mint` from the authority's `funder` account into
+/// the reward vault of `pool`'s farm.
+pub fn fund_farm_reward(
+    pool: &PoolAddresses,
+    authority: &Pubkey,
+    reward_mint: &Pubkey,
+    funder: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let (farm, _) = farm_address(&pool.config);
+    build(
+        accounts::FundFarmReward {
+            authority: *authority,
+            config: pool.config,
+            farm,
+            reward_mint: *reward_mint,
+            reward_vault: get_associated_token_address(&farm, reward_mint),
+            funder: *funder,
+            token_program: token::ID,
+        },
+        instruction::FundFarmReward { amount },
+    )
+}
+
+fn stake_accounts(pool: &PoolAddresses, owner: &Pubkey) -> accounts::Stake {
+    let (farm, _) = farm_address(&pool.config);
+    accounts::Stake {
+        owner: *owner,
+        farm,
+        mint_lp: pool.mint_lp,
+        position: stake_position_address(&farm, owner).0,
+        owner_lp: get_associated_token_address(owner, &pool.mint_lp),
+        lp_vault: get_associated_token_address(&farm, &pool.mint_lp),
+        token_program: token::ID,
+        system_program: system_program::ID,
+    }
+}
+
+/// Stakes `amount` of `owner`'s LP in `pool`'s farm.
+pub fn stake(pool: &PoolAddresses, owner: &Pubkey, amount: u64) -> Instruction {
+    build(stake_accounts(pool, owner), instruction::Stake { amount })
+}
+
+/// Returns `amount` of `owner`'s staked LP. Earned rewards stay claimable.
+pub fn unstake(pool: &PoolAddresses, owner: &Pubkey, amount: u64) -> Instruction {
+    build(stake_accounts(pool, owner), instruction::Unstake { amount })
+}
+
+/// Pays `owner`'s earned `reward_mint` rewards to `recipient`.
+pub fn claim_farm_reward(
+    pool: &PoolAddresses,
+    owner: &Pubkey,
+    reward_mint: &Pubkey,
+    recipient: &Pubkey,
+) -> Instruction {
+    let (farm, _) = farm_address(&pool.config);
+    build(
+        accounts::ClaimFarmReward {
+            owner: *owner,
+            farm,
+            position: stake_position_address(&farm, owner).0,
+            reward_mint: *reward_mint,
+            reward_vault: get_associated_token_address(&farm, reward_mint),
+            recipient: *recipient,
+            token_program: token::ID,
+        },
+        instruction::ClaimFarmReward {},
+    )
+}
+
+/// Escrows `amount` of `owner`'s LP as lock `lock_id` until `unlock_at`,
+/// then releases it linearly until `vesting_end` when given.
+pub fn lock_lp(
+    pool: &PoolAddresses,
+    owner: &Pubkey,
+    lock_id: u64,
+    amount: u64,
+    unlock_at: i64,
+    vesting_end: Option<i64>,
+) -> Instruction {
+    let (lock, _) = lp_lock_address(&pool.config, owner, lock_id);
+    build(
+        accounts::LockLp {
+            owner: *owner,
+            mint_x: pool.mint_x,
+            mint_y: pool.mint_y,
+            config: pool.config,
+            mint_lp: pool.mint_lp,
+            vault_x: pool.vault_x,
+            vault_y: pool.vault_y,
+            lock,
+            escrow: get_associated_token_address(&lock, &pool.mint_lp),
+            owner_lp: get_associated_token_address(owner, &pool.mint_lp),
+            token_program: token::ID,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+        },
+        instruction::LockLp {
+            lock_id,
+            amount,
+            unlock_at,
+            vesting_end,
+        },
+    )
+}
+
+/// Returns the LP released so far from `owner`'s lock `lock_id`.
+pub fn unlock_lp(pool: &PoolAddresses, owner: &Pubkey, lock_id: u64) -> Instruction {
+    let (lock, _) = lp_lock_address(&pool.config, owner, lock_id);
+    build(
+        accounts::UnlockLp {
+            owner: *owner,
+            lock,
+            mint_lp: pool.mint_lp,
+            escrow: get_associated_token_address(&lock, &pool.mint_lp),
+            owner_lp: get_associated_token_address(owner, &pool.mint_lp),
+            token_program: token::ID,
+        },
+        instruction::UnlockLp {},
+    )
+}
+
+/// Pays `owner` the swap fees earned by lock `lock_id` since its last claim.
+pub fn claim_locked_fees(pool: &PoolAddresses, owner: &Pubkey, lock_id: u64) -> Instruction {
+    let (lock, _) = lp_lock_address(&pool.config, owner, lock_id);
+    let (owner_x, owner_y, _) = pool.user_accounts(owner);
+    build(
+        accounts::ClaimLockedFees {
+            owner: *owner,
+            mint_x: pool.mint_x,
+            mint_y: pool.mint_y,
+            config: pool.config,
+            mint_lp: pool.mint_lp,
+            vault_x: pool.vault_x,
+            vault_y: pool.vault_y,
+            lock,
+            escrow: get_associated_token_address(&lock, &pool.mint_lp),
+            owner_x,
+            owner_y,
+            token_program: token::ID,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+        },
+        instruction::ClaimLockedFees {},
+    )
+}
+
+/// Input and output mints of an order selling x when `is_x_in`.
+fn order_mints(pool: &PoolAddresses, is_x_in: bool) -> (Pubkey, Pubkey) {
+    match is_x_in {
+        true => (pool.mint_x, pool.mint_y),
+        false => (pool.mint_y, pool.mint_x),
+    }
+}
+
+/// Escrows `amount_in` of `owner`'s x (or y) as order `order_id`, to be
+/// sold for at least `min_out` of the other token.
+pub fn place_limit_order(
+    pool: &PoolAddresses,
+    owner: &Pubkey,
+    order_id: u64,
+    is_x_in: bool,
+    amount_in: u64,
+    min_out: u64,
+) -> Instruction {
+    let (order, _) = limit_order_address(&pool.config, owner, order_id);
+    let (mint_in, mint_out) = order_mints(pool, is_x_in);
+    build(
+        accounts::PlaceLimitOrder {
+            owner: *owner,
+            config: pool.config,
+            mint_in,
+            mint_out,
+            order,
+            escrow: get_associated_token_address(&order, &mint_in),
+            owner_in: get_associated_token_address(owner, &mint_in),
+            owner_out: get_associated_token_address(owner, &mint_out),
+            token_program: token::ID,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+        },
+        instruction::PlaceLimitOrder {
+            order_id,
+            is_x_in,
+            amount_in,
+            min_out,
+        },
+    )
+}
+
+/// Refunds what is left of `owner`'s order `order_id` and closes it.
+pub fn cancel_limit_order(
+    pool: &PoolAddresses,
+    owner: &Pubkey,
+    order_id: u64,
+    is_x_in: bool,
+) -> Instruction {
+    let (order, _) = limit_order_address(&pool.config, owner, order_id);
+    let (mint_in, _) = order_mints(pool, is_x_in);
+    build(
+        accounts::CancelLimitOrder {
+            owner: *owner,
+            order,
+            mint_in,
+            escrow: get_associated_token_address(&order, &mint_in),
+            owner_in: get_associated_token_address(owner, &mint_in),
+            token_program: token::ID,
+        },
+        instruction::CancelLimitOrder {},
+    )
+}
+
+fn fill_limit_order_accounts(
+    pool: &PoolAddresses,
+    cranker: &Pubkey,
+    order: &LimitOrder,
+    oracle: Option<Pubkey>,
+) -> accounts::FillLimitOrder {
+    let (address, _) = limit_order_address(&pool.config, &order.owner, order.order_id);
+    let (mint_in, mint_out) = order_mints(pool, order.is_x_in);
+    accounts::FillLimitOrder {
+        cranker: *cranker,
+        config: pool.config,
+        mint_in,
+        mint_out,
+        vault_in: get_associated_token_address(&pool.config, &mint_in),
+        vault_out: get_associated_token_address(&pool.config, &mint_out),
+        order: address,
+        escrow: get_associated_token_address(&address, &mint_in),
+        owner: order.owner,
+        owner_in: get_associated_token_address(&order.owner, &mint_in),
+        owner_out: get_associated_token_address(&order.owner, &mint_out),
+        oracle,
+        token_program: token::ID,
+    }
+}
+
+/// Fills as much of `order` against `pool` as its limit price allows.
+/// Anyone may sign as `cranker`.
+pub fn fill_limit_order(pool: &PoolAddresses, cranker: &Pubkey, order: &LimitOrder) -> Instruction {
+    build(
+        fill_limit_order_accounts(pool, cranker, order, None),
+        instruction::FillLimitOrder {},
+    )
+}
+
+/// Like `fill_limit_order`, on a pool guarded by the price account `oracle`.
+pub fn fill_limit_order_with_oracle(
+    pool: &PoolAddresses,
+    cranker: &Pubkey,
+    order: &LimitOrder,
+    oracle: &Pubkey,
+) -> Instruction {
+    build(
+        fill_limit_order_accounts(pool, cranker, order, Some(*oracle)),
+        instruction::FillLimitOrder {},
+    )
+}
+
+/// Escrows `total` of `owner`'s x (or y) as DCA `dca_id`, sold
+/// `amount_per_cycle` at a time, at most once per `interval` seconds, for
+/// at least `min_out_per_cycle` each, paying keepers `tip_bps` of a slice.
+#[allow(clippy::too_many_arguments)]
+pub fn open_dca(
+    pool: &PoolAddresses,
+    owner: &Pubkey,
+    dca_id: u64,
+    is_x_in: bool,
+    total: u64,
+    amount_per_cycle: u64,
+    interval: i64,
+    min_out_per_cycle: u64,
+    tip_bps: u16,
+) -> Instruction {
+    let (dca, _) = dca_address(&pool.config, owner, dca_id);
+    let (mint_in, mint_out) = order_mints(pool, is_x_in);
+    build(
+        accounts::OpenDca {
+            owner: *owner,
+            config: pool.config,
+            mint_in,
+            mint_out,
+            dca,
+            escrow: get_associated_token_address(&dca, &mint_in),
+            owner_in: get_associated_token_address(owner, &mint_in),
+            owner_out: get_associated_token_address(owner, &mint_out),
+            token_program: token::ID,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+        },
+        instruction::OpenDca {
+            dca_id,
+            is_x_in,
+            total,
+            amount_per_cycle,
+            interval,
+            min_out_per_cycle,
+            tip_bps,
+        },
+    )
+}
+
+/// Stops `owner`'s DCA `dca_id`, refunding what is left unsold.
+pub fn close_dca(pool: &PoolAddresses, owner: &Pubkey, dca_id: u64, is_x_in: bool) -> Instruction {
+    let (dca, _) = dca_address(&pool.config, owner, dca_id);
+    let (mint_in, _) = order_mints(pool, is_x_in);
+    build(
+        accounts::CloseDca {
+            owner: *owner,
+            dca,
+            mint_in,
+            escrow: get_associated_token_address(&dca, &mint_in),
+            owner_in: get_associated_token_address(owner, &mint_in),
+            token_program: token::ID,
+        },
+        instruction::CloseDca {},
+    )
+}
+
+fn execute_dca_accounts(
+    pool: &PoolAddresses,
+    keeper: &Pubkey,
+    dca: &Dca,
+    oracle: Option<Pubkey>,
+) -> accounts::ExecuteDca {
+    let (address, _) = dca_address(&pool.config, &dca.owner, dca.dca_id);
+    let (mint_in, mint_out) = order_mints(pool, dca.is_x_in);
+    accounts::ExecuteDca {
+        keeper: *keeper,
+        config: pool.config,
+        mint_in,
+        mint_out,
+        vault_in: get_associated_token_address(&pool.config, &mint_in),
+        vault_out: get_associated_token_address(&pool.config, &mint_out),
+        dca: address,
+        escrow: get_associated_token_address(&address, &mint_in),
+        owner: dca.owner,
+        owner_in: get_associated_token_address(&dca.owner, &mint_in),
+        owner_out: get_associated_token_address(&dca.owner, &mint_out),
+        keeper_in: get_associated_token_address(keeper, &mint_in),
+        oracle,
+        token_program: token::ID,
+    }
+}
+
+/// Sells the due slice of `dca`, tipping `keeper`'s ATA of the input mint.
+pub fn execute_dca(pool: &PoolAddresses, keeper: &Pubkey, dca: &Dca) -> Instruction {
+    build(
+        execute_dca_accounts(pool, keeper, dca, None),
+        instruction::ExecuteDca {},
+    )
+}
+
+/// Like `execute_dca`, on a pool guarded by the price account `oracle`.
+pub fn execute_dca_with_oracle(
+    pool: &PoolAddresses,
+    keeper: &Pubkey,
+    dca: &Dca,
+    oracle: &Pubkey,
+) -> Instruction {
+    build(
+        execute_dca_accounts(pool, keeper, dca, Some(*oracle)),
+        instruction::ExecuteDca {},
+    )
+}
+
+/// Sells `amount` of `owner`'s x (or y) into the pool evenly until
+/// `intervals` TWAMM interval boundaries from now, as order `order_id`.
+pub fn place_twamm_order(
+    pool: &PoolAddresses,
+    owner: &Pubkey,
+    order_id: u64,
+    is_x_in: bool,
+    amount: u64,
+    intervals: u32,
+) -> Instruction {
+    let (order, _) = twamm_order_address(&pool.config, owner, order_id);
+    build(
+        accounts::PlaceTwammOrder {
+            owner: *owner,
+            mint_x: pool.mint_x,
+            mint_y: pool.mint_y,
+            config: pool.config,
+            vault_x: pool.vault_x,
+            vault_y: pool.vault_y,
+            order,
+            owner_x: get_associated_token_address(owner, &pool.mint_x),
+            owner_y: get_associated_token_address(owner, &pool.mint_y),
+            token_program: token::ID,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+        },
+        instruction::PlaceTwammOrder {
+            order_id,
+            is_x_in,
+            amount,
+            intervals,
+        },
+    )
+}
+
+fn claim_twamm_order_accounts(
+    pool: &PoolAddresses,
+    owner: &Pubkey,
+    order_id: u64,
+    archived_end: Option<i64>,
+) -> accounts::ClaimTwammOrder {
+    let (order, _) = twamm_order_address(&pool.config, owner, order_id);
+    accounts::ClaimTwammOrder {
+        owner: *owner,
+        mint_x: pool.mint_x,
+        mint_y: pool.mint_y,
+        config: pool.config,
+        vault_x: pool.vault_x,
+        vault_y: pool.vault_y,
+        order,
+        archive: archived_end.map(|end| twamm_archive_address(&pool.config, end).0),
+        owner_x: get_associated_token_address(owner, &pool.mint_x),
+        owner_y: get_associated_token_address(owner, &pool.mint_y),
+        token_program: token::ID,
+    }
+}
+
+/// Pays out what `owner`'s long-term order `order_id` has bought so far.
+/// `archived_end` is the order's end once its expiry has been archived.
+pub fn withdraw_twamm_proceeds(
+    pool: &PoolAddresses,
+    owner: &Pubkey,
+    order_id: u64,
+    archived_end: Option<i64>,
+) -> Instruction {
+    build(
+        claim_twamm_order_accounts(pool, owner, order_id, archived_end),
+        instruction::WithdrawTwammProceeds {},
+    )
+}
+
+/// Closes `owner`'s long-term order `order_id`, cancelling it if it is still
+/// running, and pays out its proceeds and unsold input. `archived_end` is the
+/// order's end once its expiry has been archived.
+pub fn close_twamm_order(
+    pool: &PoolAddresses,
+    owner: &Pubkey,
+    order_id: u64,
+    archived_end: Option<i64>,
+) -> Instruction {
+    build(
+        claim_twamm_order_accounts(pool, owner, order_id, archived_end),
+        instruction::CloseTwammOrder {},
+    )
+}
+
+/// Executes `pool`'s long-term orders up to now.
+pub fn execute_twamm(pool: &PoolAddresses) -> Instruction {
+    build(
+        accounts::ExecuteTwamm {
+            config: pool.config,
+            vault_x: pool.vault_x,
+            vault_y: pool.vault_y,
+        },
+        instruction::ExecuteTwamm {},
+    )
+}
+
+/// Moves `pool`'s passed expiry of orders ending at `at` out of the pool,
+/// freeing its slot. `payer` funds the archive account.
+pub fn archive_twamm_expiry(pool: &PoolAddresses, payer: &Pubkey, at: i64) -> Instruction {
+    build(
+        accounts::ArchiveTwammExpiry {
+            payer: *payer,
+            config: pool.config,
+            vault_x: pool.vault_x,
+            vault_y: pool.vault_y,
+            archive: twamm_archive_address(&pool.config, at).0,
+            system_program: system_program::ID,
+        },
+        instruction::ArchiveTwammExpiry { at },
+    )
+}
+
+/// Submits a swap of `owner`'s x (or y) to the batch of the current window,
+/// `batch_id` being `unix_timestamp / batch_window`.
+pub fn submit_swap_intent(
+    pool: &PoolAddresses,
+    owner: &Pubkey,
+    batch_id: u64,
+    amount_in: u64,
+    min_out: u64,
+    is_x_in: bool,
+) -> Instruction {
+    let (batch, _) = batch_address(&pool.config, batch_id);
+    build(
+        accounts::SubmitSwapIntent {
+            owner: *owner,
+            mint_x: pool.mint_x,
+            mint_y: pool.mint_y,
+            config: pool.config,
+            vault_x: pool.vault_x,
+            vault_y: pool.vault_y,
+            batch,
+            batch_x: get_associated_token_address(&batch, &pool.mint_x),
+            batch_y: get_associated_token_address(&batch, &pool.mint_y),
+            owner_x: get_associated_token_address(owner, &pool.mint_x),
+            owner_y: get_associated_token_address(owner, &pool.mint_y),
+            token_program: token::ID,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+        },
+        instruction::SubmitSwapIntent {
+            batch_id,
+            amount_in,
+            min_out,
+            is_x_in,
+        },
+    )
+}
+
+fn settle_batch_accounts(
+    pool: &PoolAddresses,
+    batch_id: u64,
+    oracle: Option<Pubkey>,
+) -> accounts::SettleBatch {
+    let (batch, _) = batch_address(&pool.config, batch_id);
+    accounts::SettleBatch {
+        mint_x: pool.mint_x,
+        mint_y: pool.mint_y,
+        config: pool.config,
+        vault_x: pool.vault_x,
+        vault_y: pool.vault_y,
+        batch,
+        batch_x: get_associated_token_address(&batch, &pool.mint_x),
+        batch_y: get_associated_token_address(&batch, &pool.mint_y),
+        oracle,
+        token_program: token::ID,
+    }
+}
+
+/// Settles `pool`'s closed batch `batch_id` at its clearing price.
+pub fn settle_batch(pool: &PoolAddresses, batch_id: u64) -> Instruction {
+    build(
+        settle_batch_accounts(pool, batch_id, None),
+        instruction::SettleBatch {},
+    )
+}
+
+/// Like `settle_batch`, on a pool guarded by the price account `oracle`.
+pub fn settle_batch_with_oracle(
+    pool: &PoolAddresses,
+    batch_id: u64,
+    oracle: &Pubkey,
+) -> Instruction {
+    build(
+        settle_batch_accounts(pool, batch_id, Some(*oracle)),
+        instruction::SettleBatch {},
+    )
+}
+
+/// Pays `owner` for their intents in the settled `batch`.
+pub fn claim_swap_intents(pool: &PoolAddresses, owner: &Pubkey, batch: &Batch) -> Instruction {
+    let (address, _) = batch_address(&pool.config, batch.batch_id);
+    build(
+        accounts::ClaimSwapIntents {
+            owner: *owner,
+            mint_x: pool.mint_x,
+            mint_y: pool.mint_y,
+            config: pool.config,
+            vault_x: pool.vault_x,
+            vault_y: pool.vault_y,
+            batch: address,
+            payer: batch.payer,
+            batch_x: get_associated_token_address(&address, &pool.mint_x),
+            batch_y: get_associated_token_address(&address, &pool.mint_y),
+            owner_x: get_associated_token_address(owner, &pool.mint_x),
+            owner_y: get_associated_token_address(owner, &pool.mint_y),
+            token_program: token::ID,
+        },
+        instruction::ClaimSwapIntents {},
+    )
+}
+
+/// Creates the token metadata of `pool`'s LP mint, paid for by `payer`.
+pub fn create_lp_metadata(pool: &PoolAddresses, payer: &Pubkey) -> Instruction {
+    build(
+        accounts::CreateLpMetadata {
+            payer: *payer,
+            config: pool.config,
+            mint_x: pool.mint_x,
+            mint_y: pool.mint_y,
+            mint_lp: pool.mint_lp,
+            lp_metadata: metadata_address(&pool.mint_lp),
+            metadata_x: metadata_address(&pool.mint_x),
+            metadata_y: metadata_address(&pool.mint_y),
+            token_metadata_program: anchor_spl::metadata::ID,
+            system_program: system_program::ID,
+            rent: anchor_lang::solana_program::sysvar::rent::ID,
+        },
+        instruction::CreateLpMetadata {},
+    )
+}
+
+/// Brings `pool`'s config up to the current layout, paid for by `payer`.
+pub fn migrate_config(pool: &PoolAddresses, payer: &Pubkey) -> Instruction {
+    build(
+        accounts::MigrateConfig {
+            payer: *payer,
+            config: pool.config,
+            system_program: system_program::ID,
+        },
+        instruction::MigrateConfig {},
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swap_targets_the_pool_accounts() {
+        let pool = PoolAddresses::new(Pubkey::new_unique(), Pubkey::new_unique());
+        let user = Pubkey::new_unique();
+        let ix = swap(&pool, &user, 10, 9, true);
+
+        assert_eq!(ix.program_id, amm_contract::ID);
+        assert_eq!(ix.accounts[0].pubkey, user);
+        assert!(ix.accounts[0].is_signer);
+        assert_eq!(ix.accounts[1].pubkey, pool.config);
+        assert_eq!(ix.accounts[4].pubkey, pool.vault_x);
+        assert!(ix.accounts[4].is_writable);
+        let (user_x, user_y, _) = pool.user_accounts(&user);
+        assert_eq!(ix.accounts[6].pubkey, user_x);
+        assert_eq!(ix.accounts[7].pubkey, user_y);
+        assert_eq!(
+            ix.data,
+            instruction::Swap {
+                amount_in: 10,
+                min_out: 9,
+                is_x_in: true,
+                referral_bps: 0,
+            }
+            .data()
+        );
+    }
+
+    #[test]
+    fn config_round_trips_through_decoder() {
+        use anchor_lang::AccountSerialize;
+
+        let config = crate::Config {
+            mint_x: Pubkey::new_unique(),
+            mint_y: Pubkey::new_unique(),
+            fees: 30,
+            my_bump: 254,
+            lp_bump: 253,
+            authority: Some(Pubkey::new_unique()),
+            dynamic_fee: None,
+            fees_y_in: None,
+            max_referral_bps: 0,
+            maker_fee_bps: 0,
+            twamm: Default::default(),
+            batch_window: None,
+            circuit_breaker: None,
+            oracle: None,
+            pmm: None,
+        };
+        let mut data = Vec::new();
+        config.try_serialize(&mut data).unwrap();
+
+        let decoded = crate::decode_config(&data).unwrap();
+        assert_eq!(decoded.mint_x, config.mint_x);
+        assert_eq!(decoded.fees, 30);
+        assert_eq!(decoded.authority, config.authority);
+
+        assert!(crate::decode_config(&data[1..]).is_err());
+    }
+}
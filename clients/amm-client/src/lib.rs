@@ -0,0 +1,16 @@
+pub mod accounts;
+pub mod instructions;
+pub mod pda;
+pub mod quote;
+
+pub use accounts::*;
+pub use amm_contract::oracle::{OracleKind, OraclePrice};
+pub use amm_contract::states::{
+    Batch, CircuitBreakerParams, Config, Dca, DynamicFeeParams, Farm, FarmReward, LimitOrder,
+    LpLock, OracleGuard, Pmm, StakePosition, SwapIntent, TraderTier, Twamm, TwammArchive,
+    TwammOrder, MAX_BATCH_INTENTS, MAX_DCA_TIP_BPS, MAX_FARM_REWARDS, MIN_INTENT_BPS,
+    TWAMM_INTERVAL,
+};
+pub use amm_contract::ID as PROGRAM_ID;
+pub use pda::*;
+pub use quote::*;
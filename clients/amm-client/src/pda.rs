@@ -0,0 +1,157 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::associated_token::get_associated_token_address;
+
+/// Config PDA of the pool for the `mint_x` / `mint_y` pair.
+pub fn config_address(mint_x: &Pubkey, mint_y: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"config", mint_x.as_ref(), mint_y.as_ref()],
+        &amm_contract::ID,
+    )
+}
+
+/// LP mint PDA of the pool owning `config`.
+pub fn lp_mint_address(config: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"lp", config.as_ref()], &amm_contract::ID)
+}
+
+/// Vault holding `mint` for the pool owning `config`.
+pub fn vault_address(config: &Pubkey, mint: &Pubkey) -> Pubkey {
+    get_associated_token_address(config, mint)
+}
+
+/// Temporary WSOL account the pool owning `config` pays native SOL through.
+pub fn temp_wsol_address(config: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"wsol", config.as_ref()], &amm_contract::ID)
+}
+
+/// Fee tier of `trader` on the pool owning `config`.
+pub fn trader_tier_address(config: &Pubkey, trader: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"tier", config.as_ref(), trader.as_ref()],
+        &amm_contract::ID,
+    )
+}
+
+/// LP farm of the pool owning `config`.
+pub fn farm_address(config: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"farm", config.as_ref()], &amm_contract::ID)
+}
+
+/// Stake of `owner` in `farm`.
+pub fn stake_position_address(farm: &Pubkey, owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"stake", farm.as_ref(), owner.as_ref()],
+        &amm_contract::ID,
+    )
+}
+
+/// Lock `lock_id` of `owner`'s LP in the pool owning `config`.
+pub fn lp_lock_address(config: &Pubkey, owner: &Pubkey, lock_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"lock",
+            config.as_ref(),
+            owner.as_ref(),
+            &lock_id.to_le_bytes(),
+        ],
+        &amm_contract::ID,
+    )
+}
+
+/// Limit order `order_id` of `owner` on the pool owning `config`.
+pub fn limit_order_address(config: &Pubkey, owner: &Pubkey, order_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"order",
+            config.as_ref(),
+            owner.as_ref(),
+            &order_id.to_le_bytes(),
+        ],
+        &amm_contract::ID,
+    )
+}
+
+/// DCA `dca_id` of `owner` on the pool owning `config`.
+pub fn dca_address(config: &Pubkey, owner: &Pubkey, dca_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"dca",
+            config.as_ref(),
+            owner.as_ref(),
+            &dca_id.to_le_bytes(),
+        ],
+        &amm_contract::ID,
+    )
+}
+
+/// Long-term order `order_id` of `owner` on the pool owning `config`.
+pub fn twamm_order_address(config: &Pubkey, owner: &Pubkey, order_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"twamm",
+            config.as_ref(),
+            owner.as_ref(),
+            &order_id.to_le_bytes(),
+        ],
+        &amm_contract::ID,
+    )
+}
+
+/// Archived expiry of long-term orders ending at `at` on the pool owning
+/// `config`.
+pub fn twamm_archive_address(config: &Pubkey, at: i64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"twamm_archive", config.as_ref(), &at.to_le_bytes()],
+        &amm_contract::ID,
+    )
+}
+
+/// Batch of window `batch_id` on the pool owning `config`.
+pub fn batch_address(config: &Pubkey, batch_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"batch", config.as_ref(), &batch_id.to_le_bytes()],
+        &amm_contract::ID,
+    )
+}
+
+/// Token metadata account of `mint`.
+pub fn metadata_address(mint: &Pubkey) -> Pubkey {
+    let (address, _) = anchor_spl::metadata::mpl_token_metadata::accounts::Metadata::find_pda(mint);
+    address
+}
+
+/// Every address derived from a pool's mint pair.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoolAddresses {
+    pub mint_x: Pubkey,
+    pub mint_y: Pubkey,
+    pub config: Pubkey,
+    pub mint_lp: Pubkey,
+    pub vault_x: Pubkey,
+    pub vault_y: Pubkey,
+}
+
+impl PoolAddresses {
+    pub fn new(mint_x: Pubkey, mint_y: Pubkey) -> Self {
+        let (config, _) = config_address(&mint_x, &mint_y);
+        let (mint_lp, _) = lp_mint_address(&config);
+
+        Self {
+            mint_x,
+            mint_y,
+            config,
+            mint_lp,
+            vault_x: vault_address(&config, &mint_x),
+            vault_y: vault_address(&config, &mint_y),
+        }
+    }
+
+    /// `owner`'s associated token accounts for x, y and the LP mint.
+    pub fn user_accounts(&self, owner: &Pubkey) -> (Pubkey, Pubkey, Pubkey) {
+        (
+            get_associated_token_address(owner, &self.mint_x),
+            get_associated_token_address(owner, &self.mint_y),
+            get_associated_token_address(owner, &self.mint_lp),
+        )
+    }
+}
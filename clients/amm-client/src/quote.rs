@@ -0,0 +1,294 @@
+use amm_contract::constant_product_curve::{
+    mul_div, ConstantProductCurve, RoundDirection, XYAmounts,
+};
+use amm_contract::error::AmmError;
+use amm_contract::states::{Config, TraderTier};
+use anchor_lang::prelude::*;
+
+/// Basis-point denominator used for price impact.
+pub const BPS: u64 = 10_000;
+
+/// Result of quoting a swap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwapQuote {
+    pub amount_in: u64,
+    pub fee: u64,
+    pub amount_out: u64,
+    /// Shortfall of `amount_out` against the spot price, fee excluded.
+    pub price_impact_bps: u64,
+}
+
+/// Snapshot of a pool, enough to quote any instruction off-chain.
+#[derive(Clone)]
+pub struct PoolState {
+    pub config: Config,
+    pub reserve_x: u64,
+    pub reserve_y: u64,
+    pub lp_supply: u64,
+    /// Clock time the swap is expected to land at, for dynamic fee pools.
+    pub unix_timestamp: i64,
+    /// Fee ceiling of the swapping trader's tier, if they have one.
+    pub tier_fee_bps: Option<u16>,
+    /// Oracle price as raw y per raw x, Q64.64, which PMM pools quote around.
+    pub oracle_price: Option<u128>,
+}
+
+impl PoolState {
+    pub fn new(config: Config, reserve_x: u64, reserve_y: u64, lp_supply: u64) -> Self {
+        Self {
+            config,
+            reserve_x,
+            reserve_y,
+            lp_supply,
+            unix_timestamp: 0,
+            tier_fee_bps: None,
+            oracle_price: None,
+        }
+    }
+
+    /// Snapshot of a pool from its vault balances at `unix_timestamp`, with
+    /// its long-term orders executed up to then and their balances set aside,
+    /// as the program does before every instruction.
+    pub fn from_vaults(
+        mut config: Config,
+        vault_x: u64,
+        vault_y: u64,
+        lp_supply: u64,
+        unix_timestamp: i64,
+    ) -> Result<Self> {
+        let (reserve_x, reserve_y) = config.sync_twamm(vault_x, vault_y, unix_timestamp)?;
+        Ok(Self::new(config, reserve_x, reserve_y, lp_supply).at(unix_timestamp))
+    }
+
+    /// The same snapshot, quoting swaps for the holder of `tier`.
+    pub fn with_tier(self, tier: &TraderTier) -> Self {
+        Self {
+            tier_fee_bps: Some(tier.fee_bps),
+            ..self
+        }
+    }
+
+    /// The same snapshot, quoting swaps at the oracle's `price`, raw y per raw
+    /// x in Q64.64, as `OraclePrice::to_q64` gives it.
+    pub fn with_oracle_price(self, price: u128) -> Self {
+        Self {
+            oracle_price: Some(price),
+            ..self
+        }
+    }
+
+    /// The same snapshot, quoting swaps as if executed at `unix_timestamp`.
+    pub fn at(self, unix_timestamp: i64) -> Self {
+        Self {
+            unix_timestamp,
+            ..self
+        }
+    }
+
+    fn balances(&self, is_x_in: bool) -> (u64, u64) {
+        match is_x_in {
+            true => (self.reserve_x, self.reserve_y),
+            false => (self.reserve_y, self.reserve_x),
+        }
+    }
+
+    /// Quotes `swap(amount_in, _, is_x_in)` exactly as the program executes it.
+    pub fn quote_swap(&self, amount_in: u64, is_x_in: bool) -> Result<SwapQuote> {
+        require!(amount_in > 0, AmmError::ZeroAmount);
+
+        let (in_bal, out_bal) = self.balances(is_x_in);
+        let fee_bps = self.config.clone().swap_fee(
+            self.reserve_x,
+            self.reserve_y,
+            amount_in,
+            is_x_in,
+            self.unix_timestamp,
+        )?;
+        let fee_bps = match self.tier_fee_bps {
+            Some(tier_fee_bps) => fee_bps.min(tier_fee_bps),
+            None => fee_bps,
+        };
+        let (amounts, spot_out) = match self.config.pmm {
+            Some(mut pmm) => {
+                let price = self.oracle_price.ok_or(AmmError::MissingOracle)?;
+                let amounts = pmm.swap(
+                    self.reserve_x,
+                    self.reserve_y,
+                    amount_in,
+                    fee_bps,
+                    is_x_in,
+                    price,
+                )?;
+                // output at the oracle price
+                let (num, den) = match is_x_in {
+                    true => (price, 1 << 64),
+                    false => (1 << 64, price),
+                };
+                let spot_out = mul_div(
+                    (amount_in - amounts.fee) as u128,
+                    num,
+                    den,
+                    RoundDirection::Floor,
+                )?;
+                (amounts, spot_out)
+            }
+            None => {
+                let amounts = ConstantProductCurve::swap(in_bal, out_bal, amount_in, fee_bps)?;
+                // output at the spot price, i.e. with no curve slippage
+                let spot_out = mul_div(
+                    (amount_in - amounts.fee) as u128,
+                    out_bal as u128,
+                    in_bal as u128,
+                    RoundDirection::Floor,
+                )?;
+                (amounts, spot_out)
+            }
+        };
+        let price_impact_bps = match spot_out {
+            0 => 0,
+            _ => mul_div(
+                spot_out.saturating_sub(amounts.amount_out as u128),
+                BPS as u128,
+                spot_out,
+                RoundDirection::Ceiling,
+            )? as u64,
+        };
+
+        Ok(SwapQuote {
+            amount_in,
+            fee: amounts.fee,
+            amount_out: amounts.amount_out,
+            price_impact_bps,
+        })
+    }
+
+    /// Amounts of x and y `deposit(amount_lp, ..)` will take. The first
+    /// deposit into an empty pool sets the price, so it cannot be quoted.
+    pub fn quote_deposit(&self, amount_lp: u64) -> Result<XYAmounts> {
+        require!(amount_lp > 0, AmmError::ZeroAmount);
+        require!(
+            self.lp_supply > 0 || self.reserve_x > 0,
+            AmmError::InsufficientLiquidity
+        );
+
+        ConstantProductCurve::give_lp_take_xy(
+            self.reserve_x,
+            self.reserve_y,
+            self.lp_supply,
+            amount_lp,
+        )
+    }
+
+    /// Amounts of x and y `withdraw(.., lp_amount)` will pay out.
+    pub fn quote_withdraw(&self, lp_amount: u64) -> Result<XYAmounts> {
+        require!(lp_amount > 0, AmmError::ZeroAmount);
+
+        ConstantProductCurve::take_lp_give_xy(
+            self.reserve_x,
+            self.reserve_y,
+            self.lp_supply,
+            lp_amount,
+        )
+    }
+}
+
+/// Lowest acceptable output for `amount` given a `slippage_bps` tolerance.
+pub fn min_amount_with_slippage(amount: u64, slippage_bps: u64) -> Result<u64> {
+    require!(slippage_bps <= BPS, AmmError::InvalidFee);
+
+    Ok(mul_div(
+        amount as u128,
+        (BPS - slippage_bps) as u128,
+        BPS as u128,
+        RoundDirection::Floor,
+    )? as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amm_contract::states::Pmm;
+
+    fn pool(fees: u16, x: u64, y: u64, lp: u64) -> PoolState {
+        let config = Config {
+            mint_x: Pubkey::new_unique(),
+            mint_y: Pubkey::new_unique(),
+            fees,
+            my_bump: 255,
+            lp_bump: 255,
+            authority: None,
+            dynamic_fee: None,
+            fees_y_in: None,
+            max_referral_bps: 0,
+            maker_fee_bps: 0,
+            twamm: Default::default(),
+            batch_window: None,
+            circuit_breaker: None,
+            oracle: None,
+            pmm: None,
+        };
+        PoolState::new(config, x, y, lp)
+    }
+
+    #[test]
+    fn swap_quote_matches_program_rounding() {
+        let quote = pool(30, 100_000_000, 200_000_000, 50_000_000)
+            .quote_swap(10_000_000, true)
+            .unwrap();
+
+        assert_eq!(quote.fee, 30_000);
+        assert_eq!(quote.amount_out, 18_132_217);
+        // spot would give 19_940_000, so ~9.07% impact
+        assert_eq!(quote.price_impact_bps, 907);
+    }
+
+    #[test]
+    fn swap_quote_uses_directional_fee() {
+        let mut pool = pool(30, 100_000_000, 100_000_000, 50_000_000);
+        pool.config.fees_y_in = Some(100);
+
+        assert_eq!(pool.quote_swap(1_000_000, true).unwrap().fee, 3_000);
+        assert_eq!(pool.quote_swap(1_000_000, false).unwrap().fee, 10_000);
+    }
+
+    #[test]
+    fn pmm_quote_needs_the_oracle_price() {
+        let mut pool = pool(30, 100_000_000, 100_000_000, 50_000_000);
+        pool.config.pmm = Some(Pmm::new(1_000).unwrap());
+        assert!(pool.quote_swap(1_000_000, true).is_err());
+
+        // x at 1.5 y pays out about 1.5 y, less the fee
+        let quote = pool
+            .with_oracle_price(3 << 63)
+            .quote_swap(1_000_000, true)
+            .unwrap();
+        assert_eq!(quote.fee, 3_000);
+        assert!(quote.amount_out > 1_490_000 && quote.amount_out < 1_495_500);
+        // k = 10% slips about a tenth of what the constant product would
+        assert!(quote.price_impact_bps <= 20);
+    }
+
+    #[test]
+    fn deposit_and_withdraw_round_toward_pool() {
+        let pool = pool(30, 1_001, 2_003, 1_000);
+
+        let deposit = pool.quote_deposit(10).unwrap();
+        assert_eq!((deposit.x, deposit.y), (11, 21));
+
+        let withdraw = pool.quote_withdraw(10).unwrap();
+        assert_eq!((withdraw.x, withdraw.y), (10, 20));
+    }
+
+    #[test]
+    fn empty_pool_cannot_quote_deposit() {
+        assert!(pool(30, 0, 0, 0).quote_deposit(10).is_err());
+    }
+
+    #[test]
+    fn slippage_bound_rounds_down() {
+        assert_eq!(
+            min_amount_with_slippage(18_132_217, 50).unwrap(),
+            18_041_555
+        );
+    }
+}
@@ -0,0 +1,32 @@
+//! Random `initialize` / `deposit` / `swap` / `withdraw` sequences against the
+//! in-process program, checking the invariants in `amm_harness::state_machine`.
+//!
+//!     cargo +nightly fuzz run state_machine
+
+#![no_main]
+
+use std::cell::RefCell;
+
+use amm_harness::state_machine::{run, Scenario};
+use amm_harness::{tokio, Harness};
+use libfuzzer_sys::fuzz_target;
+
+thread_local! {
+    // starting the bank is slow, so every input gets a fresh pool in one bank
+    static BANK: RefCell<Option<(tokio::runtime::Runtime, Harness)>> = const { RefCell::new(None) };
+}
+
+fuzz_target!(|scenario: Scenario| {
+    BANK.with(|bank| {
+        let mut bank = bank.borrow_mut();
+        let (rt, h) = bank.get_or_insert_with(|| {
+            let rt = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("runtime");
+            let h = rt.block_on(Harness::new());
+            (rt, h)
+        });
+        rt.block_on(run(h, &scenario));
+    });
+});
@@ -0,0 +1,224 @@
+//! Compute units consumed by each instruction, checked against the report in
+//! `harness/compute_units.json`.
+//!
+//!     cargo bench -p amm-harness --bench compute_units
+//!
+//! Runs the compiled program from `target/deploy` when `anchor build` has
+//! produced it, and the native build otherwise. Native runs only meter the
+//! token program CPIs, so a run in a different mode than the report's fails
+//! instead of comparing. The report is recorded from the SBF build: set
+//! `UPDATE_CU_REPORT=1` after `anchor build` to rewrite it with the measured
+//! values.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use amm_client::{instructions, PoolAddresses};
+use amm_harness::{program_test_native, program_test_sbf, tokio, Harness};
+use anchor_spl::token::spl_token;
+use serde::{Deserialize, Serialize};
+use solana_sdk::signature::{keypair_from_seed, Keypair, Signer};
+use solana_system_interface::instruction as system_instruction;
+
+/// Allowed growth over the report before the run fails, in percent.
+const DEFAULT_THRESHOLD_PCT: u64 = 5;
+
+#[derive(Serialize, Deserialize)]
+struct Report {
+    mode: String,
+    threshold_pct: u64,
+    instructions: BTreeMap<String, u64>,
+}
+
+fn keypair(seed: u8) -> Keypair {
+    keypair_from_seed(&[seed; 32]).expect("keypair from seed")
+}
+
+fn report_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("compute_units.json")
+}
+
+async fn measure(h: &mut Harness) -> BTreeMap<String, u64> {
+    let mut units = BTreeMap::new();
+
+    let mint_authority = Keypair::new();
+    // fixed addresses, since PDA bump searches cost compute units
+    let mint_x = h
+        .create_mint_at(keypair(1), &mint_authority.pubkey(), 6)
+        .await;
+    let mint_y = h
+        .create_mint_at(keypair(2), &mint_authority.pubkey(), 6)
+        .await;
+    let pool = PoolAddresses::new(mint_x, mint_y);
+    let payer = h.payer();
+    let cu = h
+        .process_metered(
+            &[instructions::initialize(&pool, &payer, 30, None, None)],
+            &[],
+        )
+        .await
+        .expect("initialize");
+    units.insert("initialize".into(), cu);
+    let pool = amm_harness::Pool {
+        addresses: pool,
+        mint_authority,
+    };
+
+    let alice = h.funded_user(&pool, 1_000_000_000, 1_000_000_000).await;
+    let bob = h.funded_user(&pool, 1_000_000_000, 1_000_000_000).await;
+    let owner = alice.pubkey();
+    let addresses = &pool.addresses;
+
+    let mut run = async |name: &str, ix, signer: &Keypair| {
+        let cu = h.process_metered(&[ix], &[signer]).await.expect(name);
+        units.insert(name.to_string(), cu);
+    };
+    run(
+        "deposit_first",
+        instructions::deposit(addresses, &owner, 100_000_000, 100_000_000, 200_000_000),
+        &alice,
+    )
+    .await;
+    run(
+        "deposit",
+        instructions::deposit(addresses, &bob.pubkey(), 10_000_000, u64::MAX, u64::MAX),
+        &bob,
+    )
+    .await;
+    run(
+        "swap_x_to_y",
+        instructions::swap(addresses, &owner, 1_000_000, 0, true),
+        &alice,
+    )
+    .await;
+    run(
+        "swap_y_to_x",
+        instructions::swap(addresses, &owner, 1_000_000, 0, false),
+        &alice,
+    )
+    .await;
+    run(
+        "withdraw",
+        instructions::withdraw(addresses, &owner, 0, 0, 10_000_000),
+        &alice,
+    )
+    .await;
+
+    // a holder with only an LP account, so withdraw creates its x and y ATAs
+    let carol = keypair(3);
+    let payer = h.payer();
+    h.process(
+        &[system_instruction::transfer(
+            &payer,
+            &carol.pubkey(),
+            1_000_000_000,
+        )],
+        &[],
+    )
+    .await
+    .expect("fund carol");
+    let carol_lp = h.create_ata(&carol.pubkey(), &addresses.mint_lp).await;
+    let (_, _, alice_lp) = addresses.user_accounts(&owner);
+    let transfer = spl_token::instruction::transfer(
+        &spl_token::ID,
+        &alice_lp,
+        &carol_lp,
+        &owner,
+        &[],
+        1_000_000,
+    )
+    .expect("transfer");
+    h.process(&[transfer], &[&alice]).await.expect("move LP");
+    let cu = h
+        .process_metered(
+            &[instructions::withdraw(
+                addresses,
+                &carol.pubkey(),
+                0,
+                0,
+                1_000_000,
+            )],
+            &[&carol],
+        )
+        .await
+        .expect("withdraw_init_atas");
+    units.insert("withdraw_init_atas".into(), cu);
+
+    units
+}
+
+/// Entries of `measured` more than `threshold_pct` above `baseline`.
+fn regressions(
+    baseline: &BTreeMap<String, u64>,
+    measured: &BTreeMap<String, u64>,
+    threshold_pct: u64,
+) -> Vec<String> {
+    measured
+        .iter()
+        .filter_map(|(name, &units)| {
+            let &base = baseline.get(name)?;
+            let limit = base as u128 * (100 + threshold_pct) as u128 / 100;
+            (units as u128 > limit).then(|| format!("{name}: {units} CU, report has {base}"))
+        })
+        .collect()
+}
+
+#[tokio::main]
+async fn main() {
+    let (mode, program_test) = match program_test_sbf() {
+        Some(pt) => ("sbf", pt),
+        None => ("native", program_test_native()),
+    };
+    let mut h = Harness::start(program_test).await;
+    let measured = measure(&mut h).await;
+
+    let path = report_path();
+    let baseline: Option<Report> = std::fs::read(&path)
+        .ok()
+        .map(|data| serde_json::from_slice(&data).expect("valid compute unit report"));
+
+    println!("{:<20} {:>10} {:>10}", "instruction", "units", "report");
+    for (name, units) in &measured {
+        let base = baseline
+            .as_ref()
+            .and_then(|report| report.instructions.get(name))
+            .map_or("-".to_string(), u64::to_string);
+        println!("{name:<20} {units:>10} {base:>10}");
+    }
+
+    if std::env::var_os("UPDATE_CU_REPORT").is_some() {
+        assert_eq!(mode, "sbf", "record the report from the SBF build");
+        let report = Report {
+            mode: mode.to_string(),
+            threshold_pct: baseline
+                .as_ref()
+                .map_or(DEFAULT_THRESHOLD_PCT, |report| report.threshold_pct),
+            instructions: measured,
+        };
+        let json = serde_json::to_string_pretty(&report).expect("serialize report");
+        std::fs::write(&path, json + "\n").expect("write report");
+        println!("wrote {}", path.display());
+        return;
+    }
+
+    let Some(baseline) = baseline else {
+        panic!(
+            "no report at {}, run with UPDATE_CU_REPORT=1",
+            path.display()
+        );
+    };
+    if baseline.mode != mode {
+        panic!(
+            "report was recorded in {} mode, this run is {mode}; both must be SBF builds",
+            baseline.mode
+        );
+    }
+    let failed = regressions(&baseline.instructions, &measured, baseline.threshold_pct);
+    if !failed.is_empty() {
+        panic!(
+            "compute units regressed by more than {}%:\n{}",
+            baseline.threshold_pct,
+            failed.join("\n")
+        );
+    }
+}
@@ -0,0 +1,515 @@
+//! In-process fixtures for exercising amm-contract without a validator.
+//!
+//! Tests run the compiled program when `anchor build` has left it in
+//! `target/deploy`, so rebuild after changing the program. Without it the
+//! program runs natively inside `solana-program-test`, so `cargo test` needs
+//! neither `solana-test-validator` nor a compiled `.so`.
+
+use amm_client::{decode_config, instructions, Config, PoolAddresses};
+use anchor_lang::prelude::{AccountInfo, Clock, Pubkey, Rent};
+use anchor_lang::solana_program::entrypoint::ProgramResult;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::Event;
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::associated_token::spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+use anchor_spl::token::spl_token;
+use base64::prelude::{Engine, BASE64_STANDARD};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::account::Account;
+use solana_sdk::bpf_loader;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::{Transaction, TransactionError};
+use solana_system_interface::instruction as system_instruction;
+
+pub use solana_program_test::tokio;
+
+pub mod event_log;
+pub mod oracle;
+pub mod state_machine;
+pub mod token_metadata;
+
+fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    // anchor's entry wants the accounts to outlive the call
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    amm_contract::entry(program_id, accounts, data)
+}
+
+/// Adds the token metadata stand-in, so LP metadata can be created.
+fn add_token_metadata(pt: &mut ProgramTest) {
+    pt.add_program(
+        "mpl_token_metadata",
+        anchor_spl::metadata::ID,
+        processor!(token_metadata::process_instruction),
+    );
+}
+
+/// Adds the wrapped SOL mint, which every cluster has but program-test lacks.
+fn add_native_mint(pt: &mut ProgramTest) {
+    let mut data = vec![0; spl_token::state::Mint::LEN];
+    spl_token::state::Mint {
+        decimals: spl_token::native_mint::DECIMALS,
+        is_initialized: true,
+        ..Default::default()
+    }
+    .pack_into_slice(&mut data);
+
+    pt.add_account(
+        spl_token::native_mint::ID,
+        Account {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner: spl_token::ID,
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+}
+
+/// `ProgramTest` running the compiled program when there is one, and the
+/// native build otherwise.
+pub fn program_test() -> ProgramTest {
+    program_test_sbf().unwrap_or_else(program_test_native)
+}
+
+/// `ProgramTest` with amm-contract registered as a native program.
+pub fn program_test_native() -> ProgramTest {
+    let mut pt = ProgramTest::new(
+        "amm_contract",
+        amm_contract::ID,
+        processor!(process_instruction),
+    );
+    pt.prefer_bpf(false);
+    add_native_mint(&mut pt);
+    add_token_metadata(&mut pt);
+    pt
+}
+
+/// `ProgramTest` running the compiled `target/deploy/amm_contract.so`, if
+/// `anchor build` has produced one. Only this mode meters compute units.
+pub fn program_test_sbf() -> Option<ProgramTest> {
+    let path =
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../target/deploy/amm_contract.so");
+    let data = std::fs::read(path).ok()?;
+
+    let mut pt = ProgramTest::default();
+    // only amm-contract is compiled, the other programs run natively
+    pt.prefer_bpf(false);
+    pt.add_account(
+        amm_contract::ID,
+        Account {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner: bpf_loader::ID,
+            executable: true,
+            rent_epoch: 0,
+        },
+    );
+    add_native_mint(&mut pt);
+    add_token_metadata(&mut pt);
+    Some(pt)
+}
+
+/// A pool created through the harness, along with its mint authority.
+pub struct Pool {
+    pub addresses: PoolAddresses,
+    pub mint_authority: Keypair,
+}
+
+pub struct Harness {
+    pub ctx: ProgramTestContext,
+}
+
+impl Harness {
+    pub async fn new() -> Self {
+        Self::start(program_test()).await
+    }
+
+    pub async fn start(program_test: ProgramTest) -> Self {
+        let ctx = program_test.start_with_context().await;
+        event_log::install();
+        Self { ctx }
+    }
+
+    pub fn payer(&self) -> Pubkey {
+        self.ctx.payer.pubkey()
+    }
+
+    async fn transaction(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Transaction {
+        let blockhash = self
+            .ctx
+            .get_new_latest_blockhash()
+            .await
+            .expect("blockhash");
+        let mut all_signers = vec![&self.ctx.payer];
+        all_signers.extend_from_slice(signers);
+
+        Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.ctx.payer.pubkey()),
+            &all_signers,
+            blockhash,
+        )
+    }
+
+    /// Signs `instructions` with the payer and `signers` and executes them.
+    pub async fn process(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), BanksClientError> {
+        let tx = self.transaction(instructions, signers).await;
+        self.ctx.banks_client.process_transaction(tx).await
+    }
+
+    /// Like `process`, returning the compute units the transaction consumed.
+    pub async fn process_metered(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<u64, BanksClientError> {
+        let tx = self.transaction(instructions, signers).await;
+        let outcome = self
+            .ctx
+            .banks_client
+            .process_transaction_with_metadata(tx)
+            .await?;
+        outcome.result?;
+        Ok(outcome
+            .metadata
+            .map(|metadata| metadata.compute_units_consumed)
+            .unwrap_or_default())
+    }
+
+    /// Like `process`, returning the `E` events the transaction emitted.
+    pub async fn process_events<E: Event>(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<Vec<E>, BanksClientError> {
+        let tx = self.transaction(instructions, signers).await;
+        let outcome = self
+            .ctx
+            .banks_client
+            .process_transaction_with_metadata(tx)
+            .await?;
+        outcome.result?;
+        let logs = outcome
+            .metadata
+            .map(|metadata| metadata.log_messages)
+            .unwrap_or_default();
+
+        Ok(logs
+            .iter()
+            .filter_map(|log| {
+                // as logged by SBF builds, or forwarded by `event_log`
+                log.strip_prefix(event_log::DATA_PREFIX).or_else(|| {
+                    log.strip_prefix("Program log: ")?
+                        .strip_prefix(event_log::DATA_PREFIX)
+                })
+            })
+            .filter_map(|data| BASE64_STANDARD.decode(data).ok())
+            .filter_map(|data| {
+                let event = data.strip_prefix(E::DISCRIMINATOR)?;
+                E::deserialize(&mut &event[..]).ok()
+            })
+            .collect())
+    }
+
+    /// Moves the clock sysvar to `unix_timestamp`, keeping the slot.
+    pub async fn set_unix_timestamp(&mut self, unix_timestamp: i64) {
+        let mut clock: Clock = self
+            .ctx
+            .banks_client
+            .get_sysvar()
+            .await
+            .expect("clock sysvar");
+        clock.unix_timestamp = unix_timestamp;
+        self.ctx.set_sysvar(&clock);
+    }
+
+    /// Creates a keypair holding `lamports` SOL.
+    pub async fn create_user(&mut self, lamports: u64) -> Keypair {
+        let user = Keypair::new();
+        let payer = self.payer();
+        self.process(
+            &[system_instruction::transfer(
+                &payer,
+                &user.pubkey(),
+                lamports,
+            )],
+            &[],
+        )
+        .await
+        .expect("fund user");
+        user
+    }
+
+    /// Creates an SPL token mint controlled by `authority`.
+    pub async fn create_mint(&mut self, authority: &Pubkey, decimals: u8) -> Pubkey {
+        self.create_mint_at(Keypair::new(), authority, decimals)
+            .await
+    }
+
+    /// Like `create_mint`, at the address of the given keypair.
+    pub async fn create_mint_at(
+        &mut self,
+        mint: Keypair,
+        authority: &Pubkey,
+        decimals: u8,
+    ) -> Pubkey {
+        let payer = self.payer();
+        let rent = self
+            .ctx
+            .banks_client
+            .get_rent()
+            .await
+            .expect("rent")
+            .minimum_balance(spl_token::state::Mint::LEN);
+
+        self.process(
+            &[
+                system_instruction::create_account(
+                    &payer,
+                    &mint.pubkey(),
+                    rent,
+                    spl_token::state::Mint::LEN as u64,
+                    &spl_token::ID,
+                ),
+                spl_token::instruction::initialize_mint2(
+                    &spl_token::ID,
+                    &mint.pubkey(),
+                    authority,
+                    None,
+                    decimals,
+                )
+                .expect("initialize mint"),
+            ],
+            &[&mint],
+        )
+        .await
+        .expect("create mint");
+        mint.pubkey()
+    }
+
+    /// Creates `owner`'s associated token account for `mint` if missing.
+    pub async fn create_ata(&mut self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        let payer = self.payer();
+        self.process(
+            &[create_associated_token_account_idempotent(
+                &payer,
+                owner,
+                mint,
+                &spl_token::ID,
+            )],
+            &[],
+        )
+        .await
+        .expect("create ata");
+        get_associated_token_address(owner, mint)
+    }
+
+    /// Mints `amount` of `mint` into `owner`'s associated token account.
+    pub async fn mint_to(
+        &mut self,
+        mint: &Pubkey,
+        mint_authority: &Keypair,
+        owner: &Pubkey,
+        amount: u64,
+    ) -> Pubkey {
+        let ata = self.create_ata(owner, mint).await;
+        self.process(
+            &[spl_token::instruction::mint_to(
+                &spl_token::ID,
+                mint,
+                &ata,
+                &mint_authority.pubkey(),
+                &[],
+                amount,
+            )
+            .expect("mint to")],
+            &[mint_authority],
+        )
+        .await
+        .expect("mint tokens");
+        ata
+    }
+
+    /// Creates two 6-decimal mints and initializes a pool for them.
+    pub async fn create_pool(&mut self, fees: u16, authority: Option<Pubkey>) -> Pool {
+        let mint_authority = Keypair::new();
+        let mint_x = self.create_mint(&mint_authority.pubkey(), 6).await;
+        let mint_y = self.create_mint(&mint_authority.pubkey(), 6).await;
+        let addresses = self.initialize_pool(mint_x, mint_y, fees, authority).await;
+
+        Pool {
+            addresses,
+            mint_authority,
+        }
+    }
+
+    /// Initializes a pool for two existing mints.
+    pub async fn initialize_pool(
+        &mut self,
+        mint_x: Pubkey,
+        mint_y: Pubkey,
+        fees: u16,
+        authority: Option<Pubkey>,
+    ) -> PoolAddresses {
+        let addresses = PoolAddresses::new(mint_x, mint_y);
+        let payer = self.payer();
+        self.process(
+            &[instructions::initialize(
+                &addresses, &payer, fees, authority, None,
+            )],
+            &[],
+        )
+        .await
+        .expect("initialize pool");
+        addresses
+    }
+
+    /// Creates a user holding `amount_x` / `amount_y` of the pool's tokens
+    /// and an empty LP account.
+    pub async fn funded_user(&mut self, pool: &Pool, amount_x: u64, amount_y: u64) -> Keypair {
+        let user = self.create_user(1_000_000_000).await;
+        let owner = user.pubkey();
+        let addresses = pool.addresses;
+
+        self.mint_to(&addresses.mint_x, &pool.mint_authority, &owner, amount_x)
+            .await;
+        self.mint_to(&addresses.mint_y, &pool.mint_authority, &owner, amount_y)
+            .await;
+        self.create_ata(&owner, &addresses.mint_lp).await;
+        user
+    }
+
+    pub async fn deposit(
+        &mut self,
+        pool: &PoolAddresses,
+        user: &Keypair,
+        amount_lp: u64,
+        max_x: u64,
+        max_y: u64,
+    ) -> Result<(), BanksClientError> {
+        let ix = instructions::deposit(pool, &user.pubkey(), amount_lp, max_x, max_y);
+        self.process(&[ix], &[user]).await
+    }
+
+    pub async fn swap(
+        &mut self,
+        pool: &PoolAddresses,
+        user: &Keypair,
+        amount_in: u64,
+        min_out: u64,
+        is_x_in: bool,
+    ) -> Result<(), BanksClientError> {
+        let ix = instructions::swap(pool, &user.pubkey(), amount_in, min_out, is_x_in);
+        self.process(&[ix], &[user]).await
+    }
+
+    pub async fn withdraw(
+        &mut self,
+        pool: &PoolAddresses,
+        user: &Keypair,
+        min_x: u64,
+        min_y: u64,
+        lp_amount: u64,
+    ) -> Result<(), BanksClientError> {
+        let ix = instructions::withdraw(pool, &user.pubkey(), min_x, min_y, lp_amount);
+        self.process(&[ix], &[user]).await
+    }
+
+    /// Balances of `owner`'s x, y and LP associated token accounts.
+    pub async fn user_balances(&mut self, pool: &PoolAddresses, owner: &Pubkey) -> (u64, u64, u64) {
+        let (x, y, lp) = pool.user_accounts(owner);
+        (
+            self.token_balance(&x).await,
+            self.token_balance(&y).await,
+            self.token_balance(&lp).await,
+        )
+    }
+
+    /// Vault balances and LP supply of `pool`.
+    pub async fn reserves(&mut self, pool: &PoolAddresses) -> (u64, u64, u64) {
+        (
+            self.token_balance(&pool.vault_x).await,
+            self.token_balance(&pool.vault_y).await,
+            self.mint_supply(&pool.mint_lp).await,
+        )
+    }
+
+    pub async fn lamports(&mut self, address: &Pubkey) -> u64 {
+        self.ctx
+            .banks_client
+            .get_balance(*address)
+            .await
+            .expect("get balance")
+    }
+
+    pub async fn token_balance(&mut self, address: &Pubkey) -> u64 {
+        let account = self
+            .ctx
+            .banks_client
+            .get_account(*address)
+            .await
+            .expect("get account")
+            .expect("token account exists");
+        spl_token::state::Account::unpack(&account.data)
+            .expect("token account")
+            .amount
+    }
+
+    pub async fn mint_supply(&mut self, mint: &Pubkey) -> u64 {
+        self.mint(mint).await.supply
+    }
+
+    pub async fn mint(&mut self, mint: &Pubkey) -> spl_token::state::Mint {
+        let account = self
+            .ctx
+            .banks_client
+            .get_account(*mint)
+            .await
+            .expect("get account")
+            .expect("mint exists");
+        spl_token::state::Mint::unpack(&account.data).expect("mint")
+    }
+
+    pub async fn config(&mut self, pool: &PoolAddresses) -> Config {
+        let account = self
+            .ctx
+            .banks_client
+            .get_account(pool.config)
+            .await
+            .expect("get account")
+            .expect("config exists");
+        decode_config(&account.data).expect("config")
+    }
+}
+
+/// Custom program error code carried by a failed transaction, if any.
+pub fn custom_error(result: Result<(), BanksClientError>) -> Option<u32> {
+    match result {
+        Err(BanksClientError::TransactionError(TransactionError::InstructionError(
+            _,
+            anchor_lang::solana_program::instruction::InstructionError::Custom(code),
+        ))) => Some(code),
+        Err(BanksClientError::SimulationError {
+            err:
+                TransactionError::InstructionError(
+                    _,
+                    anchor_lang::solana_program::instruction::InstructionError::Custom(code),
+                ),
+            ..
+        }) => Some(code),
+        _ => None,
+    }
+}
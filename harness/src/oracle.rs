@@ -0,0 +1,36 @@
+//! Mock price accounts laid out as the Pyth receiver and Switchboard
+//! On-Demand write them, for pools with an oracle guard.
+
+use amm_contract::oracle::*;
+use anchor_lang::prelude::Pubkey;
+use solana_sdk::account::Account;
+
+fn owned_by(owner: Pubkey, data: Vec<u8>) -> Account {
+    Account {
+        lamports: 1_000_000_000,
+        data,
+        owner,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+/// A fully verified Pyth `PriceUpdateV2` of `price * 10^exponent`.
+pub fn pyth_price_update(price: i64, exponent: i32, publish_time: i64) -> Account {
+    let mut data = vec![0; PYTH_PRICE_UPDATE_LEN];
+    data[..8].copy_from_slice(&PYTH_PRICE_UPDATE_DISCRIMINATOR);
+    data[40] = PYTH_FULL_VERIFICATION;
+    data[PYTH_PRICE_OFFSET..][..8].copy_from_slice(&price.to_le_bytes());
+    data[PYTH_EXPONENT_OFFSET..][..4].copy_from_slice(&exponent.to_le_bytes());
+    data[PYTH_PUBLISH_TIME_OFFSET..][..8].copy_from_slice(&publish_time.to_le_bytes());
+    owned_by(PYTH_RECEIVER_ID, data)
+}
+
+/// A Switchboard pull feed whose latest result is `value`, with 18 decimals.
+pub fn switchboard_feed(value: i128, last_update: i64) -> Account {
+    let mut data = vec![0; SWITCHBOARD_FEED_LEN];
+    data[..8].copy_from_slice(&SWITCHBOARD_FEED_DISCRIMINATOR);
+    data[SWITCHBOARD_TIMESTAMP_OFFSET..][..8].copy_from_slice(&last_update.to_le_bytes());
+    data[SWITCHBOARD_VALUE_OFFSET..][..16].copy_from_slice(&value.to_le_bytes());
+    owned_by(SWITCHBOARD_ON_DEMAND_ID, data)
+}
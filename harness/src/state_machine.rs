@@ -0,0 +1,337 @@
+//! Random sequences of pool instructions with invariant checks after every
+//! step. Driven by the `fuzz/` target and by a seeded regression test.
+
+use amm_client::{PoolAddresses, PoolState};
+use arbitrary::Arbitrary;
+use solana_sdk::signature::{Keypair, Signer};
+
+use crate::{Harness, Pool};
+
+/// Number of users taking part in a scenario.
+pub const USERS: usize = 3;
+/// Starting x and y balance of every user.
+pub const USER_BALANCE: u64 = 1_000_000_000_000;
+
+#[derive(Arbitrary, Clone, Debug)]
+pub enum Action {
+    /// Mint `lp` LP, allowing `slack_*` extra over the quoted amounts.
+    Deposit {
+        user: u8,
+        lp: u64,
+        slack_x: u16,
+        slack_y: u16,
+    },
+    Swap {
+        user: u8,
+        amount_in: u64,
+        is_x_in: bool,
+    },
+    Withdraw {
+        user: u8,
+        lp: u64,
+    },
+}
+
+#[derive(Arbitrary, Clone, Debug)]
+pub struct Scenario {
+    pub fees: u16,
+    pub seed_x: u32,
+    pub seed_y: u32,
+    pub seed_lp: u32,
+    pub actions: Vec<Action>,
+}
+
+/// Bookkeeping the program itself does not keep.
+struct Model {
+    users: Vec<Keypair>,
+    lp_minted: u64,
+    lp_burned: u64,
+    /// x and y each user has paid into the pool, net of what it paid out.
+    paid_in: Vec<(i128, i128)>,
+}
+
+impl Model {
+    fn record(&mut self, user: usize, before: &Snapshot, after: &Snapshot) {
+        let paid_in = &mut self.paid_in[user];
+        paid_in.0 += before.user.0 as i128 - after.user.0 as i128;
+        paid_in.1 += before.user.1 as i128 - after.user.1 as i128;
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Snapshot {
+    reserve_x: u64,
+    reserve_y: u64,
+    lp_supply: u64,
+    user: (u64, u64, u64),
+}
+
+async fn snapshot(h: &mut Harness, pool: &PoolAddresses, user: &Keypair) -> Snapshot {
+    let (reserve_x, reserve_y, lp_supply) = h.reserves(pool).await;
+    Snapshot {
+        reserve_x,
+        reserve_y,
+        lp_supply,
+        user: h.user_balances(pool, &user.pubkey()).await,
+    }
+}
+
+/// `a / b >= c / d` for non-negative fractions, with `b`, `d` > 0.
+fn ratio_not_below(a: u64, b: u64, c: u64, d: u64) -> bool {
+    a as u128 * d as u128 >= c as u128 * b as u128
+}
+
+/// Runs `scenario` on a fresh pool inside `h`, panicking on the first
+/// broken invariant.
+pub async fn run(h: &mut Harness, scenario: &Scenario) {
+    let pool = h.create_pool(scenario.fees % 10_001, None).await;
+    let mut model = Model {
+        users: Vec::with_capacity(USERS),
+        lp_minted: 0,
+        lp_burned: 0,
+        paid_in: vec![(0, 0); USERS],
+    };
+    for _ in 0..USERS {
+        let user = h.funded_user(&pool, USER_BALANCE, USER_BALANCE).await;
+        model.users.push(user);
+    }
+
+    let seed_lp = scenario.seed_lp.max(1) as u64;
+    let before = snapshot(h, &pool.addresses, &model.users[0]).await;
+    h.deposit(
+        &pool.addresses,
+        &model.users[0],
+        seed_lp,
+        scenario.seed_x.max(1) as u64,
+        scenario.seed_y.max(1) as u64,
+    )
+    .await
+    .expect("seed deposit");
+    let after = snapshot(h, &pool.addresses, &model.users[0]).await;
+    model.lp_minted += seed_lp;
+    model.record(0, &before, &after);
+
+    for action in &scenario.actions {
+        step(h, &pool, &mut model, action).await;
+        check_pool(h, &pool, &model).await;
+    }
+}
+
+async fn step(h: &mut Harness, pool: &Pool, model: &mut Model, action: &Action) {
+    let addresses = &pool.addresses;
+    let config = h.config(addresses).await;
+
+    match *action {
+        Action::Deposit {
+            user,
+            lp,
+            slack_x,
+            slack_y,
+        } => {
+            let index = user as usize % USERS;
+            let user = &model.users[index];
+            let before = snapshot(h, addresses, user).await;
+            let state =
+                PoolState::new(config, before.reserve_x, before.reserve_y, before.lp_supply);
+            let lp = lp % (before.lp_supply.max(1_000_000).saturating_mul(2) + 1);
+            // an empty pool takes the maximums as they are
+            let first = before.lp_supply == 0 && before.reserve_x == 0;
+            let quote = match first {
+                true => Some((slack_x as u64, slack_y as u64)),
+                false => state.quote_deposit(lp).ok().map(|q| (q.x, q.y)),
+            };
+            let (max_x, max_y) = match (first, quote) {
+                (false, Some((x, y))) => (
+                    x.saturating_add(slack_x as u64),
+                    y.saturating_add(slack_y as u64),
+                ),
+                _ => (slack_x as u64, slack_y as u64),
+            };
+
+            let result = h.deposit(addresses, user, lp, max_x, max_y).await;
+            let after = snapshot(h, addresses, user).await;
+
+            let Some((x, y)) = quote.filter(|_| result.is_ok()) else {
+                assert_eq!(before, after, "failed deposit changed state");
+                return;
+            };
+            model.lp_minted += lp;
+            model.record(index, &before, &after);
+
+            // paid exactly the quote and got exactly `lp`
+            assert_eq!(after.user.0, before.user.0 - x);
+            assert_eq!(after.user.1, before.user.1 - y);
+            assert_eq!(after.user.2, before.user.2 + lp);
+            if first {
+                return;
+            }
+            // value per LP share must not drop
+            assert!(ratio_not_below(
+                after.reserve_x,
+                after.lp_supply,
+                before.reserve_x,
+                before.lp_supply
+            ));
+            assert!(ratio_not_below(
+                after.reserve_y,
+                after.lp_supply,
+                before.reserve_y,
+                before.lp_supply
+            ));
+        }
+        Action::Swap {
+            user,
+            amount_in,
+            is_x_in,
+        } => {
+            let index = user as usize % USERS;
+            let user = &model.users[index];
+            let before = snapshot(h, addresses, user).await;
+            let state =
+                PoolState::new(config, before.reserve_x, before.reserve_y, before.lp_supply);
+            let held = match is_x_in {
+                true => before.user.0,
+                false => before.user.1,
+            };
+            let amount_in = amount_in % (held + 1);
+            let quote = state.quote_swap(amount_in, is_x_in).ok();
+
+            let result = h.swap(addresses, user, amount_in, 0, is_x_in).await;
+            let after = snapshot(h, addresses, user).await;
+
+            let Some(quote) = quote.filter(|_| result.is_ok()) else {
+                assert_eq!(before, after, "failed swap changed state");
+                return;
+            };
+
+            // received exactly the quote
+            let (paid, received) = match is_x_in {
+                true => (before.user.0 - after.user.0, after.user.1 - before.user.1),
+                false => (before.user.1 - after.user.1, after.user.0 - before.user.0),
+            };
+            model.record(index, &before, &after);
+            assert_eq!(paid, amount_in);
+            assert_eq!(received, quote.amount_out);
+            // k never decreases across a swap
+            assert!(
+                after.reserve_x as u128 * after.reserve_y as u128
+                    >= before.reserve_x as u128 * before.reserve_y as u128,
+                "k decreased"
+            );
+        }
+        Action::Withdraw { user, lp } => {
+            let index = user as usize % USERS;
+            let user = &model.users[index];
+            let before = snapshot(h, addresses, user).await;
+            let state =
+                PoolState::new(config, before.reserve_x, before.reserve_y, before.lp_supply);
+            let lp = lp % (before.user.2 + 1);
+            let quote = state.quote_withdraw(lp).ok();
+
+            let result = h.withdraw(addresses, user, 0, 0, lp).await;
+            let after = snapshot(h, addresses, user).await;
+
+            let Some(quote) = quote.filter(|_| result.is_ok()) else {
+                assert_eq!(before, after, "failed withdraw changed state");
+                return;
+            };
+            model.lp_burned += lp;
+            model.record(index, &before, &after);
+
+            assert_eq!(after.user.0, before.user.0 + quote.x);
+            assert_eq!(after.user.1, before.user.1 + quote.y);
+            assert_eq!(after.user.2, before.user.2 - lp);
+            if after.lp_supply > 0 {
+                assert!(ratio_not_below(
+                    after.reserve_x,
+                    after.lp_supply,
+                    before.reserve_x,
+                    before.lp_supply
+                ));
+                assert!(ratio_not_below(
+                    after.reserve_y,
+                    after.lp_supply,
+                    before.reserve_y,
+                    before.lp_supply
+                ));
+            }
+        }
+    }
+}
+
+/// Invariants that hold after any step, whether it succeeded or not.
+async fn check_pool(h: &mut Harness, pool: &Pool, model: &Model) {
+    let addresses = &pool.addresses;
+    let config = h.config(addresses).await;
+    let (reserve_x, reserve_y, lp_supply) = h.reserves(addresses).await;
+
+    assert_eq!(
+        lp_supply,
+        model.lp_minted - model.lp_burned,
+        "LP supply does not match minted minus burned"
+    );
+
+    // every token in the vaults was paid in by one of the users
+    let paid_x: i128 = model.paid_in.iter().map(|paid| paid.0).sum();
+    let paid_y: i128 = model.paid_in.iter().map(|paid| paid.1).sum();
+    assert_eq!(
+        (paid_x, paid_y),
+        (reserve_x as i128, reserve_y as i128),
+        "vaults do not match what users paid in"
+    );
+
+    // the vaults cover every holder's claim
+    let state = PoolState::new(config, reserve_x, reserve_y, lp_supply);
+    let (mut claim_x, mut claim_y, mut held) = (0u64, 0u64, 0u64);
+    let mut claims = Vec::with_capacity(USERS);
+    for user in &model.users {
+        let (_, _, lp) = h.user_balances(addresses, &user.pubkey()).await;
+        held += lp;
+        let claim = match lp {
+            0 => (0, 0),
+            _ => {
+                let claim = state.quote_withdraw(lp).expect("holder can withdraw");
+                (claim.x, claim.y)
+            }
+        };
+        claim_x += claim.0;
+        claim_y += claim.1;
+        claims.push(claim);
+    }
+    assert_eq!(held, lp_supply, "LP held outside the scenario's users");
+    assert!(
+        claim_x <= reserve_x && claim_y <= reserve_y,
+        "claims exceed vaults"
+    );
+
+    // valued at the pool price, a user can only come out ahead by what the
+    // others lost: what each could walk away with, against what it paid in
+    if reserve_x == 0 || reserve_y == 0 {
+        return;
+    }
+    // y per x is reserve_y / reserve_x, so values are scaled by reserve_x
+    let value = |x: i128, y: i128| x * reserve_y as i128 + y * reserve_x as i128;
+    let profits = claims
+        .iter()
+        .zip(&model.paid_in)
+        .map(|(claim, paid)| value(claim.0 as i128 - paid.0, claim.1 as i128 - paid.1))
+        .collect::<Vec<_>>();
+    check_profits(&profits);
+}
+
+/// Panics unless every user's profit is covered by the losses of the
+/// others, profits and losses being signed values at the pool price.
+pub fn check_profits(profits: &[i128]) {
+    for (user, profit) in profits.iter().enumerate() {
+        let others_lost: i128 = profits
+            .iter()
+            .enumerate()
+            .filter(|&(other, loss)| other != user && *loss < 0)
+            .map(|(_, loss)| -loss)
+            .sum();
+        assert!(
+            *profit <= others_lost,
+            "user {user} extracted more than the others lost"
+        );
+    }
+}
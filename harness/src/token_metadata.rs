@@ -0,0 +1,99 @@
+//! Stand-in for the Metaplex token metadata program, which program-test does
+//! not ship. Handles `CreateMetadataAccountV3` only, with the checks
+//! amm-contract relies on: the metadata PDA and the mint authority's
+//! signature.
+
+use anchor_lang::prelude::{AccountInfo, ProgramError, Pubkey, Rent};
+use anchor_lang::solana_program::entrypoint::ProgramResult;
+use anchor_lang::solana_program::program::invoke_signed;
+use anchor_lang::solana_program::program_option::COption;
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::solana_program::sysvar::Sysvar;
+use anchor_spl::metadata::mpl_token_metadata::accounts::Metadata;
+use anchor_spl::metadata::mpl_token_metadata::instructions::CreateMetadataAccountV3InstructionArgs;
+use anchor_spl::metadata::mpl_token_metadata::types::{Key, TokenStandard};
+use anchor_spl::token::spl_token;
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_system_interface::instruction as system_instruction;
+
+const CREATE_METADATA_ACCOUNT_V3: u8 = 33;
+
+/// Metadata account contents for `mint` with the given name and symbol.
+pub fn metadata(mint: &Pubkey, update_authority: &Pubkey, name: &str, symbol: &str) -> Metadata {
+    Metadata {
+        key: Key::MetadataV1,
+        update_authority: *update_authority,
+        mint: *mint,
+        name: name.to_string(),
+        symbol: symbol.to_string(),
+        uri: String::new(),
+        seller_fee_basis_points: 0,
+        creators: None,
+        primary_sale_happened: false,
+        is_mutable: true,
+        edition_nonce: None,
+        token_standard: Some(TokenStandard::Fungible),
+        collection: None,
+        uses: None,
+        collection_details: None,
+        programmable_config: None,
+    }
+}
+
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let Some((&CREATE_METADATA_ACCOUNT_V3, args)) = data.split_first() else {
+        return Err(ProgramError::InvalidInstructionData);
+    };
+    let args = CreateMetadataAccountV3InstructionArgs::try_from_slice(args)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    let [metadata_account, mint, mint_authority, payer, update_authority, system_program, ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let (address, bump) = Metadata::find_pda(mint.key);
+    if address != *metadata_account.key {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    let mint_state = spl_token::state::Mint::unpack(&mint.try_borrow_data()?)?;
+    if !mint_authority.is_signer || mint_state.mint_authority != COption::Some(*mint_authority.key)
+    {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let mut contents = metadata(
+        mint.key,
+        update_authority.key,
+        &args.data.name,
+        &args.data.symbol,
+    );
+    contents.is_mutable = args.is_mutable;
+    let contents = contents
+        .try_to_vec()
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            metadata_account.key,
+            Rent::get()?.minimum_balance(contents.len()),
+            contents.len() as u64,
+            program_id,
+        ),
+        &[
+            payer.clone(),
+            metadata_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[b"metadata", program_id.as_ref(), mint.key.as_ref(), &[bump]]],
+    )?;
+    metadata_account
+        .try_borrow_mut_data()?
+        .copy_from_slice(&contents);
+    Ok(())
+}
@@ -0,0 +1,241 @@
+use amm_client::{batch_address, decode_batch, instructions, Batch};
+use amm_contract::error::AmmError;
+use amm_contract::events::BatchSettleEvent;
+use amm_harness::{custom_error, tokio, Harness, Pool};
+use solana_sdk::signature::{Keypair, Signer};
+
+/// On a window boundary.
+const NOW: i64 = 1_700_002_800;
+const WINDOW: i64 = 60;
+const BATCH_ID: u64 = (NOW / WINDOW) as u64;
+
+/// A seeded 30 bps pool at 1 y per x, batching swaps every minute.
+async fn batched_pool(h: &mut Harness) -> Pool {
+    h.set_unix_timestamp(NOW).await;
+    let authority = h.create_user(1_000_000_000).await;
+    let pool = h.create_pool(30, Some(authority.pubkey())).await;
+    let lp = h.funded_user(&pool, 100_000_000, 100_000_000).await;
+    h.deposit(&pool.addresses, &lp, 100_000_000, 100_000_000, 100_000_000)
+        .await
+        .unwrap();
+    let ix = instructions::set_batch_window(&pool.addresses, &authority.pubkey(), Some(WINDOW));
+    h.process(&[ix], &[&authority]).await.unwrap();
+    pool
+}
+
+async fn batch(h: &mut Harness, pool: &Pool) -> Option<Batch> {
+    let (address, _) = batch_address(&pool.addresses.config, BATCH_ID);
+    let account = h.ctx.banks_client.get_account(address).await.unwrap()?;
+    Some(decode_batch(&account.data).unwrap())
+}
+
+async fn submit(
+    h: &mut Harness,
+    pool: &Pool,
+    owner: &Keypair,
+    amount_in: u64,
+    min_out: u64,
+    is_x_in: bool,
+) {
+    let ix = instructions::submit_swap_intent(
+        &pool.addresses,
+        &owner.pubkey(),
+        BATCH_ID,
+        amount_in,
+        min_out,
+        is_x_in,
+    );
+    h.process(&[ix], &[owner]).await.unwrap();
+}
+
+/// Settles the batch once its window has closed.
+async fn settle(h: &mut Harness, pool: &Pool) -> BatchSettleEvent {
+    h.set_unix_timestamp(NOW + WINDOW).await;
+    let ix = instructions::settle_batch(&pool.addresses, BATCH_ID);
+    let mut events: Vec<BatchSettleEvent> = h.process_events(&[ix], &[]).await.unwrap();
+    events.remove(0)
+}
+
+async fn claim(h: &mut Harness, pool: &Pool, owner: &Keypair) {
+    let batch = batch(h, pool).await.unwrap();
+    let ix = instructions::claim_swap_intents(&pool.addresses, &owner.pubkey(), &batch);
+    h.process(&[ix], &[owner]).await.unwrap();
+}
+
+#[tokio::test]
+async fn batch_settles_both_sides_at_one_price() {
+    let mut h = Harness::new().await;
+    let pool = batched_pool(&mut h).await;
+    let addresses = pool.addresses;
+    let seller_x = h.funded_user(&pool, 1_000_000, 0).await;
+    let seller_y = h.funded_user(&pool, 0, 500_000).await;
+
+    // swaps no longer execute right away
+    let result = h.swap(&addresses, &seller_x, 1_000_000, 0, true).await;
+    assert_eq!(
+        custom_error(result),
+        Some(AmmError::BatchAuctionOnly.into())
+    );
+
+    submit(&mut h, &pool, &seller_x, 1_000_000, 0, true).await;
+    submit(&mut h, &pool, &seller_y, 500_000, 0, false).await;
+
+    let ix = instructions::settle_batch(&addresses, BATCH_ID);
+    let result = h.process(&[ix], &[]).await;
+    assert_eq!(custom_error(result), Some(AmmError::BatchNotClosed.into()));
+
+    let event = settle(&mut h, &pool).await;
+    assert_eq!((event.amount_x_in, event.amount_y_in), (1_000_000, 500_000));
+
+    // 30 bps off each input, then (y + y_in) / (x + x_in) y per x
+    let (x, y) = (100_000_000u128 + 997_000, 100_000_000u128 + 498_500);
+    let expected_y = (997_000 * y / x) as u64;
+    let expected_x = (498_500 * x / y) as u64;
+    assert_eq!(
+        (event.amount_x_out, event.amount_y_out),
+        (expected_x, expected_y)
+    );
+
+    claim(&mut h, &pool, &seller_x).await;
+    claim(&mut h, &pool, &seller_y).await;
+    assert_eq!(
+        h.user_balances(&addresses, &seller_x.pubkey()).await.1,
+        expected_y
+    );
+    assert_eq!(
+        h.user_balances(&addresses, &seller_y.pubkey()).await.0,
+        expected_x
+    );
+
+    // the last claim closes the batch and leaves the pool its k
+    assert!(batch(&mut h, &pool).await.is_none());
+    let (vx, vy, _) = h.reserves(&addresses).await;
+    assert!(vx as u128 * vy as u128 >= 100_000_000u128 * 100_000_000);
+}
+
+#[tokio::test]
+async fn order_within_the_batch_does_not_matter() {
+    let mut h = Harness::new().await;
+    let pool = batched_pool(&mut h).await;
+    let attacker = h.funded_user(&pool, 10_000_000, 0).await;
+    let victim = h.funded_user(&pool, 1_000_000, 0).await;
+
+    // a front-run in the same batch trades at the victim's price
+    submit(&mut h, &pool, &attacker, 10_000_000, 0, true).await;
+    submit(&mut h, &pool, &victim, 1_000_000, 0, true).await;
+    settle(&mut h, &pool).await;
+
+    let intents = batch(&mut h, &pool).await.unwrap().intents;
+    let (attacker_out, victim_out) = (intents[0].amount_out, intents[1].amount_out);
+    assert_eq!(attacker_out / 10, victim_out);
+}
+
+#[tokio::test]
+async fn intent_missing_its_limit_is_refunded() {
+    let mut h = Harness::new().await;
+    let pool = batched_pool(&mut h).await;
+    let addresses = pool.addresses;
+    let whale = h.funded_user(&pool, 20_000_000, 0).await;
+    let trader = h.funded_user(&pool, 1_000_000, 0).await;
+
+    submit(&mut h, &pool, &whale, 20_000_000, 0, true).await;
+    submit(&mut h, &pool, &trader, 1_000_000, 900_000, true).await;
+    let event = settle(&mut h, &pool).await;
+    assert_eq!(event.amount_x_in, 20_000_000);
+
+    // less the fee, which stays with the pool
+    claim(&mut h, &pool, &trader).await;
+    assert_eq!(
+        h.user_balances(&addresses, &trader.pubkey()).await,
+        (997_000, 0, 0)
+    );
+    // the batch stays open for the whale's claim
+    assert!(batch(&mut h, &pool).await.is_some());
+    claim(&mut h, &pool, &whale).await;
+    assert!(batch(&mut h, &pool).await.is_none());
+}
+
+#[tokio::test]
+async fn intents_go_to_the_current_window() {
+    let mut h = Harness::new().await;
+    let pool = batched_pool(&mut h).await;
+    let owner = h.funded_user(&pool, 1_000_000, 0).await;
+
+    let ix = instructions::submit_swap_intent(
+        &pool.addresses,
+        &owner.pubkey(),
+        BATCH_ID - 1,
+        1_000_000,
+        0,
+        true,
+    );
+    let result = h.process(&[ix], &[&owner]).await;
+    assert_eq!(custom_error(result), Some(AmmError::BatchClosed.into()));
+
+    // pools swap right away unless they opt in
+    let plain = h.create_pool(30, None).await;
+    let owner = h.funded_user(&plain, 1_000_000, 0).await;
+    let ix = instructions::submit_swap_intent(
+        &plain.addresses,
+        &owner.pubkey(),
+        BATCH_ID,
+        1_000_000,
+        0,
+        true,
+    );
+    let result = h.process(&[ix], &[&owner]).await;
+    assert_eq!(
+        custom_error(result),
+        Some(AmmError::BatchAuctionDisabled.into())
+    );
+}
+
+#[tokio::test]
+async fn honest_intents_get_into_a_spammed_window() {
+    let mut h = Harness::new().await;
+    let pool = batched_pool(&mut h).await;
+    let addresses = pool.addresses;
+    // 10 bps of the 100_000_000 x reserve
+    let min = 100_000;
+    let griefer = h.funded_user(&pool, 2 * min, 0).await;
+
+    let spam = |amount_in| {
+        instructions::submit_swap_intent(
+            &addresses,
+            &griefer.pubkey(),
+            BATCH_ID,
+            amount_in,
+            u64::MAX,
+            true,
+        )
+    };
+    let result = h.process(&[spam(min - 1)], &[&griefer]).await;
+    assert_eq!(custom_error(result), Some(AmmError::IntentTooSmall.into()));
+    h.process(&[spam(min)], &[&griefer]).await.unwrap();
+    let result = h.process(&[spam(min)], &[&griefer]).await;
+    assert_eq!(
+        custom_error(result),
+        Some(AmmError::IntentAlreadySubmitted.into())
+    );
+
+    let trader = h.funded_user(&pool, 1_000_000, 0).await;
+    submit(&mut h, &pool, &trader, 1_000_000, 0, true).await;
+    let event = settle(&mut h, &pool).await;
+    assert_eq!(event.amount_x_in, 1_000_000);
+
+    // the intent that could never fill still pays the fee
+    claim(&mut h, &pool, &griefer).await;
+    assert_eq!(
+        h.user_balances(&addresses, &griefer.pubkey()).await.0,
+        2 * min - 300
+    );
+    claim(&mut h, &pool, &trader).await;
+    assert_eq!(
+        h.user_balances(&addresses, &trader.pubkey()).await.1,
+        event.amount_y_out
+    );
+    assert_eq!(
+        h.reserves(&addresses).await.0,
+        100_000_000 + 1_000_000 + 300
+    );
+}
@@ -0,0 +1,155 @@
+use amm_client::{batch_address, decode_batch, instructions, CircuitBreakerParams};
+use amm_contract::error::AmmError;
+use amm_contract::events::BatchSettleEvent;
+use amm_harness::{custom_error, tokio, Harness, Pool};
+use solana_sdk::signature::{Keypair, Signer};
+
+fn params() -> CircuitBreakerParams {
+    CircuitBreakerParams {
+        max_swap_move_bps: 100,
+        max_slot_move_bps: 250,
+    }
+}
+
+/// A seeded 30 bps pool at 1 y per x with the circuit breaker on, a trader
+/// and the pool's authority.
+async fn guarded_pool(h: &mut Harness) -> (Pool, Keypair, Keypair) {
+    let authority = Keypair::new();
+    let pool = h.create_pool(30, Some(authority.pubkey())).await;
+    let ix =
+        instructions::set_circuit_breaker(&pool.addresses, &authority.pubkey(), Some(params()));
+    h.process(&[ix], &[&authority]).await.unwrap();
+
+    let lp = h.funded_user(&pool, 100_000_000, 100_000_000).await;
+    h.deposit(&pool.addresses, &lp, 100_000_000, 100_000_000, 100_000_000)
+        .await
+        .unwrap();
+    let trader = h.funded_user(&pool, 50_000_000, 50_000_000).await;
+    (pool, trader, authority)
+}
+
+#[tokio::test]
+async fn authority_sets_and_clears_circuit_breaker() {
+    let mut h = Harness::new().await;
+    let authority = Keypair::new();
+    let pool = h.create_pool(30, Some(authority.pubkey())).await;
+
+    for params in [
+        CircuitBreakerParams {
+            max_swap_move_bps: 0,
+            max_slot_move_bps: 100,
+        },
+        CircuitBreakerParams {
+            max_swap_move_bps: 300,
+            max_slot_move_bps: 100,
+        },
+    ] {
+        let ix =
+            instructions::set_circuit_breaker(&pool.addresses, &authority.pubkey(), Some(params));
+        let result = h.process(&[ix], &[&authority]).await;
+        assert_eq!(
+            custom_error(result),
+            Some(AmmError::InvalidCircuitBreaker.into())
+        );
+    }
+
+    let ix =
+        instructions::set_circuit_breaker(&pool.addresses, &authority.pubkey(), Some(params()));
+    h.process(&[ix], &[&authority]).await.unwrap();
+    let circuit_breaker = h.config(&pool.addresses).await.circuit_breaker.unwrap();
+    assert_eq!(circuit_breaker.params, params());
+
+    let ix = instructions::set_circuit_breaker(&pool.addresses, &authority.pubkey(), None);
+    h.process(&[ix], &[&authority]).await.unwrap();
+    assert_eq!(h.config(&pool.addresses).await.circuit_breaker, None);
+}
+
+#[tokio::test]
+async fn trade_moving_price_too_far_is_rejected() {
+    let mut h = Harness::new().await;
+    let (pool, trader, _) = guarded_pool(&mut h).await;
+
+    // about 2% in one go
+    let result = h.swap(&pool.addresses, &trader, 1_000_000, 0, true).await;
+    assert_eq!(
+        custom_error(result),
+        Some(AmmError::PriceBandExceeded.into())
+    );
+    // about 0.8% passes
+    h.swap(&pool.addresses, &trader, 400_000, 0, true)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn slot_band_resets_on_the_next_slot() {
+    let mut h = Harness::new().await;
+    let (pool, trader, _) = guarded_pool(&mut h).await;
+
+    // three steps of about 0.8% stay within 2.5% of the slot's start
+    for amount_in in [400_000, 400_001, 400_002] {
+        h.swap(&pool.addresses, &trader, amount_in, 0, true)
+            .await
+            .unwrap();
+    }
+    let result = h.swap(&pool.addresses, &trader, 400_003, 0, true).await;
+    assert_eq!(
+        custom_error(result),
+        Some(AmmError::PriceBandExceeded.into())
+    );
+
+    let slot = h.ctx.banks_client.get_root_slot().await.unwrap();
+    h.ctx.warp_to_slot(slot + 2).unwrap();
+    h.swap(&pool.addresses, &trader, 400_003, 0, true)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn batches_past_the_band_are_refunded() {
+    const NOW: i64 = 1_700_002_800;
+    const WINDOW: i64 = 60;
+    let batch_id = (NOW / WINDOW) as u64;
+
+    let mut h = Harness::new().await;
+    h.set_unix_timestamp(NOW).await;
+    let (pool, trader, authority) = guarded_pool(&mut h).await;
+    let addresses = pool.addresses;
+    let ix = instructions::set_batch_window(&addresses, &authority.pubkey(), Some(WINDOW));
+    h.process(&[ix], &[&authority]).await.unwrap();
+
+    // about a 4% move, past the 1% trade band
+    let ix = instructions::submit_swap_intent(
+        &addresses,
+        &trader.pubkey(),
+        batch_id,
+        2_000_000,
+        0,
+        true,
+    );
+    h.process(&[ix], &[&trader]).await.unwrap();
+    h.set_unix_timestamp(NOW + WINDOW).await;
+    let ix = instructions::settle_batch(&addresses, batch_id);
+    let events: Vec<BatchSettleEvent> = h.process_events(&[ix], &[]).await.unwrap();
+    assert_eq!((events[0].amount_x_in, events[0].amount_y_out), (0, 0));
+    assert_eq!(
+        h.reserves(&addresses).await,
+        (100_000_000, 100_000_000, 100_000_000)
+    );
+
+    let (address, _) = batch_address(&addresses.config, batch_id);
+    let account = h
+        .ctx
+        .banks_client
+        .get_account(address)
+        .await
+        .unwrap()
+        .unwrap();
+    let batch = decode_batch(&account.data).unwrap();
+    let ix = instructions::claim_swap_intents(&addresses, &trader.pubkey(), &batch);
+    h.process(&[ix], &[&trader]).await.unwrap();
+    assert_eq!(
+        h.user_balances(&addresses, &trader.pubkey()).await.0,
+        50_000_000
+    );
+}
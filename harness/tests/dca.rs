@@ -0,0 +1,228 @@
+use amm_client::{dca_address, decode_dca, instructions, Dca, PoolState};
+use amm_contract::error::AmmError;
+use amm_harness::{custom_error, tokio, Harness, Pool};
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::spl_token;
+use solana_sdk::signature::{Keypair, Signer};
+
+const NOW: i64 = 1_700_000_000;
+const WEEK: i64 = 7 * 24 * 3600;
+
+/// A seeded 30 bps pool at `NOW`, a DCA owner holding 3_000_000 x and a
+/// keeper.
+async fn seeded_pool(h: &mut Harness) -> (Pool, Keypair, Keypair) {
+    h.set_unix_timestamp(NOW).await;
+    let pool = h.create_pool(30, None).await;
+    let lp = h.funded_user(&pool, 100_000_000, 100_000_000).await;
+    h.deposit(&pool.addresses, &lp, 100_000_000, 100_000_000, 100_000_000)
+        .await
+        .unwrap();
+    let owner = h.funded_user(&pool, 3_000_000, 0).await;
+    let keeper = h.funded_user(&pool, 0, 0).await;
+    (pool, owner, keeper)
+}
+
+async fn dca(h: &mut Harness, pool: &Pool, owner: &Keypair, dca_id: u64) -> Option<Dca> {
+    let (address, _) = dca_address(&pool.addresses.config, &owner.pubkey(), dca_id);
+    let account = h.ctx.banks_client.get_account(address).await.unwrap()?;
+    Some(decode_dca(&account.data).unwrap())
+}
+
+/// Opens a weekly DCA selling 1_000_000 x at a time, tipping 10 bps.
+async fn open_weekly(h: &mut Harness, pool: &Pool, owner: &Keypair, min_out_per_cycle: u64) {
+    let ix = instructions::open_dca(
+        &pool.addresses,
+        &owner.pubkey(),
+        0,
+        true,
+        3_000_000,
+        1_000_000,
+        WEEK,
+        min_out_per_cycle,
+        10,
+    );
+    h.process(&[ix], &[owner]).await.unwrap();
+}
+
+#[tokio::test]
+async fn keeper_sells_one_slice_per_interval() {
+    let mut h = Harness::new().await;
+    let (pool, owner, keeper) = seeded_pool(&mut h).await;
+    let addresses = pool.addresses;
+    open_weekly(&mut h, &pool, &owner, 900_000).await;
+
+    // the first slice is due right away, and sold like a swap
+    let config = h.config(&addresses).await;
+    let (x, y, supply) = h.reserves(&addresses).await;
+    let quote = PoolState::new(config, x, y, supply)
+        .quote_swap(999_000, true)
+        .unwrap();
+    let opened = dca(&mut h, &pool, &owner, 0).await.unwrap();
+    let ix = instructions::execute_dca(&addresses, &keeper.pubkey(), &opened);
+    h.process(&[ix], &[&keeper]).await.unwrap();
+
+    assert_eq!(
+        h.user_balances(&addresses, &owner.pubkey()).await.1,
+        quote.amount_out
+    );
+    assert_eq!(h.user_balances(&addresses, &keeper.pubkey()).await.0, 1_000);
+    let running = dca(&mut h, &pool, &owner, 0).await.unwrap();
+    assert_eq!(running.remaining, 2_000_000);
+    assert_eq!(running.next_at, NOW + WEEK);
+
+    h.set_unix_timestamp(NOW + WEEK - 1).await;
+    let ix = instructions::execute_dca(&addresses, &keeper.pubkey(), &running);
+    let result = h.process(&[ix], &[&keeper]).await;
+    assert_eq!(custom_error(result), Some(AmmError::DcaNotDue.into()));
+
+    for week in 1..=2 {
+        h.set_unix_timestamp(NOW + week * WEEK).await;
+        let ix = instructions::execute_dca(&addresses, &keeper.pubkey(), &running);
+        h.process(&[ix], &[&keeper]).await.unwrap();
+    }
+
+    // everything is sold, so the DCA and its escrow are closed
+    assert!(dca(&mut h, &pool, &owner, 0).await.is_none());
+    let (address, _) = dca_address(&addresses.config, &owner.pubkey(), 0);
+    let escrow = get_associated_token_address(&address, &addresses.mint_x);
+    assert_eq!(h.lamports(&escrow).await, 0);
+    assert_eq!(h.user_balances(&addresses, &keeper.pubkey()).await.0, 3_000);
+}
+
+#[tokio::test]
+async fn slice_below_the_price_bound_waits() {
+    let mut h = Harness::new().await;
+    let (pool, owner, keeper) = seeded_pool(&mut h).await;
+    let addresses = pool.addresses;
+    open_weekly(&mut h, &pool, &owner, 1_000_000).await;
+
+    let opened = dca(&mut h, &pool, &owner, 0).await.unwrap();
+    let ix = instructions::execute_dca(&addresses, &keeper.pubkey(), &opened);
+    let result = h.process(&[ix], &[&keeper]).await;
+    assert_eq!(
+        custom_error(result),
+        Some(AmmError::SlippageExceeded.into())
+    );
+    assert_eq!(
+        dca(&mut h, &pool, &owner, 0).await.unwrap().remaining,
+        3_000_000
+    );
+
+    // the owner gives up and gets the input back
+    let ix = instructions::close_dca(&addresses, &owner.pubkey(), 0, true);
+    h.process(&[ix], &[&owner]).await.unwrap();
+    assert_eq!(
+        h.user_balances(&addresses, &owner.pubkey()).await.0,
+        3_000_000
+    );
+    assert!(dca(&mut h, &pool, &owner, 0).await.is_none());
+}
+
+#[tokio::test]
+async fn dca_schedule_is_validated() {
+    let mut h = Harness::new().await;
+    let (pool, owner, _) = seeded_pool(&mut h).await;
+
+    // slice above the total, no interval, tip above the cap
+    for (amount_per_cycle, interval, tip_bps) in [(3_000_001, WEEK, 0), (1, 0, 0), (1, WEEK, 101)] {
+        let ix = instructions::open_dca(
+            &pool.addresses,
+            &owner.pubkey(),
+            0,
+            true,
+            3_000_000,
+            amount_per_cycle,
+            interval,
+            0,
+            tip_bps,
+        );
+        let result = h.process(&[ix], &[&owner]).await;
+        assert_eq!(
+            custom_error(result),
+            Some(AmmError::InvalidDcaSchedule.into())
+        );
+    }
+}
+
+#[tokio::test]
+async fn dust_in_the_escrow_does_not_block_the_last_slice() {
+    let mut h = Harness::new().await;
+    let (pool, owner, keeper) = seeded_pool(&mut h).await;
+    let addresses = pool.addresses;
+    let griefer = h.funded_user(&pool, 1, 0).await;
+    open_weekly(&mut h, &pool, &owner, 0).await;
+
+    let (address, _) = dca_address(&addresses.config, &owner.pubkey(), 0);
+    let escrow = get_associated_token_address(&address, &addresses.mint_x);
+    let ix = spl_token::instruction::transfer(
+        &spl_token::ID,
+        &get_associated_token_address(&griefer.pubkey(), &addresses.mint_x),
+        &escrow,
+        &griefer.pubkey(),
+        &[],
+        1,
+    )
+    .unwrap();
+    h.process(&[ix], &[&griefer]).await.unwrap();
+
+    let opened = dca(&mut h, &pool, &owner, 0).await.unwrap();
+    for week in 0..3 {
+        h.set_unix_timestamp(NOW + week * WEEK).await;
+        let ix = instructions::execute_dca(&addresses, &keeper.pubkey(), &opened);
+        h.process(&[ix], &[&keeper]).await.unwrap();
+    }
+
+    // the dust goes back to the owner with the escrow's rent
+    assert!(dca(&mut h, &pool, &owner, 0).await.is_none());
+    assert_eq!(h.lamports(&escrow).await, 0);
+    assert_eq!(h.user_balances(&addresses, &owner.pubkey()).await.0, 1);
+}
+
+#[tokio::test]
+async fn keeper_cannot_redirect_the_proceeds() {
+    let mut h = Harness::new().await;
+    let (pool, owner, keeper) = seeded_pool(&mut h).await;
+    let addresses = pool.addresses;
+    open_weekly(&mut h, &pool, &owner, 0).await;
+
+    let opened = dca(&mut h, &pool, &owner, 0).await.unwrap();
+    let mut ix = instructions::execute_dca(&addresses, &keeper.pubkey(), &opened);
+    ix.accounts[8].pubkey = keeper.pubkey();
+    ix.accounts[9].pubkey = get_associated_token_address(&keeper.pubkey(), &addresses.mint_x);
+    ix.accounts[10].pubkey = get_associated_token_address(&keeper.pubkey(), &addresses.mint_y);
+    assert!(h.process(&[ix], &[&keeper]).await.is_err());
+    assert_eq!(
+        dca(&mut h, &pool, &owner, 0).await.unwrap().remaining,
+        3_000_000
+    );
+}
+
+#[tokio::test]
+async fn slices_wait_while_swaps_are_batched() {
+    let mut h = Harness::new().await;
+    h.set_unix_timestamp(NOW).await;
+    let authority = h.create_user(1_000_000_000).await;
+    let pool = h.create_pool(30, Some(authority.pubkey())).await;
+    let addresses = pool.addresses;
+    let lp = h.funded_user(&pool, 100_000_000, 100_000_000).await;
+    h.deposit(&addresses, &lp, 100_000_000, 100_000_000, 100_000_000)
+        .await
+        .unwrap();
+    let owner = h.funded_user(&pool, 3_000_000, 0).await;
+    let keeper = h.funded_user(&pool, 0, 0).await;
+    open_weekly(&mut h, &pool, &owner, 0).await;
+
+    let ix = instructions::set_batch_window(&addresses, &authority.pubkey(), Some(60));
+    h.process(&[ix], &[&authority]).await.unwrap();
+    let opened = dca(&mut h, &pool, &owner, 0).await.unwrap();
+    let ix = instructions::execute_dca(&addresses, &keeper.pubkey(), &opened);
+    let result = h.process(&[ix], &[&keeper]).await;
+    assert_eq!(
+        custom_error(result),
+        Some(AmmError::BatchAuctionOnly.into())
+    );
+    assert_eq!(
+        dca(&mut h, &pool, &owner, 0).await.unwrap().remaining,
+        3_000_000
+    );
+}
@@ -0,0 +1,70 @@
+use amm_contract::error::AmmError;
+use amm_harness::{custom_error, tokio, Harness};
+use solana_sdk::signature::Signer;
+
+#[tokio::test]
+async fn first_deposit_sets_the_ratio() {
+    let mut h = Harness::new().await;
+    let pool = h.create_pool(30, None).await;
+    let user = h.funded_user(&pool, 1_000, 2_000).await;
+
+    h.deposit(&pool.addresses, &user, 500, 100, 200)
+        .await
+        .unwrap();
+
+    assert_eq!(h.reserves(&pool.addresses).await, (100, 200, 500));
+    assert_eq!(
+        h.user_balances(&pool.addresses, &user.pubkey()).await,
+        (900, 1_800, 500)
+    );
+}
+
+#[tokio::test]
+async fn later_deposits_are_proportional_and_round_up() {
+    let mut h = Harness::new().await;
+    let pool = h.create_pool(30, None).await;
+    let first = h.funded_user(&pool, 1_001, 2_003).await;
+    let second = h.funded_user(&pool, 1_000, 1_000).await;
+
+    h.deposit(&pool.addresses, &first, 1_000, 1_001, 2_003)
+        .await
+        .unwrap();
+    h.deposit(&pool.addresses, &second, 10, 1_000, 1_000)
+        .await
+        .unwrap();
+
+    // 1001 * 10 / 1000 = 10.01 -> 11, 2003 * 10 / 1000 = 20.03 -> 21
+    assert_eq!(h.reserves(&pool.addresses).await, (1_012, 2_024, 1_010));
+    assert_eq!(
+        h.user_balances(&pool.addresses, &second.pubkey()).await,
+        (989, 979, 10)
+    );
+}
+
+#[tokio::test]
+async fn deposit_above_max_fails() {
+    let mut h = Harness::new().await;
+    let pool = h.create_pool(30, None).await;
+    let user = h.funded_user(&pool, 1_000, 2_000).await;
+
+    h.deposit(&pool.addresses, &user, 500, 100, 200)
+        .await
+        .unwrap();
+    let result = h.deposit(&pool.addresses, &user, 100, 10, 100).await;
+
+    assert_eq!(
+        custom_error(result),
+        Some(AmmError::DepositExceedsMax.into())
+    );
+    assert_eq!(h.reserves(&pool.addresses).await, (100, 200, 500));
+}
+
+#[tokio::test]
+async fn zero_deposit_fails() {
+    let mut h = Harness::new().await;
+    let pool = h.create_pool(30, None).await;
+    let user = h.funded_user(&pool, 1_000, 2_000).await;
+
+    let result = h.deposit(&pool.addresses, &user, 0, 100, 200).await;
+    assert_eq!(custom_error(result), Some(AmmError::ZeroAmount.into()));
+}
@@ -0,0 +1,190 @@
+use amm_client::{instructions, DynamicFeeParams, PoolState};
+use amm_contract::error::AmmError;
+use amm_harness::{custom_error, tokio, Harness, Pool};
+use solana_sdk::signature::{Keypair, Signer};
+
+const START: i64 = 1_700_000_000;
+
+fn params() -> DynamicFeeParams {
+    DynamicFeeParams {
+        filter_period: 30,
+        decay_period: 600,
+        reduction_factor: 5_000,
+        variable_fee_control: 4_000,
+        max_volatility_accumulator: 3_500,
+        max_fee: 500,
+    }
+}
+
+/// A seeded 30 bps pool with dynamic fees on, and a trader.
+async fn dynamic_pool(h: &mut Harness) -> (Pool, Keypair) {
+    let authority = Keypair::new();
+    let pool = h.create_pool(30, Some(authority.pubkey())).await;
+    let ix = instructions::set_dynamic_fee(&pool.addresses, &authority.pubkey(), Some(params()));
+    h.process(&[ix], &[&authority]).await.unwrap();
+
+    let lp = h.funded_user(&pool, 100_000_000, 100_000_000).await;
+    h.deposit(&pool.addresses, &lp, 100_000_000, 100_000_000, 100_000_000)
+        .await
+        .unwrap();
+    let trader = h.funded_user(&pool, 50_000_000, 50_000_000).await;
+    (pool, trader)
+}
+
+async fn quote(h: &mut Harness, pool: &Pool, amount_in: u64, is_x_in: bool, now: i64) -> u64 {
+    let config = h.config(&pool.addresses).await;
+    let (x, y, lp) = h.reserves(&pool.addresses).await;
+    PoolState::new(config, x, y, lp)
+        .at(now)
+        .quote_swap(amount_in, is_x_in)
+        .unwrap()
+        .fee
+}
+
+#[tokio::test]
+async fn authority_sets_and_clears_dynamic_fee() {
+    let mut h = Harness::new().await;
+    let authority = Keypair::new();
+    let pool = h.create_pool(30, Some(authority.pubkey())).await;
+
+    let ix = instructions::set_dynamic_fee(&pool.addresses, &authority.pubkey(), Some(params()));
+    h.process(&[ix], &[&authority]).await.unwrap();
+    let dynamic_fee = h.config(&pool.addresses).await.dynamic_fee.unwrap();
+    assert_eq!(dynamic_fee.params, params());
+    assert_eq!(dynamic_fee.volatility_accumulator, 0);
+
+    let ix = instructions::set_dynamic_fee(&pool.addresses, &authority.pubkey(), None);
+    h.process(&[ix], &[&authority]).await.unwrap();
+    assert_eq!(h.config(&pool.addresses).await.dynamic_fee, None);
+}
+
+#[tokio::test]
+async fn non_authority_cannot_set_dynamic_fee() {
+    let mut h = Harness::new().await;
+    let pool = h.create_pool(30, Some(Keypair::new().pubkey())).await;
+    let intruder = Keypair::new();
+
+    let ix = instructions::set_dynamic_fee(&pool.addresses, &intruder.pubkey(), Some(params()));
+    let result = h.process(&[ix], &[&intruder]).await;
+    assert_eq!(custom_error(result), Some(AmmError::Unauthorized.into()));
+}
+
+#[tokio::test]
+async fn base_fee_must_stay_below_max_fee() {
+    let mut h = Harness::new().await;
+    let authority = Keypair::new();
+    let pool = h.create_pool(600, Some(authority.pubkey())).await;
+
+    let ix = instructions::set_dynamic_fee(&pool.addresses, &authority.pubkey(), Some(params()));
+    let result = h.process(&[ix], &[&authority]).await;
+    assert_eq!(custom_error(result), Some(AmmError::InvalidFee.into()));
+
+    let ix = instructions::update_config(
+        &pool.addresses,
+        &authority.pubkey(),
+        30,
+        Some(authority.pubkey()),
+    );
+    h.process(&[ix], &[&authority]).await.unwrap();
+    let ix = instructions::set_dynamic_fee(&pool.addresses, &authority.pubkey(), Some(params()));
+    h.process(&[ix], &[&authority]).await.unwrap();
+
+    let ix = instructions::update_config(
+        &pool.addresses,
+        &authority.pubkey(),
+        501,
+        Some(authority.pubkey()),
+    );
+    let result = h.process(&[ix], &[&authority]).await;
+    assert_eq!(custom_error(result), Some(AmmError::InvalidFee.into()));
+}
+
+#[tokio::test]
+async fn volatility_raises_fee_until_it_decays() {
+    let mut h = Harness::new().await;
+    let (pool, trader) = dynamic_pool(&mut h).await;
+    let amount_in = 1_000_000;
+    let flat_fee = amount_in * 30 / 10_000;
+
+    h.set_unix_timestamp(START).await;
+    // a 1% trade alone moves the price about 2%
+    assert!(quote(&mut h, &pool, amount_in, true, START).await > flat_fee);
+
+    // a burst of trades within the filter period keeps compounding
+    let mut last_fee = 0;
+    for i in 0..3 {
+        let now = START + i;
+        h.set_unix_timestamp(now).await;
+        let fee = quote(&mut h, &pool, amount_in, true, now).await;
+        assert!(fee > last_fee);
+        last_fee = fee;
+        h.swap(&pool.addresses, &trader, amount_in, 0, true)
+            .await
+            .unwrap();
+    }
+    let burst = h.config(&pool.addresses).await.dynamic_fee.unwrap();
+    assert_eq!(burst.last_update, START + 2);
+    assert!(burst.volatility_accumulator > 0);
+
+    // a tiny trade after the burst still pays for the recent volatility
+    let now = START + 60;
+    h.set_unix_timestamp(now).await;
+    let expected = quote(&mut h, &pool, amount_in, false, now).await;
+    assert!(expected > flat_fee);
+    let before = h.user_balances(&pool.addresses, &trader.pubkey()).await;
+    h.swap(&pool.addresses, &trader, amount_in, 0, false)
+        .await
+        .unwrap();
+    let after = h.user_balances(&pool.addresses, &trader.pubkey()).await;
+    assert_eq!(before.1 - after.1, amount_in);
+    let reference = h.config(&pool.addresses).await.dynamic_fee.unwrap();
+    assert_eq!(
+        reference.volatility_reference,
+        burst.volatility_accumulator / 2
+    );
+
+    // and once the pool has been quiet for the decay period it is forgotten
+    let now = START + 60 + 600;
+    h.set_unix_timestamp(now).await;
+    assert_eq!(quote(&mut h, &pool, 1_000, true, now).await, 3);
+}
+
+#[tokio::test]
+async fn swap_charges_the_quoted_dynamic_fee() {
+    let mut h = Harness::new().await;
+    let (pool, trader) = dynamic_pool(&mut h).await;
+    h.set_unix_timestamp(START).await;
+    h.swap(&pool.addresses, &trader, 5_000_000, 0, true)
+        .await
+        .unwrap();
+
+    let now = START + 5;
+    h.set_unix_timestamp(now).await;
+    let config = h.config(&pool.addresses).await;
+    let (x, y, lp) = h.reserves(&pool.addresses).await;
+    let quote = PoolState::new(config, x, y, lp)
+        .at(now)
+        .quote_swap(2_000_000, true)
+        .unwrap();
+
+    let before = h.user_balances(&pool.addresses, &trader.pubkey()).await;
+    h.swap(&pool.addresses, &trader, 2_000_000, quote.amount_out, true)
+        .await
+        .unwrap();
+    let after = h.user_balances(&pool.addresses, &trader.pubkey()).await;
+    assert_eq!(after.1 - before.1, quote.amount_out);
+    assert!(quote.fee > 2_000_000 * 30 / 10_000);
+}
+
+#[tokio::test]
+async fn max_fee_covers_both_directions() {
+    let mut h = Harness::new().await;
+    let authority = Keypair::new();
+    let pool = h.create_pool(30, Some(authority.pubkey())).await;
+    let ix = instructions::set_directional_fees(&pool.addresses, &authority.pubkey(), Some(600));
+    h.process(&[ix], &[&authority]).await.unwrap();
+
+    let ix = instructions::set_dynamic_fee(&pool.addresses, &authority.pubkey(), Some(params()));
+    let result = h.process(&[ix], &[&authority]).await;
+    assert_eq!(custom_error(result), Some(AmmError::InvalidFee.into()));
+}
@@ -0,0 +1,387 @@
+use amm_client::{
+    decode_farm, decode_stake_position, farm_address, instructions, stake_position_address, Farm,
+    StakePosition,
+};
+use amm_contract::error::AmmError;
+use amm_harness::{custom_error, tokio, Harness, Pool};
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::AccountSerialize;
+use solana_sdk::signature::{Keypair, Signer};
+
+const START: i64 = 1_700_000_000;
+
+struct Setup {
+    pool: Pool,
+    authority: Keypair,
+    reward_mint: Pubkey,
+    alice: Keypair,
+    bob: Keypair,
+}
+
+/// A pool with a farm emitting 100 reward tokens a second for 1_000
+/// seconds from `START`, fully funded, and two LPs holding 1_000 LP each.
+async fn farm_setup(h: &mut Harness) -> Setup {
+    h.set_unix_timestamp(START - 100).await;
+    let authority = h.create_user(1_000_000_000).await;
+    let pool = h.create_pool(30, Some(authority.pubkey())).await;
+    let addresses = pool.addresses;
+
+    let alice = h.funded_user(&pool, 1_000, 1_000).await;
+    h.deposit(&addresses, &alice, 1_000, 1_000, 1_000)
+        .await
+        .unwrap();
+    let bob = h.funded_user(&pool, 1_000, 1_000).await;
+    h.deposit(&addresses, &bob, 1_000, 1_000, 1_000)
+        .await
+        .unwrap();
+
+    let reward_mint = h.create_mint(&pool.mint_authority.pubkey(), 6).await;
+    let funder = h
+        .mint_to(
+            &reward_mint,
+            &pool.mint_authority,
+            &authority.pubkey(),
+            100_000,
+        )
+        .await;
+
+    let ixs = [
+        instructions::initialize_farm(&addresses, &authority.pubkey()),
+        instructions::set_farm_reward(
+            &addresses,
+            &authority.pubkey(),
+            &reward_mint,
+            0,
+            100,
+            START,
+            START + 1_000,
+        ),
+        instructions::fund_farm_reward(
+            &addresses,
+            &authority.pubkey(),
+            &reward_mint,
+            &funder,
+            100_000,
+        ),
+    ];
+    h.process(&ixs, &[&authority]).await.unwrap();
+
+    Setup {
+        pool,
+        authority,
+        reward_mint,
+        alice,
+        bob,
+    }
+}
+
+async fn farm(h: &mut Harness, pool: &Pool) -> Farm {
+    let (address, _) = farm_address(&pool.addresses.config);
+    let account = h.ctx.banks_client.get_account(address).await.unwrap();
+    decode_farm(&account.unwrap().data).unwrap()
+}
+
+async fn position(h: &mut Harness, pool: &Pool, owner: &Keypair) -> StakePosition {
+    let (farm, _) = farm_address(&pool.addresses.config);
+    let (address, _) = stake_position_address(&farm, &owner.pubkey());
+    let account = h.ctx.banks_client.get_account(address).await.unwrap();
+    decode_stake_position(&account.unwrap().data).unwrap()
+}
+
+async fn claim(h: &mut Harness, s: &Setup, owner: &Keypair) -> u64 {
+    let recipient = h.create_ata(&owner.pubkey(), &s.reward_mint).await;
+    let before = h.token_balance(&recipient).await;
+    let ix = instructions::claim_farm_reward(
+        &s.pool.addresses,
+        &owner.pubkey(),
+        &s.reward_mint,
+        &recipient,
+    );
+    h.process(&[ix], &[owner]).await.unwrap();
+    h.token_balance(&recipient).await - before
+}
+
+#[tokio::test]
+async fn stakers_earn_pro_rata_over_the_schedule() {
+    let mut h = Harness::new().await;
+    let s = farm_setup(&mut h).await;
+    let addresses = s.pool.addresses;
+
+    h.set_unix_timestamp(START).await;
+    let ix = instructions::stake(&addresses, &s.alice.pubkey(), 1_000);
+    h.process(&[ix], &[&s.alice]).await.unwrap();
+
+    // alice alone for 200s, then bob joins with a third of the stake
+    h.set_unix_timestamp(START + 200).await;
+    let ix = instructions::stake(&addresses, &s.bob.pubkey(), 500);
+    h.process(&[ix], &[&s.bob]).await.unwrap();
+    assert_eq!(farm(&mut h, &s.pool).await.total_staked, 1_500);
+    assert_eq!(h.user_balances(&addresses, &s.bob.pubkey()).await.2, 500);
+
+    // past the end of the schedule nothing more is emitted
+    h.set_unix_timestamp(START + 5_000).await;
+    assert_eq!(claim(&mut h, &s, &s.alice).await, 20_000 + 80_000 * 2 / 3);
+    assert_eq!(claim(&mut h, &s, &s.bob).await, 80_000 / 3);
+    assert_eq!(claim(&mut h, &s, &s.bob).await, 0);
+}
+
+#[tokio::test]
+async fn unstake_returns_lp_and_keeps_rewards() {
+    let mut h = Harness::new().await;
+    let s = farm_setup(&mut h).await;
+    let addresses = s.pool.addresses;
+
+    h.set_unix_timestamp(START).await;
+    let ix = instructions::stake(&addresses, &s.alice.pubkey(), 1_000);
+    h.process(&[ix], &[&s.alice]).await.unwrap();
+
+    h.set_unix_timestamp(START + 100).await;
+    let ix = instructions::unstake(&addresses, &s.alice.pubkey(), 1_000);
+    h.process(&[ix], &[&s.alice]).await.unwrap();
+    assert_eq!(
+        h.user_balances(&addresses, &s.alice.pubkey()).await.2,
+        1_000
+    );
+    let settled = position(&mut h, &s.pool, &s.alice).await;
+    assert_eq!(settled.amount, 0);
+    assert_eq!(settled.pending[0], 10_000);
+
+    h.set_unix_timestamp(START + 500).await;
+    assert_eq!(claim(&mut h, &s, &s.alice).await, 10_000);
+
+    let ix = instructions::unstake(&addresses, &s.alice.pubkey(), 1);
+    let result = h.process(&[ix], &[&s.alice]).await;
+    assert_eq!(
+        custom_error(result),
+        Some(AmmError::InsufficientStake.into())
+    );
+}
+
+#[tokio::test]
+async fn unstake_forfeits_earnings_it_cannot_settle() {
+    let mut h = Harness::new().await;
+    let s = farm_setup(&mut h).await;
+    let addresses = s.pool.addresses;
+
+    h.set_unix_timestamp(START).await;
+    let ix = instructions::stake(&addresses, &s.alice.pubkey(), 1_000);
+    h.process(&[ix], &[&s.alice]).await.unwrap();
+
+    // pending rewards so large that settling any more overflows
+    let mut full = position(&mut h, &s.pool, &s.alice).await;
+    full.pending[0] = u64::MAX;
+    let mut data = vec![];
+    full.try_serialize(&mut data).unwrap();
+    let (farm, _) = farm_address(&addresses.config);
+    let (address, _) = stake_position_address(&farm, &s.alice.pubkey());
+    let mut account = h
+        .ctx
+        .banks_client
+        .get_account(address)
+        .await
+        .unwrap()
+        .unwrap();
+    account.data = data;
+    h.ctx.set_account(&address, &account.into());
+
+    h.set_unix_timestamp(START + 100).await;
+    let ix = instructions::unstake(&addresses, &s.alice.pubkey(), 1_000);
+    h.process(&[ix], &[&s.alice]).await.unwrap();
+    assert_eq!(
+        h.user_balances(&addresses, &s.alice.pubkey()).await.2,
+        1_000
+    );
+    assert_eq!(position(&mut h, &s.pool, &s.alice).await.amount, 0);
+}
+
+#[tokio::test]
+async fn claim_is_limited_to_the_funded_rewards() {
+    let mut h = Harness::new().await;
+    let s = farm_setup(&mut h).await;
+    let addresses = s.pool.addresses;
+
+    // double the rate: 200k is owed over the schedule but only 100k funded
+    let ix = instructions::set_farm_reward(
+        &addresses,
+        &s.authority.pubkey(),
+        &s.reward_mint,
+        0,
+        200,
+        START,
+        START + 1_000,
+    );
+    h.process(&[ix], &[&s.authority]).await.unwrap();
+
+    h.set_unix_timestamp(START).await;
+    let ix = instructions::stake(&addresses, &s.alice.pubkey(), 1_000);
+    h.process(&[ix], &[&s.alice]).await.unwrap();
+
+    h.set_unix_timestamp(START + 1_000).await;
+    assert_eq!(claim(&mut h, &s, &s.alice).await, 100_000);
+    assert_eq!(
+        position(&mut h, &s.pool, &s.alice).await.pending[0],
+        100_000
+    );
+
+    // a top-up pays out the rest
+    let funder = h
+        .mint_to(
+            &s.reward_mint,
+            &s.pool.mint_authority,
+            &s.authority.pubkey(),
+            100_000,
+        )
+        .await;
+    let ix = instructions::fund_farm_reward(
+        &addresses,
+        &s.authority.pubkey(),
+        &s.reward_mint,
+        &funder,
+        100_000,
+    );
+    h.process(&[ix], &[&s.authority]).await.unwrap();
+    assert_eq!(claim(&mut h, &s, &s.alice).await, 100_000);
+}
+
+#[tokio::test]
+async fn only_authority_manages_rewards() {
+    let mut h = Harness::new().await;
+    let s = farm_setup(&mut h).await;
+    let addresses = s.pool.addresses;
+
+    let ix = instructions::set_farm_reward(
+        &addresses,
+        &s.alice.pubkey(),
+        &s.reward_mint,
+        0,
+        1_000_000,
+        START,
+        START + 1_000,
+    );
+    let result = h.process(&[ix], &[&s.alice]).await;
+    assert_eq!(custom_error(result), Some(AmmError::Unauthorized.into()));
+
+    // a slot cannot change token, nor a token take a second slot
+    let other_mint = h.create_mint(&s.pool.mint_authority.pubkey(), 6).await;
+    let ix = instructions::set_farm_reward(
+        &addresses,
+        &s.authority.pubkey(),
+        &other_mint,
+        0,
+        1,
+        START,
+        START + 1_000,
+    );
+    let result = h.process(&[ix], &[&s.authority]).await;
+    assert_eq!(custom_error(result), Some(AmmError::InvalidMint.into()));
+    let ix = instructions::set_farm_reward(
+        &addresses,
+        &s.authority.pubkey(),
+        &s.reward_mint,
+        1,
+        1,
+        START,
+        START + 1_000,
+    );
+    let result = h.process(&[ix], &[&s.authority]).await;
+    assert_eq!(custom_error(result), Some(AmmError::InvalidMint.into()));
+
+    let ix = instructions::set_farm_reward(
+        &addresses,
+        &s.authority.pubkey(),
+        &other_mint,
+        3,
+        1,
+        START,
+        START + 1_000,
+    );
+    let result = h.process(&[ix], &[&s.authority]).await;
+    assert_eq!(
+        custom_error(result),
+        Some(AmmError::InvalidRewardSchedule.into())
+    );
+}
+
+#[tokio::test]
+async fn lp_and_unpayable_rewards_are_refused() {
+    let mut h = Harness::new().await;
+    let s = farm_setup(&mut h).await;
+    let addresses = s.pool.addresses;
+
+    // paying out LP would pay out the stakers' own
+    let ix = instructions::set_farm_reward(
+        &addresses,
+        &s.authority.pubkey(),
+        &addresses.mint_lp,
+        1,
+        1,
+        START,
+        START + 1_000,
+    );
+    let result = h.process(&[ix], &[&s.authority]).await;
+    assert_eq!(custom_error(result), Some(AmmError::InvalidMint.into()));
+
+    // more than any vault can hold
+    let ix = instructions::set_farm_reward(
+        &addresses,
+        &s.authority.pubkey(),
+        &s.reward_mint,
+        0,
+        u64::MAX / 1_000 + 1,
+        START,
+        START + 1_000,
+    );
+    let result = h.process(&[ix], &[&s.authority]).await;
+    assert_eq!(
+        custom_error(result),
+        Some(AmmError::InvalidRewardSchedule.into())
+    );
+}
+
+#[tokio::test]
+async fn second_reward_emits_alongside_the_first() {
+    let mut h = Harness::new().await;
+    let s = farm_setup(&mut h).await;
+    let addresses = s.pool.addresses;
+
+    let bonus_mint = h.create_mint(&s.pool.mint_authority.pubkey(), 9).await;
+    let funder = h
+        .mint_to(
+            &bonus_mint,
+            &s.pool.mint_authority,
+            &s.authority.pubkey(),
+            5_000,
+        )
+        .await;
+    let ixs = [
+        instructions::set_farm_reward(
+            &addresses,
+            &s.authority.pubkey(),
+            &bonus_mint,
+            1,
+            10,
+            START + 500,
+            START + 1_000,
+        ),
+        instructions::fund_farm_reward(
+            &addresses,
+            &s.authority.pubkey(),
+            &bonus_mint,
+            &funder,
+            5_000,
+        ),
+    ];
+    h.process(&ixs, &[&s.authority]).await.unwrap();
+
+    h.set_unix_timestamp(START).await;
+    let ix = instructions::stake(&addresses, &s.alice.pubkey(), 1_000);
+    h.process(&[ix], &[&s.alice]).await.unwrap();
+
+    h.set_unix_timestamp(START + 1_000).await;
+    let recipient = h.create_ata(&s.alice.pubkey(), &bonus_mint).await;
+    let ix =
+        instructions::claim_farm_reward(&addresses, &s.alice.pubkey(), &bonus_mint, &recipient);
+    h.process(&[ix], &[&s.alice]).await.unwrap();
+    assert_eq!(h.token_balance(&recipient).await, 5_000);
+    assert_eq!(claim(&mut h, &s, &s.alice).await, 100_000);
+}
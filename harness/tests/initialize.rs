@@ -0,0 +1,152 @@
+use amm_client::{instructions, PoolAddresses};
+use amm_contract::error::AmmError;
+use amm_harness::{custom_error, tokio, Harness};
+use solana_sdk::signature::{Keypair, Signer};
+
+#[tokio::test]
+async fn initialize_creates_config_mint_and_vaults() {
+    let mut h = Harness::new().await;
+    let authority = Keypair::new().pubkey();
+    let pool = h.create_pool(30, Some(authority)).await;
+    let addresses = pool.addresses;
+
+    let config = h.config(&addresses).await;
+    assert_eq!(config.mint_x, addresses.mint_x);
+    assert_eq!(config.mint_y, addresses.mint_y);
+    assert_eq!(config.fees, 30);
+    assert_eq!(config.authority, Some(authority));
+    assert_eq!(
+        config.my_bump,
+        amm_client::config_address(&addresses.mint_x, &addresses.mint_y).1
+    );
+    assert_eq!(
+        config.lp_bump,
+        amm_client::lp_mint_address(&addresses.config).1
+    );
+
+    assert_eq!(h.reserves(&addresses).await, (0, 0, 0));
+}
+
+#[tokio::test]
+async fn initialize_rejects_fee_above_100_percent() {
+    let mut h = Harness::new().await;
+    let authority = Keypair::new();
+    let mint_x = h.create_mint(&authority.pubkey(), 6).await;
+    let mint_y = h.create_mint(&authority.pubkey(), 6).await;
+    let addresses = PoolAddresses::new(mint_x, mint_y);
+    let payer = h.payer();
+
+    let result = h
+        .process(
+            &[instructions::initialize(
+                &addresses, &payer, 10_001, None, None,
+            )],
+            &[],
+        )
+        .await;
+    assert_eq!(custom_error(result), Some(AmmError::InvalidFee.into()));
+}
+
+#[tokio::test]
+async fn initialize_twice_fails() {
+    let mut h = Harness::new().await;
+    let pool = h.create_pool(30, None).await;
+    let payer = h.payer();
+
+    let result = h
+        .process(
+            &[instructions::initialize(
+                &pool.addresses,
+                &payer,
+                50,
+                None,
+                None,
+            )],
+            &[],
+        )
+        .await;
+    assert!(result.is_err());
+    assert_eq!(h.config(&pool.addresses).await.fees, 30);
+}
+
+#[tokio::test]
+async fn authority_updates_config() {
+    let mut h = Harness::new().await;
+    let authority = Keypair::new();
+    let pool = h.create_pool(30, Some(authority.pubkey())).await;
+    let next = Keypair::new().pubkey();
+
+    let ix = instructions::update_config(&pool.addresses, &authority.pubkey(), 50, Some(next));
+    h.process(&[ix], &[&authority]).await.unwrap();
+
+    let config = h.config(&pool.addresses).await;
+    assert_eq!(config.fees, 50);
+    assert_eq!(config.authority, Some(next));
+}
+
+#[tokio::test]
+async fn non_authority_cannot_update_config() {
+    let mut h = Harness::new().await;
+    let pool = h.create_pool(30, Some(Keypair::new().pubkey())).await;
+    let intruder = Keypair::new();
+
+    let ix = instructions::update_config(&pool.addresses, &intruder.pubkey(), 0, None);
+    let result = h.process(&[ix], &[&intruder]).await;
+    assert_eq!(custom_error(result), Some(AmmError::Unauthorized.into()));
+}
+
+#[tokio::test]
+async fn lp_decimals_default_to_mean_of_pair() {
+    let mut h = Harness::new().await;
+    let authority = Keypair::new();
+    let mint_x = h.create_mint(&authority.pubkey(), 6).await;
+    let mint_y = h.create_mint(&authority.pubkey(), 9).await;
+
+    let pool = h.initialize_pool(mint_x, mint_y, 30, None).await;
+
+    assert_eq!(h.mint(&pool.mint_lp).await.decimals, 8);
+}
+
+#[tokio::test]
+async fn lp_decimals_can_be_chosen() {
+    let mut h = Harness::new().await;
+    let authority = Keypair::new();
+    let mint_x = h.create_mint(&authority.pubkey(), 6).await;
+    let mint_y = h.create_mint(&authority.pubkey(), 9).await;
+    let addresses = PoolAddresses::new(mint_x, mint_y);
+    let payer = h.payer();
+
+    h.process(
+        &[instructions::initialize(
+            &addresses,
+            &payer,
+            30,
+            None,
+            Some(2),
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(h.mint(&addresses.mint_lp).await.decimals, 2);
+}
+
+#[tokio::test]
+async fn lp_decimals_are_capped() {
+    let mut h = Harness::new().await;
+    let authority = Keypair::new();
+    let mint_x = h.create_mint(&authority.pubkey(), 6).await;
+    let mint_y = h.create_mint(&authority.pubkey(), 9).await;
+    let addresses = PoolAddresses::new(mint_x, mint_y);
+    let payer = h.payer();
+    let initialize =
+        |lp_decimals| instructions::initialize(&addresses, &payer, 30, None, Some(lp_decimals));
+
+    let result = h.process(&[initialize(19)], &[]).await;
+    assert_eq!(
+        custom_error(result),
+        Some(AmmError::InvalidLpDecimals.into())
+    );
+    h.process(&[initialize(18)], &[]).await.unwrap();
+}
@@ -0,0 +1,310 @@
+use amm_client::{decode_limit_order, instructions, limit_order_address, LimitOrder};
+use amm_contract::error::AmmError;
+use amm_contract::events::LimitOrderFillEvent;
+use amm_harness::{custom_error, tokio, Harness, Pool};
+use anchor_lang::AccountSerialize;
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::spl_token;
+use solana_sdk::signature::{Keypair, Signer};
+
+/// A seeded 30 bps pool at 1 y per x, its authority and a maker.
+async fn seeded_pool(h: &mut Harness) -> (Pool, Keypair, Keypair) {
+    let authority = h.create_user(1_000_000_000).await;
+    let pool = h.create_pool(30, Some(authority.pubkey())).await;
+    let lp = h.funded_user(&pool, 100_000_000, 100_000_000).await;
+    h.deposit(&pool.addresses, &lp, 100_000_000, 100_000_000, 100_000_000)
+        .await
+        .unwrap();
+    let maker = h.funded_user(&pool, 20_000_000, 0).await;
+    (pool, authority, maker)
+}
+
+async fn order(h: &mut Harness, pool: &Pool, owner: &Keypair, order_id: u64) -> Option<LimitOrder> {
+    let (address, _) = limit_order_address(&pool.addresses.config, &owner.pubkey(), order_id);
+    let account = h.ctx.banks_client.get_account(address).await.unwrap()?;
+    Some(decode_limit_order(&account.data).unwrap())
+}
+
+/// Moves on a slot, as fills wait for the slot after an order is placed.
+async fn next_slot(h: &mut Harness) {
+    let slot = h.ctx.banks_client.get_root_slot().await.unwrap();
+    h.ctx.warp_to_slot(slot + 1).unwrap();
+}
+
+/// Pushes the price of x up to about 1.21 y.
+async fn pump_x(h: &mut Harness, pool: &Pool) {
+    let trader = h.funded_user(pool, 0, 10_000_000).await;
+    h.swap(&pool.addresses, &trader, 10_000_000, 0, false)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn order_fills_once_the_price_reaches_it() {
+    let mut h = Harness::new().await;
+    let (pool, _, maker) = seeded_pool(&mut h).await;
+    let addresses = pool.addresses;
+    let cranker = h.create_user(1_000_000_000).await;
+
+    let ix =
+        instructions::place_limit_order(&addresses, &maker.pubkey(), 3, true, 1_000_000, 1_050_000);
+    h.process(&[ix], &[&maker]).await.unwrap();
+    let (address, _) = limit_order_address(&addresses.config, &maker.pubkey(), 3);
+    let escrow = get_associated_token_address(&address, &addresses.mint_x);
+    assert_eq!(h.token_balance(&escrow).await, 1_000_000);
+    let placed = order(&mut h, &pool, &maker, 3).await.unwrap();
+    assert_eq!(placed.remaining, 1_000_000);
+
+    next_slot(&mut h).await;
+    let ix = instructions::fill_limit_order(&addresses, &cranker.pubkey(), &placed);
+    let result = h.process(&[ix], &[&cranker]).await;
+    assert_eq!(
+        custom_error(result),
+        Some(AmmError::OrderNotFillable.into())
+    );
+
+    pump_x(&mut h, &pool).await;
+    let ix = instructions::fill_limit_order(&addresses, &cranker.pubkey(), &placed);
+    let events: Vec<LimitOrderFillEvent> = h.process_events(&[ix], &[&cranker]).await.unwrap();
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].amount_in, 1_000_000);
+    // the maker fee starts out at the swap fee
+    assert_eq!(events[0].fee, 3_000);
+    assert_eq!(events[0].remaining, 0);
+    let (x, y, _) = h.user_balances(&addresses, &maker.pubkey()).await;
+    assert_eq!(x, 19_000_000);
+    assert_eq!(y, events[0].amount_out);
+    assert!(y >= 1_050_000);
+    assert!(order(&mut h, &pool, &maker, 3).await.is_none());
+    assert_eq!(h.lamports(&escrow).await, 0);
+}
+
+#[tokio::test]
+async fn large_order_fills_only_down_to_its_limit() {
+    let mut h = Harness::new().await;
+    let (pool, _, maker) = seeded_pool(&mut h).await;
+    let addresses = pool.addresses;
+
+    let ix = instructions::place_limit_order(
+        &addresses,
+        &maker.pubkey(),
+        0,
+        true,
+        20_000_000,
+        22_000_000,
+    );
+    h.process(&[ix], &[&maker]).await.unwrap();
+    next_slot(&mut h).await;
+    pump_x(&mut h, &pool).await;
+
+    let placed = order(&mut h, &pool, &maker, 0).await.unwrap();
+    let ix = instructions::fill_limit_order(&addresses, &maker.pubkey(), &placed);
+    h.process(&[ix], &[&maker]).await.unwrap();
+
+    let filled = order(&mut h, &pool, &maker, 0).await.unwrap();
+    assert!(filled.remaining > 0 && filled.remaining < 20_000_000);
+    let sold = 20_000_000 - filled.remaining;
+    let bought = h.user_balances(&addresses, &maker.pubkey()).await.1;
+    assert!(bought as u128 * 20_000_000 >= sold as u128 * 22_000_000);
+
+    // the pool is left right at the limit price, leaving out the fee kept
+    let (vx, vy, _) = h.reserves(&addresses).await;
+    let fee = (sold as u128 * 30).div_ceil(10_000);
+    assert!(vy as u128 * 20_000_000 >= (vx as u128 - fee) * 22_000_000);
+    let ix = instructions::fill_limit_order(&addresses, &maker.pubkey(), &filled);
+    let result = h.process(&[ix], &[&maker]).await;
+    assert_eq!(
+        custom_error(result),
+        Some(AmmError::OrderNotFillable.into())
+    );
+
+    // what is left comes back on cancel
+    let ix = instructions::cancel_limit_order(&addresses, &maker.pubkey(), 0, true);
+    h.process(&[ix], &[&maker]).await.unwrap();
+    assert_eq!(
+        h.user_balances(&addresses, &maker.pubkey()).await.0,
+        filled.remaining
+    );
+    assert!(order(&mut h, &pool, &maker, 0).await.is_none());
+}
+
+#[tokio::test]
+async fn makers_pay_the_maker_fee() {
+    let mut h = Harness::new().await;
+    let (pool, authority, maker) = seeded_pool(&mut h).await;
+    let addresses = pool.addresses;
+
+    for too_high_or_low in [31, 14] {
+        let ix = instructions::set_maker_fee(&addresses, &authority.pubkey(), too_high_or_low);
+        let result = h.process(&[ix], &[&authority]).await;
+        assert_eq!(custom_error(result), Some(AmmError::InvalidFee.into()));
+    }
+    let ix = instructions::set_maker_fee(&addresses, &authority.pubkey(), 20);
+    h.process(&[ix], &[&authority]).await.unwrap();
+    assert_eq!(h.config(&addresses).await.maker_fee_bps, 20);
+
+    let ix =
+        instructions::place_limit_order(&addresses, &maker.pubkey(), 0, true, 1_000_000, 1_050_000);
+    h.process(&[ix], &[&maker]).await.unwrap();
+    next_slot(&mut h).await;
+    pump_x(&mut h, &pool).await;
+
+    let placed = order(&mut h, &pool, &maker, 0).await.unwrap();
+    let ix = instructions::fill_limit_order(&addresses, &maker.pubkey(), &placed);
+    let events: Vec<LimitOrderFillEvent> = h.process_events(&[ix], &[&maker]).await.unwrap();
+    assert_eq!(events[0].fee, 2_000);
+}
+
+#[tokio::test]
+async fn orders_cannot_be_filled_in_the_slot_they_are_placed() {
+    let mut h = Harness::new().await;
+    let (pool, _, maker) = seeded_pool(&mut h).await;
+    let addresses = pool.addresses;
+    pump_x(&mut h, &pool).await;
+
+    // placing and filling at once would swap at the maker fee
+    let place =
+        instructions::place_limit_order(&addresses, &maker.pubkey(), 0, true, 1_000_000, 1_050_000);
+    let placed = LimitOrder {
+        config: addresses.config,
+        owner: maker.pubkey(),
+        order_id: 0,
+        is_x_in: true,
+        amount_in: 1_000_000,
+        min_out: 1_050_000,
+        remaining: 1_000_000,
+        placed_slot: 0,
+        bump: 0,
+    };
+    let fill = instructions::fill_limit_order(&addresses, &maker.pubkey(), &placed);
+    let result = h.process(&[place.clone(), fill.clone()], &[&maker]).await;
+    assert_eq!(custom_error(result), Some(AmmError::OrderTooFresh.into()));
+
+    h.process(&[place], &[&maker]).await.unwrap();
+    let result = h.process(std::slice::from_ref(&fill), &[&maker]).await;
+    assert_eq!(custom_error(result), Some(AmmError::OrderTooFresh.into()));
+
+    // a slot later it fills, paying what a swap of the same size would
+    next_slot(&mut h).await;
+    let events: Vec<LimitOrderFillEvent> = h.process_events(&[fill], &[&maker]).await.unwrap();
+    assert_eq!(events[0].fee, 3_000);
+}
+
+#[tokio::test]
+async fn configs_without_a_maker_fee_charge_half_the_swap_fee() {
+    let mut h = Harness::new().await;
+    let (pool, _, maker) = seeded_pool(&mut h).await;
+    let addresses = pool.addresses;
+
+    // as zero-extended by `migrate_config`
+    let mut config = h.config(&addresses).await;
+    config.maker_fee_bps = 0;
+    let mut account = h
+        .ctx
+        .banks_client
+        .get_account(addresses.config)
+        .await
+        .unwrap()
+        .unwrap();
+    account.data.clear();
+    config.try_serialize(&mut account.data).unwrap();
+    h.ctx.set_account(&addresses.config, &account.into());
+
+    let ix =
+        instructions::place_limit_order(&addresses, &maker.pubkey(), 0, true, 1_000_000, 1_050_000);
+    h.process(&[ix], &[&maker]).await.unwrap();
+    next_slot(&mut h).await;
+    pump_x(&mut h, &pool).await;
+
+    let placed = order(&mut h, &pool, &maker, 0).await.unwrap();
+    let ix = instructions::fill_limit_order(&addresses, &maker.pubkey(), &placed);
+    let events: Vec<LimitOrderFillEvent> = h.process_events(&[ix], &[&maker]).await.unwrap();
+    assert_eq!(events[0].fee, 1_500);
+}
+
+#[tokio::test]
+async fn dust_in_the_escrow_does_not_block_the_last_fill() {
+    let mut h = Harness::new().await;
+    let (pool, _, maker) = seeded_pool(&mut h).await;
+    let addresses = pool.addresses;
+    let griefer = h.funded_user(&pool, 1, 0).await;
+
+    let ix =
+        instructions::place_limit_order(&addresses, &maker.pubkey(), 0, true, 1_000_000, 1_050_000);
+    h.process(&[ix], &[&maker]).await.unwrap();
+    let (address, _) = limit_order_address(&addresses.config, &maker.pubkey(), 0);
+    let escrow = get_associated_token_address(&address, &addresses.mint_x);
+    let ix = spl_token::instruction::transfer(
+        &spl_token::ID,
+        &get_associated_token_address(&griefer.pubkey(), &addresses.mint_x),
+        &escrow,
+        &griefer.pubkey(),
+        &[],
+        1,
+    )
+    .unwrap();
+    h.process(&[ix], &[&griefer]).await.unwrap();
+    next_slot(&mut h).await;
+    pump_x(&mut h, &pool).await;
+
+    // the dust goes back to the owner with the escrow's rent
+    let placed = order(&mut h, &pool, &maker, 0).await.unwrap();
+    let ix = instructions::fill_limit_order(&addresses, &maker.pubkey(), &placed);
+    h.process(&[ix], &[&maker]).await.unwrap();
+    assert!(order(&mut h, &pool, &maker, 0).await.is_none());
+    assert_eq!(h.lamports(&escrow).await, 0);
+    assert_eq!(
+        h.user_balances(&addresses, &maker.pubkey()).await.0,
+        19_000_001
+    );
+}
+
+#[tokio::test]
+async fn fills_pay_only_the_order_owner() {
+    let mut h = Harness::new().await;
+    let (pool, _, maker) = seeded_pool(&mut h).await;
+    let addresses = pool.addresses;
+    let thief = h.funded_user(&pool, 0, 1).await;
+
+    let ix =
+        instructions::place_limit_order(&addresses, &maker.pubkey(), 0, true, 1_000_000, 1_050_000);
+    h.process(&[ix], &[&maker]).await.unwrap();
+    next_slot(&mut h).await;
+    pump_x(&mut h, &pool).await;
+
+    // the crank points the owner and payout at itself
+    let placed = order(&mut h, &pool, &maker, 0).await.unwrap();
+    let mut ix = instructions::fill_limit_order(&addresses, &thief.pubkey(), &placed);
+    ix.accounts[8].pubkey = thief.pubkey();
+    ix.accounts[9].pubkey = get_associated_token_address(&thief.pubkey(), &addresses.mint_x);
+    ix.accounts[10].pubkey = get_associated_token_address(&thief.pubkey(), &addresses.mint_y);
+    assert!(h.process(&[ix], &[&thief]).await.is_err());
+    assert_eq!(
+        order(&mut h, &pool, &maker, 0).await.unwrap().remaining,
+        1_000_000
+    );
+}
+
+#[tokio::test]
+async fn fills_wait_while_swaps_are_batched() {
+    let mut h = Harness::new().await;
+    let (pool, authority, maker) = seeded_pool(&mut h).await;
+    let addresses = pool.addresses;
+
+    let ix =
+        instructions::place_limit_order(&addresses, &maker.pubkey(), 0, true, 1_000_000, 1_050_000);
+    h.process(&[ix], &[&maker]).await.unwrap();
+    next_slot(&mut h).await;
+    pump_x(&mut h, &pool).await;
+
+    let ix = instructions::set_batch_window(&addresses, &authority.pubkey(), Some(60));
+    h.process(&[ix], &[&authority]).await.unwrap();
+    let placed = order(&mut h, &pool, &maker, 0).await.unwrap();
+    let ix = instructions::fill_limit_order(&addresses, &maker.pubkey(), &placed);
+    let result = h.process(&[ix], &[&maker]).await;
+    assert_eq!(
+        custom_error(result),
+        Some(AmmError::BatchAuctionOnly.into())
+    );
+}
@@ -0,0 +1,190 @@
+use amm_client::{decode_lp_lock, instructions, lp_lock_address, LpLock};
+use amm_contract::error::AmmError;
+use amm_harness::{custom_error, tokio, Harness, Pool};
+use anchor_spl::associated_token::get_associated_token_address;
+use solana_sdk::signature::{Keypair, Signer};
+
+const NOW: i64 = 1_700_000_000;
+
+/// A seeded pool and an LP holding 1_000_000 LP, at `NOW`.
+async fn seeded_pool(h: &mut Harness) -> (Pool, Keypair) {
+    h.set_unix_timestamp(NOW).await;
+    let pool = h.create_pool(30, None).await;
+    let lp = h.funded_user(&pool, 1_000_000, 1_000_000).await;
+    h.deposit(&pool.addresses, &lp, 1_000_000, 1_000_000, 1_000_000)
+        .await
+        .unwrap();
+    (pool, lp)
+}
+
+async fn lock(h: &mut Harness, pool: &Pool, owner: &Keypair, lock_id: u64) -> Option<LpLock> {
+    let (address, _) = lp_lock_address(&pool.addresses.config, &owner.pubkey(), lock_id);
+    let account = h.ctx.banks_client.get_account(address).await.unwrap()?;
+    Some(decode_lp_lock(&account.data).unwrap())
+}
+
+#[tokio::test]
+async fn locked_lp_is_escrowed_until_unlock() {
+    let mut h = Harness::new().await;
+    let (pool, lp) = seeded_pool(&mut h).await;
+    let addresses = pool.addresses;
+
+    let ix = instructions::lock_lp(&addresses, &lp.pubkey(), 7, 600_000, NOW + 1_000, None);
+    h.process(&[ix], &[&lp]).await.unwrap();
+
+    let (address, _) = lp_lock_address(&addresses.config, &lp.pubkey(), 7);
+    let escrow = get_associated_token_address(&address, &addresses.mint_lp);
+    assert_eq!(h.token_balance(&escrow).await, 600_000);
+    assert_eq!(h.user_balances(&addresses, &lp.pubkey()).await.2, 400_000);
+    let locked = lock(&mut h, &pool, &lp, 7).await.unwrap();
+    assert_eq!(locked.config, addresses.config);
+    assert_eq!(locked.owner, lp.pubkey());
+    assert_eq!(locked.amount, 600_000);
+    assert_eq!(locked.vesting_end, NOW + 1_000);
+
+    h.set_unix_timestamp(NOW + 999).await;
+    let ix = instructions::unlock_lp(&addresses, &lp.pubkey(), 7);
+    let result = h.process(&[ix], &[&lp]).await;
+    assert_eq!(custom_error(result), Some(AmmError::StillLocked.into()));
+
+    // everything comes back at once, and the lock is closed
+    h.set_unix_timestamp(NOW + 1_000).await;
+    let ix = instructions::unlock_lp(&addresses, &lp.pubkey(), 7);
+    h.process(&[ix], &[&lp]).await.unwrap();
+    assert_eq!(h.user_balances(&addresses, &lp.pubkey()).await.2, 1_000_000);
+    assert!(lock(&mut h, &pool, &lp, 7).await.is_none());
+    assert_eq!(h.lamports(&escrow).await, 0);
+}
+
+#[tokio::test]
+async fn vesting_lock_releases_linearly() {
+    let mut h = Harness::new().await;
+    let (pool, lp) = seeded_pool(&mut h).await;
+    let addresses = pool.addresses;
+
+    let ix = instructions::lock_lp(
+        &addresses,
+        &lp.pubkey(),
+        0,
+        1_000_000,
+        NOW + 100,
+        Some(NOW + 1_100),
+    );
+    h.process(&[ix], &[&lp]).await.unwrap();
+
+    h.set_unix_timestamp(NOW + 350).await;
+    let ix = instructions::unlock_lp(&addresses, &lp.pubkey(), 0);
+    h.process(&[ix], &[&lp]).await.unwrap();
+    assert_eq!(h.user_balances(&addresses, &lp.pubkey()).await.2, 250_000);
+    assert_eq!(
+        lock(&mut h, &pool, &lp, 0).await.unwrap().withdrawn,
+        250_000
+    );
+
+    h.set_unix_timestamp(NOW + 5_000).await;
+    let ix = instructions::unlock_lp(&addresses, &lp.pubkey(), 0);
+    h.process(&[ix], &[&lp]).await.unwrap();
+    assert_eq!(h.user_balances(&addresses, &lp.pubkey()).await.2, 1_000_000);
+    assert!(lock(&mut h, &pool, &lp, 0).await.is_none());
+}
+
+#[tokio::test]
+async fn lock_must_unlock_in_the_future() {
+    let mut h = Harness::new().await;
+    let (pool, lp) = seeded_pool(&mut h).await;
+
+    for (unlock_at, vesting_end) in [(NOW, None), (NOW + 100, Some(NOW + 99))] {
+        let ix = instructions::lock_lp(&pool.addresses, &lp.pubkey(), 0, 1, unlock_at, vesting_end);
+        let result = h.process(&[ix], &[&lp]).await;
+        assert_eq!(
+            custom_error(result),
+            Some(AmmError::InvalidLockSchedule.into())
+        );
+    }
+}
+
+#[tokio::test]
+async fn only_owner_unlocks() {
+    let mut h = Harness::new().await;
+    let (pool, lp) = seeded_pool(&mut h).await;
+    let addresses = pool.addresses;
+    let thief = h.funded_user(&pool, 0, 0).await;
+
+    let ix = instructions::lock_lp(&addresses, &lp.pubkey(), 0, 1_000, NOW + 10, None);
+    h.process(&[ix], &[&lp]).await.unwrap();
+    h.set_unix_timestamp(NOW + 10).await;
+
+    // the thief signs for the owner's lock, paying out to their own ATA
+    let mut ix = instructions::unlock_lp(&addresses, &lp.pubkey(), 0);
+    let thief_lp = get_associated_token_address(&thief.pubkey(), &addresses.mint_lp);
+    ix.accounts[0].pubkey = thief.pubkey();
+    ix.accounts[4].pubkey = thief_lp;
+    assert!(h.process(&[ix], &[&thief]).await.is_err());
+    assert_eq!(lock(&mut h, &pool, &lp, 0).await.unwrap().withdrawn, 0);
+}
+
+#[tokio::test]
+async fn claiming_fees_keeps_the_principal_locked() {
+    let mut h = Harness::new().await;
+    let (pool, lp) = seeded_pool(&mut h).await;
+    let addresses = pool.addresses;
+
+    let ix = instructions::lock_lp(&addresses, &lp.pubkey(), 0, 1_000_000, NOW + 100, None);
+    h.process(&[ix], &[&lp]).await.unwrap();
+    let (vx0, vy0, _) = h.reserves(&addresses).await;
+
+    let trader = h.funded_user(&pool, 500_000, 500_000).await;
+    for is_x_in in [true, false, true, false] {
+        h.swap(&addresses, &trader, 200_000, 0, is_x_in)
+            .await
+            .unwrap();
+    }
+
+    let ix = instructions::claim_locked_fees(&addresses, &lp.pubkey(), 0);
+    h.process(&[ix], &[&lp]).await.unwrap();
+    let (x, y, _) = h.user_balances(&addresses, &lp.pubkey()).await;
+    assert!(x > 0 && y > 0);
+
+    // the lock owns the whole pool, so what stays locked is worth at
+    // least what was locked
+    let locked = lock(&mut h, &pool, &lp, 0).await.unwrap();
+    let (vx, vy, supply) = h.reserves(&addresses).await;
+    assert!(locked.amount < 1_000_000);
+    assert_eq!(supply, locked.amount);
+    assert!(vx as u128 * vy as u128 >= vx0 as u128 * vy0 as u128);
+
+    // nothing more to claim until more swaps happen
+    let ix = instructions::claim_locked_fees(&addresses, &lp.pubkey(), 0);
+    let result = h.process(&[ix], &[&lp]).await;
+    assert_eq!(custom_error(result), Some(AmmError::NoFeesAccrued.into()));
+
+    // the principal still unlocks in full
+    h.set_unix_timestamp(NOW + 100).await;
+    let ix = instructions::unlock_lp(&addresses, &lp.pubkey(), 0);
+    h.process(&[ix], &[&lp]).await.unwrap();
+    assert_eq!(
+        h.user_balances(&addresses, &lp.pubkey()).await.2,
+        locked.amount
+    );
+    assert!(lock(&mut h, &pool, &lp, 0).await.is_none());
+}
+
+#[tokio::test]
+async fn only_owner_claims_locked_fees() {
+    let mut h = Harness::new().await;
+    let (pool, lp) = seeded_pool(&mut h).await;
+    let addresses = pool.addresses;
+    let thief = h.funded_user(&pool, 100_000, 0).await;
+
+    let ix = instructions::lock_lp(&addresses, &lp.pubkey(), 0, 1_000, NOW + 10, None);
+    h.process(&[ix], &[&lp]).await.unwrap();
+    h.swap(&addresses, &thief, 100_000, 0, true).await.unwrap();
+
+    let mut ix = instructions::claim_locked_fees(&addresses, &lp.pubkey(), 0);
+    let (thief_x, thief_y, _) = addresses.user_accounts(&thief.pubkey());
+    ix.accounts[0].pubkey = thief.pubkey();
+    ix.accounts[9].pubkey = thief_x;
+    ix.accounts[10].pubkey = thief_y;
+    assert!(h.process(&[ix], &[&thief]).await.is_err());
+    assert_eq!(lock(&mut h, &pool, &lp, 0).await.unwrap().amount, 1_000);
+}
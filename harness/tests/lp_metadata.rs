@@ -0,0 +1,114 @@
+use amm_client::{instructions, metadata_address, PoolAddresses};
+use amm_contract::error::AmmError;
+use amm_harness::{custom_error, token_metadata, tokio, Harness};
+use anchor_spl::metadata::mpl_token_metadata::accounts::Metadata;
+use anchor_spl::token::spl_token::native_mint;
+use borsh::BorshSerialize;
+use solana_sdk::account::Account;
+use solana_sdk::signature::{Keypair, Signer};
+
+async fn lp_metadata(h: &mut Harness, pool: &PoolAddresses) -> Metadata {
+    let account = h
+        .ctx
+        .banks_client
+        .get_account(metadata_address(&pool.mint_lp))
+        .await
+        .unwrap()
+        .expect("LP metadata exists");
+    Metadata::from_bytes(&account.data).unwrap()
+}
+
+#[tokio::test]
+async fn names_lp_after_the_pair_symbols() {
+    let mut h = Harness::new().await;
+    let authority = Keypair::new();
+    let mint_y = h.create_mint(&authority.pubkey(), 6).await;
+    let padded = format!("{:\0<10}", "USDC");
+    let data = token_metadata::metadata(&mint_y, &authority.pubkey(), "USD Coin", &padded)
+        .try_to_vec()
+        .unwrap();
+    h.ctx.set_account(
+        &metadata_address(&mint_y),
+        &Account {
+            lamports: 1_000_000_000,
+            data,
+            owner: anchor_spl::metadata::ID,
+            executable: false,
+            rent_epoch: 0,
+        }
+        .into(),
+    );
+    let pool = h.initialize_pool(native_mint::ID, mint_y, 30, None).await;
+
+    let payer = h.payer();
+    h.process(&[instructions::create_lp_metadata(&pool, &payer)], &[])
+        .await
+        .unwrap();
+
+    let metadata = lp_metadata(&mut h, &pool).await;
+    assert_eq!(metadata.name, "SOL-USDC LP");
+    assert_eq!(metadata.symbol, "SOLUSDCLP");
+    assert_eq!(metadata.mint, pool.mint_lp);
+    assert_eq!(metadata.update_authority, pool.config);
+    assert!(!metadata.is_mutable);
+}
+
+#[tokio::test]
+async fn mints_without_metadata_use_their_address() {
+    let mut h = Harness::new().await;
+    let pool = h.create_pool(30, None).await.addresses;
+    let user = h.create_user(1_000_000_000).await;
+
+    // anyone can pay for it
+    h.process(
+        &[instructions::create_lp_metadata(&pool, &user.pubkey())],
+        &[&user],
+    )
+    .await
+    .unwrap();
+
+    let x = &pool.mint_x.to_string()[..4];
+    let y = &pool.mint_y.to_string()[..4];
+    let metadata = lp_metadata(&mut h, &pool).await;
+    assert_eq!(metadata.name, format!("{x}-{y} LP"));
+    assert_eq!(metadata.symbol, format!("{x}{y}LP"));
+}
+
+#[tokio::test]
+async fn lp_metadata_is_created_once() {
+    let mut h = Harness::new().await;
+    let pool = h.create_pool(30, None).await.addresses;
+    let payer = h.payer();
+    h.process(&[instructions::create_lp_metadata(&pool, &payer)], &[])
+        .await
+        .unwrap();
+
+    let result = h
+        .process(&[instructions::create_lp_metadata(&pool, &payer)], &[])
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn pools_with_an_authority_leave_it_to_the_authority() {
+    let mut h = Harness::new().await;
+    let authority = h.create_user(1_000_000_000).await;
+    let pool = h.create_pool(30, Some(authority.pubkey())).await.addresses;
+    let user = h.create_user(1_000_000_000).await;
+
+    let result = h
+        .process(
+            &[instructions::create_lp_metadata(&pool, &user.pubkey())],
+            &[&user],
+        )
+        .await;
+    assert_eq!(custom_error(result), Some(AmmError::Unauthorized.into()));
+
+    h.process(
+        &[instructions::create_lp_metadata(&pool, &authority.pubkey())],
+        &[&authority],
+    )
+    .await
+    .unwrap();
+    assert_eq!(lp_metadata(&mut h, &pool).await.mint, pool.mint_lp);
+}
@@ -0,0 +1,91 @@
+use amm_client::instructions;
+use amm_contract::error::AmmError;
+use amm_contract::states::Config;
+use amm_harness::{custom_error, tokio, Harness, Pool};
+use anchor_lang::error::ErrorCode;
+use anchor_lang::prelude::Rent;
+use anchor_lang::{AnchorSerialize, Discriminator};
+use solana_sdk::account::Account;
+use solana_sdk::signature::{Keypair, Signer};
+
+/// Size of a config before any field was appended: mints, fee, bumps and
+/// an optional authority.
+const V1_LEN: usize = 8 + 32 + 32 + 2 + 1 + 1 + 33;
+
+/// Rewrites `pool`'s config in the layout the first release created.
+async fn downgrade(h: &mut Harness, pool: &Pool) {
+    let config = h.config(&pool.addresses).await;
+    let mut data = Config::DISCRIMINATOR.to_vec();
+    (
+        config.mint_x,
+        config.mint_y,
+        config.fees,
+        config.my_bump,
+        config.lp_bump,
+        config.authority,
+    )
+        .serialize(&mut data)
+        .unwrap();
+    data.resize(V1_LEN, 0);
+
+    h.ctx.set_account(
+        &pool.addresses.config,
+        &Account {
+            lamports: Rent::default().minimum_balance(V1_LEN),
+            data,
+            owner: amm_contract::ID,
+            executable: false,
+            rent_epoch: 0,
+        }
+        .into(),
+    );
+}
+
+#[tokio::test]
+async fn old_configs_work_again_once_migrated() {
+    let mut h = Harness::new().await;
+    let authority = Keypair::new().pubkey();
+    let pool = h.create_pool(30, Some(authority)).await;
+    let lp = h.funded_user(&pool, 100_000_000, 100_000_000).await;
+    h.deposit(&pool.addresses, &lp, 50_000_000, 100_000_000, 100_000_000)
+        .await
+        .unwrap();
+    let trader = h.funded_user(&pool, 1_000_000, 0).await;
+    downgrade(&mut h, &pool).await;
+
+    let result = h.swap(&pool.addresses, &trader, 1_000_000, 0, true).await;
+    assert_eq!(
+        custom_error(result),
+        Some(ErrorCode::AccountDidNotDeserialize.into())
+    );
+
+    let payer = h.payer();
+    h.process(
+        &[instructions::migrate_config(&pool.addresses, &payer)],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let config = h.config(&pool.addresses).await;
+    assert_eq!((config.fees, config.authority), (30, Some(authority)));
+    assert!(config.dynamic_fee.is_none() && config.oracle.is_none());
+    h.swap(&pool.addresses, &trader, 1_000_000, 0, true)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn current_configs_are_left_alone() {
+    let mut h = Harness::new().await;
+    let pool = h.create_pool(30, None).await;
+    let payer = h.payer();
+
+    let result = h
+        .process(
+            &[instructions::migrate_config(&pool.addresses, &payer)],
+            &[],
+        )
+        .await;
+    assert_eq!(custom_error(result), Some(AmmError::ConfigUpToDate.into()));
+}
@@ -0,0 +1,149 @@
+use amm_client::{instructions, temp_wsol_address, PoolAddresses};
+use amm_contract::error::AmmError;
+use amm_harness::{custom_error, tokio, Harness};
+use anchor_lang::prelude::Rent;
+use anchor_spl::token::spl_token::native_mint;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_system_interface::instruction as system_instruction;
+
+const SOL: u64 = 1_000_000_000;
+
+/// A SOL (x) / token (y) pool seeded with 10 SOL and 1000 tokens, and a
+/// trader holding 5 SOL and 500 tokens but no WSOL account.
+async fn sol_pool(h: &mut Harness) -> (PoolAddresses, Keypair) {
+    let mint_authority = Keypair::new();
+    let mint_y = h.create_mint(&mint_authority.pubkey(), 6).await;
+    let pool = h.initialize_pool(native_mint::ID, mint_y, 30, None).await;
+
+    let lp = h.create_user(20 * SOL).await;
+    h.mint_to(&mint_y, &mint_authority, &lp.pubkey(), 1_000_000_000)
+        .await;
+    h.create_ata(&lp.pubkey(), &pool.mint_lp).await;
+    let ix = instructions::deposit_native(&pool, &lp.pubkey(), 1_000_000, 10 * SOL, 1_000_000_000);
+    h.process(&[ix], &[&lp]).await.unwrap();
+
+    let trader = h.create_user(5 * SOL).await;
+    h.mint_to(&mint_y, &mint_authority, &trader.pubkey(), 500_000_000)
+        .await;
+    h.create_ata(&trader.pubkey(), &pool.mint_lp).await;
+    (pool, trader)
+}
+
+#[tokio::test]
+async fn deposit_wraps_lamports_into_the_vault() {
+    let mut h = Harness::new().await;
+    let (pool, trader) = sol_pool(&mut h).await;
+
+    let ix = instructions::deposit_native(&pool, &trader.pubkey(), 100_000, u64::MAX, u64::MAX);
+    h.process(&[ix], &[&trader]).await.unwrap();
+
+    assert_eq!(h.lamports(&trader.pubkey()).await, 4 * SOL);
+    assert_eq!(
+        h.reserves(&pool).await,
+        (11 * SOL, 1_100_000_000, 1_100_000)
+    );
+}
+
+#[tokio::test]
+async fn swap_sol_in_and_out_without_wsol_account() {
+    let mut h = Harness::new().await;
+    let (pool, trader) = sol_pool(&mut h).await;
+    let owner = trader.pubkey();
+    let (wsol, trader_y, _) = pool.user_accounts(&owner);
+
+    let ix = instructions::swap_native(&pool, &owner, SOL, 0, true);
+    h.process(&[ix], &[&trader]).await.unwrap();
+
+    let (x, y, _) = h.reserves(&pool).await;
+    assert_eq!(x, 11 * SOL);
+    assert_eq!(h.lamports(&owner).await, 4 * SOL);
+    let received = h.token_balance(&trader_y).await - 500_000_000;
+    assert_eq!(received, 1_000_000_000 - y);
+
+    let ix = instructions::swap_native(&pool, &owner, received, 0, false);
+    h.process(&[ix], &[&trader]).await.unwrap();
+
+    let (x_after, _, _) = h.reserves(&pool).await;
+    assert_eq!(h.lamports(&owner).await, 4 * SOL + x - x_after);
+    assert!(h
+        .ctx
+        .banks_client
+        .get_account(wsol)
+        .await
+        .unwrap()
+        .is_none());
+    let (temp, _) = temp_wsol_address(&pool.config);
+    assert!(h
+        .ctx
+        .banks_client
+        .get_account(temp)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn withdraw_pays_sol_as_lamports() {
+    let mut h = Harness::new().await;
+    let (pool, trader) = sol_pool(&mut h).await;
+    let owner = trader.pubkey();
+    let ix = instructions::deposit_native(&pool, &owner, 100_000, u64::MAX, u64::MAX);
+    h.process(&[ix], &[&trader]).await.unwrap();
+
+    let ix = instructions::withdraw_native(&pool, &owner, SOL, 100_000_000, 100_000);
+    h.process(&[ix], &[&trader]).await.unwrap();
+
+    assert_eq!(h.lamports(&owner).await, 5 * SOL);
+    let (_, _, trader_lp) = pool.user_accounts(&owner);
+    assert_eq!(h.token_balance(&trader_lp).await, 0);
+    assert_eq!(
+        h.reserves(&pool).await,
+        (10 * SOL, 1_000_000_000, 1_000_000)
+    );
+}
+
+#[tokio::test]
+async fn payout_survives_lamports_sent_to_temp_account() {
+    let mut h = Harness::new().await;
+    let (pool, trader) = sol_pool(&mut h).await;
+    let (temp, _) = temp_wsol_address(&pool.config);
+    let stray = Rent::default().minimum_balance(0);
+    let payer = h.payer();
+    h.process(&[system_instruction::transfer(&payer, &temp, stray)], &[])
+        .await
+        .unwrap();
+
+    let ix = instructions::swap_native(&pool, &trader.pubkey(), 10_000_000, 0, false);
+    h.process(&[ix], &[&trader]).await.unwrap();
+
+    let (x, _, _) = h.reserves(&pool).await;
+    // the stray lamports go to the trader along with the payout
+    assert_eq!(
+        h.lamports(&trader.pubkey()).await,
+        5 * SOL + 10 * SOL - x + stray
+    );
+    assert!(h
+        .ctx
+        .banks_client
+        .get_account(temp)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn omitted_account_for_token_side_fails() {
+    let mut h = Harness::new().await;
+    let (pool, trader) = sol_pool(&mut h).await;
+
+    let mut ix = instructions::deposit_native(&pool, &trader.pubkey(), 100_000, u64::MAX, u64::MAX);
+    // anchor reads the program id as an omitted optional account
+    ix.accounts[7].pubkey = amm_contract::ID;
+    ix.accounts[7].is_writable = false;
+    let result = h.process(&[ix], &[&trader]).await;
+
+    assert_eq!(
+        custom_error(result),
+        Some(AmmError::MissingTokenAccount.into())
+    );
+}
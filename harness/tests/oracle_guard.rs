@@ -0,0 +1,348 @@
+use amm_client::{
+    batch_address, dca_address, decode_batch, decode_dca, decode_limit_order, instructions,
+    limit_order_address, OracleGuard, OracleKind,
+};
+use amm_contract::error::AmmError;
+use amm_contract::events::BatchSettleEvent;
+use amm_harness::{custom_error, oracle, tokio, Harness, Pool};
+use solana_program_test::BanksClientError;
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const NOW: i64 = 1_700_000_000;
+
+fn guard(kind: OracleKind, feed: Pubkey) -> OracleGuard {
+    OracleGuard {
+        kind,
+        feed,
+        invert: false,
+        max_deviation_bps: 200,
+        max_staleness: 60,
+    }
+}
+
+/// A seeded 30 bps pool at 1 y per x guarded by `feed`, holding `account`,
+/// a trader and the pool's authority.
+async fn guarded_pool(
+    h: &mut Harness,
+    guard: OracleGuard,
+    account: Account,
+) -> (Pool, Keypair, Keypair) {
+    h.set_unix_timestamp(NOW).await;
+    h.ctx.set_account(&guard.feed, &account.into());
+    let authority = Keypair::new();
+    let pool = h.create_pool(30, Some(authority.pubkey())).await;
+    let ix = instructions::set_oracle(&pool.addresses, &authority.pubkey(), Some(guard));
+    h.process(&[ix], &[&authority]).await.unwrap();
+
+    let lp = h.funded_user(&pool, 100_000_000, 100_000_000).await;
+    h.deposit(&pool.addresses, &lp, 100_000_000, 100_000_000, 100_000_000)
+        .await
+        .unwrap();
+    let trader = h.funded_user(&pool, 50_000_000, 50_000_000).await;
+    (pool, trader, authority)
+}
+
+async fn swap(
+    h: &mut Harness,
+    pool: &Pool,
+    trader: &Keypair,
+    feed: &Pubkey,
+    amount_in: u64,
+    is_x_in: bool,
+) -> Result<(), BanksClientError> {
+    let ix = instructions::swap_with_oracle(
+        &pool.addresses,
+        &trader.pubkey(),
+        feed,
+        amount_in,
+        0,
+        is_x_in,
+    );
+    h.process(&[ix], &[trader]).await
+}
+
+#[tokio::test]
+async fn swaps_near_the_feed_pass() {
+    let mut h = Harness::new().await;
+    let feed = Pubkey::new_unique();
+    let account = oracle::pyth_price_update(100_000_000, -8, NOW);
+    let (pool, trader, _) = guarded_pool(&mut h, guard(OracleKind::Pyth, feed), account).await;
+
+    swap(&mut h, &pool, &trader, &feed, 100_000, true)
+        .await
+        .unwrap();
+    swap(&mut h, &pool, &trader, &feed, 100_000, false)
+        .await
+        .unwrap();
+
+    // about 5% under the feed
+    let result = swap(&mut h, &pool, &trader, &feed, 5_000_000, true).await;
+    assert_eq!(
+        custom_error(result),
+        Some(AmmError::OraclePriceDeviation.into())
+    );
+}
+
+#[tokio::test]
+async fn arbitrage_catches_the_pool_up_with_the_feed() {
+    let mut h = Harness::new().await;
+    let feed = Pubkey::new_unique();
+    let account = oracle::pyth_price_update(100_000_000, -8, NOW);
+    let (pool, trader, _) = guarded_pool(&mut h, guard(OracleKind::Pyth, feed), account).await;
+
+    // the feed jumps about 5% over the pool's price
+    h.ctx.set_account(
+        &feed,
+        &oracle::pyth_price_update(105_000_000, -8, NOW).into(),
+    );
+    let result = swap(&mut h, &pool, &trader, &feed, 100_000, true).await;
+    assert_eq!(
+        custom_error(result),
+        Some(AmmError::OraclePriceDeviation.into())
+    );
+    // buying x fills under the feed, but moves the pool toward it
+    swap(&mut h, &pool, &trader, &feed, 1_000_000, false)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn swaps_must_pass_a_fresh_feed() {
+    let mut h = Harness::new().await;
+    let feed = Pubkey::new_unique();
+    let account = oracle::pyth_price_update(100_000_000, -8, NOW);
+    let (pool, trader, _) = guarded_pool(&mut h, guard(OracleKind::Pyth, feed), account).await;
+
+    let result = h.swap(&pool.addresses, &trader, 100_000, 0, true).await;
+    assert_eq!(custom_error(result), Some(AmmError::MissingOracle.into()));
+
+    // another feed, and one the oracle program does not own
+    let other = Pubkey::new_unique();
+    h.ctx.set_account(
+        &other,
+        &oracle::pyth_price_update(100_000_000, -8, NOW).into(),
+    );
+    let result = swap(&mut h, &pool, &trader, &other, 100_000, true).await;
+    assert_eq!(custom_error(result), Some(AmmError::InvalidOracle.into()));
+    let mut forged = oracle::pyth_price_update(100_000_000, -8, NOW);
+    forged.owner = Pubkey::new_unique();
+    h.ctx.set_account(&feed, &forged.into());
+    let result = swap(&mut h, &pool, &trader, &feed, 100_000, true).await;
+    assert_eq!(custom_error(result), Some(AmmError::InvalidOracle.into()));
+
+    h.ctx.set_account(
+        &feed,
+        &oracle::pyth_price_update(100_000_000, -8, NOW).into(),
+    );
+    h.set_unix_timestamp(NOW + 61).await;
+    let result = swap(&mut h, &pool, &trader, &feed, 100_000, true).await;
+    assert_eq!(custom_error(result), Some(AmmError::StaleOracle.into()));
+}
+
+#[tokio::test]
+async fn switchboard_feeds_may_quote_the_pair_inverted() {
+    let mut h = Harness::new().await;
+    let feed = Pubkey::new_unique();
+    // 1.05 x per y, so y trades about 5% over the pool's price
+    let account = oracle::switchboard_feed(1_050_000_000_000_000_000, NOW);
+    let guard = OracleGuard {
+        invert: true,
+        ..guard(OracleKind::Switchboard, feed)
+    };
+    let (pool, trader, _) = guarded_pool(&mut h, guard, account).await;
+
+    // buying y only widens the gap; selling it would be arbitrage
+    let result = swap(&mut h, &pool, &trader, &feed, 100_000, false).await;
+    assert_eq!(
+        custom_error(result),
+        Some(AmmError::OraclePriceDeviation.into())
+    );
+    // once the feed is back at the pool's price
+    h.ctx.set_account(
+        &feed,
+        &oracle::switchboard_feed(1_000_000_000_000_000_000, NOW).into(),
+    );
+    swap(&mut h, &pool, &trader, &feed, 100_000, true)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn fills_and_slices_must_pass_the_feed() {
+    let mut h = Harness::new().await;
+    let feed = Pubkey::new_unique();
+    let account = oracle::pyth_price_update(100_000_000, -8, NOW);
+    let (pool, trader, _) = guarded_pool(&mut h, guard(OracleKind::Pyth, feed), account).await;
+    let addresses = pool.addresses;
+    let cranker = h.funded_user(&pool, 0, 0).await;
+
+    let ix =
+        instructions::place_limit_order(&addresses, &trader.pubkey(), 0, true, 500_000, 450_000);
+    h.process(&[ix], &[&trader]).await.unwrap();
+    let ix = instructions::open_dca(
+        &addresses,
+        &trader.pubkey(),
+        0,
+        true,
+        1_000_000,
+        500_000,
+        3600,
+        0,
+        10,
+    );
+    h.process(&[ix], &[&trader]).await.unwrap();
+    let slot = h.ctx.banks_client.get_root_slot().await.unwrap();
+    h.ctx.warp_to_slot(slot + 1).unwrap();
+    h.set_unix_timestamp(NOW).await;
+
+    let (address, _) = limit_order_address(&addresses.config, &trader.pubkey(), 0);
+    let account = h.ctx.banks_client.get_account(address).await.unwrap();
+    let order = decode_limit_order(&account.unwrap().data).unwrap();
+    let (address, _) = dca_address(&addresses.config, &trader.pubkey(), 0);
+    let account = h.ctx.banks_client.get_account(address).await.unwrap();
+    let dca = decode_dca(&account.unwrap().data).unwrap();
+
+    let fill = instructions::fill_limit_order(&addresses, &cranker.pubkey(), &order);
+    let result = h.process(&[fill], &[&cranker]).await;
+    assert_eq!(custom_error(result), Some(AmmError::MissingOracle.into()));
+    let slice = instructions::execute_dca(&addresses, &cranker.pubkey(), &dca);
+    let result = h.process(&[slice], &[&cranker]).await;
+    assert_eq!(custom_error(result), Some(AmmError::MissingOracle.into()));
+
+    // the feed moves about 5% over the pool's price
+    h.ctx.set_account(
+        &feed,
+        &oracle::pyth_price_update(105_000_000, -8, NOW).into(),
+    );
+    let fill =
+        instructions::fill_limit_order_with_oracle(&addresses, &cranker.pubkey(), &order, &feed);
+    let result = h.process(std::slice::from_ref(&fill), &[&cranker]).await;
+    assert_eq!(
+        custom_error(result),
+        Some(AmmError::OraclePriceDeviation.into())
+    );
+    let slice = instructions::execute_dca_with_oracle(&addresses, &cranker.pubkey(), &dca, &feed);
+    let result = h.process(std::slice::from_ref(&slice), &[&cranker]).await;
+    assert_eq!(
+        custom_error(result),
+        Some(AmmError::OraclePriceDeviation.into())
+    );
+
+    h.ctx.set_account(
+        &feed,
+        &oracle::pyth_price_update(100_000_000, -8, NOW).into(),
+    );
+    h.process(&[fill], &[&cranker]).await.unwrap();
+    h.process(&[slice], &[&cranker]).await.unwrap();
+}
+
+#[tokio::test]
+async fn batches_far_from_the_feed_are_refunded() {
+    const WINDOW: i64 = 60;
+    let batch_id = (NOW / WINDOW) as u64;
+
+    let mut h = Harness::new().await;
+    let feed = Pubkey::new_unique();
+    let account = oracle::pyth_price_update(100_000_000, -8, NOW);
+    let (pool, trader, authority) =
+        guarded_pool(&mut h, guard(OracleKind::Pyth, feed), account).await;
+    let addresses = pool.addresses;
+    let ix = instructions::set_batch_window(&addresses, &authority.pubkey(), Some(WINDOW));
+    h.process(&[ix], &[&authority]).await.unwrap();
+
+    let ix =
+        instructions::submit_swap_intent(&addresses, &trader.pubkey(), batch_id, 100_000, 0, true);
+    h.process(&[ix], &[&trader]).await.unwrap();
+    h.set_unix_timestamp((batch_id as i64 + 1) * WINDOW).await;
+
+    let ix = instructions::settle_batch(&addresses, batch_id);
+    let result = h.process(&[ix], &[]).await;
+    assert_eq!(custom_error(result), Some(AmmError::MissingOracle.into()));
+
+    // the feed moves about 5% over the pool's price
+    h.ctx.set_account(
+        &feed,
+        &oracle::pyth_price_update(105_000_000, -8, NOW).into(),
+    );
+    let ix = instructions::settle_batch_with_oracle(&addresses, batch_id, &feed);
+    let events: Vec<BatchSettleEvent> = h.process_events(&[ix], &[]).await.unwrap();
+    assert_eq!((events[0].amount_x_in, events[0].amount_y_out), (0, 0));
+    assert_eq!(
+        h.reserves(&addresses).await,
+        (100_000_000, 100_000_000, 100_000_000)
+    );
+
+    let (address, _) = batch_address(&addresses.config, batch_id);
+    let account = h.ctx.banks_client.get_account(address).await.unwrap();
+    let batch = decode_batch(&account.unwrap().data).unwrap();
+    let ix = instructions::claim_swap_intents(&addresses, &trader.pubkey(), &batch);
+    h.process(&[ix], &[&trader]).await.unwrap();
+    assert_eq!(
+        h.user_balances(&addresses, &trader.pubkey()).await.0,
+        50_000_000
+    );
+}
+
+#[tokio::test]
+async fn batches_past_a_stale_feed_are_refunded() {
+    const WINDOW: i64 = 60;
+    let batch_id = (NOW / WINDOW) as u64;
+
+    let mut h = Harness::new().await;
+    let feed = Pubkey::new_unique();
+    let account = oracle::pyth_price_update(100_000_000, -8, NOW);
+    let (pool, trader, authority) =
+        guarded_pool(&mut h, guard(OracleKind::Pyth, feed), account).await;
+    let addresses = pool.addresses;
+    let ix = instructions::set_batch_window(&addresses, &authority.pubkey(), Some(WINDOW));
+    h.process(&[ix], &[&authority]).await.unwrap();
+
+    let ix =
+        instructions::submit_swap_intent(&addresses, &trader.pubkey(), batch_id, 100_000, 0, true);
+    h.process(&[ix], &[&trader]).await.unwrap();
+    h.set_unix_timestamp(NOW + 61).await;
+
+    // settling must not wait on the feed, or the intents stay locked
+    let ix = instructions::settle_batch_with_oracle(&addresses, batch_id, &feed);
+    let events: Vec<BatchSettleEvent> = h.process_events(&[ix], &[]).await.unwrap();
+    assert_eq!((events[0].amount_x_in, events[0].amount_y_out), (0, 0));
+
+    let (address, _) = batch_address(&addresses.config, batch_id);
+    let account = h.ctx.banks_client.get_account(address).await.unwrap();
+    let batch = decode_batch(&account.unwrap().data).unwrap();
+    let ix = instructions::claim_swap_intents(&addresses, &trader.pubkey(), &batch);
+    h.process(&[ix], &[&trader]).await.unwrap();
+    assert_eq!(
+        h.user_balances(&addresses, &trader.pubkey()).await.0,
+        50_000_000
+    );
+}
+
+#[tokio::test]
+async fn authority_sets_and_clears_oracle() {
+    let mut h = Harness::new().await;
+    let authority = Keypair::new();
+    let pool = h.create_pool(30, Some(authority.pubkey())).await;
+    let guard = guard(OracleKind::Pyth, Pubkey::new_unique());
+
+    let invalid = OracleGuard {
+        max_staleness: 0,
+        ..guard
+    };
+    let ix = instructions::set_oracle(&pool.addresses, &authority.pubkey(), Some(invalid));
+    let result = h.process(&[ix], &[&authority]).await;
+    assert_eq!(
+        custom_error(result),
+        Some(AmmError::InvalidOracleGuard.into())
+    );
+
+    let ix = instructions::set_oracle(&pool.addresses, &authority.pubkey(), Some(guard));
+    h.process(&[ix], &[&authority]).await.unwrap();
+    assert_eq!(h.config(&pool.addresses).await.oracle, Some(guard));
+
+    let ix = instructions::set_oracle(&pool.addresses, &authority.pubkey(), None);
+    h.process(&[ix], &[&authority]).await.unwrap();
+    assert_eq!(h.config(&pool.addresses).await.oracle, None);
+}
@@ -0,0 +1,200 @@
+use amm_client::{instructions, OracleGuard, OracleKind, PoolState};
+use amm_contract::error::AmmError;
+use amm_harness::{custom_error, oracle, tokio, Harness, Pool};
+use solana_program_test::BanksClientError;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const NOW: i64 = 1_700_000_000;
+const ONE: u128 = 1 << 64;
+
+/// Sets the Pyth feed at `feed` to `price` y per x, in hundredths.
+fn set_price(h: &mut Harness, feed: &Pubkey, price: i64) {
+    let account = oracle::pyth_price_update(price, -2, NOW);
+    h.ctx.set_account(feed, &account.into());
+}
+
+/// A seeded 30 bps pool at 1 y per x quoting on the PMM curve with k = 10%,
+/// its liquidity provider and a trader.
+async fn pmm_pool(h: &mut Harness, feed: &Pubkey) -> (Pool, Keypair, Keypair) {
+    h.set_unix_timestamp(NOW).await;
+    set_price(h, feed, 100);
+    let authority = Keypair::new();
+    let pool = h.create_pool(30, Some(authority.pubkey())).await;
+    let guard = OracleGuard {
+        kind: OracleKind::Pyth,
+        feed: *feed,
+        invert: false,
+        max_deviation_bps: 200,
+        max_staleness: 60,
+    };
+    let ixs = [
+        instructions::set_oracle(&pool.addresses, &authority.pubkey(), Some(guard)),
+        instructions::set_pmm(&pool.addresses, &authority.pubkey(), Some(1_000)),
+    ];
+    h.process(&ixs, &[&authority]).await.unwrap();
+
+    let lp = h.funded_user(&pool, 100_000_000, 100_000_000).await;
+    h.deposit(&pool.addresses, &lp, 100_000_000, 100_000_000, 100_000_000)
+        .await
+        .unwrap();
+    let trader = h.funded_user(&pool, 50_000_000, 50_000_000).await;
+    (pool, lp, trader)
+}
+
+/// Sells `amount_in` x, returning the y received.
+async fn sell_x(
+    h: &mut Harness,
+    pool: &Pool,
+    trader: &Keypair,
+    feed: &Pubkey,
+    amount_in: u64,
+) -> Result<u64, BanksClientError> {
+    let (_, before, _) = h.user_balances(&pool.addresses, &trader.pubkey()).await;
+    let ix =
+        instructions::swap_with_oracle(&pool.addresses, &trader.pubkey(), feed, amount_in, 0, true);
+    h.process(&[ix], &[trader]).await?;
+    let (_, after, _) = h.user_balances(&pool.addresses, &trader.pubkey()).await;
+    Ok(after - before)
+}
+
+#[tokio::test]
+async fn pmm_trades_near_the_oracle_price() {
+    let mut h = Harness::new().await;
+    let feed = Pubkey::new_unique();
+    let (pool, lp, trader) = pmm_pool(&mut h, &feed).await;
+
+    let (x, y, supply) = h.reserves(&pool.addresses).await;
+    let config = h.config(&pool.addresses).await;
+    let quote = PoolState::new(config, x, y, supply)
+        .at(NOW)
+        .with_oracle_price(ONE)
+        .quote_swap(5_000_000, true)
+        .unwrap();
+    let out = sell_x(&mut h, &pool, &trader, &feed, 5_000_000)
+        .await
+        .unwrap();
+    assert_eq!(out, quote.amount_out);
+
+    // the constant product pays about 4.75m for the same trade
+    assert!(out > 4_950_000);
+    let pmm = h.config(&pool.addresses).await.pmm.unwrap();
+    assert_eq!((pmm.target_x, pmm.target_y), (100_000_000, 100_000_000));
+
+    // withdrawing half the liquidity halves the targets
+    h.withdraw(&pool.addresses, &lp, 0, 0, 50_000_000)
+        .await
+        .unwrap();
+    let pmm = h.config(&pool.addresses).await.pmm.unwrap();
+    assert_eq!((pmm.target_x, pmm.target_y), (50_000_000, 50_000_000));
+}
+
+#[tokio::test]
+async fn pmm_quotes_move_with_the_oracle() {
+    let mut h = Harness::new().await;
+    let feed = Pubkey::new_unique();
+    let (pool, _, trader) = pmm_pool(&mut h, &feed).await;
+
+    let at_one = sell_x(&mut h, &pool, &trader, &feed, 1_000_000)
+        .await
+        .unwrap();
+    set_price(&mut h, &feed, 110);
+    let at_one_ten = sell_x(&mut h, &pool, &trader, &feed, 1_000_001)
+        .await
+        .unwrap();
+    assert!(at_one_ten > at_one * 108 / 100);
+}
+
+#[tokio::test]
+async fn pmm_needs_an_oracle() {
+    let mut h = Harness::new().await;
+    let authority = Keypair::new();
+    let pool = h.create_pool(30, Some(authority.pubkey())).await;
+    let set_pmm = |k| instructions::set_pmm(&pool.addresses, &authority.pubkey(), Some(k));
+
+    let result = h.process(&[set_pmm(1_000)], &[&authority]).await;
+    assert_eq!(custom_error(result), Some(AmmError::PmmNeedsOracle.into()));
+
+    let guard = OracleGuard {
+        kind: OracleKind::Pyth,
+        feed: Pubkey::new_unique(),
+        invert: false,
+        max_deviation_bps: 200,
+        max_staleness: 60,
+    };
+    let ix = instructions::set_oracle(&pool.addresses, &authority.pubkey(), Some(guard));
+    h.process(&[ix], &[&authority]).await.unwrap();
+    let result = h.process(&[set_pmm(0)], &[&authority]).await;
+    assert_eq!(custom_error(result), Some(AmmError::InvalidPmmSlope.into()));
+    h.process(&[set_pmm(1_000)], &[&authority]).await.unwrap();
+
+    // the oracle stays while the pool quotes around it
+    let ix = instructions::set_oracle(&pool.addresses, &authority.pubkey(), None);
+    let result = h.process(&[ix], &[&authority]).await;
+    assert_eq!(custom_error(result), Some(AmmError::PmmNeedsOracle.into()));
+}
+
+#[tokio::test]
+async fn long_term_orders_are_refused() {
+    let mut h = Harness::new().await;
+    let feed = Pubkey::new_unique();
+    let (pool, _, trader) = pmm_pool(&mut h, &feed).await;
+
+    // they quote on the constant product
+    let ix =
+        instructions::place_twamm_order(&pool.addresses, &trader.pubkey(), 0, true, 1_000_000, 1);
+    let result = h.process(&[ix], &[&trader]).await;
+    assert_eq!(custom_error(result), Some(AmmError::PmmUnsupported.into()));
+}
+
+#[tokio::test]
+async fn pmm_waits_for_twamm_orders_to_run_out() {
+    let mut h = Harness::new().await;
+    h.set_unix_timestamp(NOW).await;
+    let authority = Keypair::new();
+    let pool = h.create_pool(30, Some(authority.pubkey())).await;
+    let guard = OracleGuard {
+        kind: OracleKind::Pyth,
+        feed: Pubkey::new_unique(),
+        invert: false,
+        max_deviation_bps: 200,
+        max_staleness: 60,
+    };
+    let ix = instructions::set_oracle(&pool.addresses, &authority.pubkey(), Some(guard));
+    h.process(&[ix], &[&authority]).await.unwrap();
+    let lp = h.funded_user(&pool, 100_000_000, 100_000_000).await;
+    h.deposit(&pool.addresses, &lp, 100_000_000, 100_000_000, 100_000_000)
+        .await
+        .unwrap();
+    let seller = h.funded_user(&pool, 1_000_000, 0).await;
+    let ix =
+        instructions::place_twamm_order(&pool.addresses, &seller.pubkey(), 0, true, 1_000_000, 4);
+    h.process(&[ix], &[&seller]).await.unwrap();
+
+    let ix = instructions::set_pmm(&pool.addresses, &authority.pubkey(), Some(1_000));
+    let result = h.process(&[ix], &[&authority]).await;
+    assert_eq!(
+        custom_error(result),
+        Some(AmmError::TwammOrdersRunning.into())
+    );
+}
+
+#[tokio::test]
+async fn locked_lp_claims_no_fees_on_pmm_pools() {
+    let mut h = Harness::new().await;
+    let feed = Pubkey::new_unique();
+    let (pool, lp, _) = pmm_pool(&mut h, &feed).await;
+
+    let ix = instructions::lock_lp(
+        &pool.addresses,
+        &lp.pubkey(),
+        0,
+        1_000_000,
+        NOW + 1_000,
+        None,
+    );
+    h.process(&[ix], &[&lp]).await.unwrap();
+    let ix = instructions::claim_locked_fees(&pool.addresses, &lp.pubkey(), 0);
+    let result = h.process(&[ix], &[&lp]).await;
+    assert_eq!(custom_error(result), Some(AmmError::PmmUnsupported.into()));
+}
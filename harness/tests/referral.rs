@@ -0,0 +1,135 @@
+use amm_client::{instructions, PoolState};
+use amm_contract::error::AmmError;
+use amm_contract::events::SwapEvent;
+use amm_harness::{custom_error, tokio, Harness, Pool};
+use solana_sdk::signature::{Keypair, Signer};
+
+/// A seeded 30 bps pool allowing referrals of up to half the fee, its
+/// authority, a trader and a referrer's x account.
+async fn referral_pool(h: &mut Harness) -> (Pool, Keypair, Keypair, solana_sdk::pubkey::Pubkey) {
+    let authority = Keypair::new();
+    let pool = h.create_pool(30, Some(authority.pubkey())).await;
+    let ix = instructions::set_max_referral(&pool.addresses, &authority.pubkey(), 5_000);
+    h.process(&[ix], &[&authority]).await.unwrap();
+
+    let lp = h.funded_user(&pool, 100_000_000, 100_000_000).await;
+    h.deposit(&pool.addresses, &lp, 100_000_000, 100_000_000, 100_000_000)
+        .await
+        .unwrap();
+    let trader = h.funded_user(&pool, 10_000_000, 10_000_000).await;
+    let referrer = h
+        .create_ata(&Keypair::new().pubkey(), &pool.addresses.mint_x)
+        .await;
+    (pool, authority, trader, referrer)
+}
+
+#[tokio::test]
+async fn referrer_is_paid_out_of_the_fee() {
+    let mut h = Harness::new().await;
+    let (pool, _, trader, referrer) = referral_pool(&mut h).await;
+
+    let config = h.config(&pool.addresses).await;
+    let (x, y, supply) = h.reserves(&pool.addresses).await;
+    let quote = PoolState::new(config, x, y, supply)
+        .quote_swap(1_000_000, true)
+        .unwrap();
+    assert_eq!(quote.fee, 3_000);
+
+    let before = h.user_balances(&pool.addresses, &trader.pubkey()).await;
+    let ix = instructions::swap_with_referral(
+        &pool.addresses,
+        &trader.pubkey(),
+        &referrer,
+        5_000,
+        1_000_000,
+        quote.amount_out,
+        true,
+    );
+    let events: Vec<SwapEvent> = h.process_events(&[ix], &[&trader]).await.unwrap();
+    let after = h.user_balances(&pool.addresses, &trader.pubkey()).await;
+
+    // the trader pays and receives exactly what a plain swap would
+    assert_eq!(before.0 - after.0, 1_000_000);
+    assert_eq!(after.1 - before.1, quote.amount_out);
+    assert_eq!(h.token_balance(&referrer).await, 1_500);
+    assert_eq!(
+        h.reserves(&pool.addresses).await,
+        (x + 1_000_000 - 1_500, y - quote.amount_out, supply)
+    );
+
+    assert_eq!(events.len(), 1);
+    let event = &events[0];
+    assert_eq!(event.config, pool.addresses.config);
+    assert_eq!(event.user, trader.pubkey());
+    assert!(event.is_x_in);
+    assert_eq!(event.amount_in, 1_000_000);
+    assert_eq!(event.amount_out, quote.amount_out);
+    assert_eq!(event.fee, 3_000);
+    assert_eq!(event.referral_fee, 1_500);
+    assert_eq!(event.referrer, Some(referrer));
+}
+
+#[tokio::test]
+async fn referral_above_maximum_fails() {
+    let mut h = Harness::new().await;
+    let (pool, _, trader, referrer) = referral_pool(&mut h).await;
+
+    let ix = instructions::swap_with_referral(
+        &pool.addresses,
+        &trader.pubkey(),
+        &referrer,
+        5_001,
+        1_000_000,
+        0,
+        true,
+    );
+    let result = h.process(&[ix], &[&trader]).await;
+    assert_eq!(custom_error(result), Some(AmmError::ReferralTooHigh.into()));
+}
+
+#[tokio::test]
+async fn referrer_must_hold_the_input_mint() {
+    let mut h = Harness::new().await;
+    let (pool, _, trader, referrer) = referral_pool(&mut h).await;
+
+    let ix = instructions::swap_with_referral(
+        &pool.addresses,
+        &trader.pubkey(),
+        &referrer,
+        5_000,
+        1_000_000,
+        0,
+        false,
+    );
+    let result = h.process(&[ix], &[&trader]).await;
+    assert_eq!(custom_error(result), Some(AmmError::InvalidMint.into()));
+}
+
+#[tokio::test]
+async fn swap_without_referrer_reports_no_referral() {
+    let mut h = Harness::new().await;
+    let (pool, _, trader, _) = referral_pool(&mut h).await;
+
+    let ix = instructions::swap(&pool.addresses, &trader.pubkey(), 1_000_000, 0, false);
+    let events: Vec<SwapEvent> = h.process_events(&[ix], &[&trader]).await.unwrap();
+
+    assert_eq!(events.len(), 1);
+    assert!(!events[0].is_x_in);
+    assert_eq!(events[0].fee, 3_000);
+    assert_eq!(events[0].referral_fee, 0);
+    assert_eq!(events[0].referrer, None);
+}
+
+#[tokio::test]
+async fn only_authority_sets_max_referral() {
+    let mut h = Harness::new().await;
+    let (pool, authority, trader, _) = referral_pool(&mut h).await;
+
+    let ix = instructions::set_max_referral(&pool.addresses, &trader.pubkey(), 10_000);
+    let result = h.process(&[ix], &[&trader]).await;
+    assert_eq!(custom_error(result), Some(AmmError::Unauthorized.into()));
+
+    let ix = instructions::set_max_referral(&pool.addresses, &authority.pubkey(), 10_001);
+    let result = h.process(&[ix], &[&authority]).await;
+    assert_eq!(custom_error(result), Some(AmmError::ReferralTooHigh.into()));
+}
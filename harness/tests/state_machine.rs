@@ -0,0 +1,49 @@
+use amm_harness::state_machine::{check_profits, run, Action, Scenario};
+use amm_harness::{tokio, Harness};
+use arbitrary::{Arbitrary, Unstructured};
+
+/// xorshift64, enough to feed `arbitrary` deterministic bytes.
+fn bytes(mut seed: u64, len: usize) -> Vec<u8> {
+    (0..len)
+        .map(|_| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed as u8
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn random_sequences_keep_invariants() {
+    let mut h = Harness::new().await;
+
+    for seed in 1..=8u64 {
+        let data = bytes(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15), 1024);
+        let mut u = Unstructured::new(&data);
+        let scenario = Scenario {
+            fees: u.arbitrary().unwrap(),
+            seed_x: u.arbitrary().unwrap(),
+            seed_y: u.arbitrary().unwrap(),
+            seed_lp: u.arbitrary().unwrap(),
+            actions: (0..24)
+                .map(|_| Action::arbitrary(&mut u).unwrap())
+                .collect(),
+        };
+        run(&mut h, &scenario).await;
+    }
+}
+
+#[test]
+fn profits_covered_by_the_others_losses_pass() {
+    check_profits(&[10, -6, -4]);
+    check_profits(&[0, 0, -3]);
+}
+
+/// What a model that misses one of user 0's payments would report: user 0
+/// ahead by more than user 1 lost, while user 2 broke even.
+#[test]
+#[should_panic(expected = "user 0 extracted more than the others lost")]
+fn profits_past_the_others_losses_fail() {
+    check_profits(&[10, -6, 0]);
+}
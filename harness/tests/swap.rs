@@ -0,0 +1,295 @@
+use amm_client::{instructions, PoolState};
+use amm_contract::error::AmmError;
+use amm_harness::{custom_error, tokio, Harness, Pool};
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_spl::token::spl_token;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_system_interface::instruction as system_instruction;
+
+async fn seeded_pool(h: &mut Harness) -> (Pool, Keypair) {
+    let pool = h.create_pool(30, None).await;
+    let lp = h.funded_user(&pool, 100_000_000, 200_000_000).await;
+    h.deposit(&pool.addresses, &lp, 50_000_000, 100_000_000, 200_000_000)
+        .await
+        .unwrap();
+
+    let trader = h.funded_user(&pool, 50_000_000, 50_000_000).await;
+    (pool, trader)
+}
+
+#[tokio::test]
+async fn swap_x_for_y_matches_quote() {
+    let mut h = Harness::new().await;
+    let (pool, trader) = seeded_pool(&mut h).await;
+
+    let config = h.config(&pool.addresses).await;
+    let quote = PoolState::new(config, 100_000_000, 200_000_000, 50_000_000)
+        .quote_swap(10_000_000, true)
+        .unwrap();
+
+    h.swap(&pool.addresses, &trader, 10_000_000, quote.amount_out, true)
+        .await
+        .unwrap();
+
+    assert_eq!(quote.amount_out, 18_132_217);
+    assert_eq!(
+        h.reserves(&pool.addresses).await,
+        (110_000_000, 200_000_000 - 18_132_217, 50_000_000)
+    );
+    assert_eq!(
+        h.user_balances(&pool.addresses, &trader.pubkey()).await,
+        (40_000_000, 50_000_000 + 18_132_217, 0)
+    );
+}
+
+#[tokio::test]
+async fn swap_y_for_x_keeps_k() {
+    let mut h = Harness::new().await;
+    let (pool, trader) = seeded_pool(&mut h).await;
+
+    h.swap(&pool.addresses, &trader, 7_000_000, 0, false)
+        .await
+        .unwrap();
+
+    let (x, y, _) = h.reserves(&pool.addresses).await;
+    assert_eq!(y, 207_000_000);
+    assert!(x < 100_000_000);
+    assert!(x as u128 * y as u128 >= 100_000_000u128 * 200_000_000);
+}
+
+#[tokio::test]
+async fn swap_below_min_out_fails() {
+    let mut h = Harness::new().await;
+    let (pool, trader) = seeded_pool(&mut h).await;
+
+    let result = h
+        .swap(&pool.addresses, &trader, 10_000_000, 18_132_218, true)
+        .await;
+
+    assert_eq!(
+        custom_error(result),
+        Some(AmmError::SlippageExceeded.into())
+    );
+    assert_eq!(
+        h.reserves(&pool.addresses).await,
+        (100_000_000, 200_000_000, 50_000_000)
+    );
+}
+
+#[tokio::test]
+async fn zero_swap_fails() {
+    let mut h = Harness::new().await;
+    let (pool, trader) = seeded_pool(&mut h).await;
+
+    let result = h.swap(&pool.addresses, &trader, 0, 0, true).await;
+    assert_eq!(custom_error(result), Some(AmmError::ZeroAmount.into()));
+}
+
+#[tokio::test]
+async fn swap_pays_out_to_another_wallet() {
+    let mut h = Harness::new().await;
+    let (pool, trader) = seeded_pool(&mut h).await;
+    let customer = Keypair::new();
+    let recipient = h
+        .create_ata(&customer.pubkey(), &pool.addresses.mint_y)
+        .await;
+    let (source, _, _) = pool.addresses.user_accounts(&trader.pubkey());
+
+    let ix = instructions::swap_with_accounts(
+        &pool.addresses,
+        &trader.pubkey(),
+        &source,
+        &recipient,
+        10_000_000,
+        18_132_217,
+        true,
+    );
+    h.process(&[ix], &[&trader]).await.unwrap();
+
+    assert_eq!(h.token_balance(&recipient).await, 18_132_217);
+    assert_eq!(
+        h.user_balances(&pool.addresses, &trader.pubkey()).await,
+        (40_000_000, 50_000_000, 0)
+    );
+}
+
+#[tokio::test]
+async fn delegate_swaps_from_non_ata_source() {
+    let mut h = Harness::new().await;
+    let (pool, trader) = seeded_pool(&mut h).await;
+    let custody = h.create_user(1_000_000_000).await;
+
+    // a plain token account owned by the trader, not its ATA
+    let source = Keypair::new();
+    let rent = h
+        .ctx
+        .banks_client
+        .get_rent()
+        .await
+        .unwrap()
+        .minimum_balance(spl_token::state::Account::LEN);
+    let payer = h.payer();
+    let owner = trader.pubkey();
+    let (trader_x, trader_y, _) = pool.addresses.user_accounts(&owner);
+    h.process(
+        &[
+            system_instruction::create_account(
+                &payer,
+                &source.pubkey(),
+                rent,
+                spl_token::state::Account::LEN as u64,
+                &spl_token::ID,
+            ),
+            spl_token::instruction::initialize_account3(
+                &spl_token::ID,
+                &source.pubkey(),
+                &pool.addresses.mint_y,
+                &owner,
+            )
+            .unwrap(),
+            spl_token::instruction::transfer(
+                &spl_token::ID,
+                &trader_y,
+                &source.pubkey(),
+                &owner,
+                &[],
+                7_000_000,
+            )
+            .unwrap(),
+            spl_token::instruction::approve(
+                &spl_token::ID,
+                &source.pubkey(),
+                &custody.pubkey(),
+                &owner,
+                &[],
+                7_000_000,
+            )
+            .unwrap(),
+        ],
+        &[&source, &trader],
+    )
+    .await
+    .unwrap();
+
+    let ix = instructions::swap_with_accounts(
+        &pool.addresses,
+        &custody.pubkey(),
+        &source.pubkey(),
+        &trader_x,
+        7_000_000,
+        0,
+        false,
+    );
+    h.process(&[ix], &[&custody]).await.unwrap();
+
+    assert_eq!(h.token_balance(&source.pubkey()).await, 0);
+    let (x, y, _) = h.reserves(&pool.addresses).await;
+    assert_eq!(y, 207_000_000);
+    assert_eq!(
+        h.token_balance(&trader_x).await,
+        50_000_000 + 100_000_000 - x
+    );
+}
+
+#[tokio::test]
+async fn swap_from_account_signer_cannot_spend_fails() {
+    let mut h = Harness::new().await;
+    let (pool, trader) = seeded_pool(&mut h).await;
+    let thief = h.funded_user(&pool, 0, 0).await;
+    let (trader_x, _, _) = pool.addresses.user_accounts(&trader.pubkey());
+    let (_, thief_y, _) = pool.addresses.user_accounts(&thief.pubkey());
+
+    let ix = instructions::swap_with_accounts(
+        &pool.addresses,
+        &thief.pubkey(),
+        &trader_x,
+        &thief_y,
+        10_000_000,
+        0,
+        true,
+    );
+    let result = h.process(&[ix], &[&thief]).await;
+
+    assert_eq!(
+        custom_error(result),
+        Some(AmmError::InvalidSourceAuthority.into())
+    );
+}
+
+#[tokio::test]
+async fn recipient_of_input_mint_fails() {
+    let mut h = Harness::new().await;
+    let (pool, trader) = seeded_pool(&mut h).await;
+    let (trader_x, _, _) = pool.addresses.user_accounts(&trader.pubkey());
+
+    let ix = instructions::swap_with_accounts(
+        &pool.addresses,
+        &trader.pubkey(),
+        &trader_x,
+        &trader_x,
+        10_000_000,
+        0,
+        true,
+    );
+    let result = h.process(&[ix], &[&trader]).await;
+
+    assert_eq!(custom_error(result), Some(AmmError::InvalidMint.into()));
+}
+
+#[tokio::test]
+async fn directional_fees_apply_by_side() {
+    let mut h = Harness::new().await;
+    let authority = Keypair::new();
+    let pool = h.create_pool(30, Some(authority.pubkey())).await;
+    let lp = h.funded_user(&pool, 100_000_000, 100_000_000).await;
+    h.deposit(&pool.addresses, &lp, 100_000_000, 100_000_000, 100_000_000)
+        .await
+        .unwrap();
+    let trader = h.funded_user(&pool, 10_000_000, 10_000_000).await;
+
+    let ix = instructions::set_directional_fees(&pool.addresses, &authority.pubkey(), Some(300));
+    h.process(&[ix], &[&authority]).await.unwrap();
+    assert_eq!(h.config(&pool.addresses).await.fees_y_in, Some(300));
+
+    for is_x_in in [true, false] {
+        let config = h.config(&pool.addresses).await;
+        let (x, y, supply) = h.reserves(&pool.addresses).await;
+        let quote = PoolState::new(config, x, y, supply)
+            .quote_swap(1_000_000, is_x_in)
+            .unwrap();
+        assert_eq!(quote.fee, if is_x_in { 3_000 } else { 30_000 });
+
+        let before = h.user_balances(&pool.addresses, &trader.pubkey()).await;
+        h.swap(
+            &pool.addresses,
+            &trader,
+            1_000_000,
+            quote.amount_out,
+            is_x_in,
+        )
+        .await
+        .unwrap();
+        let after = h.user_balances(&pool.addresses, &trader.pubkey()).await;
+        let received = match is_x_in {
+            true => after.1 - before.1,
+            false => after.0 - before.0,
+        };
+        assert_eq!(received, quote.amount_out);
+    }
+}
+
+#[tokio::test]
+async fn directional_fee_is_validated() {
+    let mut h = Harness::new().await;
+    let authority = Keypair::new();
+    let pool = h.create_pool(30, Some(authority.pubkey())).await;
+
+    let ix = instructions::set_directional_fees(&pool.addresses, &authority.pubkey(), Some(10_001));
+    let result = h.process(&[ix], &[&authority]).await;
+    assert_eq!(custom_error(result), Some(AmmError::InvalidFee.into()));
+
+    let intruder = Keypair::new();
+    let ix = instructions::set_directional_fees(&pool.addresses, &intruder.pubkey(), Some(100));
+    let result = h.process(&[ix], &[&intruder]).await;
+    assert_eq!(custom_error(result), Some(AmmError::Unauthorized.into()));
+}
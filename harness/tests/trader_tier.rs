@@ -0,0 +1,146 @@
+use amm_client::{instructions, trader_tier_address, PoolState, TraderTier};
+use amm_contract::error::AmmError;
+use amm_harness::{custom_error, tokio, Harness, Pool};
+use anchor_lang::AccountDeserialize;
+use solana_sdk::signature::{Keypair, Signer};
+
+/// A seeded 30 bps pool, its authority and a trader.
+async fn seeded_pool(h: &mut Harness) -> (Pool, Keypair, Keypair) {
+    let authority = h.create_user(1_000_000_000).await;
+    let pool = h.create_pool(30, Some(authority.pubkey())).await;
+    let lp = h.funded_user(&pool, 100_000_000, 100_000_000).await;
+    h.deposit(&pool.addresses, &lp, 100_000_000, 100_000_000, 100_000_000)
+        .await
+        .unwrap();
+    let trader = h.funded_user(&pool, 10_000_000, 10_000_000).await;
+    (pool, authority, trader)
+}
+
+async fn tier(h: &mut Harness, pool: &Pool, trader: &Keypair) -> Option<TraderTier> {
+    let (address, _) = trader_tier_address(&pool.addresses.config, &trader.pubkey());
+    let account = h.ctx.banks_client.get_account(address).await.unwrap()?;
+    Some(TraderTier::try_deserialize(&mut account.data.as_slice()).unwrap())
+}
+
+#[tokio::test]
+async fn tiered_trader_swaps_at_reduced_fee() {
+    let mut h = Harness::new().await;
+    let (pool, authority, trader) = seeded_pool(&mut h).await;
+
+    let ix =
+        instructions::set_trader_tier(&pool.addresses, &authority.pubkey(), &trader.pubkey(), 5);
+    h.process(&[ix], &[&authority]).await.unwrap();
+    let granted = tier(&mut h, &pool, &trader).await.unwrap();
+    assert_eq!(granted.fee_bps, 5);
+    assert_eq!(granted.trader, trader.pubkey());
+
+    let config = h.config(&pool.addresses).await;
+    let (x, y, supply) = h.reserves(&pool.addresses).await;
+    let quote = PoolState::new(config, x, y, supply)
+        .with_tier(&granted)
+        .quote_swap(1_000_000, true)
+        .unwrap();
+    assert_eq!(quote.fee, 500);
+
+    let before = h.user_balances(&pool.addresses, &trader.pubkey()).await;
+    let ix = instructions::swap_with_tier(
+        &pool.addresses,
+        &trader.pubkey(),
+        1_000_000,
+        quote.amount_out,
+        true,
+    );
+    h.process(&[ix], &[&trader]).await.unwrap();
+    let after = h.user_balances(&pool.addresses, &trader.pubkey()).await;
+    assert_eq!(after.1 - before.1, quote.amount_out);
+}
+
+#[tokio::test]
+async fn tier_never_raises_the_fee() {
+    let mut h = Harness::new().await;
+    let (pool, authority, trader) = seeded_pool(&mut h).await;
+
+    let ix =
+        instructions::set_trader_tier(&pool.addresses, &authority.pubkey(), &trader.pubkey(), 100);
+    h.process(&[ix], &[&authority]).await.unwrap();
+
+    let config = h.config(&pool.addresses).await;
+    let (x, y, supply) = h.reserves(&pool.addresses).await;
+    let quote = PoolState::new(config, x, y, supply)
+        .quote_swap(1_000_000, true)
+        .unwrap();
+    let before = h.user_balances(&pool.addresses, &trader.pubkey()).await;
+    let ix = instructions::swap_with_tier(
+        &pool.addresses,
+        &trader.pubkey(),
+        1_000_000,
+        quote.amount_out,
+        true,
+    );
+    h.process(&[ix], &[&trader]).await.unwrap();
+    let after = h.user_balances(&pool.addresses, &trader.pubkey()).await;
+    assert_eq!(after.1 - before.1, quote.amount_out);
+}
+
+#[tokio::test]
+async fn tier_cannot_be_borrowed() {
+    let mut h = Harness::new().await;
+    let (pool, authority, trader) = seeded_pool(&mut h).await;
+    let other = h.funded_user(&pool, 1_000_000, 0).await;
+
+    let ix =
+        instructions::set_trader_tier(&pool.addresses, &authority.pubkey(), &trader.pubkey(), 0);
+    h.process(&[ix], &[&authority]).await.unwrap();
+
+    // `other` signs but presents `trader`'s tier
+    let mut ix = instructions::swap_with_tier(&pool.addresses, &other.pubkey(), 1_000_000, 0, true);
+    let (borrowed, _) = trader_tier_address(&pool.addresses.config, &trader.pubkey());
+    let (own, _) = trader_tier_address(&pool.addresses.config, &other.pubkey());
+    for meta in &mut ix.accounts {
+        if meta.pubkey == own {
+            meta.pubkey = borrowed;
+        }
+    }
+    let result = h.process(&[ix], &[&other]).await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn only_authority_manages_tiers() {
+    let mut h = Harness::new().await;
+    let (pool, authority, trader) = seeded_pool(&mut h).await;
+
+    let ix = instructions::set_trader_tier(&pool.addresses, &trader.pubkey(), &trader.pubkey(), 0);
+    let result = h.process(&[ix], &[&trader]).await;
+    assert_eq!(custom_error(result), Some(AmmError::Unauthorized.into()));
+
+    let ix = instructions::set_trader_tier(
+        &pool.addresses,
+        &authority.pubkey(),
+        &trader.pubkey(),
+        10_001,
+    );
+    let result = h.process(&[ix], &[&authority]).await;
+    assert_eq!(custom_error(result), Some(AmmError::InvalidFee.into()));
+}
+
+#[tokio::test]
+async fn removed_tier_refunds_authority() {
+    let mut h = Harness::new().await;
+    let (pool, authority, trader) = seeded_pool(&mut h).await;
+    let funded = h.lamports(&authority.pubkey()).await;
+
+    let ix =
+        instructions::set_trader_tier(&pool.addresses, &authority.pubkey(), &trader.pubkey(), 0);
+    h.process(&[ix], &[&authority]).await.unwrap();
+    assert!(h.lamports(&authority.pubkey()).await < funded);
+
+    let ix =
+        instructions::remove_trader_tier(&pool.addresses, &authority.pubkey(), &trader.pubkey());
+    h.process(&[ix], &[&authority]).await.unwrap();
+    assert!(tier(&mut h, &pool, &trader).await.is_none());
+    assert_eq!(h.lamports(&authority.pubkey()).await, funded);
+
+    let ix = instructions::swap_with_tier(&pool.addresses, &trader.pubkey(), 1_000, 0, true);
+    assert!(h.process(&[ix], &[&trader]).await.is_err());
+}
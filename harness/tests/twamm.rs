@@ -0,0 +1,263 @@
+use amm_client::{
+    decode_twamm_order, instructions, twamm_archive_address, twamm_order_address, PoolState,
+    TwammOrder,
+};
+use amm_contract::error::AmmError;
+use amm_contract::states::MAX_TWAMM_EXPIRIES;
+use amm_harness::{custom_error, tokio, Harness, Pool};
+use solana_sdk::signature::{Keypair, Signer};
+
+/// On an interval boundary, so orders run whole intervals.
+const NOW: i64 = 1_700_002_800;
+const HOUR: i64 = 3600;
+
+/// A seeded 30 bps pool at `NOW`, 1 y per x.
+async fn seeded_pool(h: &mut Harness) -> Pool {
+    h.set_unix_timestamp(NOW).await;
+    let pool = h.create_pool(30, None).await;
+    let lp = h.funded_user(&pool, 100_000_000, 100_000_000).await;
+    h.deposit(&pool.addresses, &lp, 100_000_000, 100_000_000, 100_000_000)
+        .await
+        .unwrap();
+    pool
+}
+
+async fn order(h: &mut Harness, pool: &Pool, owner: &Keypair, order_id: u64) -> Option<TwammOrder> {
+    let (address, _) = twamm_order_address(&pool.addresses.config, &owner.pubkey(), order_id);
+    let account = h.ctx.banks_client.get_account(address).await.unwrap()?;
+    Some(decode_twamm_order(&account.data).unwrap())
+}
+
+/// The pool as the program sees it at `unix_timestamp`.
+async fn pool_state(h: &mut Harness, pool: &Pool, unix_timestamp: i64) -> PoolState {
+    let config = h.config(&pool.addresses).await;
+    let (x, y, supply) = h.reserves(&pool.addresses).await;
+    PoolState::from_vaults(config, x, y, supply, unix_timestamp).unwrap()
+}
+
+#[tokio::test]
+async fn order_sells_evenly_until_it_ends() {
+    let mut h = Harness::new().await;
+    let pool = seeded_pool(&mut h).await;
+    let addresses = pool.addresses;
+    let owner = h.funded_user(&pool, 2_000_000, 0).await;
+
+    let ix = instructions::place_twamm_order(&addresses, &owner.pubkey(), 0, true, 2_000_000, 2);
+    h.process(&[ix], &[&owner]).await.unwrap();
+    let placed = order(&mut h, &pool, &owner, 0).await.unwrap();
+    assert_eq!((placed.start, placed.end), (NOW, NOW + 2 * HOUR));
+
+    // the input is set aside, not added to the pool
+    let state = pool_state(&mut h, &pool, NOW).await;
+    assert_eq!(
+        (state.reserve_x, state.reserve_y),
+        (100_000_000, 100_000_000)
+    );
+
+    // half way, half is sold, as one swap of it would have, but for the
+    // rounding of the sale rate
+    h.set_unix_timestamp(NOW + HOUR).await;
+    let expected = PoolState::new(h.config(&addresses).await, 100_000_000, 100_000_000, 0)
+        .quote_swap(1_000_000, true)
+        .unwrap()
+        .amount_out;
+    let ix = instructions::withdraw_twamm_proceeds(&addresses, &owner.pubkey(), 0, None);
+    h.process(&[ix], &[&owner]).await.unwrap();
+    let (_, halfway, _) = h.user_balances(&addresses, &owner.pubkey()).await;
+    assert!(halfway.abs_diff(expected) <= 3, "{halfway} vs {expected}");
+
+    h.set_unix_timestamp(NOW + 3 * HOUR).await;
+    let ix = instructions::close_twamm_order(&addresses, &owner.pubkey(), 0, None);
+    h.process(&[ix], &[&owner]).await.unwrap();
+    let (x, y, _) = h.user_balances(&addresses, &owner.pubkey()).await;
+    assert!(x <= 1, "only rounding dust is unsold");
+    assert!(y > 2 * halfway - 2 * halfway / 50 && y < 2 * halfway);
+    assert!(order(&mut h, &pool, &owner, 0).await.is_none());
+
+    // nothing is owed any more, beyond rounding dust left to the pool
+    let twamm = h.config(&addresses).await.twamm;
+    assert_eq!(twamm.sell_x.sale_rate, 0);
+    assert!(twamm.reserved_x <= 2 && twamm.reserved_y <= 2);
+    assert!(twamm.expiries.iter().all(|e| e.orders == 0));
+}
+
+#[tokio::test]
+async fn opposing_orders_trade_with_each_other() {
+    let mut h = Harness::new().await;
+    let pool = seeded_pool(&mut h).await;
+    let addresses = pool.addresses;
+    let seller_x = h.funded_user(&pool, 10_000_000, 0).await;
+    let seller_y = h.funded_user(&pool, 0, 10_000_000).await;
+
+    for (owner, is_x_in) in [(&seller_x, true), (&seller_y, false)] {
+        let ix =
+            instructions::place_twamm_order(&addresses, &owner.pubkey(), 0, is_x_in, 10_000_000, 4);
+        h.process(&[ix], &[owner]).await.unwrap();
+    }
+
+    h.set_unix_timestamp(NOW + 4 * HOUR).await;
+    for owner in [&seller_x, &seller_y] {
+        let ix = instructions::close_twamm_order(&addresses, &owner.pubkey(), 0, None);
+        h.process(&[ix], &[owner]).await.unwrap();
+    }
+
+    // matched flows pay only the fee, where a swap of 10% of the pool
+    // would lose about 9% to price impact
+    let (_, y, _) = h.user_balances(&addresses, &seller_x.pubkey()).await;
+    let (x, _, _) = h.user_balances(&addresses, &seller_y.pubkey()).await;
+    for bought in [x, y] {
+        assert!((9_960_000..=9_970_000).contains(&bought), "{bought}");
+    }
+    let state = pool_state(&mut h, &pool, NOW + 4 * HOUR).await;
+    assert!(state.reserve_x.abs_diff(state.reserve_y) <= 2);
+}
+
+#[tokio::test]
+async fn closing_early_refunds_the_unsold_input() {
+    let mut h = Harness::new().await;
+    let pool = seeded_pool(&mut h).await;
+    let addresses = pool.addresses;
+    let owner = h.funded_user(&pool, 0, 4_000_000).await;
+
+    let ix = instructions::place_twamm_order(&addresses, &owner.pubkey(), 7, false, 4_000_000, 4);
+    h.process(&[ix], &[&owner]).await.unwrap();
+
+    h.set_unix_timestamp(NOW + HOUR).await;
+    let ix = instructions::close_twamm_order(&addresses, &owner.pubkey(), 7, None);
+    h.process(&[ix], &[&owner]).await.unwrap();
+
+    let (x, y, _) = h.user_balances(&addresses, &owner.pubkey()).await;
+    assert_eq!(y, 3_000_000);
+    assert!(x > 980_000 && x < 1_000_000);
+    assert!(order(&mut h, &pool, &owner, 7).await.is_none());
+
+    // the pool stops selling y
+    let twamm = h.config(&addresses).await.twamm;
+    assert_eq!(twamm.sell_y.sale_rate, 0);
+    h.set_unix_timestamp(NOW + 2 * HOUR).await;
+    let before = pool_state(&mut h, &pool, NOW + HOUR).await;
+    let after = pool_state(&mut h, &pool, NOW + 2 * HOUR).await;
+    assert_eq!(
+        (before.reserve_x, before.reserve_y),
+        (after.reserve_x, after.reserve_y)
+    );
+}
+
+#[tokio::test]
+async fn swaps_execute_pending_sales_first() {
+    let mut h = Harness::new().await;
+    let pool = seeded_pool(&mut h).await;
+    let addresses = pool.addresses;
+    let owner = h.funded_user(&pool, 20_000_000, 0).await;
+    let trader = h.funded_user(&pool, 0, 1_000_000).await;
+
+    let ix = instructions::place_twamm_order(&addresses, &owner.pubkey(), 0, true, 20_000_000, 2);
+    h.process(&[ix], &[&owner]).await.unwrap();
+
+    // the order has pushed x down, so buying it is cheaper than at 1:1
+    h.set_unix_timestamp(NOW + HOUR).await;
+    let quote = pool_state(&mut h, &pool, NOW + HOUR)
+        .await
+        .quote_swap(1_000_000, false)
+        .unwrap();
+    assert!(quote.amount_out > 1_000_000);
+    h.swap(&addresses, &trader, 1_000_000, quote.amount_out, false)
+        .await
+        .unwrap();
+    let (x, _, _) = h.user_balances(&addresses, &trader.pubkey()).await;
+    assert_eq!(x, quote.amount_out);
+    assert_eq!(h.config(&addresses).await.twamm.last_execution, NOW + HOUR);
+}
+
+#[tokio::test]
+async fn orders_are_validated() {
+    let mut h = Harness::new().await;
+    let pool = seeded_pool(&mut h).await;
+    let owner = h.funded_user(&pool, 1_000_000, 0).await;
+
+    for (amount, intervals) in [(0, 1), (1_000_000, 0)] {
+        let ix = instructions::place_twamm_order(
+            &pool.addresses,
+            &owner.pubkey(),
+            0,
+            true,
+            amount,
+            intervals,
+        );
+        let result = h.process(&[ix], &[&owner]).await;
+        assert_eq!(
+            custom_error(result),
+            Some(AmmError::InvalidTwammOrder.into())
+        );
+    }
+
+    // an empty pool has no price to sell at
+    let empty = h.create_pool(30, None).await;
+    let owner = h.funded_user(&empty, 1_000_000, 0).await;
+    let ix =
+        instructions::place_twamm_order(&empty.addresses, &owner.pubkey(), 0, true, 1_000_000, 1);
+    let result = h.process(&[ix], &[&owner]).await;
+    assert_eq!(
+        custom_error(result),
+        Some(AmmError::InsufficientLiquidity.into())
+    );
+}
+
+#[tokio::test]
+async fn passed_expiries_can_be_archived_to_free_their_slot() {
+    let mut h = Harness::new().await;
+    let pool = seeded_pool(&mut h).await;
+    let addresses = pool.addresses;
+    let payer = h.payer();
+
+    // dust orders, never closed, take every slot
+    let griefer = h.funded_user(&pool, MAX_TWAMM_EXPIRIES as u64, 0).await;
+    for i in 0..MAX_TWAMM_EXPIRIES as u32 {
+        let ix = instructions::place_twamm_order(
+            &addresses,
+            &griefer.pubkey(),
+            i as u64,
+            true,
+            1,
+            i + 1,
+        );
+        h.process(&[ix], &[&griefer]).await.unwrap();
+    }
+    let owner = h.funded_user(&pool, 1_000_000, 0).await;
+    let place = instructions::place_twamm_order(&addresses, &owner.pubkey(), 0, true, 1_000_000, 9);
+    let result = h.process(std::slice::from_ref(&place), &[&owner]).await;
+    assert_eq!(
+        custom_error(result),
+        Some(AmmError::TwammExpiriesFull.into())
+    );
+
+    // they stay taken once passed, until archived
+    h.set_unix_timestamp(NOW + 2 * HOUR).await;
+    let result = h.process(std::slice::from_ref(&place), &[&owner]).await;
+    assert_eq!(
+        custom_error(result),
+        Some(AmmError::TwammExpiriesFull.into())
+    );
+    let ix = instructions::archive_twamm_expiry(&addresses, &payer, NOW + 3 * HOUR);
+    let result = h.process(&[ix], &[]).await;
+    assert_eq!(
+        custom_error(result),
+        Some(AmmError::TwammExpiryRunning.into())
+    );
+    let ix = instructions::archive_twamm_expiry(&addresses, &payer, NOW + HOUR);
+    h.process(&[ix], &[]).await.unwrap();
+    h.process(&[place], &[&owner]).await.unwrap();
+
+    // the archived order still closes, taking the archive with it
+    let ix = instructions::close_twamm_order(&addresses, &griefer.pubkey(), 0, None);
+    let result = h.process(&[ix], &[&griefer]).await;
+    assert_eq!(
+        custom_error(result),
+        Some(AmmError::InvalidTwammOrder.into())
+    );
+    let ix = instructions::close_twamm_order(&addresses, &griefer.pubkey(), 0, Some(NOW + HOUR));
+    h.process(&[ix], &[&griefer]).await.unwrap();
+    assert!(order(&mut h, &pool, &griefer, 0).await.is_none());
+    let (archive, _) = twamm_archive_address(&addresses.config, NOW + HOUR);
+    assert_eq!(h.lamports(&archive).await, 0);
+}
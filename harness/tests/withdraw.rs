@@ -0,0 +1,106 @@
+use amm_client::instructions;
+use amm_contract::error::AmmError;
+use amm_harness::{custom_error, tokio, Harness};
+use anchor_spl::token::spl_token;
+use solana_sdk::signature::Signer;
+
+#[tokio::test]
+async fn withdraw_pays_out_share_and_burns_lp() {
+    let mut h = Harness::new().await;
+    let pool = h.create_pool(30, None).await;
+    let user = h.funded_user(&pool, 1_001, 2_003).await;
+    h.deposit(&pool.addresses, &user, 1_000, 1_001, 2_003)
+        .await
+        .unwrap();
+
+    h.withdraw(&pool.addresses, &user, 10, 20, 10)
+        .await
+        .unwrap();
+
+    // 1001 * 10 / 1000 = 10.01 -> 10, 2003 * 10 / 1000 = 20.03 -> 20
+    assert_eq!(h.reserves(&pool.addresses).await, (991, 1_983, 990));
+    assert_eq!(
+        h.user_balances(&pool.addresses, &user.pubkey()).await,
+        (10, 20, 990)
+    );
+}
+
+#[tokio::test]
+async fn withdraw_creates_missing_token_accounts() {
+    let mut h = Harness::new().await;
+    let pool = h.create_pool(30, None).await;
+    let depositor = h.funded_user(&pool, 100, 200).await;
+    h.deposit(&pool.addresses, &depositor, 100, 100, 200)
+        .await
+        .unwrap();
+
+    // hand LP to a user who has no x or y accounts yet
+    let holder = h.create_user(1_000_000_000).await;
+    let holder_lp = h
+        .create_ata(&holder.pubkey(), &pool.addresses.mint_lp)
+        .await;
+    let (_, _, depositor_lp) = pool.addresses.user_accounts(&depositor.pubkey());
+    let transfer = spl_token::instruction::transfer(
+        &spl_token::ID,
+        &depositor_lp,
+        &holder_lp,
+        &depositor.pubkey(),
+        &[],
+        50,
+    )
+    .unwrap();
+    h.process(&[transfer], &[&depositor]).await.unwrap();
+
+    let ix = instructions::withdraw(&pool.addresses, &holder.pubkey(), 0, 0, 50);
+    h.process(&[ix], &[&holder]).await.unwrap();
+
+    assert_eq!(
+        h.user_balances(&pool.addresses, &holder.pubkey()).await,
+        (50, 100, 0)
+    );
+}
+
+#[tokio::test]
+async fn withdraw_below_min_fails() {
+    let mut h = Harness::new().await;
+    let pool = h.create_pool(30, None).await;
+    let user = h.funded_user(&pool, 1_001, 2_003).await;
+    h.deposit(&pool.addresses, &user, 1_000, 1_001, 2_003)
+        .await
+        .unwrap();
+
+    let result = h.withdraw(&pool.addresses, &user, 11, 0, 10).await;
+    assert_eq!(
+        custom_error(result),
+        Some(AmmError::SlippageExceeded.into())
+    );
+}
+
+#[tokio::test]
+async fn withdraw_more_than_supply_fails() {
+    let mut h = Harness::new().await;
+    let pool = h.create_pool(30, None).await;
+    let user = h.funded_user(&pool, 1_000, 1_000).await;
+    h.deposit(&pool.addresses, &user, 100, 1_000, 1_000)
+        .await
+        .unwrap();
+
+    let result = h.withdraw(&pool.addresses, &user, 0, 0, 101).await;
+    assert_eq!(
+        custom_error(result),
+        Some(AmmError::InsufficientLiquidity.into())
+    );
+}
+
+#[tokio::test]
+async fn zero_withdraw_fails() {
+    let mut h = Harness::new().await;
+    let pool = h.create_pool(30, None).await;
+    let user = h.funded_user(&pool, 1_000, 1_000).await;
+    h.deposit(&pool.addresses, &user, 100, 1_000, 1_000)
+        .await
+        .unwrap();
+
+    let result = h.withdraw(&pool.addresses, &user, 0, 0, 0).await;
+    assert_eq!(custom_error(result), Some(AmmError::ZeroAmount.into()));
+}
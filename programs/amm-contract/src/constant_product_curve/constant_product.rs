@@ -1,100 +1,372 @@
 use anchor_lang::prelude::*;
 
+use crate::error::AmmError;
+
 pub struct XYAmounts {
     pub x: u64,
     pub y: u64,
 }
 
+pub struct SwapAmounts {
+    pub fee: u64,
+    pub amount_out: u64,
+}
+
+/// Which way a division should round. Every quote picks the direction that
+/// favours the pool, so rounding dust always stays in the vaults.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RoundDirection {
+    Floor,
+    Ceiling,
+}
+
 pub struct ConstantProductCurve {}
 
-#[error_code]
-pub enum CurveError {
-    #[msg("Overflow")]
-    Overflow,
-    #[msg("Invalid Precision")]
-    InvalidPrecision,
+pub const FEE_DENOMINATOR: u64 = 10_000;
+
+/// `a * b / c` in u128, rounded in the given direction.
+pub fn mul_div(a: u128, b: u128, c: u128, round: RoundDirection) -> Result<u128> {
+    let product = a.checked_mul(b).ok_or(AmmError::Overflow)?;
+    div(product, c, round)
+}
+
+/// `a / b` in u128, rounded in the given direction.
+pub fn div(a: u128, b: u128, round: RoundDirection) -> Result<u128> {
+    let quotient = a.checked_div(b).ok_or(AmmError::Overflow)?;
+    let remainder = a.checked_rem(b).ok_or(AmmError::Overflow)?;
+    match round {
+        RoundDirection::Floor => Ok(quotient),
+        RoundDirection::Ceiling if remainder > 0 => {
+            Ok(quotient.checked_add(1).ok_or(AmmError::Overflow)?)
+        }
+        RoundDirection::Ceiling => Ok(quotient),
+    }
+}
+
+/// Integer square root, rounded down.
+pub fn isqrt(n: u128) -> u128 {
+    if n < 2 {
+        return n;
+    }
+    // Newton's method from an overestimate decreases monotonically
+    let mut x = 1u128 << (128 - n.leading_zeros()).div_ceil(2);
+    loop {
+        let next = (x + n / x) / 2;
+        if next >= x {
+            return x;
+        }
+        x = next;
+    }
+}
+
+fn to_u64(value: u128) -> Result<u64> {
+    Ok(u64::try_from(value).map_err(|_| AmmError::Overflow)?)
 }
 
 impl ConstantProductCurve {
+    /// Amounts of x and y a depositor must pay to mint `lp_required` LP.
+    /// Rounded up, so the depositor never gets LP for less than its share.
     pub fn give_lp_take_xy(
         total_x: u64,
         total_y: u64,
         total_lp: u64,
         lp_required: u64,
-        precision: u32,
     ) -> Result<XYAmounts> {
-        let scaling_factor = 10u32
-            .checked_pow(precision as u32)
-            .ok_or(CurveError::InvalidPrecision)?;
-        let ratio = (total_lp as u128)
-            .checked_add(lp_required as u128)
-            .ok_or(CurveError::Overflow)?
-            .checked_mul(scaling_factor as u128)
-            .ok_or(CurveError::Overflow)?
-            .checked_div(total_lp as u128)
-            .ok_or(CurveError::Overflow)?;
-
-        let deposit_x = (total_x as u128)
-            .checked_mul(ratio as u128)
-            .ok_or(CurveError::Overflow)?
-            .checked_div(scaling_factor as u128)
-            .ok_or(CurveError::Overflow)?
-            .checked_sub(total_x as u128)
-            .ok_or(CurveError::Overflow)? as u64;
-
-        let deposit_y = (total_y as u128)
-            .checked_mul(ratio as u128)
-            .ok_or(CurveError::Overflow)?
-            .checked_div(scaling_factor as u128)
-            .ok_or(CurveError::Overflow)?
-            .checked_sub(total_y as u128)
-            .ok_or(CurveError::Overflow)? as u64;
+        let deposit_x = mul_div(
+            total_x as u128,
+            lp_required as u128,
+            total_lp as u128,
+            RoundDirection::Ceiling,
+        )?;
+
+        let deposit_y = mul_div(
+            total_y as u128,
+            lp_required as u128,
+            total_lp as u128,
+            RoundDirection::Ceiling,
+        )?;
 
         Ok(XYAmounts {
-            x: deposit_x,
-            y: deposit_y,
+            x: to_u64(deposit_x)?,
+            y: to_u64(deposit_y)?,
         })
     }
 
+    /// Amounts of x and y paid out for burning `lp_give` LP.
+    /// Rounded down, so the withdrawer never takes more than its share.
     pub fn take_lp_give_xy(
         total_x: u64,
         total_y: u64,
         total_lp: u64,
         lp_give: u64,
-        precision: u32,
     ) -> Result<XYAmounts> {
-        let scaling_factor = 10u32
-            .checked_pow(precision as u32)
-            .ok_or(CurveError::InvalidPrecision)?;
-        let ratio = ((total_lp - lp_give) as u128)
-            .checked_mul(scaling_factor as u128)
-            .ok_or(CurveError::Overflow)?
-            .checked_div(total_lp as u128)
-            .ok_or(CurveError::Overflow)?;
-
-        let withdraw_x = (total_x as u128)
-            .checked_sub(
-                (total_x as u128)
-                    .checked_mul(ratio)
-                    .ok_or(CurveError::Overflow)?
-                    .checked_div(precision as u128)
-                    .ok_or(CurveError::Overflow)?,
-            )
-            .ok_or(CurveError::Overflow)? as u64;
-
-        let withdraw_y = (total_y as u128)
-            .checked_sub(
-                (total_y as u128)
-                    .checked_mul(ratio)
-                    .ok_or(CurveError::Overflow)?
-                    .checked_div(precision as u128)
-                    .ok_or(CurveError::Overflow)?,
-            )
-            .ok_or(CurveError::Overflow)? as u64;
+        require!(lp_give <= total_lp, AmmError::InsufficientLiquidity);
+
+        let withdraw_x = mul_div(
+            total_x as u128,
+            lp_give as u128,
+            total_lp as u128,
+            RoundDirection::Floor,
+        )?;
+
+        let withdraw_y = mul_div(
+            total_y as u128,
+            lp_give as u128,
+            total_lp as u128,
+            RoundDirection::Floor,
+        )?;
 
         Ok(XYAmounts {
-            x: withdraw_x,
-            y: withdraw_y,
+            x: to_u64(withdraw_x)?,
+            y: to_u64(withdraw_y)?,
         })
     }
+
+    /// `sqrt(x * y)` per LP in Q64.64. Only swap fees make it grow, as
+    /// deposits and withdrawals keep it and swaps keep `k` otherwise.
+    pub fn sqrt_k_per_lp(total_x: u64, total_y: u64, total_lp: u64) -> Result<u128> {
+        require!(total_lp > 0, AmmError::InsufficientLiquidity);
+        let sqrt_k = isqrt(total_x as u128 * total_y as u128);
+        mul_div(sqrt_k, 1 << 64, total_lp as u128, RoundDirection::Floor)
+    }
+
+    /// Slice of `amount_lp` that is fee growth since `sqrt_k_per_lp` was
+    /// `then`: burning it leaves the rest worth what all of it was worth
+    /// then. Rounded down.
+    pub fn fee_lp(amount_lp: u64, then: u128, now: u128) -> Result<u64> {
+        if now <= then {
+            return Ok(0);
+        }
+        to_u64(mul_div(
+            amount_lp as u128,
+            now - then,
+            now,
+            RoundDirection::Floor,
+        )?)
+    }
+
+    /// Most input, before a `fee_bps` fee, that leaves the marginal price at
+    /// or above `limit_out / limit_in` output per input. Every unit filled
+    /// up to it gets a better average price than the limit. Rounded down.
+    pub fn fill_to_price(
+        in_bal: u64,
+        out_bal: u64,
+        limit_in: u64,
+        limit_out: u64,
+        fee_bps: u16,
+    ) -> Result<u64> {
+        require!((fee_bps as u64) < FEE_DENOMINATOR, AmmError::InvalidFee);
+        // out * in = k and out / in >= limit_out / limit_in
+        // give in <= sqrt(k * limit_in / limit_out)
+        let scaled = mul_div(
+            out_bal as u128,
+            limit_in as u128,
+            limit_out as u128,
+            RoundDirection::Floor,
+        )?;
+        let max_in = match scaled.checked_mul(in_bal as u128) {
+            Some(product) => isqrt(product),
+            None => return Ok(u64::MAX),
+        };
+        let Some(after_fee) = max_in.checked_sub(in_bal as u128) else {
+            return Ok(0);
+        };
+
+        let gross = mul_div(
+            after_fee,
+            FEE_DENOMINATOR as u128,
+            (FEE_DENOMINATOR - fee_bps as u64) as u128,
+            RoundDirection::Floor,
+        )?;
+        Ok(u64::try_from(gross).unwrap_or(u64::MAX))
+    }
+
+    /// Fee charged on `amount_in` at `fee_bps` basis points. Rounded up.
+    pub fn fee(amount_in: u64, fee_bps: u16) -> Result<u64> {
+        require!(fee_bps as u64 <= FEE_DENOMINATOR, AmmError::InvalidFee);
+
+        let fee = mul_div(
+            amount_in as u128,
+            fee_bps as u128,
+            FEE_DENOMINATOR as u128,
+            RoundDirection::Ceiling,
+        )?;
+
+        to_u64(fee)
+    }
+
+    /// Share of `fee` at `referral_bps` basis points. Rounded down, in the
+    /// pool's favour.
+    pub fn referral_fee(fee: u64, referral_bps: u16) -> Result<u64> {
+        let referral = mul_div(
+            fee as u128,
+            referral_bps as u128,
+            FEE_DENOMINATOR as u128,
+            RoundDirection::Floor,
+        )?;
+
+        to_u64(referral)
+    }
+
+    /// Output for swapping `amount_in` into a pool holding `in_bal` / `out_bal`.
+    /// The fee is rounded up and the output down, so `k` never decreases.
+    pub fn swap(in_bal: u64, out_bal: u64, amount_in: u64, fee_bps: u16) -> Result<SwapAmounts> {
+        let fee = Self::fee(amount_in, fee_bps)?;
+        let amount_in_after_fee = amount_in - fee;
+
+        // out = out_bal - ceil(k / (in_bal + in_after_fee))
+        //     = floor(out_bal * in_after_fee / (in_bal + in_after_fee))
+        let new_in = (in_bal as u128)
+            .checked_add(amount_in_after_fee as u128)
+            .ok_or(AmmError::Overflow)?;
+        let amount_out = mul_div(
+            out_bal as u128,
+            amount_in_after_fee as u128,
+            new_in,
+            RoundDirection::Floor,
+        )?;
+
+        Ok(SwapAmounts {
+            fee,
+            amount_out: to_u64(amount_out)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn div_rounds_in_requested_direction() {
+        assert_eq!(div(7, 2, RoundDirection::Floor).unwrap(), 3);
+        assert_eq!(div(7, 2, RoundDirection::Ceiling).unwrap(), 4);
+        assert_eq!(div(8, 2, RoundDirection::Ceiling).unwrap(), 4);
+        assert!(div(1, 0, RoundDirection::Floor).is_err());
+    }
+
+    #[test]
+    fn withdraw_more_than_supply_fails() {
+        assert!(ConstantProductCurve::take_lp_give_xy(100, 100, 10, 11).is_err());
+    }
+
+    #[test]
+    fn fee_above_denominator_fails() {
+        assert!(ConstantProductCurve::fee(100, 10_001).is_err());
+    }
+
+    #[test]
+    fn isqrt_rounds_down() {
+        assert_eq!(isqrt(0), 0);
+        assert_eq!(isqrt(15), 3);
+        assert_eq!(isqrt(16), 4);
+        assert_eq!(isqrt(u64::MAX as u128 * u64::MAX as u128), u64::MAX as u128);
+        assert_eq!(isqrt(u128::MAX), u64::MAX as u128);
+    }
+
+    #[test]
+    fn fee_lp_leaves_the_locked_value() {
+        let then = ConstantProductCurve::sqrt_k_per_lp(1_000, 1_000, 1_000).unwrap();
+        // fees grew k by 21%, so sqrt(k) by 10%
+        let now = ConstantProductCurve::sqrt_k_per_lp(1_100, 1_100, 1_000).unwrap();
+        assert_eq!(ConstantProductCurve::fee_lp(1_000, then, now).unwrap(), 90);
+        assert_eq!(ConstantProductCurve::fee_lp(1_000, now, then).unwrap(), 0);
+    }
+
+    #[test]
+    fn fill_to_price_stops_at_the_limit() {
+        // 1 y per x now, so an order asking 1 per x cannot fill
+        assert_eq!(
+            ConstantProductCurve::fill_to_price(1_000, 1_000, 1, 1, 0).unwrap(),
+            0
+        );
+        // x may go in until the price drops to 1/4, i.e. x doubles
+        assert_eq!(
+            ConstantProductCurve::fill_to_price(1_000, 1_000, 4, 1, 0).unwrap(),
+            1_000
+        );
+        assert_eq!(
+            ConstantProductCurve::fill_to_price(1_000, 1_000, 4, 1, 5_000).unwrap(),
+            2_000
+        );
+    }
+
+    #[test]
+    fn referral_fee_rounds_down() {
+        assert_eq!(ConstantProductCurve::referral_fee(3, 5_000).unwrap(), 1);
+        assert_eq!(
+            ConstantProductCurve::referral_fee(3_000, 10_000).unwrap(),
+            3_000
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn swap_never_decreases_k(
+            in_bal in 1..=u64::MAX / 2,
+            out_bal in 1..=u64::MAX,
+            amount_in in 0..=u64::MAX / 2,
+            fee_bps in 0u16..=10_000,
+        ) {
+            let quote = ConstantProductCurve::swap(in_bal, out_bal, amount_in, fee_bps).unwrap();
+            prop_assert!(quote.amount_out < out_bal);
+
+            let k_before = in_bal as u128 * out_bal as u128;
+            let k_after = (in_bal as u128 + amount_in as u128)
+                * (out_bal - quote.amount_out) as u128;
+            prop_assert!(k_after >= k_before);
+        }
+
+        #[test]
+        fn deposit_never_dilutes_lp(
+            total_x in 1..=u64::MAX / 2,
+            total_y in 1..=u64::MAX / 2,
+            total_lp in 1..=u64::MAX / 2,
+            lp in 0..=u32::MAX as u64,
+        ) {
+            prop_assume!(lp <= total_lp);
+            let amounts = ConstantProductCurve::give_lp_take_xy(total_x, total_y, total_lp, lp).unwrap();
+
+            // value per LP after >= value per LP before, for both sides
+            let new_lp = total_lp as u128 + lp as u128;
+            prop_assert!((total_x as u128 + amounts.x as u128) * total_lp as u128 >= total_x as u128 * new_lp);
+            prop_assert!((total_y as u128 + amounts.y as u128) * total_lp as u128 >= total_y as u128 * new_lp);
+        }
+
+        #[test]
+        fn withdraw_never_dilutes_lp(
+            total_x in 0..=u64::MAX,
+            total_y in 0..=u64::MAX,
+            total_lp in 1..=u64::MAX,
+            lp in 0..=u64::MAX,
+        ) {
+            prop_assume!(lp <= total_lp);
+            let amounts = ConstantProductCurve::take_lp_give_xy(total_x, total_y, total_lp, lp).unwrap();
+
+            let new_lp = (total_lp - lp) as u128;
+            prop_assert!((total_x - amounts.x) as u128 * total_lp as u128 >= total_x as u128 * new_lp);
+            prop_assert!((total_y - amounts.y) as u128 * total_lp as u128 >= total_y as u128 * new_lp);
+        }
+
+        #[test]
+        fn deposit_then_withdraw_never_profits(
+            total_x in 1..=u32::MAX as u64,
+            total_y in 1..=u32::MAX as u64,
+            total_lp in 1..=u32::MAX as u64,
+            lp in 1..=u32::MAX as u64,
+        ) {
+            let paid = ConstantProductCurve::give_lp_take_xy(total_x, total_y, total_lp, lp).unwrap();
+            let received = ConstantProductCurve::take_lp_give_xy(
+                total_x + paid.x,
+                total_y + paid.y,
+                total_lp + lp,
+                lp,
+            )
+            .unwrap();
+
+            prop_assert!(received.x <= paid.x);
+            prop_assert!(received.y <= paid.y);
+        }
+    }
 }
@@ -1,2 +1,6 @@
 pub mod constant_product;
+pub mod pmm;
+pub mod twamm;
 pub use constant_product::*;
+pub use pmm::*;
+pub use twamm::*;
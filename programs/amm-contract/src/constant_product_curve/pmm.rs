@@ -0,0 +1,151 @@
+use anchor_lang::prelude::*;
+
+use super::{div, isqrt, mul_div, RoundDirection, FEE_DENOMINATOR};
+use crate::error::AmmError;
+
+/// Denominator of the slope `k`, so `k = FEE_DENOMINATOR` is the constant
+/// product curve at the targets and `k = 0` a constant sum.
+const K_ONE: u128 = FEE_DENOMINATOR as u128;
+
+/// The quadratics below run on operands shifted down to this many bits, so
+/// their squares and sums fit in u128. The bits shifted off are well under
+/// the precision of any quote, and are rounded in the pool's favour.
+const OPERAND_BITS: u32 = 48;
+
+fn shift_for(max: u128) -> u32 {
+    (128 - max.leading_zeros()).saturating_sub(OPERAND_BITS)
+}
+
+fn isqrt_ceil(n: u128) -> u128 {
+    let root = isqrt(n);
+    match root * root < n {
+        true => root + 1,
+        false => root,
+    }
+}
+
+/// How far above `v1` the target of the short side is, when the long side's
+/// excess over its own target is worth `fair` of it at the oracle price.
+///
+/// Solves `t + k t² / v1 = fair`: buying the short side back up to its
+/// target along the curve costs exactly the excess.
+pub fn pmm_target_gap(v1: u64, fair: u128, k: u16) -> Result<u128> {
+    let k = k as u128;
+    if fair == 0 || k == 0 {
+        return Ok(fair);
+    }
+
+    // K t² + N v1 t - N v1 fair = 0
+    let shift = shift_for(fair.max(v1 as u128));
+    let (v1, fair) = ((v1 as u128) >> shift, fair >> shift);
+    let linear = K_ONE * v1;
+    let disc = linear * linear + 4 * k * K_ONE * v1 * fair;
+    let gap = div(isqrt(disc) - linear, 2 * k, RoundDirection::Floor)?;
+    Ok(gap << shift)
+}
+
+/// Worth, in units of the short side, of moving it from `v2` up to `v1` on
+/// the curve with target `v0`, where `v0 >= v1 >= v2 > 0`.
+///
+/// `(v1 - v2) (1 - k + k v0² / (v1 v2))`, rounded down.
+pub fn pmm_integrate(v0: u64, v1: u64, v2: u64, k: u16) -> Result<u128> {
+    let (v0, v1, v2, k) = (v0 as u128, v1 as u128, v2 as u128, k as u128);
+    let moved = v1 - v2;
+    let linear = mul_div(moved, K_ONE - k, K_ONE, RoundDirection::Floor)?;
+    let curved = mul_div(
+        mul_div(v0, moved, v1, RoundDirection::Floor)?,
+        v0,
+        v2,
+        RoundDirection::Floor,
+    )?;
+    Ok(linear + mul_div(curved, k, K_ONE, RoundDirection::Floor)?)
+}
+
+/// Amount of the short side, holding `v1` against a target of `v0`, that
+/// `value` of it buys along the curve. Rounded down.
+///
+/// The inverse of `pmm_integrate`: solves for the end point `v2` in
+/// `(N - K) v2² + b v2 - K v0² = 0`, `b = N value - (N - K) v1 + K v0² / v1`.
+pub fn pmm_sell_into_short(v0: u64, v1: u64, value: u128, k: u16) -> Result<u64> {
+    if value == 0 {
+        return Ok(0);
+    }
+    let k = k as u128;
+    let v0_sq_over_v1 = mul_div(v0 as u128, v0 as u128, v1 as u128, RoundDirection::Ceiling)?;
+    let shift = shift_for(value.max(v0 as u128).max(v1 as u128).max(v0_sq_over_v1));
+    // a higher target and v0² / v1, and a lower balance and value, all
+    // leave less to pay out
+    let up = |n: u128| div(n, 1 << shift, RoundDirection::Ceiling);
+    let (v0, v0_sq_over_v1) = (up(v0 as u128)?, up(v0_sq_over_v1)?);
+    let (v1, value) = ((v1 as u128) >> shift, value >> shift);
+
+    let b = (K_ONE * value + k * v0_sq_over_v1) as i128 - ((K_ONE - k) * v1) as i128;
+    let v2 = match k {
+        K_ONE => div(k * v0 * v0, b as u128, RoundDirection::Ceiling)?,
+        _ => {
+            let disc = b.unsigned_abs() * b.unsigned_abs() + 4 * (K_ONE - k) * k * v0 * v0;
+            let root = isqrt_ceil(disc) as i128;
+            div((root - b) as u128, 2 * (K_ONE - k), RoundDirection::Ceiling)?
+        }
+    };
+    let out = v1.saturating_sub(v2) << shift;
+    Ok(u64::try_from(out).map_err(|_| AmmError::Overflow)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constant_product_curve::ConstantProductCurve;
+    use proptest::prelude::*;
+
+    #[test]
+    fn full_slope_is_the_constant_product_at_the_target() {
+        // at 1:1 the value paid in is the amount paid in
+        let out = pmm_sell_into_short(1_000_000, 1_000_000, 10_000, 10_000).unwrap();
+        let cp = ConstantProductCurve::swap(1_000_000, 1_000_000, 10_000, 0).unwrap();
+        assert!(cp.amount_out - out <= 1);
+    }
+
+    #[test]
+    fn flat_slope_trades_near_the_price() {
+        let out = pmm_sell_into_short(1_000_000, 1_000_000, 10_000, 100).unwrap();
+        // k = 1% barely slips
+        assert!((9_990..=10_000).contains(&out));
+        let steep = pmm_sell_into_short(1_000_000, 1_000_000, 10_000, 5_000).unwrap();
+        assert!(steep < out);
+    }
+
+    #[test]
+    fn target_gap_buys_back_the_excess() {
+        // the short side's worth of moving back to its target is the excess
+        let gap = pmm_target_gap(900_000, 120_000, 2_000).unwrap() as u64;
+        let worth = pmm_integrate(900_000 + gap, 900_000 + gap, 900_000, 2_000).unwrap();
+        assert!(worth.abs_diff(120_000) <= 2);
+        assert_eq!(pmm_target_gap(900_000, 0, 2_000).unwrap(), 0);
+    }
+
+    #[test]
+    fn large_balances_do_not_overflow() {
+        let v = u64::MAX / 2;
+        let out = pmm_sell_into_short(v, v / 2, 1 << 80, 5_000).unwrap();
+        assert!(out > 0 && out < v / 2);
+        pmm_target_gap(v, 1 << 90, 5_000).unwrap();
+    }
+
+    proptest! {
+        #[test]
+        fn selling_then_buying_back_never_profits(
+            v0 in 1_000u64..1_000_000_000_000,
+            short_bps in 0u64..9_000,
+            value in 1u64..1_000_000_000,
+            k in 1u16..=10_000,
+        ) {
+            let v1 = v0 - v0 / 10_000 * short_bps;
+            let out = pmm_sell_into_short(v0, v1, value as u128, k).unwrap();
+            prop_assume!(out > 0 && out < v1);
+            // selling `out` back fetches no more than was paid for it
+            let worth = pmm_integrate(v0, v1, v1 - out, k).unwrap();
+            prop_assert!(worth <= value as u128);
+        }
+    }
+}
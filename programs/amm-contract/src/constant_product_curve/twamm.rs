@@ -0,0 +1,167 @@
+use anchor_lang::prelude::*;
+
+use super::{div, isqrt, mul_div, RoundDirection, XYAmounts};
+use crate::error::AmmError;
+
+const Q32: u128 = 1 << 32;
+const Q64: u128 = 1 << 64;
+
+/// Past this exponent `e^z` is so large that the pool has settled at the
+/// ratio of the two flows, to within the 32 fractional bits used.
+const MAX_EXPONENT: u128 = 20 * Q32;
+
+/// `a * b >> 64` without overflowing in between, or `None` when the result
+/// does not fit.
+pub fn mul_shr64(a: u128, b: u128) -> Option<u128> {
+    let (a1, a0) = (a >> 64, a & (Q64 - 1));
+    let (b1, b0) = (b >> 64, b & (Q64 - 1));
+    (a1.checked_mul(b1)?.checked_mul(Q64)?)
+        .checked_add(a1 * b0)?
+        .checked_add(a0 * b1)?
+        .checked_add((a0 * b0) >> 64)
+}
+
+/// `e^z` in Q32.32, for `z < MAX_EXPONENT`.
+fn exp_q32(z: u128) -> Result<u128> {
+    // e^z = (e^(z / 32))^32, with the Taylor series on the small exponent
+    let w = z >> 5;
+    let mut sum = Q32;
+    let mut term = Q32;
+    for i in 1..40u128 {
+        term = mul_div(term, w, Q32 * i, RoundDirection::Floor)?;
+        if term == 0 {
+            break;
+        }
+        sum += term;
+    }
+    for _ in 0..5 {
+        sum = mul_div(sum, sum, Q32, RoundDirection::Floor)?;
+    }
+    Ok(sum)
+}
+
+/// Reserves of a pool holding `x` / `y` after `x_in` and `y_in`, fees
+/// already taken, are sold into it evenly over one period.
+///
+/// Uses the closed-form TWAMM solution for two opposing flows on a constant
+/// product curve. Where the fixed-point solution is inexact the end point is
+/// clamped, and the side not solved for is rounded up, so `k` never
+/// decreases and neither flow is paid more than came in from the other.
+pub fn twamm_reserves(x: u64, y: u64, x_in: u64, y_in: u64) -> Result<XYAmounts> {
+    let k = x as u128 * y as u128;
+    let x_max = x as u128 + x_in as u128;
+    let y_max = y as u128 + y_in as u128;
+
+    let x_end = match (x_in, y_in) {
+        (0, 0) => return Ok(XYAmounts { x, y }),
+        (_, 0) => x_max,
+        (0, _) => div(k, y_max, RoundDirection::Ceiling)?,
+        _ => closed_form_x(x, y, x_in, y_in)?
+            .unwrap_or(x_max)
+            .clamp(div(k, y_max, RoundDirection::Ceiling)?, x_max),
+    };
+    // with no x flowing in, nothing may be paid out in y
+    let y_end = match x_in {
+        0 => y_max,
+        _ => div(k, x_end, RoundDirection::Ceiling)?,
+    };
+
+    Ok(XYAmounts {
+        x: u64::try_from(x_end).map_err(|_| AmmError::Overflow)?,
+        y: u64::try_from(y_end).map_err(|_| AmmError::Overflow)?,
+    })
+}
+
+/// `x_end = sqrt(k x_in / y_in) (e^z + c) / (e^z - c)`, with
+/// `z = 2 sqrt(x_in y_in / k)` and
+/// `c = (sqrt(x y_in) - sqrt(y x_in)) / (sqrt(x y_in) + sqrt(y x_in))`.
+/// `None` when the flows are too lopsided for the fixed-point ratio.
+fn closed_form_x(x: u64, y: u64, x_in: u64, y_in: u64) -> Result<Option<u128>> {
+    let sqrt_k = isqrt(x as u128 * y as u128);
+    let Ok(ratio) = mul_div(x_in as u128, Q64, y_in as u128, RoundDirection::Floor) else {
+        return Ok(None);
+    };
+    let equilibrium = mul_div(sqrt_k, isqrt(ratio), Q32, RoundDirection::Floor)?;
+
+    let z = 2 * mul_div(
+        isqrt(x_in as u128 * y_in as u128),
+        Q32,
+        sqrt_k,
+        RoundDirection::Floor,
+    )?;
+    if z >= MAX_EXPONENT {
+        return Ok(Some(equilibrium));
+    }
+    let e = exp_q32(z)?;
+
+    let p = isqrt(x as u128 * y_in as u128);
+    let q = isqrt(y as u128 * x_in as u128);
+    let c = mul_div(p.abs_diff(q), Q32, p + q, RoundDirection::Floor)?;
+    let (num, den) = match p >= q {
+        true => (e + c, e - c),
+        false => (e - c, e + c),
+    };
+
+    Ok(Some(mul_div(equilibrium, num, den, RoundDirection::Floor)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn exp_matches_float() {
+        for z in [0.0, 0.5, 1.0, 3.0, 10.0, 19.5] {
+            let fixed = exp_q32((z * Q32 as f64) as u128).unwrap() as f64 / Q32 as f64;
+            let float: f64 = f64::exp(z);
+            assert!((fixed - float).abs() / float < 1e-6, "e^{z}");
+        }
+    }
+
+    #[test]
+    fn mul_shr64_handles_wide_products() {
+        assert_eq!(mul_shr64(u128::MAX, 1), Some(u128::MAX >> 64));
+        assert_eq!(mul_shr64(1 << 100, 1 << 90), Some(1 << 126));
+        assert_eq!(mul_shr64(u128::MAX, u128::MAX), None);
+    }
+
+    #[test]
+    fn one_sided_flow_is_a_single_swap() {
+        let end = twamm_reserves(1_000, 1_000, 1_000, 0).unwrap();
+        assert_eq!((end.x, end.y), (2_000, 500));
+        let end = twamm_reserves(1_000, 1_000, 0, 1_000).unwrap();
+        assert_eq!((end.x, end.y), (500, 2_000));
+    }
+
+    #[test]
+    fn balanced_flows_trade_with_each_other() {
+        let end = twamm_reserves(1_000_000, 1_000_000, 10_000, 10_000).unwrap();
+        assert!(end.x.abs_diff(1_000_000) <= 1);
+        assert!(end.y.abs_diff(1_000_000) <= 1);
+    }
+
+    #[test]
+    fn long_flows_settle_at_their_ratio() {
+        // 4x more x than y sold for long enough leaves x/y at 4
+        let end = twamm_reserves(1_000, 1_000, 1_000_000_000, 250_000_000).unwrap();
+        let ratio = end.x as f64 / end.y as f64;
+        assert!((ratio - 4.0).abs() < 0.01, "{ratio}");
+    }
+
+    proptest! {
+        #[test]
+        fn twamm_never_decreases_k(
+            x in 1..=u64::MAX / 4,
+            y in 1..=u64::MAX / 4,
+            x_in in 0..=u64::MAX / 4,
+            y_in in 0..=u64::MAX / 4,
+        ) {
+            let end = twamm_reserves(x, y, x_in, y_in).unwrap();
+            prop_assert!(end.x as u128 * end.y as u128 >= x as u128 * y as u128);
+            // neither flow gets more than the pool plus the other flow holds
+            prop_assert!(end.x as u128 <= x as u128 + x_in as u128);
+            prop_assert!(end.y as u128 <= y as u128 + y_in as u128);
+        }
+    }
+}
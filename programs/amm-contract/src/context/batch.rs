@@ -0,0 +1,525 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token::{close_account, CloseAccount, Token},
+    token_interface::{transfer_checked, Mint, TokenAccount, TransferChecked},
+};
+
+use crate::constant_product_curve::{XYAmounts, FEE_DENOMINATOR};
+use crate::error::AmmError;
+use crate::events::BatchSettleEvent;
+use crate::states::{Batch, Config, SwapIntent, BATCH_SEED, MIN_INTENT_BPS};
+
+#[derive(Accounts)]
+#[instruction(batch_id: u64)]
+pub struct SubmitSwapIntent<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    pub mint_y: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds = [b"config", mint_x.key().as_ref(), mint_y.key().as_ref()],
+        bump = config.my_bump
+    )]
+    pub config: Account<'info, Config>,
+
+    /// Size the smallest intent.
+    #[account(
+        associated_token::mint = mint_x,
+        associated_token::authority = config
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        associated_token::mint = mint_y,
+        associated_token::authority = config
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+
+    /// Opened by the window's first intent.
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + Batch::INIT_SPACE,
+        seeds = [BATCH_SEED, config.key().as_ref(), batch_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub batch: Box<Account<'info, Batch>>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = mint_x,
+        associated_token::authority = batch
+    )]
+    pub batch_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = mint_y,
+        associated_token::authority = batch
+    )]
+    pub batch_y: InterfaceAccount<'info, TokenAccount>,
+
+    /// Pays the input when selling x, claims the output otherwise.
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = mint_x,
+        associated_token::authority = owner
+    )]
+    pub owner_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = mint_y,
+        associated_token::authority = owner
+    )]
+    pub owner_y: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> SubmitSwapIntent<'info> {
+    /// Adds a swap to the batch of the current window, escrowing its input
+    /// until settlement.
+    pub fn submit(
+        &mut self,
+        batch_id: u64,
+        amount_in: u64,
+        min_out: u64,
+        is_x_in: bool,
+        bumps: &SubmitSwapIntentBumps,
+    ) -> Result<()> {
+        require!(amount_in > 0, AmmError::ZeroAmount);
+        require!(self.config.pmm.is_none(), AmmError::PmmUnsupported);
+        let window = self
+            .config
+            .batch_window
+            .ok_or(AmmError::BatchAuctionDisabled)?;
+        let now = Clock::get()?.unix_timestamp;
+        require!(batch_id as i64 == now / window, AmmError::BatchClosed);
+
+        let (reserve_x, reserve_y) = self
+            .config
+            .twamm
+            .reserves(self.vault_x.amount, self.vault_y.amount)?;
+        let reserve_in = if is_x_in { reserve_x } else { reserve_y };
+        require!(
+            amount_in as u128 * FEE_DENOMINATOR as u128
+                >= reserve_in as u128 * MIN_INTENT_BPS as u128,
+            AmmError::IntentTooSmall
+        );
+
+        if self.batch.intent_count == 0 {
+            self.batch.set_inner(Batch {
+                config: self.config.key(),
+                batch_id,
+                closes_at: (batch_id as i64 + 1) * window,
+                payer: self.owner.key(),
+                settled: false,
+                intent_count: 0,
+                intents: Default::default(),
+                bump: bumps.batch,
+            });
+        }
+        self.batch.push(SwapIntent {
+            owner: self.owner.key(),
+            is_x_in,
+            amount_in,
+            min_out,
+            amount_out: 0,
+            fee: 0,
+            claimed: false,
+        })?;
+
+        let (from, to, mint) = match is_x_in {
+            true => (&self.owner_x, &self.batch_x, &self.mint_x),
+            false => (&self.owner_y, &self.batch_y, &self.mint_y),
+        };
+        let cpi_context = CpiContext::new(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from: from.to_account_info(),
+                to: to.to_account_info(),
+                mint: mint.to_account_info(),
+                authority: self.owner.to_account_info(),
+            },
+        );
+
+        transfer_checked(cpi_context, amount_in, mint.decimals)
+    }
+}
+
+#[derive(Accounts)]
+pub struct SettleBatch<'info> {
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    pub mint_y: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds = [b"config", mint_x.key().as_ref(), mint_y.key().as_ref()],
+        bump = config.my_bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        has_one = config,
+        seeds = [BATCH_SEED, config.key().as_ref(), batch.batch_id.to_le_bytes().as_ref()],
+        bump = batch.bump
+    )]
+    pub batch: Box<Account<'info, Batch>>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = batch
+    )]
+    pub batch_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = batch
+    )]
+    pub batch_y: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: the pool's price feed, required when it has an oracle guard;
+    /// its key, owner and layout are checked against the guard
+    pub oracle: Option<UncheckedAccount<'info>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> SettleBatch<'info> {
+    /// Clears a closed batch at one price against the pool, at the base
+    /// fees, moving the filled inputs into the vaults and every output into
+    /// the batch for its owner to claim. Anyone may settle.
+    ///
+    /// A clearing price past the circuit breaker's bands, or too far from
+    /// the oracle's, refunds the whole batch, as does a stale oracle.
+    pub fn settle(&mut self) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(now >= self.batch.closes_at, AmmError::BatchNotClosed);
+        require!(!self.batch.settled, AmmError::BatchSettled);
+
+        let oracle = self.config.read_oracle(self.oracle.as_deref())?;
+        let (reserve_x, reserve_y) =
+            self.config
+                .sync_twamm(self.vault_x.amount, self.vault_y.amount, now)?;
+        let (fee_x, fee_y) = (self.config.base_fee(true), self.config.base_fee(false));
+        let (mut amounts_in, mut amounts_out) =
+            self.batch.clear(reserve_x, reserve_y, fee_x, fee_y)?;
+
+        let after = (
+            reserve_x
+                .checked_add(amounts_in.x)
+                .and_then(|x| x.checked_sub(amounts_out.x))
+                .ok_or(AmmError::Overflow)?,
+            reserve_y
+                .checked_add(amounts_in.y)
+                .and_then(|y| y.checked_sub(amounts_out.y))
+                .ok_or(AmmError::Overflow)?,
+        );
+        let slot = Clock::get()?.slot;
+        let decimals = (self.mint_x.decimals, self.mint_y.decimals);
+        let strays_from_oracle = |(amount_in, amount_out), is_x_in| match &oracle {
+            Some((guard, price)) => {
+                amount_in > 0
+                    && guard
+                        .check(
+                            price,
+                            now,
+                            decimals,
+                            (amount_in, amount_out),
+                            is_x_in,
+                            Some(((reserve_x, reserve_y), after)),
+                        )
+                        .is_err()
+            }
+            None => false,
+        };
+        // failing would strand the inputs, so a batch the circuit breaker or
+        // the oracle guard stops, stale feed included, trades nothing and is
+        // refunded instead
+        if strays_from_oracle((amounts_in.x, amounts_out.y), true)
+            || strays_from_oracle((amounts_in.y, amounts_out.x), false)
+            || self
+                .config
+                .check_reserves_move((reserve_x, reserve_y), after, slot)
+                .is_err()
+        {
+            self.batch.refund_all();
+            amounts_in = XYAmounts { x: 0, y: 0 };
+            amounts_out = XYAmounts { x: 0, y: 0 };
+        }
+
+        self.pay_from_batch(&self.batch_x, &self.vault_x, &self.mint_x, amounts_in.x)?;
+        self.pay_from_batch(&self.batch_y, &self.vault_y, &self.mint_y, amounts_in.y)?;
+        self.pay_from_vault(&self.vault_x, &self.batch_x, &self.mint_x, amounts_out.x)?;
+        self.pay_from_vault(&self.vault_y, &self.batch_y, &self.mint_y, amounts_out.y)?;
+
+        emit!(BatchSettleEvent {
+            config: self.config.key(),
+            batch_id: self.batch.batch_id,
+            amount_x_in: amounts_in.x,
+            amount_y_in: amounts_in.y,
+            amount_x_out: amounts_out.x,
+            amount_y_out: amounts_out.y,
+        });
+
+        Ok(())
+    }
+
+    fn pay_from_batch(
+        &self,
+        from: &InterfaceAccount<'info, TokenAccount>,
+        to: &InterfaceAccount<'info, TokenAccount>,
+        mint: &InterfaceAccount<'info, Mint>,
+        amount: u64,
+    ) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+        let config = self.config.key();
+        let batch_id = self.batch.batch_id.to_le_bytes();
+        let seeds = &[
+            BATCH_SEED,
+            config.as_ref(),
+            batch_id.as_ref(),
+            &[self.batch.bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from: from.to_account_info(),
+                to: to.to_account_info(),
+                mint: mint.to_account_info(),
+                authority: self.batch.to_account_info(),
+            },
+            signer_seeds,
+        );
+        transfer_checked(cpi_context, amount, mint.decimals)
+    }
+
+    fn pay_from_vault(
+        &self,
+        from: &InterfaceAccount<'info, TokenAccount>,
+        to: &InterfaceAccount<'info, TokenAccount>,
+        mint: &InterfaceAccount<'info, Mint>,
+        amount: u64,
+    ) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+        let seeds = &[
+            b"config",
+            self.config.mint_x.as_ref(),
+            self.config.mint_y.as_ref(),
+            &[self.config.my_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from: from.to_account_info(),
+                to: to.to_account_info(),
+                mint: mint.to_account_info(),
+                authority: self.config.to_account_info(),
+            },
+            signer_seeds,
+        );
+        transfer_checked(cpi_context, amount, mint.decimals)
+    }
+}
+
+#[derive(Accounts)]
+pub struct ClaimSwapIntents<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    pub mint_y: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds = [b"config", mint_x.key().as_ref(), mint_y.key().as_ref()],
+        bump = config.my_bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        has_one = config,
+        has_one = payer,
+        seeds = [BATCH_SEED, config.key().as_ref(), batch.batch_id.to_le_bytes().as_ref()],
+        bump = batch.bump
+    )]
+    pub batch: Box<Account<'info, Batch>>,
+
+    /// CHECK: the batch's rent payer, refunded once every intent is claimed
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = batch
+    )]
+    pub batch_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = batch
+    )]
+    pub batch_y: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = owner
+    )]
+    pub owner_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = owner
+    )]
+    pub owner_y: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> ClaimSwapIntents<'info> {
+    /// Pays the owner the output of each of their filled intents and
+    /// refunds the input of those left out, less the fee of those that
+    /// missed their limit. The last claim hands those fees and the rounding
+    /// dust to the pool and closes the batch.
+    pub fn claim(&mut self) -> Result<()> {
+        require!(self.batch.settled, AmmError::BatchNotSettled);
+
+        let owner = self.owner.key();
+        let (mut pay_x, mut pay_y) = (0, 0);
+        let count = self.batch.intent_count as usize;
+        for intent in self.batch.intents[..count]
+            .iter_mut()
+            .filter(|intent| intent.owner == owner && !intent.claimed)
+        {
+            intent.claimed = true;
+            match (intent.is_x_in, intent.amount_out > 0) {
+                (true, true) => pay_y += intent.amount_out,
+                (false, true) => pay_x += intent.amount_out,
+                (true, false) => pay_x += intent.amount_in - intent.fee,
+                (false, false) => pay_y += intent.amount_in - intent.fee,
+            }
+        }
+        self.pay_from_batch(&self.batch_x, &self.owner_x, &self.mint_x, pay_x)?;
+        self.pay_from_batch(&self.batch_y, &self.owner_y, &self.mint_y, pay_y)?;
+
+        if !self.batch.intents().iter().all(|intent| intent.claimed) {
+            return Ok(());
+        }
+        let dust_x = self.batch_x.amount - pay_x;
+        let dust_y = self.batch_y.amount - pay_y;
+        self.pay_from_batch(&self.batch_x, &self.vault_x, &self.mint_x, dust_x)?;
+        self.pay_from_batch(&self.batch_y, &self.vault_y, &self.mint_y, dust_y)?;
+        self.close_escrow(&self.batch_x)?;
+        self.close_escrow(&self.batch_y)?;
+        self.batch.close(self.payer.to_account_info())
+    }
+
+    fn signer_seeds(&self) -> ([u8; 8], [u8; 1]) {
+        (self.batch.batch_id.to_le_bytes(), [self.batch.bump])
+    }
+
+    fn pay_from_batch(
+        &self,
+        from: &InterfaceAccount<'info, TokenAccount>,
+        to: &InterfaceAccount<'info, TokenAccount>,
+        mint: &InterfaceAccount<'info, Mint>,
+        amount: u64,
+    ) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+        let config = self.config.key();
+        let (batch_id, bump) = self.signer_seeds();
+        let seeds = &[BATCH_SEED, config.as_ref(), batch_id.as_ref(), &bump];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from: from.to_account_info(),
+                to: to.to_account_info(),
+                mint: mint.to_account_info(),
+                authority: self.batch.to_account_info(),
+            },
+            signer_seeds,
+        );
+        transfer_checked(cpi_context, amount, mint.decimals)
+    }
+
+    fn close_escrow(&self, escrow: &InterfaceAccount<'info, TokenAccount>) -> Result<()> {
+        let config = self.config.key();
+        let (batch_id, bump) = self.signer_seeds();
+        let seeds = &[BATCH_SEED, config.as_ref(), batch_id.as_ref(), &bump];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            CloseAccount {
+                account: escrow.to_account_info(),
+                destination: self.payer.to_account_info(),
+                authority: self.batch.to_account_info(),
+            },
+            signer_seeds,
+        );
+        close_account(cpi_context)
+    }
+}
@@ -0,0 +1,121 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    metadata::{
+        create_metadata_accounts_v3, mpl_token_metadata::types::DataV2, CreateMetadataAccountsV3,
+        Metadata,
+    },
+    token_interface::Mint,
+};
+
+use crate::error::AmmError;
+use crate::lp_metadata::{lp_name_and_symbol, side_symbol};
+use crate::states::Config;
+
+/// Gives the LP mint a token metadata account so wallets can name it. The
+/// metadata is immutable, so on pools with an authority only the authority
+/// may create it, once the pair's own metadata is in place. Anyone may pay
+/// for it on pools without one.
+#[derive(Accounts)]
+pub struct CreateLpMetadata<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        seeds = [b"config", mint_x.key().as_ref(), mint_y.key().as_ref()],
+        bump = config.my_bump,
+        has_one = mint_x,
+        has_one = mint_y,
+        constraint = config.authority.is_none_or(|authority| authority == payer.key())
+            @ AmmError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    pub mint_y: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        seeds = [b"lp", config.key().as_ref()],
+        bump = config.lp_bump
+    )]
+    pub mint_lp: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: created by the token metadata program
+    #[account(
+        mut,
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), mint_lp.key().as_ref()],
+        seeds::program = token_metadata_program.key(),
+        bump
+    )]
+    pub lp_metadata: UncheckedAccount<'info>,
+
+    /// CHECK: metadata of mint x, read for its symbol and may not exist
+    #[account(
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), mint_x.key().as_ref()],
+        seeds::program = token_metadata_program.key(),
+        bump
+    )]
+    pub metadata_x: UncheckedAccount<'info>,
+
+    /// CHECK: metadata of mint y, read for its symbol and may not exist
+    #[account(
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), mint_y.key().as_ref()],
+        seeds::program = token_metadata_program.key(),
+        bump
+    )]
+    pub metadata_y: UncheckedAccount<'info>,
+
+    pub token_metadata_program: Program<'info, Metadata>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+impl<'info> CreateLpMetadata<'info> {
+    pub fn create_lp_metadata(&mut self) -> Result<()> {
+        let (name, symbol) = lp_name_and_symbol(
+            &side_symbol(&self.mint_x.key(), &self.metadata_x),
+            &side_symbol(&self.mint_y.key(), &self.metadata_y),
+        );
+
+        let mint_x_key = self.mint_x.key();
+        let mint_y_key = self.mint_y.key();
+        let seeds: &[&[u8]] = &[
+            b"config",
+            mint_x_key.as_ref(),
+            mint_y_key.as_ref(),
+            &[self.config.my_bump],
+        ];
+        let signer_seeds: &[&[&[u8]]] = &[seeds];
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_metadata_program.to_account_info(),
+            CreateMetadataAccountsV3 {
+                metadata: self.lp_metadata.to_account_info(),
+                mint: self.mint_lp.to_account_info(),
+                mint_authority: self.config.to_account_info(),
+                payer: self.payer.to_account_info(),
+                update_authority: self.config.to_account_info(),
+                system_program: self.system_program.to_account_info(),
+                rent: self.rent.to_account_info(),
+            },
+            signer_seeds,
+        );
+
+        create_metadata_accounts_v3(
+            cpi_context,
+            DataV2 {
+                name,
+                symbol,
+                uri: String::new(),
+                seller_fee_basis_points: 0,
+                creators: None,
+                collection: None,
+                uses: None,
+            },
+            // no instruction can sign an update for the config, and the name
+            // is derived from the pool anyway
+            false,
+            true,
+            None,
+        )
+    }
+}
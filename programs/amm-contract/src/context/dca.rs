@@ -0,0 +1,452 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token::{close_account, CloseAccount, Token},
+    token_interface::{transfer_checked, Mint, TokenAccount, TransferChecked},
+};
+
+use crate::constant_product_curve::ConstantProductCurve;
+use crate::error::AmmError;
+use crate::events::SwapEvent;
+use crate::states::{trade_reserves, Config, Dca, DCA_SEED};
+
+#[derive(Accounts)]
+#[instruction(dca_id: u64, is_x_in: bool)]
+pub struct OpenDca<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [b"config", config.mint_x.as_ref(), config.mint_y.as_ref()],
+        bump = config.my_bump,
+        constraint = (mint_in.key(), mint_out.key()) == match is_x_in {
+            true => (config.mint_x, config.mint_y),
+            false => (config.mint_y, config.mint_x),
+        } @ AmmError::InvalidMint
+    )]
+    pub config: Account<'info, Config>,
+
+    pub mint_in: InterfaceAccount<'info, Mint>,
+    pub mint_out: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + Dca::INIT_SPACE,
+        seeds = [
+            DCA_SEED,
+            config.key().as_ref(),
+            owner.key().as_ref(),
+            dca_id.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub dca: Account<'info, Dca>,
+
+    #[account(
+        init,
+        payer = owner,
+        associated_token::mint = mint_in,
+        associated_token::authority = dca
+    )]
+    pub escrow: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_in,
+        associated_token::authority = owner
+    )]
+    pub owner_in: InterfaceAccount<'info, TokenAccount>,
+
+    /// Where slices are paid, so keepers never have to create it.
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = mint_out,
+        associated_token::authority = owner
+    )]
+    pub owner_out: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> OpenDca<'info> {
+    /// Escrows `total` to be sold `amount_per_cycle` at a time, at most once
+    /// per `interval`, starting now.
+    #[allow(clippy::too_many_arguments)]
+    pub fn open(
+        &mut self,
+        dca_id: u64,
+        is_x_in: bool,
+        total: u64,
+        amount_per_cycle: u64,
+        interval: i64,
+        min_out_per_cycle: u64,
+        tip_bps: u16,
+        bumps: &OpenDcaBumps,
+    ) -> Result<()> {
+        self.dca.set_inner(Dca {
+            config: self.config.key(),
+            owner: self.owner.key(),
+            dca_id,
+            is_x_in,
+            amount_per_cycle,
+            min_out_per_cycle,
+            interval,
+            next_at: Clock::get()?.unix_timestamp,
+            tip_bps,
+            remaining: total,
+            bump: bumps.dca,
+        });
+        self.dca.validate()?;
+
+        let cpi_context = CpiContext::new(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from: self.owner_in.to_account_info(),
+                to: self.escrow.to_account_info(),
+                mint: self.mint_in.to_account_info(),
+                authority: self.owner.to_account_info(),
+            },
+        );
+
+        transfer_checked(cpi_context, total, self.mint_in.decimals)
+    }
+}
+
+#[derive(Accounts)]
+pub struct CloseDca<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [
+            DCA_SEED,
+            dca.config.as_ref(),
+            owner.key().as_ref(),
+            dca.dca_id.to_le_bytes().as_ref()
+        ],
+        bump = dca.bump
+    )]
+    pub dca: Account<'info, Dca>,
+
+    pub mint_in: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_in,
+        associated_token::authority = dca
+    )]
+    pub escrow: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_in,
+        associated_token::authority = owner
+    )]
+    pub owner_in: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> CloseDca<'info> {
+    /// Stops the DCA, returning the unsold input.
+    pub fn close(&mut self) -> Result<()> {
+        let config = self.dca.config;
+        let owner = self.owner.key();
+        let dca_id = self.dca.dca_id.to_le_bytes();
+        let seeds = &[
+            DCA_SEED,
+            config.as_ref(),
+            owner.as_ref(),
+            dca_id.as_ref(),
+            &[self.dca.bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from: self.escrow.to_account_info(),
+                to: self.owner_in.to_account_info(),
+                mint: self.mint_in.to_account_info(),
+                authority: self.dca.to_account_info(),
+            },
+            signer_seeds,
+        );
+        transfer_checked(cpi_context, self.escrow.amount, self.mint_in.decimals)?;
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            CloseAccount {
+                account: self.escrow.to_account_info(),
+                destination: self.owner.to_account_info(),
+                authority: self.dca.to_account_info(),
+            },
+            signer_seeds,
+        );
+        close_account(cpi_context)?;
+        self.dca.close(self.owner.to_account_info())
+    }
+}
+
+#[derive(Accounts)]
+pub struct ExecuteDca<'info> {
+    /// Anyone may execute a due slice.
+    pub keeper: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"config", config.mint_x.as_ref(), config.mint_y.as_ref()],
+        bump = config.my_bump,
+        constraint = (mint_in.key(), mint_out.key()) == match dca.is_x_in {
+            true => (config.mint_x, config.mint_y),
+            false => (config.mint_y, config.mint_x),
+        } @ AmmError::InvalidMint
+    )]
+    pub config: Account<'info, Config>,
+
+    pub mint_in: InterfaceAccount<'info, Mint>,
+    pub mint_out: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_in,
+        associated_token::authority = config
+    )]
+    pub vault_in: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_out,
+        associated_token::authority = config
+    )]
+    pub vault_out: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        has_one = config,
+        has_one = owner,
+        seeds = [
+            DCA_SEED,
+            config.key().as_ref(),
+            dca.owner.as_ref(),
+            dca.dca_id.to_le_bytes().as_ref()
+        ],
+        bump = dca.bump
+    )]
+    pub dca: Account<'info, Dca>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_in,
+        associated_token::authority = dca
+    )]
+    pub escrow: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: the DCA's owner, refunded the rent once everything is sold
+    #[account(mut)]
+    pub owner: UncheckedAccount<'info>,
+
+    /// Refunded anything sent to the escrow besides the DCA's input.
+    #[account(
+        mut,
+        associated_token::mint = mint_in,
+        associated_token::authority = owner
+    )]
+    pub owner_in: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_out,
+        associated_token::authority = owner
+    )]
+    pub owner_out: InterfaceAccount<'info, TokenAccount>,
+
+    /// Receives the tip; any account of the input mint.
+    #[account(mut, token::mint = mint_in)]
+    pub keeper_in: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: the pool's price feed, required when it has an oracle guard;
+    /// its key, owner and layout are checked against the guard
+    pub oracle: Option<UncheckedAccount<'info>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> ExecuteDca<'info> {
+    /// Sells the due slice, less the keeper's tip, exactly as a swap would,
+    /// and schedules the next one an interval from now. The DCA is closed
+    /// once everything is sold.
+    pub fn execute(&mut self) -> Result<()> {
+        require!(self.config.pmm.is_none(), AmmError::PmmUnsupported);
+        require!(
+            self.config.batch_window.is_none(),
+            AmmError::BatchAuctionOnly
+        );
+        let now = Clock::get()?.unix_timestamp;
+        require!(now >= self.dca.next_at, AmmError::DcaNotDue);
+
+        let slice = self.dca.slice();
+        let tip = self.dca.tip(slice)?;
+        let amount_in = slice - tip;
+        let is_x_in = self.dca.is_x_in;
+
+        let (in_bal, out_bal) = self.config.sync_twamm_directed(
+            self.vault_in.amount,
+            self.vault_out.amount,
+            is_x_in,
+            now,
+        )?;
+        let (reserve_x, reserve_y) = match is_x_in {
+            true => (in_bal, out_bal),
+            false => (out_bal, in_bal),
+        };
+        let fee = self
+            .config
+            .swap_fee(reserve_x, reserve_y, amount_in, is_x_in, now)?;
+        let quote = ConstantProductCurve::swap(in_bal, out_bal, amount_in, fee)?;
+        // the bound is on the whole slice, tip included
+        require!(
+            quote.amount_out as u128 * self.dca.amount_per_cycle as u128
+                >= slice as u128 * self.dca.min_out_per_cycle as u128,
+            AmmError::SlippageExceeded
+        );
+
+        self.config.check_price_move(
+            (in_bal, out_bal),
+            (amount_in, quote.amount_out),
+            is_x_in,
+            Clock::get()?.slot,
+        )?;
+        if let Some((guard, price)) = self.config.read_oracle(self.oracle.as_deref())? {
+            let (mint_in, mint_out) = (&self.mint_in, &self.mint_out);
+            guard.check(
+                &price,
+                now,
+                match is_x_in {
+                    true => (mint_in.decimals, mint_out.decimals),
+                    false => (mint_out.decimals, mint_in.decimals),
+                },
+                (amount_in, quote.amount_out),
+                is_x_in,
+                Some(trade_reserves(
+                    (in_bal, out_bal),
+                    (amount_in, quote.amount_out),
+                    is_x_in,
+                )?),
+            )?;
+        }
+
+        self.dca.remaining -= slice;
+        self.dca.next_at = now + self.dca.interval;
+        self.pay_from_escrow(&self.vault_in, amount_in)?;
+        if tip > 0 {
+            self.pay_from_escrow(&self.keeper_in, tip)?;
+        }
+        self.pay_out(quote.amount_out)?;
+
+        emit!(SwapEvent {
+            config: self.config.key(),
+            user: self.owner.key(),
+            is_x_in,
+            amount_in,
+            amount_out: quote.amount_out,
+            fee: quote.fee,
+            referral_fee: 0,
+            referrer: None,
+        });
+
+        if self.dca.remaining > 0 {
+            return Ok(());
+        }
+        self.close_escrow()?;
+        self.dca.close(self.owner.to_account_info())
+    }
+
+    fn pay_from_escrow(
+        &self,
+        to: &InterfaceAccount<'info, TokenAccount>,
+        amount: u64,
+    ) -> Result<()> {
+        let config = self.config.key();
+        let dca_id = self.dca.dca_id.to_le_bytes();
+        let seeds = &[
+            DCA_SEED,
+            config.as_ref(),
+            self.dca.owner.as_ref(),
+            dca_id.as_ref(),
+            &[self.dca.bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from: self.escrow.to_account_info(),
+                to: to.to_account_info(),
+                mint: self.mint_in.to_account_info(),
+                authority: self.dca.to_account_info(),
+            },
+            signer_seeds,
+        );
+        transfer_checked(cpi_context, amount, self.mint_in.decimals)
+    }
+
+    fn pay_out(&self, amount: u64) -> Result<()> {
+        let seeds = &[
+            b"config",
+            self.config.mint_x.as_ref(),
+            self.config.mint_y.as_ref(),
+            &[self.config.my_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from: self.vault_out.to_account_info(),
+                to: self.owner_out.to_account_info(),
+                mint: self.mint_out.to_account_info(),
+                authority: self.config.to_account_info(),
+            },
+            signer_seeds,
+        );
+        transfer_checked(cpi_context, amount, self.mint_out.decimals)
+    }
+
+    /// Returns anything else sent to the escrow to the owner, which would
+    /// otherwise keep it from closing, and closes it.
+    fn close_escrow(&mut self) -> Result<()> {
+        self.escrow.reload()?;
+        if self.escrow.amount > 0 {
+            self.pay_from_escrow(&self.owner_in, self.escrow.amount)?;
+        }
+
+        let config = self.config.key();
+        let dca_id = self.dca.dca_id.to_le_bytes();
+        let seeds = &[
+            DCA_SEED,
+            config.as_ref(),
+            self.dca.owner.as_ref(),
+            dca_id.as_ref(),
+            &[self.dca.bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            CloseAccount {
+                account: self.escrow.to_account_info(),
+                destination: self.owner.to_account_info(),
+                authority: self.dca.to_account_info(),
+            },
+            signer_seeds,
+        );
+        close_account(cpi_context)
+    }
+}
@@ -6,6 +6,8 @@ use anchor_spl::{
 };
 
 use crate::constant_product_curve::*;
+use crate::error::AmmError;
+use crate::native_sol;
 use crate::states::Config;
 
 #[derive(Accounts)]
@@ -36,19 +38,21 @@ pub struct Deposit<'info> {
     )]
     pub vault_y: InterfaceAccount<'info, TokenAccount>,
 
+    /// Omitted to pay in lamports when `mint_x` is the native mint.
     #[account(
         mut,
         associated_token::authority = user,
         associated_token::mint = mint_x
     )]
-    pub user_x: InterfaceAccount<'info, TokenAccount>,
+    pub user_x: Option<InterfaceAccount<'info, TokenAccount>>,
 
+    /// Omitted to pay in lamports when `mint_y` is the native mint.
     #[account(
         mut,
         associated_token::authority = user,
         associated_token::mint = mint_y
     )]
-    pub user_y: InterfaceAccount<'info, TokenAccount>,
+    pub user_y: Option<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         mut,
@@ -58,6 +62,7 @@ pub struct Deposit<'info> {
     pub user_lp: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
+        mut,
         has_one = mint_x,
         has_one = mint_y,
         seeds = [b"config", mint_x.key().as_ref(), mint_y.key().as_ref()],
@@ -77,25 +82,33 @@ impl<'info> Deposit<'info> {
         // it should be less than equal to what they will deposite
         // deposite both token
         // transfer lp tokens to user
-        assert!(amount > 0);
-
-        let (x, y) = match self.mint_lp.supply == 0 && self.vault_x.amount == 0 {
+        require!(amount > 0, AmmError::ZeroAmount);
+
+        let (reserve_x, reserve_y) = self.config.sync_twamm(
+            self.vault_x.amount,
+            self.vault_y.amount,
+            Clock::get()?.unix_timestamp,
+        )?;
+        let (x, y) = match self.mint_lp.supply == 0 && reserve_x == 0 {
             true => (max_x, max_y),
             false => {
                 let amounts = ConstantProductCurve::give_lp_take_xy(
-                    self.vault_x.amount,
-                    self.vault_y.amount,
+                    reserve_x,
+                    reserve_y,
                     self.mint_lp.supply,
                     amount,
-                    6,
-                )
-                .unwrap();
+                )?;
 
                 (amounts.x, amounts.y)
             }
         };
 
-        assert!(x <= max_x && y <= max_y);
+        require!(x <= max_x && y <= max_y, AmmError::DepositExceedsMax);
+        if let Some(pmm) = self.config.pmm.as_mut() {
+            let supply = self.mint_lp.supply;
+            let after = supply.checked_add(amount).ok_or(AmmError::Overflow)?;
+            pmm.scale(supply, after)?;
+        }
         self.deposit_token(x, true)?;
         self.deposit_token(y, false)?;
         self.mint_lp_token(amount)?;
@@ -104,27 +117,41 @@ impl<'info> Deposit<'info> {
     }
 
     pub fn deposit_token(&self, amount: u64, is_x: bool) -> Result<()> {
-        let (from, to, mint, decimals) = match is_x {
+        let (user_account, to, mint, decimals) = match is_x {
             true => (
-                self.user_x.to_account_info(),
+                &self.user_x,
                 self.vault_x.to_account_info(),
                 self.mint_x.to_account_info(),
                 self.mint_x.decimals,
             ),
             false => (
-                self.user_y.to_account_info(),
+                &self.user_y,
                 self.vault_y.to_account_info(),
                 self.mint_y.to_account_info(),
                 self.mint_y.decimals,
             ),
         };
 
+        let Some(from) = user_account else {
+            require!(
+                native_sol::is_native(mint.key),
+                AmmError::MissingTokenAccount
+            );
+            return native_sol::wrap(
+                self.user.to_account_info(),
+                to,
+                self.system_program.to_account_info(),
+                self.token_program.to_account_info(),
+                amount,
+            );
+        };
+
         let cpi_ctx = CpiContext::new(
             self.token_program.to_account_info(),
             TransferChecked {
-                from,
+                from: from.to_account_info(),
                 to,
-                mint: mint,
+                mint,
                 authority: self.user.to_account_info(),
             },
         );
@@ -0,0 +1,398 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token::Token,
+    token_interface::{transfer_checked, Mint, TokenAccount, TransferChecked},
+};
+
+use crate::error::AmmError;
+use crate::states::{
+    Config, Farm, FarmReward, StakePosition, FARM_SEED, MAX_FARM_REWARDS, STAKE_SEED,
+};
+
+/// Opens the LP farm of a pool. Rewards are added with `SetFarmReward`.
+#[derive(Accounts)]
+pub struct InitializeFarm<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"config", config.mint_x.as_ref(), config.mint_y.as_ref()],
+        bump = config.my_bump,
+        constraint = config.authority == Some(authority.key()) @ AmmError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        seeds = [b"lp", config.key().as_ref()],
+        bump = config.lp_bump
+    )]
+    pub mint_lp: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Farm::INIT_SPACE,
+        seeds = [FARM_SEED, config.key().as_ref()],
+        bump
+    )]
+    pub farm: Account<'info, Farm>,
+
+    #[account(
+        init,
+        payer = authority,
+        associated_token::mint = mint_lp,
+        associated_token::authority = farm
+    )]
+    pub lp_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitializeFarm<'info> {
+    pub fn init(&mut self, bumps: &InitializeFarmBumps) -> Result<()> {
+        self.farm.set_inner(Farm {
+            config: self.config.key(),
+            total_staked: 0,
+            last_update: Clock::get()?.unix_timestamp,
+            rewards: [FarmReward::default(); MAX_FARM_REWARDS],
+            bump: bumps.farm,
+        });
+
+        Ok(())
+    }
+}
+
+/// Adds a reward to a farm or changes the schedule of an existing one.
+#[derive(Accounts)]
+pub struct SetFarmReward<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"config", config.mint_x.as_ref(), config.mint_y.as_ref()],
+        bump = config.my_bump,
+        constraint = config.authority == Some(authority.key()) @ AmmError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [FARM_SEED, config.key().as_ref()],
+        bump = farm.bump
+    )]
+    pub farm: Account<'info, Farm>,
+
+    #[account(
+        seeds = [b"lp", config.key().as_ref()],
+        bump = config.lp_bump
+    )]
+    pub mint_lp: InterfaceAccount<'info, Mint>,
+
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = reward_mint,
+        associated_token::authority = farm
+    )]
+    pub reward_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> SetFarmReward<'info> {
+    /// Emits `rate_per_second` of `reward_mint` from `start` to `end` in slot
+    /// `index`. Rewards owed so far are kept at the old rate.
+    pub fn set(&mut self, index: u8, rate_per_second: u64, start: i64, end: i64) -> Result<()> {
+        let index = index as usize;
+        require!(index < MAX_FARM_REWARDS, AmmError::InvalidRewardSchedule);
+        require!(start <= end, AmmError::InvalidRewardSchedule);
+        // no more than a vault can hold, which keeps accrual from overflowing
+        require!(
+            rate_per_second as u128 * (end as i128 - start as i128) as u128 <= u64::MAX as u128,
+            AmmError::InvalidRewardSchedule
+        );
+
+        let mint = self.reward_mint.key();
+        // its vault would be the staked LP's
+        require_keys_neq!(mint, self.mint_lp.key(), AmmError::InvalidMint);
+        let farm = &mut self.farm;
+        // a slot keeps its mint, or positions would be owed the wrong token
+        require!(
+            !farm.rewards[index].is_active() || farm.rewards[index].mint == mint,
+            AmmError::InvalidMint
+        );
+        require!(
+            farm.reward_index(&mint)
+                .map_or(true, |found| found == index),
+            AmmError::InvalidMint
+        );
+
+        farm.update(Clock::get()?.unix_timestamp)?;
+        let reward = &mut farm.rewards[index];
+        reward.mint = mint;
+        reward.rate_per_second = rate_per_second;
+        reward.start = start;
+        reward.end = end;
+
+        Ok(())
+    }
+}
+
+/// Tops up a reward vault from the authority's tokens.
+#[derive(Accounts)]
+pub struct FundFarmReward<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"config", config.mint_x.as_ref(), config.mint_y.as_ref()],
+        bump = config.my_bump,
+        constraint = config.authority == Some(authority.key()) @ AmmError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        seeds = [FARM_SEED, config.key().as_ref()],
+        bump = farm.bump
+    )]
+    pub farm: Account<'info, Farm>,
+
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = reward_mint,
+        associated_token::authority = farm
+    )]
+    pub reward_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = reward_mint,
+        token::authority = authority
+    )]
+    pub funder: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> FundFarmReward<'info> {
+    pub fn fund(&mut self, amount: u64) -> Result<()> {
+        require!(amount > 0, AmmError::ZeroAmount);
+        self.farm.reward_index(&self.reward_mint.key())?;
+
+        let cpi_context = CpiContext::new(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from: self.funder.to_account_info(),
+                to: self.reward_vault.to_account_info(),
+                mint: self.reward_mint.to_account_info(),
+                authority: self.authority.to_account_info(),
+            },
+        );
+
+        transfer_checked(cpi_context, amount, self.reward_mint.decimals)
+    }
+}
+
+/// Moves LP between an owner and the farm. Shared by stake and unstake.
+#[derive(Accounts)]
+pub struct Stake<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [FARM_SEED, farm.config.as_ref()],
+        bump = farm.bump
+    )]
+    pub farm: Account<'info, Farm>,
+
+    #[account(
+        seeds = [b"lp", farm.config.as_ref()],
+        bump
+    )]
+    pub mint_lp: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + StakePosition::INIT_SPACE,
+        seeds = [STAKE_SEED, farm.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, StakePosition>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_lp,
+        associated_token::authority = owner
+    )]
+    pub owner_lp: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_lp,
+        associated_token::authority = farm
+    )]
+    pub lp_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> Stake<'info> {
+    /// Brings the farm up to now and settles the position's earnings, so
+    /// its stake can change.
+    fn settle(&mut self, bumps: &StakeBumps) -> Result<()> {
+        self.farm.update(Clock::get()?.unix_timestamp)?;
+
+        if self.position.owner == Pubkey::default() {
+            // new positions earn from now on
+            self.position.set_inner(StakePosition {
+                farm: self.farm.key(),
+                owner: self.owner.key(),
+                amount: 0,
+                reward_per_share_paid: self.farm.rewards.map(|reward| reward.reward_per_share),
+                pending: [0; MAX_FARM_REWARDS],
+                bump: bumps.position,
+            });
+        }
+        self.position.settle(&self.farm)
+    }
+
+    pub fn stake(&mut self, amount: u64, bumps: &StakeBumps) -> Result<()> {
+        require!(amount > 0, AmmError::ZeroAmount);
+        self.settle(bumps)?;
+
+        self.position.amount = self
+            .position
+            .amount
+            .checked_add(amount)
+            .ok_or(AmmError::Overflow)?;
+        self.farm.total_staked = self
+            .farm
+            .total_staked
+            .checked_add(amount)
+            .ok_or(AmmError::Overflow)?;
+
+        let cpi_context = CpiContext::new(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from: self.owner_lp.to_account_info(),
+                to: self.lp_vault.to_account_info(),
+                mint: self.mint_lp.to_account_info(),
+                authority: self.owner.to_account_info(),
+            },
+        );
+
+        transfer_checked(cpi_context, amount, self.mint_lp.decimals)
+    }
+
+    pub fn unstake(&mut self, amount: u64, bumps: &StakeBumps) -> Result<()> {
+        require!(amount > 0, AmmError::ZeroAmount);
+        // earnings that cannot be settled are forfeited rather than locking
+        // the LP in
+        if self.settle(bumps).is_err() {
+            self.position.reward_per_share_paid =
+                self.farm.rewards.map(|reward| reward.reward_per_share);
+        }
+        require!(amount <= self.position.amount, AmmError::InsufficientStake);
+
+        self.position.amount -= amount;
+        self.farm.total_staked -= amount;
+
+        let config = self.farm.config;
+        let seeds = &[FARM_SEED, config.as_ref(), &[self.farm.bump]];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from: self.lp_vault.to_account_info(),
+                to: self.owner_lp.to_account_info(),
+                mint: self.mint_lp.to_account_info(),
+                authority: self.farm.to_account_info(),
+            },
+            signer_seeds,
+        );
+
+        transfer_checked(cpi_context, amount, self.mint_lp.decimals)
+    }
+}
+
+/// Pays out what a position has earned of one reward.
+#[derive(Accounts)]
+pub struct ClaimFarmReward<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [FARM_SEED, farm.config.as_ref()],
+        bump = farm.bump
+    )]
+    pub farm: Account<'info, Farm>,
+
+    #[account(
+        mut,
+        seeds = [STAKE_SEED, farm.key().as_ref(), owner.key().as_ref()],
+        bump = position.bump
+    )]
+    pub position: Account<'info, StakePosition>,
+
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = reward_mint,
+        associated_token::authority = farm
+    )]
+    pub reward_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = reward_mint
+    )]
+    pub recipient: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> ClaimFarmReward<'info> {
+    /// Pays as much of the owed reward as the vault holds; the rest stays
+    /// owed until the authority tops the vault up.
+    pub fn claim(&mut self) -> Result<()> {
+        self.farm.update(Clock::get()?.unix_timestamp)?;
+        self.position.settle(&self.farm)?;
+
+        let index = self.farm.reward_index(&self.reward_mint.key())?;
+        let amount = self.position.pending[index].min(self.reward_vault.amount);
+        if amount == 0 {
+            return Ok(());
+        }
+        self.position.pending[index] -= amount;
+
+        let config = self.farm.config;
+        let seeds = &[FARM_SEED, config.as_ref(), &[self.farm.bump]];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from: self.reward_vault.to_account_info(),
+                to: self.recipient.to_account_info(),
+                mint: self.reward_mint.to_account_info(),
+                authority: self.farm.to_account_info(),
+            },
+            signer_seeds,
+        );
+
+        transfer_checked(cpi_context, amount, self.reward_mint.decimals)
+    }
+}
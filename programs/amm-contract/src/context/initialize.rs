@@ -5,10 +5,23 @@ use anchor_spl::{
     token_interface::{Mint, TokenAccount},
 };
 
-use crate::states::Config;
+use crate::constant_product_curve::FEE_DENOMINATOR;
+use crate::error::AmmError;
+use crate::states::{Config, Twamm};
+
+/// Most decimals the LP mint may have, chosen or defaulted. A u64 holds
+/// under 19 whole tokens at 18 decimals already.
+pub const MAX_LP_DECIMALS: u8 = 18;
+
+/// Decimals of the LP mint when the initializer does not pick them. LP
+/// amounts are conventionally around `sqrt(x * y)`, whose precision is the
+/// mean of the pair's.
+pub fn default_lp_decimals(decimals_x: u8, decimals_y: u8) -> u8 {
+    ((decimals_x as u16 + decimals_y as u16).div_ceil(2)) as u8
+}
 
 #[derive(Accounts)]
-#[instruction(seed: u64)]
+#[instruction(fees: u16, authority: Option<Pubkey>, lp_decimals: Option<u8>)]
 pub struct InitializeConfig<'info> {
     #[account(mut)]
     pub initializer: Signer<'info>,
@@ -21,7 +34,8 @@ pub struct InitializeConfig<'info> {
         payer=initializer,
         seeds=[b"lp", config.key().as_ref()],
         mint::authority = config,
-        mint::decimals = 6,
+        mint::decimals = lp_decimals
+            .unwrap_or(default_lp_decimals(mint_x.decimals, mint_y.decimals)),
         bump
     )]
     pub mint_lp: InterfaceAccount<'info, Mint>,
@@ -63,6 +77,12 @@ impl<'info> InitializeConfig<'info> {
         authority: Option<Pubkey>,
         bumps: InitializeConfigBumps,
     ) -> Result<()> {
+        require!(fees as u64 <= FEE_DENOMINATOR, AmmError::InvalidFee);
+        require!(
+            self.mint_lp.decimals <= MAX_LP_DECIMALS,
+            AmmError::InvalidLpDecimals
+        );
+
         self.config.set_inner(Config {
             authority,
             fees,
@@ -70,6 +90,15 @@ impl<'info> InitializeConfig<'info> {
             mint_y: self.mint_y.key(),
             my_bump: bumps.config,
             lp_bump: bumps.mint_lp,
+            dynamic_fee: None,
+            fees_y_in: None,
+            max_referral_bps: 0,
+            maker_fee_bps: fees,
+            twamm: Twamm::new(Clock::get()?.unix_timestamp),
+            batch_window: None,
+            circuit_breaker: None,
+            oracle: None,
+            pmm: None,
         });
 
         Ok(())
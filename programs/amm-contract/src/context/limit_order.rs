@@ -0,0 +1,450 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token::{close_account, CloseAccount, Token},
+    token_interface::{transfer_checked, Mint, TokenAccount, TransferChecked},
+};
+
+use crate::constant_product_curve::ConstantProductCurve;
+use crate::error::AmmError;
+use crate::events::LimitOrderFillEvent;
+use crate::states::{trade_reserves, Config, LimitOrder, LIMIT_ORDER_SEED};
+
+#[derive(Accounts)]
+#[instruction(order_id: u64, is_x_in: bool)]
+pub struct PlaceLimitOrder<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [b"config", config.mint_x.as_ref(), config.mint_y.as_ref()],
+        bump = config.my_bump,
+        constraint = (mint_in.key(), mint_out.key()) == match is_x_in {
+            true => (config.mint_x, config.mint_y),
+            false => (config.mint_y, config.mint_x),
+        } @ AmmError::InvalidMint
+    )]
+    pub config: Account<'info, Config>,
+
+    pub mint_in: InterfaceAccount<'info, Mint>,
+    pub mint_out: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + LimitOrder::INIT_SPACE,
+        seeds = [
+            LIMIT_ORDER_SEED,
+            config.key().as_ref(),
+            owner.key().as_ref(),
+            order_id.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub order: Account<'info, LimitOrder>,
+
+    #[account(
+        init,
+        payer = owner,
+        associated_token::mint = mint_in,
+        associated_token::authority = order
+    )]
+    pub escrow: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_in,
+        associated_token::authority = owner
+    )]
+    pub owner_in: InterfaceAccount<'info, TokenAccount>,
+
+    /// Where fills are paid, so cranks never have to create it.
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = mint_out,
+        associated_token::authority = owner
+    )]
+    pub owner_out: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> PlaceLimitOrder<'info> {
+    /// Escrows `amount_in` to be sold for at least `min_out`, filled in
+    /// parts or at once whenever the pool price reaches it.
+    pub fn place(
+        &mut self,
+        order_id: u64,
+        is_x_in: bool,
+        amount_in: u64,
+        min_out: u64,
+        bumps: &PlaceLimitOrderBumps,
+    ) -> Result<()> {
+        require!(amount_in > 0 && min_out > 0, AmmError::ZeroAmount);
+
+        self.order.set_inner(LimitOrder {
+            config: self.config.key(),
+            owner: self.owner.key(),
+            order_id,
+            is_x_in,
+            amount_in,
+            min_out,
+            remaining: amount_in,
+            placed_slot: Clock::get()?.slot,
+            bump: bumps.order,
+        });
+
+        let cpi_context = CpiContext::new(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from: self.owner_in.to_account_info(),
+                to: self.escrow.to_account_info(),
+                mint: self.mint_in.to_account_info(),
+                authority: self.owner.to_account_info(),
+            },
+        );
+
+        transfer_checked(cpi_context, amount_in, self.mint_in.decimals)
+    }
+}
+
+#[derive(Accounts)]
+pub struct CancelLimitOrder<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [
+            LIMIT_ORDER_SEED,
+            order.config.as_ref(),
+            owner.key().as_ref(),
+            order.order_id.to_le_bytes().as_ref()
+        ],
+        bump = order.bump
+    )]
+    pub order: Account<'info, LimitOrder>,
+
+    pub mint_in: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_in,
+        associated_token::authority = order
+    )]
+    pub escrow: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_in,
+        associated_token::authority = owner
+    )]
+    pub owner_in: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> CancelLimitOrder<'info> {
+    /// Returns the unfilled input and closes the order and its escrow.
+    pub fn cancel(&mut self) -> Result<()> {
+        let config = self.order.config;
+        let owner = self.owner.key();
+        let order_id = self.order.order_id.to_le_bytes();
+        let seeds = &[
+            LIMIT_ORDER_SEED,
+            config.as_ref(),
+            owner.as_ref(),
+            order_id.as_ref(),
+            &[self.order.bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from: self.escrow.to_account_info(),
+                to: self.owner_in.to_account_info(),
+                mint: self.mint_in.to_account_info(),
+                authority: self.order.to_account_info(),
+            },
+            signer_seeds,
+        );
+        transfer_checked(cpi_context, self.escrow.amount, self.mint_in.decimals)?;
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            CloseAccount {
+                account: self.escrow.to_account_info(),
+                destination: self.owner.to_account_info(),
+                authority: self.order.to_account_info(),
+            },
+            signer_seeds,
+        );
+        close_account(cpi_context)?;
+        self.order.close(self.owner.to_account_info())
+    }
+}
+
+#[derive(Accounts)]
+pub struct FillLimitOrder<'info> {
+    /// Anyone may crank a fill.
+    pub cranker: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"config", config.mint_x.as_ref(), config.mint_y.as_ref()],
+        bump = config.my_bump,
+        constraint = (mint_in.key(), mint_out.key()) == match order.is_x_in {
+            true => (config.mint_x, config.mint_y),
+            false => (config.mint_y, config.mint_x),
+        } @ AmmError::InvalidMint
+    )]
+    pub config: Account<'info, Config>,
+
+    pub mint_in: InterfaceAccount<'info, Mint>,
+    pub mint_out: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_in,
+        associated_token::authority = config
+    )]
+    pub vault_in: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_out,
+        associated_token::authority = config
+    )]
+    pub vault_out: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        has_one = config,
+        has_one = owner,
+        seeds = [
+            LIMIT_ORDER_SEED,
+            config.key().as_ref(),
+            order.owner.as_ref(),
+            order.order_id.to_le_bytes().as_ref()
+        ],
+        bump = order.bump
+    )]
+    pub order: Account<'info, LimitOrder>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_in,
+        associated_token::authority = order
+    )]
+    pub escrow: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: the order's owner, refunded the rent once it is filled
+    #[account(mut)]
+    pub owner: UncheckedAccount<'info>,
+
+    /// Refunded anything sent to the escrow besides the order's input.
+    #[account(
+        mut,
+        associated_token::mint = mint_in,
+        associated_token::authority = owner
+    )]
+    pub owner_in: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_out,
+        associated_token::authority = owner
+    )]
+    pub owner_out: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: the pool's price feed, required when it has an oracle guard;
+    /// its key, owner and layout are checked against the guard
+    pub oracle: Option<UncheckedAccount<'info>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> FillLimitOrder<'info> {
+    /// Swaps as much of the order against the pool as fits before the
+    /// marginal price falls below the limit, at the maker fee. A fully
+    /// filled order is closed.
+    pub fn fill(&mut self) -> Result<()> {
+        require!(self.config.pmm.is_none(), AmmError::PmmUnsupported);
+        require!(
+            self.config.batch_window.is_none(),
+            AmmError::BatchAuctionOnly
+        );
+        require!(
+            Clock::get()?.slot > self.order.placed_slot,
+            AmmError::OrderTooFresh
+        );
+        let (in_bal, out_bal) = self.config.sync_twamm_directed(
+            self.vault_in.amount,
+            self.vault_out.amount,
+            self.order.is_x_in,
+            Clock::get()?.unix_timestamp,
+        )?;
+        let order = &self.order;
+        let fee_bps = self.config.maker_fee(order.is_x_in);
+        let amount_in = ConstantProductCurve::fill_to_price(
+            in_bal,
+            out_bal,
+            order.amount_in,
+            order.min_out,
+            fee_bps,
+        )?
+        .min(order.remaining);
+        require!(amount_in > 0, AmmError::OrderNotFillable);
+
+        let quote = ConstantProductCurve::swap(in_bal, out_bal, amount_in, fee_bps)?;
+        // guards the rounding of the fill size
+        require!(
+            quote.amount_out as u128 * order.amount_in as u128
+                >= amount_in as u128 * order.min_out as u128,
+            AmmError::OrderNotFillable
+        );
+
+        self.config.check_price_move(
+            (in_bal, out_bal),
+            (amount_in, quote.amount_out),
+            self.order.is_x_in,
+            Clock::get()?.slot,
+        )?;
+        if let Some((guard, price)) = self.config.read_oracle(self.oracle.as_deref())? {
+            let (mint_in, mint_out) = (&self.mint_in, &self.mint_out);
+            guard.check(
+                &price,
+                Clock::get()?.unix_timestamp,
+                match self.order.is_x_in {
+                    true => (mint_in.decimals, mint_out.decimals),
+                    false => (mint_out.decimals, mint_in.decimals),
+                },
+                (amount_in, quote.amount_out),
+                self.order.is_x_in,
+                Some(trade_reserves(
+                    (in_bal, out_bal),
+                    (amount_in, quote.amount_out),
+                    self.order.is_x_in,
+                )?),
+            )?;
+        }
+
+        self.order.remaining -= amount_in;
+        self.pay_in(amount_in)?;
+        self.pay_out(quote.amount_out)?;
+
+        emit!(LimitOrderFillEvent {
+            config: self.config.key(),
+            order: self.order.key(),
+            owner: self.owner.key(),
+            is_x_in: self.order.is_x_in,
+            amount_in,
+            amount_out: quote.amount_out,
+            fee: quote.fee,
+            remaining: self.order.remaining,
+        });
+
+        if self.order.remaining > 0 {
+            return Ok(());
+        }
+        self.close_escrow()?;
+        self.order.close(self.owner.to_account_info())
+    }
+
+    fn order_seeds(&self) -> ([u8; 8], [u8; 1]) {
+        (self.order.order_id.to_le_bytes(), [self.order.bump])
+    }
+
+    fn pay_in(&self, amount: u64) -> Result<()> {
+        let config = self.config.key();
+        let (order_id, bump) = self.order_seeds();
+        let seeds = &[
+            LIMIT_ORDER_SEED,
+            config.as_ref(),
+            self.order.owner.as_ref(),
+            order_id.as_ref(),
+            &bump,
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from: self.escrow.to_account_info(),
+                to: self.vault_in.to_account_info(),
+                mint: self.mint_in.to_account_info(),
+                authority: self.order.to_account_info(),
+            },
+            signer_seeds,
+        );
+        transfer_checked(cpi_context, amount, self.mint_in.decimals)
+    }
+
+    fn pay_out(&self, amount: u64) -> Result<()> {
+        let seeds = &[
+            b"config",
+            self.config.mint_x.as_ref(),
+            self.config.mint_y.as_ref(),
+            &[self.config.my_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from: self.vault_out.to_account_info(),
+                to: self.owner_out.to_account_info(),
+                mint: self.mint_out.to_account_info(),
+                authority: self.config.to_account_info(),
+            },
+            signer_seeds,
+        );
+        transfer_checked(cpi_context, amount, self.mint_out.decimals)
+    }
+
+    /// Returns anything else sent to the escrow to the owner, which would
+    /// otherwise keep it from closing, and closes it.
+    fn close_escrow(&mut self) -> Result<()> {
+        self.escrow.reload()?;
+        let config = self.config.key();
+        let (order_id, bump) = self.order_seeds();
+        let seeds = &[
+            LIMIT_ORDER_SEED,
+            config.as_ref(),
+            self.order.owner.as_ref(),
+            order_id.as_ref(),
+            &bump,
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        if self.escrow.amount > 0 {
+            let cpi_context = CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                TransferChecked {
+                    from: self.escrow.to_account_info(),
+                    to: self.owner_in.to_account_info(),
+                    mint: self.mint_in.to_account_info(),
+                    authority: self.order.to_account_info(),
+                },
+                signer_seeds,
+            );
+            transfer_checked(cpi_context, self.escrow.amount, self.mint_in.decimals)?;
+        }
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            CloseAccount {
+                account: self.escrow.to_account_info(),
+                destination: self.owner.to_account_info(),
+                authority: self.order.to_account_info(),
+            },
+            signer_seeds,
+        );
+        close_account(cpi_context)
+    }
+}
@@ -0,0 +1,405 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token::{close_account, CloseAccount, Token},
+    token_interface::{
+        burn_checked, transfer_checked, BurnChecked, Mint, TokenAccount, TransferChecked,
+    },
+};
+
+use crate::constant_product_curve::ConstantProductCurve;
+use crate::error::AmmError;
+use crate::states::{Config, LpLock, LP_LOCK_SEED};
+
+#[derive(Accounts)]
+#[instruction(lock_id: u64)]
+pub struct LockLp<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    pub mint_y: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds = [b"config", mint_x.key().as_ref(), mint_y.key().as_ref()],
+        bump = config.my_bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        seeds = [b"lp", config.key().as_ref()],
+        bump = config.lp_bump
+    )]
+    pub mint_lp: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        associated_token::mint = mint_x,
+        associated_token::authority = config
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        associated_token::mint = mint_y,
+        associated_token::authority = config
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + LpLock::INIT_SPACE,
+        seeds = [
+            LP_LOCK_SEED,
+            config.key().as_ref(),
+            owner.key().as_ref(),
+            lock_id.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub lock: Account<'info, LpLock>,
+
+    #[account(
+        init,
+        payer = owner,
+        associated_token::mint = mint_lp,
+        associated_token::authority = lock
+    )]
+    pub escrow: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_lp,
+        associated_token::authority = owner
+    )]
+    pub owner_lp: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> LockLp<'info> {
+    /// Escrows `amount` LP until `unlock_at`, released linearly from then
+    /// until `vesting_end` when given.
+    pub fn lock(
+        &mut self,
+        lock_id: u64,
+        amount: u64,
+        unlock_at: i64,
+        vesting_end: Option<i64>,
+        bumps: &LockLpBumps,
+    ) -> Result<()> {
+        require!(amount > 0, AmmError::ZeroAmount);
+        let vesting_end = vesting_end.unwrap_or(unlock_at);
+        require!(
+            unlock_at > Clock::get()?.unix_timestamp && vesting_end >= unlock_at,
+            AmmError::InvalidLockSchedule
+        );
+
+        let (reserve_x, reserve_y) = self.config.sync_twamm(
+            self.vault_x.amount,
+            self.vault_y.amount,
+            Clock::get()?.unix_timestamp,
+        )?;
+        self.lock.set_inner(LpLock {
+            config: self.config.key(),
+            owner: self.owner.key(),
+            lock_id,
+            amount,
+            withdrawn: 0,
+            unlock_at,
+            vesting_end,
+            sqrt_k_per_lp: ConstantProductCurve::sqrt_k_per_lp(
+                reserve_x,
+                reserve_y,
+                self.mint_lp.supply,
+            )?,
+            bump: bumps.lock,
+        });
+
+        let cpi_context = CpiContext::new(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from: self.owner_lp.to_account_info(),
+                to: self.escrow.to_account_info(),
+                mint: self.mint_lp.to_account_info(),
+                authority: self.owner.to_account_info(),
+            },
+        );
+
+        transfer_checked(cpi_context, amount, self.mint_lp.decimals)
+    }
+}
+
+#[derive(Accounts)]
+pub struct UnlockLp<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [
+            LP_LOCK_SEED,
+            lock.config.as_ref(),
+            owner.key().as_ref(),
+            lock.lock_id.to_le_bytes().as_ref()
+        ],
+        bump = lock.bump
+    )]
+    pub lock: Account<'info, LpLock>,
+
+    #[account(
+        seeds = [b"lp", lock.config.as_ref()],
+        bump
+    )]
+    pub mint_lp: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_lp,
+        associated_token::authority = lock
+    )]
+    pub escrow: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_lp,
+        associated_token::authority = owner
+    )]
+    pub owner_lp: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> UnlockLp<'info> {
+    /// Returns every LP released so far. Once all of it is out, the escrow
+    /// and the lock are closed and their rent refunded.
+    pub fn unlock(&mut self) -> Result<()> {
+        let amount = self.lock.unlockable(Clock::get()?.unix_timestamp)?;
+        require!(amount > 0, AmmError::StillLocked);
+        self.lock.withdrawn += amount;
+
+        let config = self.lock.config;
+        let owner = self.owner.key();
+        let lock_id = self.lock.lock_id.to_le_bytes();
+        let seeds = &[
+            LP_LOCK_SEED,
+            config.as_ref(),
+            owner.as_ref(),
+            lock_id.as_ref(),
+            &[self.lock.bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from: self.escrow.to_account_info(),
+                to: self.owner_lp.to_account_info(),
+                mint: self.mint_lp.to_account_info(),
+                authority: self.lock.to_account_info(),
+            },
+            signer_seeds,
+        );
+        transfer_checked(cpi_context, amount, self.mint_lp.decimals)?;
+
+        if self.lock.withdrawn < self.lock.amount {
+            return Ok(());
+        }
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            CloseAccount {
+                account: self.escrow.to_account_info(),
+                destination: self.owner.to_account_info(),
+                authority: self.lock.to_account_info(),
+            },
+            signer_seeds,
+        );
+        close_account(cpi_context)?;
+        self.lock.close(self.owner.to_account_info())
+    }
+}
+
+#[derive(Accounts)]
+pub struct ClaimLockedFees<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    pub mint_y: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds = [b"config", mint_x.key().as_ref(), mint_y.key().as_ref()],
+        bump = config.my_bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [b"lp", config.key().as_ref()],
+        bump = config.lp_bump
+    )]
+    pub mint_lp: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        has_one = owner,
+        has_one = config,
+        seeds = [
+            LP_LOCK_SEED,
+            config.key().as_ref(),
+            owner.key().as_ref(),
+            lock.lock_id.to_le_bytes().as_ref()
+        ],
+        bump = lock.bump
+    )]
+    pub lock: Account<'info, LpLock>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_lp,
+        associated_token::authority = lock
+    )]
+    pub escrow: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = mint_x,
+        associated_token::authority = owner
+    )]
+    pub owner_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = mint_y,
+        associated_token::authority = owner
+    )]
+    pub owner_y: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> ClaimLockedFees<'info> {
+    /// Pays out the swap fees earned by the still-locked LP since the last
+    /// claim, by burning the slice of it that growth in `sqrt(k)` per LP
+    /// accounts for. The rest stays locked and is worth what all of it
+    /// was worth at the last claim. PMM pools don't grow `sqrt(k)` with
+    /// fees alone, so they have nothing to claim this way.
+    pub fn claim(&mut self) -> Result<()> {
+        require!(self.config.pmm.is_none(), AmmError::PmmUnsupported);
+        let (reserve_x, reserve_y) = self.config.sync_twamm(
+            self.vault_x.amount,
+            self.vault_y.amount,
+            Clock::get()?.unix_timestamp,
+        )?;
+        let now = ConstantProductCurve::sqrt_k_per_lp(reserve_x, reserve_y, self.mint_lp.supply)?;
+        let locked = self.lock.amount - self.lock.withdrawn;
+        let fee_lp = ConstantProductCurve::fee_lp(locked, self.lock.sqrt_k_per_lp, now)?;
+        require!(fee_lp > 0, AmmError::NoFeesAccrued);
+
+        let amounts = ConstantProductCurve::take_lp_give_xy(
+            reserve_x,
+            reserve_y,
+            self.mint_lp.supply,
+            fee_lp,
+        )?;
+        self.lock.amount -= fee_lp;
+        // taken after the payout, whose rounding dust also raises it
+        self.lock.sqrt_k_per_lp = ConstantProductCurve::sqrt_k_per_lp(
+            reserve_x - amounts.x,
+            reserve_y - amounts.y,
+            self.mint_lp.supply - fee_lp,
+        )?;
+
+        self.burn_escrowed(fee_lp)?;
+        self.pay(true, amounts.x)?;
+        self.pay(false, amounts.y)
+    }
+
+    fn burn_escrowed(&mut self, amount: u64) -> Result<()> {
+        let config = self.config.key();
+        let owner = self.owner.key();
+        let lock_id = self.lock.lock_id.to_le_bytes();
+        let seeds = &[
+            LP_LOCK_SEED,
+            config.as_ref(),
+            owner.as_ref(),
+            lock_id.as_ref(),
+            &[self.lock.bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            BurnChecked {
+                mint: self.mint_lp.to_account_info(),
+                from: self.escrow.to_account_info(),
+                authority: self.lock.to_account_info(),
+            },
+            signer_seeds,
+        );
+        burn_checked(cpi_context, amount, self.mint_lp.decimals)
+    }
+
+    fn pay(&mut self, is_x: bool, amount: u64) -> Result<()> {
+        let (from, to, mint, decimals) = match is_x {
+            true => (
+                self.vault_x.to_account_info(),
+                self.owner_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
+            ),
+            false => (
+                self.vault_y.to_account_info(),
+                self.owner_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
+            ),
+        };
+
+        let mint_x = self.mint_x.key();
+        let mint_y = self.mint_y.key();
+        let seeds = &[
+            b"config",
+            mint_x.as_ref(),
+            mint_y.as_ref(),
+            &[self.config.my_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from,
+                to,
+                mint,
+                authority: self.config.to_account_info(),
+            },
+            signer_seeds,
+        );
+        transfer_checked(cpi_context, amount, decimals)
+    }
+}
@@ -0,0 +1,55 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, Transfer};
+use anchor_lang::Discriminator;
+
+use crate::error::AmmError;
+use crate::states::Config;
+
+/// Grows a config created before the latest fields were appended to
+/// `Config`, which no longer deserializes. Anyone may pay for it.
+#[derive(Accounts)]
+pub struct MigrateConfig<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: an older `Config`, identified by owner and discriminator as it
+    /// cannot be deserialized yet
+    #[account(mut, owner = crate::ID)]
+    pub config: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MigrateConfig<'info> {
+    /// Zero-extends the config to the current size. Appended fields decode
+    /// from zero bytes as switched off, so the pool carries on as before.
+    pub fn migrate(&mut self) -> Result<()> {
+        let config = self.config.to_account_info();
+        require!(
+            config.try_borrow_data()?.starts_with(Config::DISCRIMINATOR),
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+        let len = 8 + Config::INIT_SPACE;
+        require!(config.data_len() < len, AmmError::ConfigUpToDate);
+
+        let rent = Rent::get()?
+            .minimum_balance(len)
+            .saturating_sub(config.lamports());
+        if rent > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    self.system_program.to_account_info(),
+                    Transfer {
+                        from: self.payer.to_account_info(),
+                        to: config.clone(),
+                    },
+                ),
+                rent,
+            )?;
+        }
+        config.resize(len)?;
+
+        Config::try_deserialize(&mut &config.try_borrow_data()?[..])?;
+        Ok(())
+    }
+}
@@ -1,9 +1,29 @@
+pub mod batch;
+pub mod create_lp_metadata;
+pub mod dca;
 pub mod deposit;
+pub mod farm;
 pub mod initialize;
+pub mod limit_order;
+pub mod lp_lock;
+pub mod migrate_config;
 pub mod swap;
+pub mod trader_tier;
+pub mod twamm;
+pub mod update_config;
 pub mod withdraw;
 
+pub use batch::*;
+pub use create_lp_metadata::*;
+pub use dca::*;
 pub use deposit::*;
+pub use farm::*;
 pub use initialize::*;
+pub use limit_order::*;
+pub use lp_lock::*;
+pub use migrate_config::*;
 pub use swap::*;
+pub use trader_tier::*;
+pub use twamm::*;
+pub use update_config::*;
 pub use withdraw::*;
@@ -1,17 +1,24 @@
-use crate::states::Config;
+use crate::constant_product_curve::ConstantProductCurve;
+use crate::error::AmmError;
+use crate::events::SwapEvent;
+use crate::native_sol::{self, UnwrapSol, WSOL_SEED};
+use crate::states::{trade_reserves, Config, TraderTier, TRADER_TIER_SEED};
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program_option::COption;
 use anchor_spl::{
-    associated_token::AssociatedToken,
     token::Token,
     token_interface::{transfer_checked, Mint, TokenAccount, TransferChecked},
 };
 
 #[derive(Accounts)]
+#[instruction(amount_in: u64, min_out: u64, is_x_in: bool)]
 pub struct Swap<'info> {
+    /// Owner or delegate of `source`.
     #[account(mut)]
     pub user: Signer<'info>,
 
     #[account(
+        mut,
         seeds = [b"config", mint_x.key().as_ref(), mint_y.key().as_ref()],
         bump = config.my_bump,
         has_one = mint_x,
@@ -36,22 +43,56 @@ pub struct Swap<'info> {
     )]
     pub vault_y: InterfaceAccount<'info, TokenAccount>,
 
+    /// Pays `amount_in`; any account of the input mint the signer may spend.
+    /// Omitted to pay in lamports when the input mint is the native mint.
     #[account(
         mut,
-        associated_token::mint = mint_x,
-        associated_token::authority = user
+        constraint = source.mint == match is_x_in {
+            true => mint_x.key(),
+            false => mint_y.key(),
+        } @ AmmError::InvalidMint,
+        constraint = source.owner == user.key()
+            || source.delegate == COption::Some(user.key()) @ AmmError::InvalidSourceAuthority
     )]
-    pub user_x: InterfaceAccount<'info, TokenAccount>,
+    pub source: Option<InterfaceAccount<'info, TokenAccount>>,
 
+    /// Receives the output; any account of the output mint. Omitted to pay
+    /// the signer in lamports when the output mint is the native mint.
     #[account(
         mut,
-        associated_token::mint = mint_y,
-        associated_token::authority = user
+        constraint = recipient.mint == match is_x_in {
+            true => mint_y.key(),
+            false => mint_x.key(),
+        } @ AmmError::InvalidMint
+    )]
+    pub recipient: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Paid `referral_bps` of the fee, in the input token, when given.
+    #[account(
+        mut,
+        constraint = referrer.mint == match is_x_in {
+            true => mint_x.key(),
+            false => mint_y.key(),
+        } @ AmmError::InvalidMint
     )]
-    pub user_y: InterfaceAccount<'info, TokenAccount>,
+    pub referrer: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Fee ceiling the authority granted `user`, if any.
+    #[account(
+        seeds = [TRADER_TIER_SEED, config.key().as_ref(), user.key().as_ref()],
+        bump = trader_tier.bump
+    )]
+    pub trader_tier: Option<Account<'info, TraderTier>>,
+
+    /// CHECK: the pool's price feed, required when it has an oracle guard;
+    /// its key, owner and layout are checked against the guard
+    pub oracle: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: created and closed by the program when paying out native SOL
+    #[account(mut, seeds = [WSOL_SEED, config.key().as_ref()], bump)]
+    pub temp_wsol: Option<UncheckedAccount<'info>>,
 
     pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
@@ -61,63 +102,140 @@ impl<'info> Swap<'info> {
         amount_in: u64,
         min_out: u64, // for Slippage control
         is_x_in: bool,
+        referral_bps: u16, // share of the fee paid to `referrer`
+        bumps: &SwapBumps,
     ) -> Result<()> {
-        // transfer the amount from user ATA to vault
         // get the amount_will_get , after deducting the fees
         // it should be grater than eqault to min_out
-        // transfer from vault to the USER ATA
+        // transfer the amount from the source account to vault
+        // transfer from vault to the recipient
 
-        if is_x_in {
-            self.transfer_in(&self.user_x, &self.vault_x, &self.mint_x, amount_in)?;
-        } else {
-            self.transfer_in(&self.user_y, &self.vault_y, &self.mint_y, amount_in)?;
-        }
+        require!(amount_in > 0, AmmError::ZeroAmount);
+        require!(
+            self.config.batch_window.is_none(),
+            AmmError::BatchAuctionOnly
+        );
 
-        let fees = self.config.fees as u128;
-        let amount_in_u128 = amount_in as u128;
-        // fees = 20 -> 0.2 (actual_fees)
-        let amount_in_after_fee = amount_in_u128
-            .checked_mul(10_000 - fees)
-            .ok_or(ErrorCode::Mathoverflow)?
-            .checked_div(10_000)
-            .ok_or(ErrorCode::Mathoverflow)?;
+        let now = Clock::get()?.unix_timestamp;
+        let (reserve_x, reserve_y) =
+            self.config
+                .sync_twamm(self.vault_x.amount, self.vault_y.amount, now)?;
+        let (in_bal, out_bal) = match is_x_in {
+            true => (reserve_x, reserve_y),
+            false => (reserve_y, reserve_x),
+        };
 
-        let (x_bal, y_bal) = (self.vault_x.amount as u128, self.vault_y.amount as u128);
-        let k = x_bal.checked_mul(y_bal).ok_or(ErrorCode::Mathoverflow)?;
+        let fee = self
+            .config
+            .swap_fee(reserve_x, reserve_y, amount_in, is_x_in, now)?;
+        let fee = match &self.trader_tier {
+            Some(tier) => tier.apply(fee),
+            None => fee,
+        };
+        let oracle = self.config.read_oracle(self.oracle.as_deref())?;
+        let quote = match (self.config.pmm, &oracle) {
+            (Some(mut pmm), Some((guard, price))) => {
+                let price =
+                    price.to_q64(self.mint_x.decimals, self.mint_y.decimals, guard.invert)?;
+                let quote = pmm.swap(reserve_x, reserve_y, amount_in, fee, is_x_in, price)?;
+                self.config.pmm = Some(pmm);
+                quote
+            }
+            // set_oracle refuses to clear the guard of a PMM pool, but a PMM
+            // quote must never fall back to the curve
+            (Some(_), None) => return err!(AmmError::MissingOracle),
+            (None, _) => ConstantProductCurve::swap(in_bal, out_bal, amount_in, fee)?,
+        };
 
-        let (mut in_bal, mut out_bal) = (x_bal, y_bal);
+        require!(quote.amount_out >= min_out, AmmError::SlippageExceeded);
 
-        if !is_x_in {
-            core::mem::swap(&mut in_bal, &mut out_bal);
+        // the referral comes out of the fee, so the pool still keeps at
+        // least `amount_in - fee` and `k` does not decrease
+        let referral_fee = match &self.referrer {
+            Some(_) => {
+                require!(
+                    referral_bps <= self.config.max_referral_bps,
+                    AmmError::ReferralTooHigh
+                );
+                ConstantProductCurve::referral_fee(quote.fee, referral_bps)?
+            }
+            None => 0,
+        };
+        self.config.check_price_move(
+            (in_bal, out_bal),
+            (amount_in - referral_fee, quote.amount_out),
+            is_x_in,
+            Clock::get()?.slot,
+        )?;
+        if let Some((guard, price)) = &oracle {
+            // PMM pools quote around the feed, whatever their reserves
+            let reserves = match self.config.pmm {
+                Some(_) => None,
+                None => Some(trade_reserves(
+                    (in_bal, out_bal),
+                    (amount_in - referral_fee, quote.amount_out),
+                    is_x_in,
+                )?),
+            };
+            guard.check(
+                price,
+                now,
+                (self.mint_x.decimals, self.mint_y.decimals),
+                (amount_in, quote.amount_out),
+                is_x_in,
+                reserves,
+            )?;
         }
 
-        let new_in = in_bal
-            .checked_add(amount_in_after_fee)
-            .ok_or(ErrorCode::Mathoverflow)?;
-        let new_out = k.checked_div(new_in).ok_or(ErrorCode::Mathoverflow)?;
-        let out_amt = out_bal
-            .checked_sub(new_out)
-            .ok_or(ErrorCode::Mathoverflow)?;
-
-        require!(out_amt as u64 >= min_out, ErrorCode::SlippageTooHigh);
+        let (vault_in, mint_in) = match is_x_in {
+            true => (&self.vault_x, &self.mint_x),
+            false => (&self.vault_y, &self.mint_y),
+        };
+        self.transfer_in(vault_in, mint_in, amount_in - referral_fee)?;
+        if let Some(referrer) = self.referrer.as_ref().filter(|_| referral_fee > 0) {
+            self.transfer_in(referrer, mint_in, referral_fee)?;
+        }
 
-        let out_u64 = out_amt as u64;
         if is_x_in {
-            self.transfer_out(&self.vault_y, &self.user_y, &self.mint_y, out_u64)?;
+            self.transfer_out(&self.vault_y, &self.mint_y, quote.amount_out, bumps)?;
         } else {
-            self.transfer_out(&self.vault_x, &self.user_x, &self.mint_x, out_u64)?;
+            self.transfer_out(&self.vault_x, &self.mint_x, quote.amount_out, bumps)?;
         }
 
+        emit!(SwapEvent {
+            config: self.config.key(),
+            user: self.user.key(),
+            is_x_in,
+            amount_in,
+            amount_out: quote.amount_out,
+            fee: quote.fee,
+            referral_fee,
+            referrer: self.referrer.as_ref().map(|referrer| referrer.key()),
+        });
+
         Ok(())
     }
 
     pub fn transfer_in(
         &self,
-        from: &InterfaceAccount<'info, TokenAccount>,
         to: &InterfaceAccount<'info, TokenAccount>,
         mint: &InterfaceAccount<'info, Mint>,
         amount: u64,
     ) -> Result<()> {
+        let Some(from) = &self.source else {
+            require!(
+                native_sol::is_native(&mint.key()),
+                AmmError::MissingTokenAccount
+            );
+            return native_sol::wrap(
+                self.user.to_account_info(),
+                to.to_account_info(),
+                self.system_program.to_account_info(),
+                self.token_program.to_account_info(),
+                amount,
+            );
+        };
+
         let cpi_context = CpiContext::new(
             self.token_program.to_account_info(),
             TransferChecked {
@@ -134,9 +252,9 @@ impl<'info> Swap<'info> {
     pub fn transfer_out(
         &self,
         from: &InterfaceAccount<'info, TokenAccount>,
-        to: &InterfaceAccount<'info, TokenAccount>,
         mint: &InterfaceAccount<'info, Mint>,
         amount: u64,
+        bumps: &SwapBumps,
     ) -> Result<()> {
         let bump_byte = self.config.my_bump;
 
@@ -149,6 +267,26 @@ impl<'info> Swap<'info> {
 
         let signer_seeds = &[&seeds[..]];
 
+        let Some(to) = &self.recipient else {
+            require!(
+                native_sol::is_native(&mint.key()),
+                AmmError::MissingTokenAccount
+            );
+            let (Some(temp), Some(temp_bump)) = (&self.temp_wsol, bumps.temp_wsol) else {
+                return err!(AmmError::MissingWsolAccount);
+            };
+            let unwrap = UnwrapSol {
+                user: self.user.to_account_info(),
+                config: self.config.to_account_info(),
+                vault: from.to_account_info(),
+                mint: mint.to_account_info(),
+                temp: temp.to_account_info(),
+                system_program: self.system_program.to_account_info(),
+                token_program: self.token_program.to_account_info(),
+            };
+            return unwrap.pay_out(amount, signer_seeds, temp_bump);
+        };
+
         let cpi_context = CpiContext::new_with_signer(
             self.token_program.to_account_info(),
             TransferChecked {
@@ -163,11 +301,3 @@ impl<'info> Swap<'info> {
         transfer_checked(cpi_context, amount, mint.decimals)
     }
 }
-
-#[error_code]
-pub enum ErrorCode {
-    #[msg("Math overflow")]
-    Mathoverflow,
-    #[msg("Slippage too high")]
-    SlippageTooHigh,
-}
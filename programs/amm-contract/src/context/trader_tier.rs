@@ -0,0 +1,70 @@
+use anchor_lang::prelude::*;
+
+use crate::constant_product_curve::FEE_DENOMINATOR;
+use crate::error::AmmError;
+use crate::states::{Config, TraderTier, TRADER_TIER_SEED};
+
+#[derive(Accounts)]
+#[instruction(trader: Pubkey)]
+pub struct SetTraderTier<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"config", config.mint_x.as_ref(), config.mint_y.as_ref()],
+        bump = config.my_bump,
+        constraint = config.authority == Some(authority.key()) @ AmmError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + TraderTier::INIT_SPACE,
+        seeds = [TRADER_TIER_SEED, config.key().as_ref(), trader.as_ref()],
+        bump
+    )]
+    pub trader_tier: Account<'info, TraderTier>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> SetTraderTier<'info> {
+    /// Caps `trader`'s swap fee on this pool at `fee_bps`, creating the tier
+    /// or changing an existing one.
+    pub fn set(&mut self, trader: Pubkey, fee_bps: u16, bumps: &SetTraderTierBumps) -> Result<()> {
+        require!(fee_bps as u64 <= FEE_DENOMINATOR, AmmError::InvalidFee);
+
+        self.trader_tier.set_inner(TraderTier {
+            config: self.config.key(),
+            trader,
+            fee_bps,
+            bump: bumps.trader_tier,
+        });
+
+        Ok(())
+    }
+}
+
+/// Drops a trader back to the pool fee, refunding the tier's rent to the
+/// authority.
+#[derive(Accounts)]
+pub struct RemoveTraderTier<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"config", config.mint_x.as_ref(), config.mint_y.as_ref()],
+        bump = config.my_bump,
+        constraint = config.authority == Some(authority.key()) @ AmmError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [TRADER_TIER_SEED, config.key().as_ref(), trader_tier.trader.as_ref()],
+        bump = trader_tier.bump
+    )]
+    pub trader_tier: Account<'info, TraderTier>,
+}
@@ -0,0 +1,400 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token::Token,
+    token_interface::{transfer_checked, Mint, TokenAccount, TransferChecked},
+};
+
+use crate::error::AmmError;
+use crate::states::{
+    Config, TwammArchive, TwammOrder, TWAMM_ARCHIVE_SEED, TWAMM_INTERVAL, TWAMM_ORDER_SEED,
+};
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct PlaceTwammOrder<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    pub mint_y: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds = [b"config", mint_x.key().as_ref(), mint_y.key().as_ref()],
+        bump = config.my_bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + TwammOrder::INIT_SPACE,
+        seeds = [
+            TWAMM_ORDER_SEED,
+            config.key().as_ref(),
+            owner.key().as_ref(),
+            order_id.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub order: Account<'info, TwammOrder>,
+
+    /// Pays the input when selling x, receives the proceeds otherwise.
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = mint_x,
+        associated_token::authority = owner
+    )]
+    pub owner_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = mint_y,
+        associated_token::authority = owner
+    )]
+    pub owner_y: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> PlaceTwammOrder<'info> {
+    /// Sells `amount` evenly from now until `intervals` interval boundaries
+    /// from now. The input waits in the vaults, set aside from the pool.
+    pub fn place(
+        &mut self,
+        order_id: u64,
+        is_x_in: bool,
+        amount: u64,
+        intervals: u32,
+        bumps: &PlaceTwammOrderBumps,
+    ) -> Result<()> {
+        require!(amount > 0 && intervals > 0, AmmError::InvalidTwammOrder);
+        require!(self.config.pmm.is_none(), AmmError::PmmUnsupported);
+
+        let now = Clock::get()?.unix_timestamp;
+        let (reserve_x, reserve_y) =
+            self.config
+                .sync_twamm(self.vault_x.amount, self.vault_y.amount, now)?;
+        require!(
+            reserve_x > 0 && reserve_y > 0,
+            AmmError::InsufficientLiquidity
+        );
+
+        let twamm = &mut self.config.twamm;
+        let start = twamm.last_execution;
+        let end = (now / TWAMM_INTERVAL)
+            .checked_add(intervals as i64)
+            .and_then(|boundary| boundary.checked_mul(TWAMM_INTERVAL))
+            .ok_or(AmmError::Overflow)?;
+        let sale_rate = TwammOrder::sale_rate(amount, end - start)?;
+        require!(sale_rate > 0, AmmError::InvalidTwammOrder);
+        twamm.add_order(is_x_in, sale_rate, end)?;
+
+        twamm.reserve(is_x_in, amount)?;
+        let (earnings_factor, from, mint) = match is_x_in {
+            true => (twamm.sell_x.earnings_factor, &self.owner_x, &self.mint_x),
+            false => (twamm.sell_y.earnings_factor, &self.owner_y, &self.mint_y),
+        };
+        let to = match is_x_in {
+            true => &self.vault_x,
+            false => &self.vault_y,
+        };
+
+        self.order.set_inner(TwammOrder {
+            config: self.config.key(),
+            owner: self.owner.key(),
+            order_id,
+            is_x_in,
+            amount,
+            sale_rate,
+            start,
+            end,
+            earnings_factor,
+            bump: bumps.order,
+        });
+
+        let cpi_context = CpiContext::new(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from: from.to_account_info(),
+                to: to.to_account_info(),
+                mint: mint.to_account_info(),
+                authority: self.owner.to_account_info(),
+            },
+        );
+
+        transfer_checked(cpi_context, amount, mint.decimals)
+    }
+}
+
+/// Pays an order's owner, and closes the order on `close_twamm_order`.
+#[derive(Accounts)]
+pub struct ClaimTwammOrder<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    pub mint_y: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds = [b"config", mint_x.key().as_ref(), mint_y.key().as_ref()],
+        bump = config.my_bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        has_one = config,
+        has_one = owner,
+        seeds = [
+            TWAMM_ORDER_SEED,
+            config.key().as_ref(),
+            owner.key().as_ref(),
+            order.order_id.to_le_bytes().as_ref()
+        ],
+        bump = order.bump
+    )]
+    pub order: Account<'info, TwammOrder>,
+
+    /// The order's expiry, once `archive_twamm_expiry` moved it out of the
+    /// pool.
+    #[account(
+        mut,
+        has_one = config,
+        seeds = [
+            TWAMM_ARCHIVE_SEED,
+            config.key().as_ref(),
+            order.end.to_le_bytes().as_ref()
+        ],
+        bump = archive.bump
+    )]
+    pub archive: Option<Account<'info, TwammArchive>>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = owner
+    )]
+    pub owner_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = owner
+    )]
+    pub owner_y: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> ClaimTwammOrder<'info> {
+    /// Pays out what the order has bought so far.
+    pub fn withdraw_proceeds(&mut self) -> Result<()> {
+        self.sync()?;
+        let proceeds = self.take_proceeds()?;
+        self.pay(!self.order.is_x_in, proceeds)
+    }
+
+    /// Pays out the proceeds and whatever is left unsold, cancelling the
+    /// order if it is still running, and closes it.
+    pub fn close(&mut self) -> Result<()> {
+        self.sync()?;
+        let proceeds = self.take_proceeds()?;
+
+        let twamm = &mut self.config.twamm;
+        let unsold = self.order.unsold(twamm.last_execution)?;
+        let archived = self.archive.as_mut().map(|archive| &mut archive.expiry);
+        twamm.remove_order(&self.order, archived)?;
+        twamm.release(self.order.is_x_in, unsold)?;
+
+        self.pay(self.order.is_x_in, unsold)?;
+        self.pay(!self.order.is_x_in, proceeds)?;
+        if let Some(archive) = &self.archive {
+            if archive.expiry.orders == 0 {
+                archive.close(self.owner.to_account_info())?;
+            }
+        }
+        self.order.close(self.owner.to_account_info())
+    }
+
+    fn sync(&mut self) -> Result<()> {
+        self.config.sync_twamm(
+            self.vault_x.amount,
+            self.vault_y.amount,
+            Clock::get()?.unix_timestamp,
+        )?;
+        Ok(())
+    }
+
+    /// Proceeds since the last withdrawal, released from the vault's
+    /// reserved balance.
+    fn take_proceeds(&mut self) -> Result<u64> {
+        let twamm = &mut self.config.twamm;
+        let archived = self.archive.as_ref().map(|archive| &archive.expiry);
+        let earnings_factor = twamm.earnings_factor(&self.order, archived)?;
+        let proceeds = self.order.proceeds(earnings_factor)?;
+        self.order.earnings_factor = earnings_factor;
+        twamm.release(!self.order.is_x_in, proceeds)?;
+        Ok(proceeds)
+    }
+
+    fn pay(&self, is_x: bool, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+        let (from, to, mint) = match is_x {
+            true => (&self.vault_x, &self.owner_x, &self.mint_x),
+            false => (&self.vault_y, &self.owner_y, &self.mint_y),
+        };
+        let seeds = &[
+            b"config",
+            self.config.mint_x.as_ref(),
+            self.config.mint_y.as_ref(),
+            &[self.config.my_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from: from.to_account_info(),
+                to: to.to_account_info(),
+                mint: mint.to_account_info(),
+                authority: self.config.to_account_info(),
+            },
+            signer_seeds,
+        );
+        transfer_checked(cpi_context, amount, mint.decimals)
+    }
+}
+
+/// Brings a pool's long-term orders up to date. Every other pool
+/// instruction does this too; this lets anyone do it alone.
+#[derive(Accounts)]
+pub struct ExecuteTwamm<'info> {
+    #[account(
+        mut,
+        seeds = [b"config", config.mint_x.as_ref(), config.mint_y.as_ref()],
+        bump = config.my_bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        associated_token::mint = config.mint_x,
+        associated_token::authority = config
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        associated_token::mint = config.mint_y,
+        associated_token::authority = config
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+}
+
+impl<'info> ExecuteTwamm<'info> {
+    pub fn execute(&mut self) -> Result<()> {
+        self.config.sync_twamm(
+            self.vault_x.amount,
+            self.vault_y.amount,
+            Clock::get()?.unix_timestamp,
+        )?;
+        Ok(())
+    }
+}
+
+/// Moves a passed expiry out of the pool, freeing its slot for new end
+/// times. Anyone may pay for it when the slots run out.
+#[derive(Accounts)]
+#[instruction(at: i64)]
+pub struct ArchiveTwammExpiry<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"config", config.mint_x.as_ref(), config.mint_y.as_ref()],
+        bump = config.my_bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        associated_token::mint = config.mint_x,
+        associated_token::authority = config
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        associated_token::mint = config.mint_y,
+        associated_token::authority = config
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + TwammArchive::INIT_SPACE,
+        seeds = [TWAMM_ARCHIVE_SEED, config.key().as_ref(), at.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub archive: Account<'info, TwammArchive>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> ArchiveTwammExpiry<'info> {
+    /// Archives the orders ending at `at`, which must have passed.
+    pub fn archive(&mut self, at: i64, bumps: &ArchiveTwammExpiryBumps) -> Result<()> {
+        self.config.sync_twamm(
+            self.vault_x.amount,
+            self.vault_y.amount,
+            Clock::get()?.unix_timestamp,
+        )?;
+        let expiry = self.config.twamm.archive_expiry(at)?;
+
+        self.archive.set_inner(TwammArchive {
+            config: self.config.key(),
+            expiry,
+            bump: bumps.archive,
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,140 @@
+use anchor_lang::prelude::*;
+
+use crate::constant_product_curve::FEE_DENOMINATOR;
+use crate::error::AmmError;
+use crate::states::{
+    CircuitBreaker, CircuitBreakerParams, Config, DynamicFee, DynamicFeeParams, OracleGuard, Pmm,
+};
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"config", config.mint_x.as_ref(), config.mint_y.as_ref()],
+        bump = config.my_bump,
+        constraint = config.authority == Some(authority.key()) @ AmmError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+}
+
+impl<'info> UpdateConfig<'info> {
+    // only the pool authority can change the fee or hand the pool over
+    // setting authority to None freezes the config for good
+    pub fn update(&mut self, fees: u16, authority: Option<Pubkey>) -> Result<()> {
+        self.config.fees = fees;
+        self.config.authority = authority;
+
+        self.config.check_fees()
+    }
+
+    /// Switches the pool to volatility-driven fees with `params`, or back to
+    /// the flat fee with `None`. Volatility starts from zero either way.
+    pub fn set_dynamic_fee(&mut self, params: Option<DynamicFeeParams>) -> Result<()> {
+        self.config.dynamic_fee = params.map(DynamicFee::new);
+
+        self.config.check_fees()
+    }
+
+    /// Bounds how far trades may move the price with `params`, or lifts the
+    /// bounds with `None`.
+    pub fn set_circuit_breaker(&mut self, params: Option<CircuitBreakerParams>) -> Result<()> {
+        if let Some(params) = &params {
+            params.validate()?;
+        }
+
+        self.config.circuit_breaker = params.map(CircuitBreaker::new);
+
+        Ok(())
+    }
+
+    /// Holds swaps to the price feed in `guard`, or stops checking them
+    /// against one with `None`.
+    pub fn set_oracle(&mut self, guard: Option<OracleGuard>) -> Result<()> {
+        if let Some(guard) = &guard {
+            guard.validate()?;
+        }
+        require!(
+            guard.is_some() || self.config.pmm.is_none(),
+            AmmError::PmmNeedsOracle
+        );
+
+        self.config.oracle = guard;
+
+        Ok(())
+    }
+
+    /// Trades swaps on the PMM curve with slope `k` around the oracle's price,
+    /// or back on the constant product with `None`. Targets already reached
+    /// are kept when only the slope changes. TWAMM orders still selling
+    /// would bypass the PMM targets, so they must have run out first.
+    pub fn set_pmm(&mut self, k: Option<u16>) -> Result<()> {
+        let Some(k) = k else {
+            self.config.pmm = None;
+            return Ok(());
+        };
+        require!(self.config.oracle.is_some(), AmmError::PmmNeedsOracle);
+        let twamm = &self.config.twamm;
+        require!(
+            twamm.sell_x.sale_rate == 0 && twamm.sell_y.sale_rate == 0,
+            AmmError::TwammOrdersRunning
+        );
+
+        let pmm = Pmm::new(k)?;
+        self.config.pmm = Some(match self.config.pmm {
+            Some(current) => Pmm { k, ..current },
+            None => pmm,
+        });
+
+        Ok(())
+    }
+
+    /// Charges `fees_y_in` on y to x swaps, keeping `fees` for x to y, or
+    /// charges `fees` both ways again with `None`.
+    pub fn set_directional_fees(&mut self, fees_y_in: Option<u16>) -> Result<()> {
+        self.config.fees_y_in = fees_y_in;
+
+        self.config.check_fees()
+    }
+
+    /// Lets swaps hand up to `max_referral_bps` of their fee to a referrer.
+    /// Zero turns referrals off.
+    pub fn set_max_referral(&mut self, max_referral_bps: u16) -> Result<()> {
+        require!(
+            max_referral_bps as u64 <= FEE_DENOMINATOR,
+            AmmError::ReferralTooHigh
+        );
+
+        self.config.max_referral_bps = max_referral_bps;
+
+        Ok(())
+    }
+
+    /// Batches swaps over `batch_window` seconds, settled at one price per
+    /// window, or lets them execute right away again with `None`.
+    pub fn set_batch_window(&mut self, batch_window: Option<i64>) -> Result<()> {
+        require!(
+            !matches!(batch_window, Some(window) if window <= 0),
+            AmmError::InvalidBatchWindow
+        );
+
+        self.config.batch_window = batch_window;
+
+        Ok(())
+    }
+
+    /// Charges filled limit orders `maker_fee_bps` instead of the swap fee,
+    /// at least half of it in either direction.
+    pub fn set_maker_fee(&mut self, maker_fee_bps: u16) -> Result<()> {
+        self.config.maker_fee_bps = maker_fee_bps;
+        for is_x_in in [true, false] {
+            require!(
+                self.config.maker_fee(is_x_in) == maker_fee_bps,
+                AmmError::InvalidFee
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -6,6 +6,8 @@ use anchor_spl::token_interface::{
 };
 
 use crate::constant_product_curve::ConstantProductCurve;
+use crate::error::AmmError;
+use crate::native_sol::{self, UnwrapSol, WSOL_SEED};
 use crate::states::Config;
 
 #[derive(Accounts)]
@@ -19,7 +21,6 @@ pub struct Withdraw<'info> {
         mut,
         seeds = [b"lp", config.key().as_ref()],
         bump = config.lp_bump,
-        mint::decimals = 6,
         mint::authority = config
     )]
     pub mint_lp: InterfaceAccount<'info, Mint>,
@@ -38,21 +39,23 @@ pub struct Withdraw<'info> {
     )]
     pub vault_y: InterfaceAccount<'info, TokenAccount>,
 
+    /// Omitted to be paid in lamports when `mint_x` is the native mint.
     #[account(
         init_if_needed,
         payer = user,
         associated_token::mint = mint_x,
         associated_token::authority = user,
     )]
-    pub user_x: InterfaceAccount<'info, TokenAccount>,
+    pub user_x: Option<InterfaceAccount<'info, TokenAccount>>,
 
+    /// Omitted to be paid in lamports when `mint_y` is the native mint.
     #[account(
         init_if_needed,
         payer = user,
         associated_token::mint = mint_y,
         associated_token::authority = user,
     )]
-    pub user_y: InterfaceAccount<'info, TokenAccount>,
+    pub user_y: Option<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         init_if_needed, // Maybe this user has good lp token transfered
@@ -63,6 +66,7 @@ pub struct Withdraw<'info> {
     pub user_lp: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
+        mut,
         has_one = mint_x,
         has_one = mint_y,
         seeds = [b"config", mint_x.key().as_ref(), mint_y.key().as_ref()],
@@ -70,55 +74,72 @@ pub struct Withdraw<'info> {
     )]
     pub config: Account<'info, Config>,
 
+    /// CHECK: created and closed by the program when paying out native SOL
+    #[account(mut, seeds = [WSOL_SEED, config.key().as_ref()], bump)]
+    pub temp_wsol: Option<UncheckedAccount<'info>>,
+
     pub token_program: Program<'info, Token>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
 impl<'info> Withdraw<'info> {
-    pub fn withdraw(&mut self, min_x: u64, min_y: u64, lp_amount: u64) -> Result<()> {
+    pub fn withdraw(
+        &mut self,
+        min_x: u64,
+        min_y: u64,
+        lp_amount: u64,
+        bumps: &WithdrawBumps,
+    ) -> Result<()> {
         // calculate amount_x, amount_y you get giving lp_amount
         // if that amount_x>=min_x and amount_y>=min_y (good to go)
         // Burn lp_amount of LP TOKENS
         // transfer amount_x of X tokens from vault_x to user_x
         // transfer amount_y of Y tokens from vault_y to user_y
 
-        let (x, y) = match self.mint_lp.supply == 0
-            && self.vault_x.amount == 0
-            && self.vault_y.amount == 0
-        {
+        require!(lp_amount > 0, AmmError::ZeroAmount);
+
+        let (reserve_x, reserve_y) = self.config.sync_twamm(
+            self.vault_x.amount,
+            self.vault_y.amount,
+            Clock::get()?.unix_timestamp,
+        )?;
+        let (x, y) = match self.mint_lp.supply == 0 && reserve_x == 0 && reserve_y == 0 {
             true => (min_x, min_y),
             false => {
                 let amounts = ConstantProductCurve::take_lp_give_xy(
-                    self.vault_x.amount,
-                    self.vault_y.amount,
+                    reserve_x,
+                    reserve_y,
                     self.mint_lp.supply,
                     lp_amount,
-                    6,
-                )
-                .unwrap();
+                )?;
 
                 (amounts.x, amounts.y)
             }
         };
 
-        require!(x >= min_x && y >= min_y, ErrorCode::InsufficientBalance);
-        self.withdraw_token(true, x)?;
-        self.withdraw_token(false, y)?;
+        require!(x >= min_x && y >= min_y, AmmError::SlippageExceeded);
+        if let Some(pmm) = self.config.pmm.as_mut() {
+            let supply = self.mint_lp.supply;
+            let after = supply.checked_sub(lp_amount).ok_or(AmmError::Overflow)?;
+            pmm.scale(supply, after)?;
+        }
+        self.withdraw_token(true, x, bumps)?;
+        self.withdraw_token(false, y, bumps)?;
         self.burn_lp_token(lp_amount)
     }
 
-    fn withdraw_token(&mut self, is_x: bool, amount: u64) -> Result<()> {
-        let (from, to, mint, decimals) = match is_x {
+    fn withdraw_token(&mut self, is_x: bool, amount: u64, bumps: &WithdrawBumps) -> Result<()> {
+        let (from, user_account, mint, decimals) = match is_x {
             true => (
                 self.vault_x.to_account_info(),
-                self.user_x.to_account_info(),
+                &self.user_x,
                 self.mint_x.to_account_info(),
                 self.mint_x.decimals,
             ),
             false => (
                 self.vault_y.to_account_info(),
-                self.user_y.to_account_info(),
+                &self.user_y,
                 self.mint_y.to_account_info(),
                 self.mint_y.decimals,
             ),
@@ -137,12 +158,32 @@ impl<'info> Withdraw<'info> {
         ];
         let signer_seeds = &[&seeds[..]];
 
+        let Some(to) = user_account else {
+            require!(
+                native_sol::is_native(mint.key),
+                AmmError::MissingTokenAccount
+            );
+            let (Some(temp), Some(temp_bump)) = (&self.temp_wsol, bumps.temp_wsol) else {
+                return err!(AmmError::MissingWsolAccount);
+            };
+            let unwrap = UnwrapSol {
+                user: self.user.to_account_info(),
+                config: self.config.to_account_info(),
+                vault: from,
+                mint,
+                temp: temp.to_account_info(),
+                system_program: self.system_program.to_account_info(),
+                token_program: self.token_program.to_account_info(),
+            };
+            return unwrap.pay_out(amount, signer_seeds, temp_bump);
+        };
+
         let cpi_context = CpiContext::new_with_signer(
             self.token_program.to_account_info(),
             TransferChecked {
                 from,
-                to,
-                mint: mint,
+                to: to.to_account_info(),
+                mint,
                 authority: self.config.to_account_info(),
             },
             signer_seeds,
@@ -164,9 +205,3 @@ impl<'info> Withdraw<'info> {
         burn_checked(cpi_context, amount, self.mint_lp.decimals)
     }
 }
-
-#[error_code]
-pub enum ErrorCode {
-    #[msg("InsufficientBalance")]
-    InsufficientBalance,
-}
@@ -0,0 +1,101 @@
+use anchor_lang::prelude::*;
+
+#[error_code]
+pub enum AmmError {
+    #[msg("Math overflow")]
+    Overflow,
+    #[msg("Insufficient liquidity")]
+    InsufficientLiquidity,
+    #[msg("Fee must not exceed 10000 basis points")]
+    InvalidFee,
+    #[msg("Amount must be greater than zero")]
+    ZeroAmount,
+    #[msg("Required deposit exceeds the given maximum")]
+    DepositExceedsMax,
+    #[msg("Slippage exceeded")]
+    SlippageExceeded,
+    #[msg("Signer is not the pool authority")]
+    Unauthorized,
+    #[msg("Token account mint does not match the pool")]
+    InvalidMint,
+    #[msg("Signer is neither owner nor delegate of the source account")]
+    InvalidSourceAuthority,
+    #[msg("Token account is required unless the mint is native SOL")]
+    MissingTokenAccount,
+    #[msg("Temporary WSOL account is required to pay out native SOL")]
+    MissingWsolAccount,
+    #[msg("Dynamic fee parameters are inconsistent")]
+    InvalidDynamicFee,
+    #[msg("Referral share exceeds the pool's maximum")]
+    ReferralTooHigh,
+    #[msg("Reward slot or schedule is invalid")]
+    InvalidRewardSchedule,
+    #[msg("Amount exceeds the staked LP")]
+    InsufficientStake,
+    #[msg("Unlock time must be in the future and vesting end no earlier")]
+    InvalidLockSchedule,
+    #[msg("No locked LP has been released yet")]
+    StillLocked,
+    #[msg("The locked LP has earned no fees since the last claim")]
+    NoFeesAccrued,
+    #[msg("The pool price does not reach the order's limit price")]
+    OrderNotFillable,
+    #[msg("DCA slice, interval or keeper tip is out of range")]
+    InvalidDcaSchedule,
+    #[msg("The next DCA slice is not due yet")]
+    DcaNotDue,
+    #[msg("Long-term order amount or duration is out of range")]
+    InvalidTwammOrder,
+    #[msg("Long-term orders already end at the maximum number of times; archive a passed one")]
+    TwammExpiriesFull,
+    #[msg("Swaps on this pool go through batch auctions")]
+    BatchAuctionOnly,
+    #[msg("This pool does not run batch auctions")]
+    BatchAuctionDisabled,
+    #[msg("Batch window must be positive")]
+    InvalidBatchWindow,
+    #[msg("The batch is closed to new intents")]
+    BatchClosed,
+    #[msg("The batch takes no more intents")]
+    BatchFull,
+    #[msg("The batch window has not closed yet")]
+    BatchNotClosed,
+    #[msg("The batch has already been settled")]
+    BatchSettled,
+    #[msg("The batch has not been settled yet")]
+    BatchNotSettled,
+    #[msg("Circuit breaker bands must be positive and the slot band the wider")]
+    InvalidCircuitBreaker,
+    #[msg("Trade moves the price past the circuit breaker's band")]
+    PriceBandExceeded,
+    #[msg("Oracle guard needs a positive deviation and staleness")]
+    InvalidOracleGuard,
+    #[msg("Pool requires its oracle price account")]
+    MissingOracle,
+    #[msg("Account is not the pool's oracle price feed")]
+    InvalidOracle,
+    #[msg("Oracle price is too old")]
+    StaleOracle,
+    #[msg("Trade price strays too far from the oracle price")]
+    OraclePriceDeviation,
+    #[msg("PMM slope must be between 1 and 10000 bps")]
+    InvalidPmmSlope,
+    #[msg("PMM pools quote around their oracle, which must be set")]
+    PmmNeedsOracle,
+    #[msg("Not supported on PMM pools")]
+    PmmUnsupported,
+    #[msg("Config already has the current layout")]
+    ConfigUpToDate,
+    #[msg("Limit orders can only be filled from the slot after they are placed")]
+    OrderTooFresh,
+    #[msg("Only expiry slots that have passed can be archived")]
+    TwammExpiryRunning,
+    #[msg("Intent is smaller than the share of the pool a batch requires")]
+    IntentTooSmall,
+    #[msg("PMM can only be switched on while no TWAMM orders are selling")]
+    TwammOrdersRunning,
+    #[msg("LP mint decimals are above the maximum")]
+    InvalidLpDecimals,
+    #[msg("Owner already has an intent in this batch")]
+    IntentAlreadySubmitted,
+}
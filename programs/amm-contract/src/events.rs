@@ -0,0 +1,41 @@
+use anchor_lang::prelude::*;
+
+#[event]
+pub struct SwapEvent {
+    pub config: Pubkey,
+    pub user: Pubkey,
+    pub is_x_in: bool,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    /// Total fee, referral included, in the input token.
+    pub fee: u64,
+    /// Part of `fee` paid to `referrer` rather than kept by the pool.
+    pub referral_fee: u64,
+    pub referrer: Option<Pubkey>,
+}
+
+#[event]
+pub struct LimitOrderFillEvent {
+    pub config: Pubkey,
+    pub order: Pubkey,
+    pub owner: Pubkey,
+    pub is_x_in: bool,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    /// Maker fee, in the input token.
+    pub fee: u64,
+    /// Input still resting; zero once the order is closed.
+    pub remaining: u64,
+}
+
+#[event]
+pub struct BatchSettleEvent {
+    pub config: Pubkey,
+    pub batch_id: u64,
+    /// Inputs of the filled intents, fees included.
+    pub amount_x_in: u64,
+    pub amount_y_in: u64,
+    /// Outputs paid into the batch for its intents to claim.
+    pub amount_x_out: u64,
+    pub amount_y_out: u64,
+}
@@ -1,38 +1,324 @@
 use anchor_lang::prelude::*;
 
-mod constant_product_curve;
+pub mod constant_product_curve;
 mod context;
-mod states;
+pub mod error;
+pub mod events;
+pub mod lp_metadata;
+pub mod native_sol;
+pub mod oracle;
+pub mod states;
 
 use context::*;
+use states::{CircuitBreakerParams, DynamicFeeParams, OracleGuard};
 
 declare_id!("EmZ1g5YExu2DiZzdwKwEp1ypNnjxjTdYgNZVf6tmpaNm");
 
-#[program]
-pub mod amm_contract {
+pub use processor::*;
+
+/// Holds the `#[program]` module, whose generated IDL instructions are
+/// emitted next to it and still call the deprecated `AccountInfo::realloc`.
+#[allow(deprecated)]
+mod processor {
     use super::*;
 
-    pub fn initialize(
-        ctx: Context<InitializeConfig>,
-        fees: u16,
-        authority: Option<Pubkey>,
-    ) -> Result<()> {
-        ctx.accounts.init(fees, authority, ctx.bumps)?;
-        Ok(())
-    }
+    #[program]
+    pub mod amm_contract {
+        use super::*;
 
-    pub fn deposit(ctx: Context<Deposit>, amount_lp: u64, max_x: u64, max_y: u64) -> Result<()> {
-        ctx.accounts.deposit(amount_lp, max_x, max_y)?;
-        Ok(())
-    }
+        pub fn initialize(
+            ctx: Context<InitializeConfig>,
+            fees: u16,
+            authority: Option<Pubkey>,
+            // only read by the LP mint's `init` constraint in `InitializeConfig`
+            _lp_decimals: Option<u8>,
+        ) -> Result<()> {
+            ctx.accounts.init(fees, authority, ctx.bumps)?;
+            Ok(())
+        }
 
-    pub fn swap(ctx: Context<Swap>, amount_in: u64, min_out: u64, is_x_in: bool) -> Result<()> {
-        ctx.accounts.swap(amount_in, min_out, is_x_in)?;
-        Ok(())
-    }
+        pub fn deposit(
+            ctx: Context<Deposit>,
+            amount_lp: u64,
+            max_x: u64,
+            max_y: u64,
+        ) -> Result<()> {
+            ctx.accounts.deposit(amount_lp, max_x, max_y)?;
+            Ok(())
+        }
+
+        pub fn swap(
+            ctx: Context<Swap>,
+            amount_in: u64,
+            min_out: u64,
+            is_x_in: bool,
+            referral_bps: u16,
+        ) -> Result<()> {
+            ctx.accounts
+                .swap(amount_in, min_out, is_x_in, referral_bps, &ctx.bumps)?;
+            Ok(())
+        }
+
+        pub fn withdraw(
+            ctx: Context<Withdraw>,
+            min_x: u64,
+            min_y: u64,
+            lp_amount: u64,
+        ) -> Result<()> {
+            ctx.accounts.withdraw(min_x, min_y, lp_amount, &ctx.bumps)?;
+            Ok(())
+        }
+
+        pub fn update_config(
+            ctx: Context<UpdateConfig>,
+            fees: u16,
+            authority: Option<Pubkey>,
+        ) -> Result<()> {
+            ctx.accounts.update(fees, authority)?;
+            Ok(())
+        }
+
+        pub fn set_dynamic_fee(
+            ctx: Context<UpdateConfig>,
+            params: Option<DynamicFeeParams>,
+        ) -> Result<()> {
+            ctx.accounts.set_dynamic_fee(params)?;
+            Ok(())
+        }
+
+        pub fn set_directional_fees(
+            ctx: Context<UpdateConfig>,
+            fees_y_in: Option<u16>,
+        ) -> Result<()> {
+            ctx.accounts.set_directional_fees(fees_y_in)?;
+            Ok(())
+        }
+
+        pub fn set_max_referral(ctx: Context<UpdateConfig>, max_referral_bps: u16) -> Result<()> {
+            ctx.accounts.set_max_referral(max_referral_bps)?;
+            Ok(())
+        }
+
+        pub fn set_maker_fee(ctx: Context<UpdateConfig>, maker_fee_bps: u16) -> Result<()> {
+            ctx.accounts.set_maker_fee(maker_fee_bps)?;
+            Ok(())
+        }
+
+        pub fn set_circuit_breaker(
+            ctx: Context<UpdateConfig>,
+            params: Option<CircuitBreakerParams>,
+        ) -> Result<()> {
+            ctx.accounts.set_circuit_breaker(params)?;
+            Ok(())
+        }
+
+        pub fn set_oracle(ctx: Context<UpdateConfig>, guard: Option<OracleGuard>) -> Result<()> {
+            ctx.accounts.set_oracle(guard)?;
+            Ok(())
+        }
+
+        pub fn set_pmm(ctx: Context<UpdateConfig>, k: Option<u16>) -> Result<()> {
+            ctx.accounts.set_pmm(k)?;
+            Ok(())
+        }
+
+        pub fn set_batch_window(
+            ctx: Context<UpdateConfig>,
+            batch_window: Option<i64>,
+        ) -> Result<()> {
+            ctx.accounts.set_batch_window(batch_window)?;
+            Ok(())
+        }
+
+        pub fn set_trader_tier(
+            ctx: Context<SetTraderTier>,
+            trader: Pubkey,
+            fee_bps: u16,
+        ) -> Result<()> {
+            ctx.accounts.set(trader, fee_bps, &ctx.bumps)?;
+            Ok(())
+        }
+
+        pub fn remove_trader_tier(_ctx: Context<RemoveTraderTier>) -> Result<()> {
+            Ok(())
+        }
+
+        pub fn initialize_farm(ctx: Context<InitializeFarm>) -> Result<()> {
+            ctx.accounts.init(&ctx.bumps)?;
+            Ok(())
+        }
+
+        pub fn set_farm_reward(
+            ctx: Context<SetFarmReward>,
+            index: u8,
+            rate_per_second: u64,
+            start: i64,
+            end: i64,
+        ) -> Result<()> {
+            ctx.accounts.set(index, rate_per_second, start, end)?;
+            Ok(())
+        }
+
+        pub fn fund_farm_reward(ctx: Context<FundFarmReward>, amount: u64) -> Result<()> {
+            ctx.accounts.fund(amount)?;
+            Ok(())
+        }
+
+        pub fn stake(ctx: Context<Stake>, amount: u64) -> Result<()> {
+            ctx.accounts.stake(amount, &ctx.bumps)?;
+            Ok(())
+        }
+
+        pub fn unstake(ctx: Context<Stake>, amount: u64) -> Result<()> {
+            ctx.accounts.unstake(amount, &ctx.bumps)?;
+            Ok(())
+        }
+
+        pub fn claim_farm_reward(ctx: Context<ClaimFarmReward>) -> Result<()> {
+            ctx.accounts.claim()?;
+            Ok(())
+        }
+
+        pub fn lock_lp(
+            ctx: Context<LockLp>,
+            lock_id: u64,
+            amount: u64,
+            unlock_at: i64,
+            vesting_end: Option<i64>,
+        ) -> Result<()> {
+            ctx.accounts
+                .lock(lock_id, amount, unlock_at, vesting_end, &ctx.bumps)?;
+            Ok(())
+        }
+
+        pub fn unlock_lp(ctx: Context<UnlockLp>) -> Result<()> {
+            ctx.accounts.unlock()?;
+            Ok(())
+        }
+
+        pub fn claim_locked_fees(ctx: Context<ClaimLockedFees>) -> Result<()> {
+            ctx.accounts.claim()?;
+            Ok(())
+        }
+
+        pub fn place_limit_order(
+            ctx: Context<PlaceLimitOrder>,
+            order_id: u64,
+            is_x_in: bool,
+            amount_in: u64,
+            min_out: u64,
+        ) -> Result<()> {
+            ctx.accounts
+                .place(order_id, is_x_in, amount_in, min_out, &ctx.bumps)?;
+            Ok(())
+        }
+
+        pub fn cancel_limit_order(ctx: Context<CancelLimitOrder>) -> Result<()> {
+            ctx.accounts.cancel()?;
+            Ok(())
+        }
+
+        pub fn fill_limit_order(ctx: Context<FillLimitOrder>) -> Result<()> {
+            ctx.accounts.fill()?;
+            Ok(())
+        }
+
+        #[allow(clippy::too_many_arguments)]
+        pub fn open_dca(
+            ctx: Context<OpenDca>,
+            dca_id: u64,
+            is_x_in: bool,
+            total: u64,
+            amount_per_cycle: u64,
+            interval: i64,
+            min_out_per_cycle: u64,
+            tip_bps: u16,
+        ) -> Result<()> {
+            ctx.accounts.open(
+                dca_id,
+                is_x_in,
+                total,
+                amount_per_cycle,
+                interval,
+                min_out_per_cycle,
+                tip_bps,
+                &ctx.bumps,
+            )?;
+            Ok(())
+        }
+
+        pub fn close_dca(ctx: Context<CloseDca>) -> Result<()> {
+            ctx.accounts.close()?;
+            Ok(())
+        }
+
+        pub fn execute_dca(ctx: Context<ExecuteDca>) -> Result<()> {
+            ctx.accounts.execute()?;
+            Ok(())
+        }
+
+        pub fn place_twamm_order(
+            ctx: Context<PlaceTwammOrder>,
+            order_id: u64,
+            is_x_in: bool,
+            amount: u64,
+            intervals: u32,
+        ) -> Result<()> {
+            ctx.accounts
+                .place(order_id, is_x_in, amount, intervals, &ctx.bumps)?;
+            Ok(())
+        }
+
+        pub fn withdraw_twamm_proceeds(ctx: Context<ClaimTwammOrder>) -> Result<()> {
+            ctx.accounts.withdraw_proceeds()?;
+            Ok(())
+        }
+
+        pub fn close_twamm_order(ctx: Context<ClaimTwammOrder>) -> Result<()> {
+            ctx.accounts.close()?;
+            Ok(())
+        }
+
+        pub fn execute_twamm(ctx: Context<ExecuteTwamm>) -> Result<()> {
+            ctx.accounts.execute()?;
+            Ok(())
+        }
+
+        pub fn archive_twamm_expiry(ctx: Context<ArchiveTwammExpiry>, at: i64) -> Result<()> {
+            ctx.accounts.archive(at, &ctx.bumps)?;
+            Ok(())
+        }
+
+        pub fn submit_swap_intent(
+            ctx: Context<SubmitSwapIntent>,
+            batch_id: u64,
+            amount_in: u64,
+            min_out: u64,
+            is_x_in: bool,
+        ) -> Result<()> {
+            ctx.accounts
+                .submit(batch_id, amount_in, min_out, is_x_in, &ctx.bumps)?;
+            Ok(())
+        }
+
+        pub fn settle_batch(ctx: Context<SettleBatch>) -> Result<()> {
+            ctx.accounts.settle()?;
+            Ok(())
+        }
+
+        pub fn claim_swap_intents(ctx: Context<ClaimSwapIntents>) -> Result<()> {
+            ctx.accounts.claim()?;
+            Ok(())
+        }
+
+        pub fn create_lp_metadata(ctx: Context<CreateLpMetadata>) -> Result<()> {
+            ctx.accounts.create_lp_metadata()?;
+            Ok(())
+        }
 
-    pub fn withdraw(ctx: Context<Withdraw>, min_x: u64, min_y: u64, lp_amount: u64) -> Result<()> {
-        ctx.accounts.withdraw(min_x, min_y, lp_amount)?;
-        Ok(())
+        pub fn migrate_config(ctx: Context<MigrateConfig>) -> Result<()> {
+            ctx.accounts.migrate()?;
+            Ok(())
+        }
     }
 }
@@ -0,0 +1,80 @@
+use anchor_lang::prelude::*;
+use anchor_spl::metadata::mpl_token_metadata::{accounts::Metadata, MAX_NAME_LENGTH};
+
+use crate::native_sol;
+
+/// Bytes of each side kept in the LP symbol.
+const SYMBOL_SIDE_LENGTH: usize = 4;
+
+/// Symbol shown for a pool side: the symbol from the mint's token metadata
+/// when it has any, otherwise "SOL" for the native mint or the first
+/// characters of the mint address.
+pub fn side_symbol(mint: &Pubkey, metadata: &AccountInfo) -> String {
+    let decoded = match metadata.owner == &anchor_spl::metadata::ID {
+        true => metadata
+            .try_borrow_data()
+            .ok()
+            .and_then(|data| Metadata::from_bytes(&data).ok()),
+        false => None,
+    };
+    if let Some(decoded) = decoded {
+        let symbol = decoded.symbol.trim_matches(char::from(0)).trim();
+        if !symbol.is_empty() {
+            return symbol.to_string();
+        }
+    }
+
+    match native_sol::is_native(mint) {
+        true => "SOL".to_string(),
+        false => clip(&mint.to_string(), SYMBOL_SIDE_LENGTH).to_string(),
+    }
+}
+
+/// Name and symbol of the LP token for a pool of `x` and `y`, e.g.
+/// "SOL-USDC LP" and "SOLUSDCLP", cut to fit the metadata limits.
+pub fn lp_name_and_symbol(x: &str, y: &str) -> (String, String) {
+    let side = (MAX_NAME_LENGTH - " LP".len() - 1) / 2;
+    let name = format!("{}-{} LP", clip(x, side), clip(y, side));
+    let symbol = format!(
+        "{}{}LP",
+        clip(x, SYMBOL_SIDE_LENGTH),
+        clip(y, SYMBOL_SIDE_LENGTH)
+    );
+
+    (name, symbol)
+}
+
+/// At most `max` bytes of `s`, never splitting a character. Metaplex limits
+/// are in bytes.
+fn clip(s: &str, max: usize) -> &str {
+    let mut end = max.min(s.len());
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_spl::metadata::mpl_token_metadata::MAX_SYMBOL_LENGTH;
+
+    #[test]
+    fn names_pair_the_side_symbols() {
+        assert_eq!(
+            lp_name_and_symbol("SOL", "USDC"),
+            ("SOL-USDC LP".to_string(), "SOLUSDCLP".to_string())
+        );
+    }
+
+    #[test]
+    fn long_symbols_fit_metadata_limits() {
+        let long = "A".repeat(40);
+        let (name, symbol) = lp_name_and_symbol(&long, "ÉTÉÉTÉÉTÉ");
+
+        assert!(name.len() <= MAX_NAME_LENGTH);
+        assert!(symbol.len() <= MAX_SYMBOL_LENGTH);
+        assert!(name.ends_with(" LP"));
+        assert_eq!(symbol, "AAAAÉTLP");
+    }
+}
@@ -0,0 +1,150 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::system_program::{self, Allocate, Assign, CreateAccount, Transfer};
+use anchor_spl::token::{self, spl_token, CloseAccount, InitializeAccount3, SyncNative};
+use anchor_spl::token_interface::{transfer_checked, TransferChecked};
+
+/// Seed of the temporary WSOL account native payouts pass through.
+pub const WSOL_SEED: &[u8] = b"wsol";
+
+pub fn is_native(mint: &Pubkey) -> bool {
+    *mint == spl_token::native_mint::ID
+}
+
+/// Wraps `amount` lamports from `user` straight into a native-mint `vault`.
+pub fn wrap<'info>(
+    user: AccountInfo<'info>,
+    vault: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    system_program::transfer(
+        CpiContext::new(
+            system_program,
+            Transfer {
+                from: user,
+                to: vault.clone(),
+            },
+        ),
+        amount,
+    )?;
+
+    token::sync_native(CpiContext::new(
+        token_program,
+        SyncNative { account: vault },
+    ))
+}
+
+/// Accounts for paying a native-mint vault out to `user` as lamports.
+pub struct UnwrapSol<'info> {
+    pub user: AccountInfo<'info>,
+    pub config: AccountInfo<'info>,
+    pub vault: AccountInfo<'info>,
+    pub mint: AccountInfo<'info>,
+    /// `[WSOL_SEED, config]` PDA, empty before and after the instruction.
+    pub temp: AccountInfo<'info>,
+    pub system_program: AccountInfo<'info>,
+    pub token_program: AccountInfo<'info>,
+}
+
+impl<'info> UnwrapSol<'info> {
+    /// Moves `amount` from the vault into a fresh temporary token account and
+    /// closes it into `user`, who gets the rent back along with the SOL.
+    pub fn pay_out(&self, amount: u64, config_seeds: &[&[&[u8]]], temp_bump: u8) -> Result<()> {
+        let config_key = self.config.key();
+        let temp_seeds: &[&[u8]] = &[WSOL_SEED, config_key.as_ref(), &[temp_bump]];
+        self.create_temp(&[temp_seeds])?;
+
+        token::initialize_account3(CpiContext::new(
+            self.token_program.clone(),
+            InitializeAccount3 {
+                account: self.temp.clone(),
+                mint: self.mint.clone(),
+                authority: self.config.clone(),
+            },
+        ))?;
+
+        transfer_checked(
+            CpiContext::new_with_signer(
+                self.token_program.clone(),
+                TransferChecked {
+                    from: self.vault.clone(),
+                    to: self.temp.clone(),
+                    mint: self.mint.clone(),
+                    authority: self.config.clone(),
+                },
+                config_seeds,
+            ),
+            amount,
+            spl_token::native_mint::DECIMALS,
+        )?;
+
+        token::close_account(CpiContext::new_with_signer(
+            self.token_program.clone(),
+            CloseAccount {
+                account: self.temp.clone(),
+                destination: self.user.clone(),
+                authority: self.config.clone(),
+            },
+            config_seeds,
+        ))
+    }
+
+    /// Creates the temporary account, even if someone has sent lamports to
+    /// its address in the meantime.
+    fn create_temp(&self, temp_seeds: &[&[&[u8]]]) -> Result<()> {
+        let space = spl_token::state::Account::LEN;
+        let rent = Rent::get()?.minimum_balance(space);
+        let lamports = self.temp.lamports();
+
+        if lamports == 0 {
+            return system_program::create_account(
+                CpiContext::new_with_signer(
+                    self.system_program.clone(),
+                    CreateAccount {
+                        from: self.user.clone(),
+                        to: self.temp.clone(),
+                    },
+                    temp_seeds,
+                ),
+                rent,
+                space as u64,
+                &spl_token::ID,
+            );
+        }
+
+        if lamports < rent {
+            system_program::transfer(
+                CpiContext::new(
+                    self.system_program.clone(),
+                    Transfer {
+                        from: self.user.clone(),
+                        to: self.temp.clone(),
+                    },
+                ),
+                rent - lamports,
+            )?;
+        }
+        system_program::allocate(
+            CpiContext::new_with_signer(
+                self.system_program.clone(),
+                Allocate {
+                    account_to_allocate: self.temp.clone(),
+                },
+                temp_seeds,
+            ),
+            space as u64,
+        )?;
+        system_program::assign(
+            CpiContext::new_with_signer(
+                self.system_program.clone(),
+                Assign {
+                    account_to_assign: self.temp.clone(),
+                },
+                temp_seeds,
+            ),
+            &spl_token::ID,
+        )
+    }
+}
@@ -0,0 +1,202 @@
+use anchor_lang::prelude::*;
+
+use crate::constant_product_curve::{div, mul_div, RoundDirection};
+use crate::error::AmmError;
+
+/// Pyth Solana Receiver, which owns `PriceUpdateV2` accounts.
+pub const PYTH_RECEIVER_ID: Pubkey = pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+/// Switchboard On-Demand, which owns `PullFeedAccountData` accounts.
+pub const SWITCHBOARD_ON_DEMAND_ID: Pubkey = pubkey!("SBondMDrcV3K4kxZR1HNVT7osZxAHVHgYXL5Ze1oMUv");
+
+pub const PYTH_PRICE_UPDATE_DISCRIMINATOR: [u8; 8] = [34, 241, 35, 99, 157, 126, 244, 205];
+pub const PYTH_PRICE_UPDATE_LEN: usize = 134;
+/// Tag of `VerificationLevel::Full`, following the 8 byte discriminator and
+/// 32 byte write authority. Partially verified updates are refused.
+pub const PYTH_FULL_VERIFICATION: u8 = 1;
+pub const PYTH_PRICE_OFFSET: usize = 73;
+pub const PYTH_EXPONENT_OFFSET: usize = 89;
+pub const PYTH_PUBLISH_TIME_OFFSET: usize = 93;
+
+pub const SWITCHBOARD_FEED_DISCRIMINATOR: [u8; 8] = [196, 27, 108, 196, 10, 215, 219, 40];
+pub const SWITCHBOARD_FEED_LEN: usize = 3208;
+pub const SWITCHBOARD_TIMESTAMP_OFFSET: usize = 2216;
+pub const SWITCHBOARD_VALUE_OFFSET: usize = 2264;
+/// Switchboard values are fixed point with 18 decimals.
+pub const SWITCHBOARD_EXPONENT: i32 = -18;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum OracleKind {
+    Pyth,
+    Switchboard,
+}
+
+/// A positive price, `mantissa * 10^exponent`, in whole tokens.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OraclePrice {
+    pub mantissa: u128,
+    pub exponent: i32,
+    pub publish_time: i64,
+}
+
+impl OraclePrice {
+    /// Reads `feed`, checking it is owned by the oracle's program and laid
+    /// out as its price account.
+    pub fn read(kind: OracleKind, feed: &AccountInfo) -> Result<Self> {
+        Self::parse(kind, feed.owner, &feed.try_borrow_data()?)
+    }
+
+    /// Parses the data of a `kind` price account owned by `owner`.
+    pub fn parse(kind: OracleKind, owner: &Pubkey, data: &[u8]) -> Result<Self> {
+        let (program, discriminator, len) = match kind {
+            OracleKind::Pyth => (
+                PYTH_RECEIVER_ID,
+                PYTH_PRICE_UPDATE_DISCRIMINATOR,
+                PYTH_PRICE_UPDATE_LEN,
+            ),
+            OracleKind::Switchboard => (
+                SWITCHBOARD_ON_DEMAND_ID,
+                SWITCHBOARD_FEED_DISCRIMINATOR,
+                SWITCHBOARD_FEED_LEN,
+            ),
+        };
+        require_keys_eq!(*owner, program, AmmError::InvalidOracle);
+        require!(
+            data.len() >= len && data[..8] == discriminator,
+            AmmError::InvalidOracle
+        );
+        match kind {
+            OracleKind::Pyth => Self::parse_pyth(data),
+            OracleKind::Switchboard => Self::parse_switchboard(data),
+        }
+    }
+
+    fn parse_pyth(data: &[u8]) -> Result<Self> {
+        require!(data[40] == PYTH_FULL_VERIFICATION, AmmError::InvalidOracle);
+        let price = i64::from_le_bytes(read(data, PYTH_PRICE_OFFSET));
+        require!(price > 0, AmmError::InvalidOracle);
+        Ok(Self {
+            mantissa: price as u128,
+            exponent: i32::from_le_bytes(read(data, PYTH_EXPONENT_OFFSET)),
+            publish_time: i64::from_le_bytes(read(data, PYTH_PUBLISH_TIME_OFFSET)),
+        })
+    }
+
+    fn parse_switchboard(data: &[u8]) -> Result<Self> {
+        let value = i128::from_le_bytes(read(data, SWITCHBOARD_VALUE_OFFSET));
+        require!(value > 0, AmmError::InvalidOracle);
+        Ok(Self {
+            mantissa: value as u128,
+            exponent: SWITCHBOARD_EXPONENT,
+            publish_time: i64::from_le_bytes(read(data, SWITCHBOARD_TIMESTAMP_OFFSET)),
+        })
+    }
+
+    /// The price as raw y per raw x, Q64.64, for mints of `decimals_x` and
+    /// `decimals_y`. The feed quotes x in y, or y in x when `invert` is set.
+    pub fn to_q64(&self, decimals_x: u8, decimals_y: u8, invert: bool) -> Result<u128> {
+        let shift = decimals_y as i32 - decimals_x as i32;
+        // keep the mantissa under 2^64 so shifting it by 64 bits cannot overflow
+        let (mut mantissa, mut exponent) = (self.mantissa, self.exponent);
+        while mantissa > u64::MAX as u128 {
+            mantissa /= 10;
+            exponent += 1;
+        }
+
+        match invert {
+            false => scaled(1 << 64, mantissa, 1, exponent + shift),
+            true => scaled(1 << 64, 1, mantissa, shift - exponent),
+        }
+    }
+}
+
+fn read<const N: usize>(data: &[u8], offset: usize) -> [u8; N] {
+    data[offset..offset + N].try_into().unwrap()
+}
+
+/// `a * num / den * 10^exponent`, with `a * num` and `a` times a power of
+/// ten up to 10^19 fitting in u128.
+fn scaled(a: u128, num: u128, den: u128, exponent: i32) -> Result<u128> {
+    let pow = |exponent: i32| {
+        10u128
+            .checked_pow(exponent.unsigned_abs())
+            .ok_or(AmmError::Overflow)
+    };
+    let a = a.checked_mul(num).ok_or(AmmError::Overflow)?;
+    match exponent {
+        ..=-1 => div(
+            a,
+            den.checked_mul(pow(exponent)?).ok_or(AmmError::Overflow)?,
+            RoundDirection::Floor,
+        ),
+        0..=19 => mul_div(a, pow(exponent)?, den, RoundDirection::Floor),
+        _ => {
+            let price = mul_div(a, pow(19)?, den, RoundDirection::Floor)?;
+            let price = price
+                .checked_mul(pow(exponent - 19)?)
+                .ok_or(AmmError::Overflow)?;
+            Ok(price)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ONE: u128 = 1 << 64;
+
+    fn price(mantissa: u128, exponent: i32) -> OraclePrice {
+        OraclePrice {
+            mantissa,
+            exponent,
+            publish_time: 0,
+        }
+    }
+
+    #[test]
+    fn decimals_shift_the_raw_price() {
+        // 150.25 y per x, x with 9 decimals and y with 6
+        let q64 = price(15_025_000_000, -8).to_q64(9, 6, false).unwrap();
+        assert_eq!(q64, ONE * 15_025 / 100_000);
+        // the same feed quoting y in x
+        let q64 = price(15_025_000_000, -8).to_q64(6, 9, true).unwrap();
+        assert_eq!(q64, ONE * 100_000 / 15_025);
+    }
+
+    #[test]
+    fn wide_mantissas_are_narrowed() {
+        // Switchboard's 18 decimals, for a price of 2
+        let q64 = price(2 * 10u128.pow(18) * 1_000, -21)
+            .to_q64(6, 6, false)
+            .unwrap();
+        assert_eq!(q64, 2 * ONE);
+        let q64 = price(2 * 10u128.pow(18), -18).to_q64(6, 6, true).unwrap();
+        assert_eq!(q64, ONE / 2);
+        // raw prices far above one
+        let q64 = price(1, 0).to_q64(0, 18, false).unwrap();
+        assert_eq!(q64, ONE * 10u128.pow(18));
+    }
+
+    #[test]
+    fn feeds_are_parsed_from_their_layout() {
+        let mut data = vec![0u8; PYTH_PRICE_UPDATE_LEN];
+        data[40] = PYTH_FULL_VERIFICATION;
+        data[PYTH_PRICE_OFFSET..][..8].copy_from_slice(&15_025i64.to_le_bytes());
+        data[PYTH_EXPONENT_OFFSET..][..4].copy_from_slice(&(-2i32).to_le_bytes());
+        data[PYTH_PUBLISH_TIME_OFFSET..][..8].copy_from_slice(&77i64.to_le_bytes());
+        assert_eq!(
+            OraclePrice::parse_pyth(&data).unwrap(),
+            OraclePrice {
+                mantissa: 15_025,
+                exponent: -2,
+                publish_time: 77
+            }
+        );
+        data[40] = 0;
+        assert!(OraclePrice::parse_pyth(&data).is_err());
+
+        let mut data = vec![0u8; SWITCHBOARD_FEED_LEN];
+        data[SWITCHBOARD_VALUE_OFFSET..][..16].copy_from_slice(&(-1i128).to_le_bytes());
+        assert!(OraclePrice::parse_switchboard(&data).is_err());
+    }
+}
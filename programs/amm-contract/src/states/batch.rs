@@ -0,0 +1,257 @@
+use anchor_lang::prelude::*;
+
+use crate::constant_product_curve::{mul_div, ConstantProductCurve, RoundDirection, XYAmounts};
+use crate::error::AmmError;
+
+pub const BATCH_SEED: &[u8] = b"batch";
+
+/// Swap intents one batch holds.
+pub const MAX_BATCH_INTENTS: usize = 16;
+
+/// Smallest intent, in bps of the pool's reserve of the token it sells, so
+/// filling a batch to shut others out takes real size. With intents that
+/// miss their limit paying the fee, it also puts a price on doing so.
+pub const MIN_INTENT_BPS: u64 = 10;
+
+/// Swap submitted to a batch, settled with the rest of it.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, InitSpace)]
+pub struct SwapIntent {
+    pub owner: Pubkey,
+    pub is_x_in: bool,
+    pub amount_in: u64,
+    pub min_out: u64,
+    /// Set at settlement; zero for intents left out, which are refunded.
+    pub amount_out: u64,
+    /// Kept from the refund of an intent that missed its own limit.
+    pub fee: u64,
+    pub claimed: bool,
+}
+
+/// Swaps submitted to a pool during one window, all settled at a single
+/// clearing price once it closes. The inputs wait in the batch's ATAs, and
+/// the outputs are paid there at settlement until each owner claims them.
+#[account]
+#[derive(InitSpace)]
+pub struct Batch {
+    pub config: Pubkey,
+    /// Index of the window, `unix_timestamp / batch_window`.
+    pub batch_id: u64,
+    /// Submissions stop and settlement may start here.
+    pub closes_at: i64,
+    /// Paid the batch's rent, refunded once every intent is claimed.
+    pub payer: Pubkey,
+    pub settled: bool,
+    pub intent_count: u8,
+    pub intents: [SwapIntent; MAX_BATCH_INTENTS],
+    pub bump: u8,
+}
+
+impl Batch {
+    pub fn intents(&self) -> &[SwapIntent] {
+        &self.intents[..self.intent_count as usize]
+    }
+
+    /// Adds `intent`, one per owner.
+    pub fn push(&mut self, intent: SwapIntent) -> Result<()> {
+        require!(
+            self.intents()
+                .iter()
+                .all(|other| other.owner != intent.owner),
+            AmmError::IntentAlreadySubmitted
+        );
+        let slot = self
+            .intents
+            .get_mut(self.intent_count as usize)
+            .ok_or(AmmError::BatchFull)?;
+        *slot = intent;
+        self.intent_count += 1;
+        Ok(())
+    }
+
+    /// Settles every intent against a pool holding `x` / `y`, returning the
+    /// inputs the filled intents pay in and the outputs they are paid.
+    ///
+    /// Both sides trade at the one price `(y + y_in) / (x + x_in)`, net of
+    /// fees. Opposing intents match each other at it and only the imbalance
+    /// trades against the pool, which ends at
+    /// `((x + x_in) y / (y + y_in), (y + y_in) x / (x + x_in))`, keeping `k`.
+    /// Intents that price misses the `min_out` of are left out, still paying
+    /// the fee on their input, and the price is found again without them.
+    pub fn clear(
+        &mut self,
+        x: u64,
+        y: u64,
+        fee_x: u16,
+        fee_y: u16,
+    ) -> Result<(XYAmounts, XYAmounts)> {
+        let count = self.intent_count as usize;
+        // inputs after fees, which stay in the pool
+        let mut net = [0u64; MAX_BATCH_INTENTS];
+        let mut fees = [0u64; MAX_BATCH_INTENTS];
+        for ((net, fee), intent) in net.iter_mut().zip(&mut fees).zip(self.intents()) {
+            let fee_bps = if intent.is_x_in { fee_x } else { fee_y };
+            *fee = ConstantProductCurve::fee(intent.amount_in, fee_bps)?;
+            *net = intent.amount_in - *fee;
+        }
+
+        let mut filled = [false; MAX_BATCH_INTENTS];
+        filled[..count].fill(true);
+        loop {
+            let (mut total_x, mut total_y) = (x as u128, y as u128);
+            for i in (0..count).filter(|&i| filled[i]) {
+                match self.intents[i].is_x_in {
+                    true => total_x += net[i] as u128,
+                    false => total_y += net[i] as u128,
+                }
+            }
+
+            let mut missed = false;
+            for i in 0..count {
+                if !filled[i] {
+                    continue;
+                }
+                let intent = &mut self.intents[i];
+                let (num, den) = match intent.is_x_in {
+                    true => (total_y, total_x),
+                    false => (total_x, total_y),
+                };
+                let out = match net[i] {
+                    0 => 0,
+                    net => mul_div(net as u128, num, den, RoundDirection::Floor)?,
+                };
+                let out = u64::try_from(out).map_err(|_| AmmError::Overflow)?;
+                match out == 0 || out < intent.min_out {
+                    true => {
+                        filled[i] = false;
+                        missed = true;
+                    }
+                    false => intent.amount_out = out,
+                }
+            }
+            if !missed {
+                break;
+            }
+        }
+
+        let mut amounts_in = XYAmounts { x: 0, y: 0 };
+        let mut amounts_out = XYAmounts { x: 0, y: 0 };
+        for ((intent, filled), fee) in self.intents[..count].iter_mut().zip(filled).zip(fees) {
+            if !filled {
+                intent.amount_out = 0;
+                intent.fee = fee;
+                continue;
+            }
+            match intent.is_x_in {
+                true => {
+                    amounts_in.x += intent.amount_in;
+                    amounts_out.y += intent.amount_out;
+                }
+                false => {
+                    amounts_in.y += intent.amount_in;
+                    amounts_out.x += intent.amount_out;
+                }
+            }
+        }
+        self.settled = true;
+        Ok((amounts_in, amounts_out))
+    }
+
+    /// Settles the batch with every intent left out, to be refunded in full.
+    pub fn refund_all(&mut self) {
+        for intent in &mut self.intents {
+            intent.amount_out = 0;
+            intent.fee = 0;
+        }
+        self.settled = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn batch_of(intents: &[(bool, u64, u64)]) -> Batch {
+        let mut batch = Batch {
+            config: Pubkey::new_unique(),
+            batch_id: 0,
+            closes_at: 0,
+            payer: Pubkey::new_unique(),
+            settled: false,
+            intent_count: 0,
+            intents: Default::default(),
+            bump: 255,
+        };
+        for &(is_x_in, amount_in, min_out) in intents {
+            batch
+                .push(SwapIntent {
+                    owner: Pubkey::new_unique(),
+                    is_x_in,
+                    amount_in,
+                    min_out,
+                    ..Default::default()
+                })
+                .unwrap();
+        }
+        batch
+    }
+
+    #[test]
+    fn both_sides_trade_at_one_price() {
+        let mut batch = batch_of(&[(true, 1_000, 0), (true, 3_000, 0), (false, 2_000, 0)]);
+        let (amounts_in, amounts_out) = batch.clear(100_000, 100_000, 0, 0).unwrap();
+
+        // price is (100_000 + 2_000) / (100_000 + 4_000) y per x
+        let outs: Vec<u64> = batch.intents().iter().map(|i| i.amount_out).collect();
+        assert_eq!(outs, vec![980, 2_942, 2_039]);
+        assert_eq!((amounts_in.x, amounts_in.y), (4_000, 2_000));
+
+        // the pool keeps at least its k
+        let x = 100_000 + amounts_in.x - amounts_out.x;
+        let y = 100_000 + amounts_in.y - amounts_out.y;
+        assert!(x as u128 * y as u128 >= 100_000u128 * 100_000);
+    }
+
+    #[test]
+    fn missed_limits_are_left_out() {
+        // the large seller pushes the price under the small one's limit
+        let mut batch = batch_of(&[(true, 50_000, 0), (true, 1_000, 900)]);
+        let (amounts_in, _) = batch.clear(100_000, 100_000, 30, 30).unwrap();
+        assert_eq!(batch.intents()[1].amount_out, 0);
+        assert_eq!(batch.intents()[1].fee, 3);
+        assert_eq!(batch.intents()[0].fee, 0);
+        assert_eq!(amounts_in.x, 50_000);
+
+        // alone, it is filled
+        let mut alone = batch_of(&[(true, 1_000, 900)]);
+        alone.clear(100_000, 100_000, 30, 30).unwrap();
+        assert!(alone.intents()[0].amount_out >= 900);
+    }
+
+    #[test]
+    fn refunded_batches_pay_nothing_out() {
+        let mut batch = batch_of(&[(true, 1_000, 0), (false, 2_000, 0)]);
+        batch.clear(100_000, 100_000, 30, 30).unwrap();
+        batch.refund_all();
+        assert!(batch.settled);
+        assert!(batch
+            .intents()
+            .iter()
+            .all(|intent| intent.amount_out == 0 && intent.fee == 0));
+    }
+
+    #[test]
+    fn batch_is_bounded() {
+        let mut batch = batch_of(&[(true, 1, 0); MAX_BATCH_INTENTS]);
+        assert!(batch.push(SwapIntent::default()).is_err());
+    }
+
+    #[test]
+    fn owners_submit_once_per_batch() {
+        let mut batch = batch_of(&[(true, 1, 0)]);
+        let again = batch.intents()[0];
+        assert_eq!(
+            batch.push(again),
+            Err(AmmError::IntentAlreadySubmitted.into())
+        );
+    }
+}
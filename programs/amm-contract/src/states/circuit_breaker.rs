@@ -0,0 +1,118 @@
+use anchor_lang::prelude::*;
+
+use crate::error::AmmError;
+use crate::states::{price_move_bps, spot_price};
+
+/// Bands on how far trades may move the pool price.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct CircuitBreakerParams {
+    /// Most one trade may move the price, in bps.
+    pub max_swap_move_bps: u16,
+    /// Most the price may move within one slot, in bps of where the slot's
+    /// first trade found it.
+    pub max_slot_move_bps: u16,
+}
+
+impl CircuitBreakerParams {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.max_swap_move_bps > 0 && self.max_swap_move_bps <= self.max_slot_move_bps,
+            AmmError::InvalidCircuitBreaker
+        );
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct CircuitBreaker {
+    pub params: CircuitBreakerParams,
+    /// Slot of the last trade.
+    pub slot: u64,
+    /// Price (y per x, Q64.64) before the first trade of `slot`.
+    pub slot_price: u128,
+}
+
+impl CircuitBreaker {
+    pub fn new(params: CircuitBreakerParams) -> Self {
+        Self {
+            params,
+            slot: 0,
+            slot_price: 0,
+        }
+    }
+
+    /// Fails a trade at `slot` taking the reserves from `before` to `after`
+    /// if it moves the price past either band.
+    pub fn check(&mut self, before: (u64, u64), after: (u64, u64), slot: u64) -> Result<()> {
+        let (Some(before), Some(after)) =
+            (spot_price(before.0, before.1), spot_price(after.0, after.1))
+        else {
+            return Ok(());
+        };
+        if slot != self.slot {
+            self.slot = slot;
+            self.slot_price = before;
+        }
+
+        require!(
+            price_move_bps(before, after) <= self.params.max_swap_move_bps as u128
+                && price_move_bps(self.slot_price, after) <= self.params.max_slot_move_bps as u128,
+            AmmError::PriceBandExceeded
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker() -> CircuitBreaker {
+        CircuitBreaker::new(CircuitBreakerParams {
+            max_swap_move_bps: 100,
+            max_slot_move_bps: 250,
+        })
+    }
+
+    #[test]
+    fn one_trade_is_bounded() {
+        let mut breaker = breaker();
+        // 1% is fine, 2% is not
+        breaker
+            .check((1_000_000, 1_000_000), (1_000_000, 1_010_000), 1)
+            .unwrap();
+        assert!(breaker
+            .check((1_000_000, 1_000_000), (1_000_000, 1_020_000), 1)
+            .is_err());
+    }
+
+    #[test]
+    fn slot_is_bounded_until_the_next_one() {
+        let mut breaker = breaker();
+        let mut y = 1_000_000;
+        for _ in 0..2 {
+            breaker
+                .check((1_000_000, y), (1_000_000, y + 10_000), 7)
+                .unwrap();
+            y += 10_000;
+        }
+        // a third 1% step in the same slot passes 2.5% from its start
+        assert!(breaker
+            .check((1_000_000, y), (1_000_000, y + 10_000), 7)
+            .is_err());
+        breaker
+            .check((1_000_000, y), (1_000_000, y + 10_000), 8)
+            .unwrap();
+    }
+
+    #[test]
+    fn params_must_be_consistent() {
+        for (max_swap_move_bps, max_slot_move_bps) in [(0, 100), (200, 100)] {
+            let params = CircuitBreakerParams {
+                max_swap_move_bps,
+                max_slot_move_bps,
+            };
+            assert!(params.validate().is_err());
+        }
+    }
+}
@@ -1,12 +1,180 @@
 use anchor_lang::prelude::*;
 
+use crate::constant_product_curve::FEE_DENOMINATOR;
+use crate::error::AmmError;
+use crate::oracle::OraclePrice;
+use crate::states::{CircuitBreaker, DynamicFee, OracleGuard, Pmm, Twamm};
+
+/// Fields are only ever appended, and must decode from zero bytes as
+/// switched off, so `migrate_config` can bring older pools up to date by
+/// zero-extending them.
 #[account]
 #[derive(InitSpace)]
 pub struct Config {
     pub mint_x: Pubkey,
     pub mint_y: Pubkey,
+    /// Swap fee in bps, for both directions unless `fees_y_in` is set.
     pub fees: u16,
     pub my_bump: u8,
     pub lp_bump: u8,
     pub authority: Option<Pubkey>,
+    /// When set, `fees` is the base fee and volatility adds to it.
+    pub dynamic_fee: Option<DynamicFee>,
+    /// Fee for y to x swaps when it differs from x to y.
+    pub fees_y_in: Option<u16>,
+    /// Most of the swap fee a referrer may be paid, in bps of the fee.
+    pub max_referral_bps: u16,
+    /// Fee limit orders pay when filled, kept between half of and all of the
+    /// swap fee.
+    pub maker_fee_bps: u16,
+    /// Long-term orders selling into the pool over time.
+    pub twamm: Twamm,
+    /// When set, swaps are batched over windows of this many seconds and
+    /// settled at one price per window instead of executing right away, and
+    /// limit orders and DCA slices, which would trade outside the batches,
+    /// wait.
+    pub batch_window: Option<i64>,
+    /// When set, swaps, limit order fills, DCA slices and batch settlements
+    /// may only move the price so far per trade and per slot.
+    pub circuit_breaker: Option<CircuitBreaker>,
+    /// When set, swaps, limit order fills, DCA slices and batch settlements
+    /// must pass this price feed and trade near its price.
+    pub oracle: Option<OracleGuard>,
+    /// When set, swaps trade on the PMM curve around the oracle's price
+    /// instead of the constant product.
+    pub pmm: Option<Pmm>,
+}
+
+impl Config {
+    /// Flat fee, or dynamic base fee, for swaps in the given direction.
+    pub fn base_fee(&self, is_x_in: bool) -> u16 {
+        match is_x_in {
+            true => self.fees,
+            false => self.fees_y_in.unwrap_or(self.fees),
+        }
+    }
+
+    /// Fee a limit order selling in the given direction pays when filled.
+    /// Never below half the base fee, which also covers configs migrated
+    /// from before the maker fee existed, nor above it once it is lowered.
+    pub fn maker_fee(&self, is_x_in: bool) -> u16 {
+        let base_fee = self.base_fee(is_x_in);
+        self.maker_fee_bps.clamp(base_fee / 2, base_fee)
+    }
+
+    /// Both directions' fees are at most 100% and, with dynamic fees, at
+    /// most the fee cap.
+    pub fn check_fees(&self) -> Result<()> {
+        for base_fee in [self.base_fee(true), self.base_fee(false)] {
+            require!(base_fee as u64 <= FEE_DENOMINATOR, AmmError::InvalidFee);
+            if let Some(dynamic_fee) = &self.dynamic_fee {
+                dynamic_fee.params.validate(base_fee)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fails a trade at `slot` taking `amount_in` into and `amount_out` out
+    /// of a pool holding `in_bal` / `out_bal`, if it trips the circuit
+    /// breaker.
+    pub fn check_price_move(
+        &mut self,
+        (in_bal, out_bal): (u64, u64),
+        (amount_in, amount_out): (u64, u64),
+        is_x_in: bool,
+        slot: u64,
+    ) -> Result<()> {
+        let (before, after) = trade_reserves((in_bal, out_bal), (amount_in, amount_out), is_x_in)?;
+        self.check_reserves_move(before, after, slot)
+    }
+
+    /// Fails a trade at `slot` taking the reserves from `before` to `after`,
+    /// both as `(x, y)`, if it trips the circuit breaker.
+    pub fn check_reserves_move(
+        &mut self,
+        before: (u64, u64),
+        after: (u64, u64),
+        slot: u64,
+    ) -> Result<()> {
+        match self.circuit_breaker.as_mut() {
+            Some(circuit_breaker) => circuit_breaker.check(before, after, slot),
+            None => Ok(()),
+        }
+    }
+
+    /// The pool's oracle guard and the price of its feed, when it has one.
+    /// The feed must then be passed as `feed`.
+    pub fn read_oracle(
+        &self,
+        feed: Option<&AccountInfo>,
+    ) -> Result<Option<(OracleGuard, OraclePrice)>> {
+        let Some(guard) = self.oracle else {
+            return Ok(None);
+        };
+        let feed = feed.ok_or(AmmError::MissingOracle)?;
+        require_keys_eq!(feed.key(), guard.feed, AmmError::InvalidOracle);
+
+        Ok(Some((guard, OraclePrice::read(guard.kind, feed)?)))
+    }
+
+    /// Fee in bps for swapping `amount_in` at `now`, updating the volatility
+    /// state of dynamic fee pools.
+    pub fn swap_fee(
+        &mut self,
+        reserve_x: u64,
+        reserve_y: u64,
+        amount_in: u64,
+        is_x_in: bool,
+        now: i64,
+    ) -> Result<u16> {
+        let base_fee = self.base_fee(is_x_in);
+        match self.dynamic_fee.as_mut() {
+            Some(dynamic_fee) => {
+                dynamic_fee.swap_fee(base_fee, reserve_x, reserve_y, amount_in, is_x_in, now)
+            }
+            None => Ok(base_fee),
+        }
+    }
+
+    /// Executes long-term orders up to `now`, at the base fees, and returns
+    /// the reserves left to the pool once their balances are set aside.
+    pub fn sync_twamm(&mut self, vault_x: u64, vault_y: u64, now: i64) -> Result<(u64, u64)> {
+        let fees = (self.base_fee(true), self.base_fee(false));
+        self.twamm.execute(vault_x, vault_y, fees, now)?;
+        self.twamm.reserves(vault_x, vault_y)
+    }
+
+    /// `sync_twamm` with the vaults and reserves ordered input first.
+    pub fn sync_twamm_directed(
+        &mut self,
+        vault_in: u64,
+        vault_out: u64,
+        is_x_in: bool,
+        now: i64,
+    ) -> Result<(u64, u64)> {
+        match is_x_in {
+            true => self.sync_twamm(vault_in, vault_out, now),
+            false => self
+                .sync_twamm(vault_out, vault_in, now)
+                .map(|(x, y)| (y, x)),
+        }
+    }
+}
+
+/// Reserves before and after a trade taking `amount_in` into and
+/// `amount_out` out of a pool holding `in_bal` / `out_bal`, both as
+/// `(x, y)`.
+pub fn trade_reserves(
+    (in_bal, out_bal): (u64, u64),
+    (amount_in, amount_out): (u64, u64),
+    is_x_in: bool,
+) -> Result<((u64, u64), (u64, u64))> {
+    let after = (
+        in_bal.checked_add(amount_in).ok_or(AmmError::Overflow)?,
+        out_bal.checked_sub(amount_out).ok_or(AmmError::Overflow)?,
+    );
+    Ok(match is_x_in {
+        true => ((in_bal, out_bal), after),
+        false => ((out_bal, in_bal), (after.1, after.0)),
+    })
 }
@@ -0,0 +1,100 @@
+use anchor_lang::prelude::*;
+
+use crate::constant_product_curve::{mul_div, RoundDirection, FEE_DENOMINATOR};
+use crate::error::AmmError;
+
+pub const DCA_SEED: &[u8] = b"dca";
+
+/// Most of each slice a DCA may pay its keeper, in bps.
+pub const MAX_DCA_TIP_BPS: u16 = 100;
+
+/// Scheduled sell of escrowed x (or y) into the pool, one slice per
+/// interval, executed by any keeper for a tip out of the slice.
+#[account]
+#[derive(InitSpace)]
+pub struct Dca {
+    pub config: Pubkey,
+    pub owner: Pubkey,
+    /// Chosen by the owner, so one owner can run several DCAs.
+    pub dca_id: u64,
+    /// Sells x for y when set, y for x otherwise.
+    pub is_x_in: bool,
+    pub amount_per_cycle: u64,
+    /// Least output for a full slice, or pro rata for the last one.
+    pub min_out_per_cycle: u64,
+    /// Seconds between slices.
+    pub interval: i64,
+    /// Earliest time the next slice may be sold.
+    pub next_at: i64,
+    /// Part of each slice paid to the keeper, in bps.
+    pub tip_bps: u16,
+    /// Input not sold yet, still in escrow.
+    pub remaining: u64,
+    pub bump: u8,
+}
+
+impl Dca {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.amount_per_cycle > 0
+                && self.amount_per_cycle <= self.remaining
+                && self.interval > 0
+                && self.tip_bps <= MAX_DCA_TIP_BPS,
+            AmmError::InvalidDcaSchedule
+        );
+        Ok(())
+    }
+
+    /// Input sold at the next execution.
+    pub fn slice(&self) -> u64 {
+        self.amount_per_cycle.min(self.remaining)
+    }
+
+    /// Keeper's cut of `slice`. Rounded down.
+    pub fn tip(&self, slice: u64) -> Result<u64> {
+        let tip = mul_div(
+            slice as u128,
+            self.tip_bps as u128,
+            FEE_DENOMINATOR as u128,
+            RoundDirection::Floor,
+        )?;
+        Ok(tip as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dca(remaining: u64) -> Dca {
+        Dca {
+            config: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            dca_id: 0,
+            is_x_in: true,
+            amount_per_cycle: 300,
+            min_out_per_cycle: 0,
+            interval: 60,
+            next_at: 0,
+            tip_bps: 50,
+            remaining,
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn last_slice_is_what_remains() {
+        assert_eq!(dca(1_000).slice(), 300);
+        assert_eq!(dca(100).slice(), 100);
+        assert_eq!(dca(1_000).tip(300).unwrap(), 1);
+    }
+
+    #[test]
+    fn schedule_is_validated() {
+        assert!(dca(1_000).validate().is_ok());
+        assert!(dca(299).validate().is_err());
+        let mut tipped = dca(1_000);
+        tipped.tip_bps = MAX_DCA_TIP_BPS + 1;
+        assert!(tipped.validate().is_err());
+    }
+}
@@ -0,0 +1,239 @@
+use anchor_lang::prelude::*;
+
+use crate::constant_product_curve::{
+    mul_div, ConstantProductCurve, RoundDirection, FEE_DENOMINATOR,
+};
+use crate::error::AmmError;
+
+/// `variable_fee_control * accumulator²` is divided by this to get bps.
+pub const VARIABLE_FEE_PRECISION: u128 = 100_000_000;
+
+/// Tuning of the volatility fee, after Trader Joe's Liquidity Book.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct DynamicFeeParams {
+    /// Swaps less than this many seconds apart measure from the same
+    /// reference, so a burst of trades accumulates volatility.
+    pub filter_period: u32,
+    /// After this many seconds without swaps volatility is forgotten.
+    pub decay_period: u32,
+    /// Share of the accumulator carried into the next window, in bps.
+    pub reduction_factor: u16,
+    /// Scales the squared accumulator into the variable fee.
+    pub variable_fee_control: u32,
+    /// Cap on the accumulator, in bps of price movement.
+    pub max_volatility_accumulator: u32,
+    /// Cap on base plus variable fee, in bps.
+    pub max_fee: u16,
+}
+
+impl DynamicFeeParams {
+    pub fn validate(&self, base_fee: u16) -> Result<()> {
+        require!(
+            self.filter_period <= self.decay_period
+                && self.reduction_factor as u64 <= FEE_DENOMINATOR,
+            AmmError::InvalidDynamicFee
+        );
+        require!(
+            self.max_fee as u64 <= FEE_DENOMINATOR && self.max_fee >= base_fee,
+            AmmError::InvalidFee
+        );
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct DynamicFee {
+    pub params: DynamicFeeParams,
+    /// Volatility in bps of price movement, as of the last swap.
+    pub volatility_accumulator: u32,
+    /// Volatility carried over from before the current window.
+    pub volatility_reference: u32,
+    /// Price (y per x, Q64.64) the current window measures movement from.
+    pub reference_price: u128,
+    pub last_update: i64,
+}
+
+/// Spot price of y per x in Q64.64, `None` while either side is empty.
+pub fn spot_price(reserve_x: u64, reserve_y: u64) -> Option<u128> {
+    if reserve_x == 0 || reserve_y == 0 {
+        return None;
+    }
+    mul_div(
+        reserve_y as u128,
+        1 << 64,
+        reserve_x as u128,
+        RoundDirection::Floor,
+    )
+    .ok()
+}
+
+/// How far `price` is from `reference`, in bps of `reference`. Saturates, as
+/// anything that large is capped by the accumulator anyway.
+pub fn price_move_bps(reference: u128, price: u128) -> u128 {
+    if reference == 0 {
+        return 0;
+    }
+    reference
+        .abs_diff(price)
+        .checked_mul(FEE_DENOMINATOR as u128)
+        .map_or(u128::MAX, |moved| moved / reference)
+}
+
+impl DynamicFee {
+    pub fn new(params: DynamicFeeParams) -> Self {
+        Self {
+            params,
+            volatility_accumulator: 0,
+            volatility_reference: 0,
+            reference_price: 0,
+            last_update: 0,
+        }
+    }
+
+    /// Opens a new window at `price` once the filter period has passed,
+    /// keeping part of the volatility unless the decay period has passed too.
+    fn update_references(&mut self, now: i64, price: u128) {
+        let elapsed = now.saturating_sub(self.last_update);
+        if elapsed < self.params.filter_period as i64 {
+            return;
+        }
+
+        self.reference_price = price;
+        self.volatility_reference = match elapsed < self.params.decay_period as i64 {
+            true => {
+                (self.volatility_accumulator as u64 * self.params.reduction_factor as u64
+                    / FEE_DENOMINATOR) as u32
+            }
+            false => 0,
+        };
+    }
+
+    /// Base fee plus `variable_fee_control * accumulator²`, capped at `max_fee`.
+    pub fn fee(&self, base_fee: u16) -> u16 {
+        let accumulator = self.volatility_accumulator as u128;
+        let variable = self.params.variable_fee_control as u128 * accumulator * accumulator
+            / VARIABLE_FEE_PRECISION;
+
+        (base_fee as u128 + variable).min(self.params.max_fee as u128) as u16
+    }
+
+    /// Fee for swapping `amount_in` at `now`. The accumulator takes in the
+    /// price move of this swap itself, estimated at the base fee, so a single
+    /// large trade pays for the volatility it causes.
+    pub fn swap_fee(
+        &mut self,
+        base_fee: u16,
+        reserve_x: u64,
+        reserve_y: u64,
+        amount_in: u64,
+        is_x_in: bool,
+        now: i64,
+    ) -> Result<u16> {
+        let Some(price) = spot_price(reserve_x, reserve_y) else {
+            return Ok(base_fee);
+        };
+        self.update_references(now, price);
+
+        let (x_after, y_after) = match is_x_in {
+            true => {
+                let out = ConstantProductCurve::swap(reserve_x, reserve_y, amount_in, base_fee)?;
+                (
+                    reserve_x.checked_add(amount_in).ok_or(AmmError::Overflow)?,
+                    reserve_y - out.amount_out,
+                )
+            }
+            false => {
+                let out = ConstantProductCurve::swap(reserve_y, reserve_x, amount_in, base_fee)?;
+                (
+                    reserve_x - out.amount_out,
+                    reserve_y.checked_add(amount_in).ok_or(AmmError::Overflow)?,
+                )
+            }
+        };
+        let moved = spot_price(x_after, y_after).map_or(u128::MAX, |after| {
+            price_move_bps(self.reference_price, after)
+        });
+
+        self.volatility_accumulator = (self.volatility_reference as u128)
+            .saturating_add(moved)
+            .min(self.params.max_volatility_accumulator as u128)
+            as u32;
+        self.last_update = now;
+
+        Ok(self.fee(base_fee))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params() -> DynamicFeeParams {
+        DynamicFeeParams {
+            filter_period: 30,
+            decay_period: 600,
+            reduction_factor: 5_000,
+            variable_fee_control: 10_000,
+            max_volatility_accumulator: 2_000,
+            max_fee: 500,
+        }
+    }
+
+    #[test]
+    fn quiet_pool_charges_base_fee() {
+        let mut fee = DynamicFee::new(params());
+        assert_eq!(
+            fee.swap_fee(30, 1_000_000_000, 1_000_000_000, 1, true, 100)
+                .unwrap(),
+            30
+        );
+    }
+
+    #[test]
+    fn volatility_raises_fee_and_decays() {
+        let mut fee = DynamicFee::new(params());
+        let calm = fee
+            .swap_fee(30, 1_000_000, 1_000_000, 10_000, true, 100)
+            .unwrap();
+        // a ~2% move within the same window
+        let busy = fee
+            .swap_fee(30, 1_010_000, 990_000, 10_000, true, 110)
+            .unwrap();
+        assert!(busy > calm);
+        let accumulated = fee.volatility_accumulator;
+
+        // a new window keeps half the volatility
+        fee.swap_fee(30, 1_020_000, 980_000, 1, true, 200).unwrap();
+        assert_eq!(fee.volatility_reference, accumulated / 2);
+
+        // and forgets it after the decay period
+        fee.swap_fee(30, 1_020_000, 980_000, 1, true, 10_000)
+            .unwrap();
+        assert_eq!(fee.volatility_reference, 0);
+        assert_eq!(fee.fee(30), 30);
+    }
+
+    #[test]
+    fn fee_is_capped() {
+        let mut fee = DynamicFee::new(DynamicFeeParams {
+            max_fee: 300,
+            ..params()
+        });
+        let charged = fee
+            .swap_fee(30, 1_000, 1_000, 1_000_000, true, 100)
+            .unwrap();
+        assert_eq!(fee.volatility_accumulator, 2_000);
+        assert_eq!(charged, 300);
+    }
+
+    #[test]
+    fn params_must_be_consistent() {
+        assert!(params().validate(30).is_ok());
+        assert!(params().validate(600).is_err());
+        let backwards = DynamicFeeParams {
+            filter_period: 700,
+            ..params()
+        };
+        assert!(backwards.validate(30).is_err());
+    }
+}
@@ -0,0 +1,236 @@
+use anchor_lang::prelude::*;
+
+use crate::constant_product_curve::{mul_div, RoundDirection};
+use crate::error::AmmError;
+
+pub const FARM_SEED: &[u8] = b"farm";
+pub const STAKE_SEED: &[u8] = b"stake";
+/// Reward tokens a farm can emit at once.
+pub const MAX_FARM_REWARDS: usize = 3;
+
+/// `reward_per_share` is scaled by this, i.e. Q64.64.
+const SHARE_SCALE: u128 = 1 << 64;
+
+/// One reward token emitted at `rate_per_second` between `start` and `end`.
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, InitSpace,
+)]
+pub struct FarmReward {
+    /// `Pubkey::default()` while the slot is unused.
+    pub mint: Pubkey,
+    pub rate_per_second: u64,
+    pub start: i64,
+    pub end: i64,
+    /// Rewards per staked LP since the farm opened, Q64.64. Wraps; only
+    /// differences are meaningful.
+    pub reward_per_share: u128,
+}
+
+impl FarmReward {
+    pub fn is_active(&self) -> bool {
+        self.mint != Pubkey::default()
+    }
+
+    /// Seconds of emission between `from` and `to`.
+    fn emitting(&self, from: i64, to: i64) -> u64 {
+        let from = from.max(self.start);
+        let to = to.min(self.end);
+        to.saturating_sub(from).max(0) as u64
+    }
+}
+
+/// Stakes the LP of one pool and emits up to `MAX_FARM_REWARDS` reward
+/// tokens pro rata to the stake. Staked LP and every reward are held by
+/// ATAs of the farm.
+#[account]
+#[derive(InitSpace)]
+pub struct Farm {
+    pub config: Pubkey,
+    pub total_staked: u64,
+    pub last_update: i64,
+    pub rewards: [FarmReward; MAX_FARM_REWARDS],
+    pub bump: u8,
+}
+
+impl Farm {
+    /// Accrues every reward up to `now`. Time with nothing staked emits
+    /// nothing, leaving those rewards in the vault. Leaves the farm as it was
+    /// on error.
+    pub fn update(&mut self, now: i64) -> Result<()> {
+        if now <= self.last_update {
+            return Ok(());
+        }
+        if self.total_staked > 0 {
+            let mut rewards = self.rewards;
+            for reward in rewards.iter_mut().filter(|reward| reward.is_active()) {
+                let emitted =
+                    reward.rate_per_second as u128 * reward.emitting(self.last_update, now) as u128;
+                let per_share = mul_div(
+                    emitted,
+                    SHARE_SCALE,
+                    self.total_staked as u128,
+                    RoundDirection::Floor,
+                )?;
+                reward.reward_per_share = reward.reward_per_share.wrapping_add(per_share);
+            }
+            self.rewards = rewards;
+        }
+        self.last_update = now;
+
+        Ok(())
+    }
+
+    /// Slot of the reward paid in `mint`.
+    pub fn reward_index(&self, mint: &Pubkey) -> Result<usize> {
+        self.rewards
+            .iter()
+            .position(|reward| reward.is_active() && reward.mint == *mint)
+            .ok_or(error!(AmmError::InvalidMint))
+    }
+}
+
+/// LP one owner has staked in a farm, and the rewards owed to it.
+#[account]
+#[derive(InitSpace)]
+pub struct StakePosition {
+    pub farm: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    /// Each reward's `reward_per_share` as of the last settlement.
+    pub reward_per_share_paid: [u128; MAX_FARM_REWARDS],
+    /// Rewards settled but not yet claimed.
+    pub pending: [u64; MAX_FARM_REWARDS],
+    pub bump: u8,
+}
+
+impl StakePosition {
+    /// Moves what the stake earned since the last settlement into
+    /// `pending`. The farm must be updated first. Leaves the position as it
+    /// was on error.
+    pub fn settle(&mut self, farm: &Farm) -> Result<()> {
+        let mut pending = self.pending;
+        for (index, reward) in farm.rewards.iter().enumerate() {
+            let growth = reward
+                .reward_per_share
+                .wrapping_sub(self.reward_per_share_paid[index]);
+            let earned = mul_div(
+                self.amount as u128,
+                growth,
+                SHARE_SCALE,
+                RoundDirection::Floor,
+            )?;
+            pending[index] = u64::try_from(earned)
+                .ok()
+                .and_then(|earned| pending[index].checked_add(earned))
+                .ok_or(AmmError::Overflow)?;
+        }
+        self.pending = pending;
+        self.reward_per_share_paid = farm.rewards.map(|reward| reward.reward_per_share);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn farm() -> Farm {
+        let mut rewards = [FarmReward::default(); MAX_FARM_REWARDS];
+        rewards[0] = FarmReward {
+            mint: Pubkey::new_unique(),
+            rate_per_second: 100,
+            start: 1_000,
+            end: 2_000,
+            reward_per_share: 0,
+        };
+        Farm {
+            config: Pubkey::new_unique(),
+            total_staked: 0,
+            last_update: 0,
+            rewards,
+            bump: 255,
+        }
+    }
+
+    fn position(amount: u64, farm: &Farm) -> StakePosition {
+        StakePosition {
+            farm: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            amount,
+            reward_per_share_paid: farm.rewards.map(|reward| reward.reward_per_share),
+            pending: [0; MAX_FARM_REWARDS],
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn rewards_split_pro_rata_within_schedule() {
+        let mut farm = farm();
+        farm.total_staked = 400;
+        let mut a = position(100, &farm);
+        let mut b = position(300, &farm);
+
+        // only 1_000..=2_000 emits
+        farm.update(5_000).unwrap();
+        a.settle(&farm).unwrap();
+        b.settle(&farm).unwrap();
+
+        assert_eq!(a.pending[0], 25_000);
+        assert_eq!(b.pending[0], 75_000);
+        assert_eq!(a.pending[1], 0);
+    }
+
+    #[test]
+    fn late_staker_earns_from_its_entry() {
+        let mut farm = farm();
+        farm.total_staked = 100;
+        let mut early = position(100, &farm);
+
+        farm.update(1_500).unwrap();
+        early.settle(&farm).unwrap();
+        let mut late = position(100, &farm);
+        farm.total_staked += 100;
+
+        farm.update(2_000).unwrap();
+        early.settle(&farm).unwrap();
+        late.settle(&farm).unwrap();
+
+        assert_eq!(early.pending[0], 50_000 + 25_000);
+        assert_eq!(late.pending[0], 25_000);
+    }
+
+    #[test]
+    fn accrual_wraps_around() {
+        let mut farm = farm();
+        farm.total_staked = 100;
+        farm.rewards[0].reward_per_share = u128::MAX - 5;
+        let mut position = position(100, &farm);
+
+        farm.update(2_000).unwrap();
+        position.settle(&farm).unwrap();
+
+        assert!(farm.rewards[0].reward_per_share < u128::MAX - 5);
+        assert_eq!(position.pending[0], 100_000);
+    }
+
+    #[test]
+    fn failed_settlement_changes_nothing() {
+        let mut farm = farm();
+        farm.total_staked = 100;
+        let mut position = position(100, &farm);
+        position.pending[0] = u64::MAX;
+
+        farm.update(2_000).unwrap();
+        let before = (position.pending, position.reward_per_share_paid);
+        assert!(position.settle(&farm).is_err());
+        assert_eq!((position.pending, position.reward_per_share_paid), before);
+    }
+
+    #[test]
+    fn nothing_accrues_without_stake() {
+        let mut farm = farm();
+        farm.update(1_500).unwrap();
+        assert_eq!(farm.rewards[0].reward_per_share, 0);
+        assert_eq!(farm.last_update, 1_500);
+    }
+}
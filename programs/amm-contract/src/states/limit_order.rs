@@ -0,0 +1,26 @@
+use anchor_lang::prelude::*;
+
+pub const LIMIT_ORDER_SEED: &[u8] = b"order";
+
+/// Resting order selling `amount_in` of one pool token for at least
+/// `min_out` of the other, or pro rata when partly filled. The input waits
+/// in the order's ATA until a crank fills it against the pool.
+#[account]
+#[derive(InitSpace)]
+pub struct LimitOrder {
+    pub config: Pubkey,
+    pub owner: Pubkey,
+    /// Chosen by the owner, so one owner can rest several orders.
+    pub order_id: u64,
+    /// Sells x for y when set, y for x otherwise.
+    pub is_x_in: bool,
+    pub amount_in: u64,
+    /// Limit price as `min_out / amount_in`.
+    pub min_out: u64,
+    /// Input not filled yet, still in escrow.
+    pub remaining: u64,
+    /// Fills wait for a later slot, so placing an order cannot stand in for
+    /// a swap at the maker fee.
+    pub placed_slot: u64,
+    pub bump: u8,
+}
@@ -0,0 +1,89 @@
+use anchor_lang::prelude::*;
+
+use crate::constant_product_curve::{mul_div, RoundDirection};
+
+pub const LP_LOCK_SEED: &[u8] = b"lock";
+
+/// LP of one pool held in escrow until `unlock_at`, then released linearly
+/// until `vesting_end`. The escrow is the lock's ATA of the pool's LP mint.
+#[account]
+#[derive(InitSpace)]
+pub struct LpLock {
+    pub config: Pubkey,
+    pub owner: Pubkey,
+    /// Chosen by the owner, so one owner can hold several locks.
+    pub lock_id: u64,
+    /// LP locked in total, unlocked or not.
+    pub amount: u64,
+    /// LP already returned to the owner.
+    pub withdrawn: u64,
+    pub unlock_at: i64,
+    /// Equal to `unlock_at` when everything unlocks at once.
+    pub vesting_end: i64,
+    /// `sqrt(x * y)` per LP (Q64.64) when the fees were last claimed.
+    pub sqrt_k_per_lp: u128,
+    pub bump: u8,
+}
+
+impl LpLock {
+    /// LP released by `now`, withdrawn or not.
+    pub fn vested(&self, now: i64) -> Result<u64> {
+        if now < self.unlock_at {
+            return Ok(0);
+        }
+        if now >= self.vesting_end {
+            return Ok(self.amount);
+        }
+
+        let vested = mul_div(
+            self.amount as u128,
+            (now - self.unlock_at) as u128,
+            (self.vesting_end - self.unlock_at) as u128,
+            RoundDirection::Floor,
+        )?;
+        Ok(vested as u64)
+    }
+
+    /// LP the owner may withdraw at `now`. Claiming fees shrinks `amount`,
+    /// so what has vested can briefly fall below what was withdrawn.
+    pub fn unlockable(&self, now: i64) -> Result<u64> {
+        Ok(self.vested(now)?.saturating_sub(self.withdrawn))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lock(unlock_at: i64, vesting_end: i64) -> LpLock {
+        LpLock {
+            config: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            lock_id: 0,
+            amount: 1_000,
+            withdrawn: 0,
+            unlock_at,
+            vesting_end,
+            sqrt_k_per_lp: 0,
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn cliff_releases_everything_at_once() {
+        let lock = lock(100, 100);
+        assert_eq!(lock.vested(99).unwrap(), 0);
+        assert_eq!(lock.vested(100).unwrap(), 1_000);
+    }
+
+    #[test]
+    fn vesting_releases_linearly_after_the_cliff() {
+        let mut lock = lock(100, 400);
+        assert_eq!(lock.vested(99).unwrap(), 0);
+        assert_eq!(lock.vested(100).unwrap(), 0);
+        assert_eq!(lock.vested(200).unwrap(), 333);
+        lock.withdrawn = 333;
+        assert_eq!(lock.unlockable(250).unwrap(), 167);
+        assert_eq!(lock.unlockable(1_000).unwrap(), 667);
+    }
+}
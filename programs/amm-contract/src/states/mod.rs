@@ -1,2 +1,24 @@
+pub mod batch;
+pub mod circuit_breaker;
 pub mod config;
-pub use config::*;
\ No newline at end of file
+pub mod dca;
+pub mod dynamic_fee;
+pub mod farm;
+pub mod limit_order;
+pub mod lp_lock;
+pub mod oracle_guard;
+pub mod pmm;
+pub mod trader_tier;
+pub mod twamm;
+pub use batch::*;
+pub use circuit_breaker::*;
+pub use config::*;
+pub use dca::*;
+pub use dynamic_fee::*;
+pub use farm::*;
+pub use limit_order::*;
+pub use lp_lock::*;
+pub use oracle_guard::*;
+pub use pmm::*;
+pub use trader_tier::*;
+pub use twamm::*;
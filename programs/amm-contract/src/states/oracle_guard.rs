@@ -0,0 +1,157 @@
+use anchor_lang::prelude::*;
+
+use crate::constant_product_curve::{mul_div, RoundDirection};
+use crate::error::AmmError;
+use crate::oracle::{OracleKind, OraclePrice};
+use crate::states::price_move_bps;
+
+/// Keeps swaps near an external price feed.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct OracleGuard {
+    pub kind: OracleKind,
+    /// Price account swaps must pass.
+    pub feed: Pubkey,
+    /// The feed quotes y in x rather than x in y.
+    pub invert: bool,
+    /// Most a swap's price, fee included, may stray from the feed's, in bps.
+    pub max_deviation_bps: u16,
+    /// Oldest the feed's price may be, in seconds.
+    pub max_staleness: u32,
+}
+
+impl OracleGuard {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.max_deviation_bps > 0 && self.max_staleness > 0,
+            AmmError::InvalidOracleGuard
+        );
+        Ok(())
+    }
+
+    /// Fails if `price` is stale at `now`.
+    pub fn check_fresh(&self, price: &OraclePrice, now: i64) -> Result<()> {
+        require!(
+            now.saturating_sub(price.publish_time) <= self.max_staleness as i64,
+            AmmError::StaleOracle
+        );
+        Ok(())
+    }
+
+    /// Fails a trade paying `amount_in` for `amount_out` between mints of
+    /// `decimals_x` and `decimals_y` if `price` is stale at `now` or the
+    /// trade's price strays too far from it.
+    ///
+    /// With `reserves`, the pool's `(x, y)` before and after on pools whose
+    /// spot price is their ratio, a trade that strays still passes when it
+    /// brings the spot price closer to the feed's. Once the feed moves past
+    /// the deviation, arbitrage can then catch the pool up with it.
+    pub fn check(
+        &self,
+        price: &OraclePrice,
+        now: i64,
+        (decimals_x, decimals_y): (u8, u8),
+        (amount_in, amount_out): (u64, u64),
+        is_x_in: bool,
+        reserves: Option<((u64, u64), (u64, u64))>,
+    ) -> Result<()> {
+        self.check_fresh(price, now)?;
+
+        let (y, x) = match is_x_in {
+            true => (amount_out, amount_in),
+            false => (amount_in, amount_out),
+        };
+        require!(x > 0, AmmError::OraclePriceDeviation);
+        let execution = mul_div(y as u128, 1 << 64, x as u128, RoundDirection::Floor)?;
+        let reference = price.to_q64(decimals_x, decimals_y, self.invert)?;
+        if price_move_bps(reference, execution) <= self.max_deviation_bps as u128 {
+            return Ok(());
+        }
+
+        let (before, after) = reserves.ok_or(AmmError::OraclePriceDeviation)?;
+        require!(
+            price_move_bps(reference, spot(after)?) < price_move_bps(reference, spot(before)?),
+            AmmError::OraclePriceDeviation
+        );
+        Ok(())
+    }
+}
+
+/// Price of x in y, as Q64.64, of a pool holding `(x, y)`.
+fn spot((x, y): (u64, u64)) -> Result<u128> {
+    require!(x > 0, AmmError::OraclePriceDeviation);
+    mul_div(y as u128, 1 << 64, x as u128, RoundDirection::Floor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guard() -> OracleGuard {
+        OracleGuard {
+            kind: OracleKind::Pyth,
+            feed: Pubkey::new_unique(),
+            invert: false,
+            max_deviation_bps: 100,
+            max_staleness: 60,
+        }
+    }
+
+    /// 2 y per x, published at 1_000.
+    const PRICE: OraclePrice = OraclePrice {
+        mantissa: 200,
+        exponent: -2,
+        publish_time: 1_000,
+    };
+
+    #[test]
+    fn trades_near_the_feed_pass() {
+        let guard = guard();
+        guard
+            .check(&PRICE, 1_000, (6, 6), (1_000, 1_990), true, None)
+            .unwrap();
+        guard
+            .check(&PRICE, 1_060, (6, 6), (2_000, 995), false, None)
+            .unwrap();
+        // selling x 2% under the feed
+        let result = guard.check(&PRICE, 1_000, (6, 6), (1_000, 1_960), true, None);
+        assert_eq!(result, Err(AmmError::OraclePriceDeviation.into()));
+    }
+
+    #[test]
+    fn trades_toward_the_feed_pass() {
+        let guard = guard();
+        // the pool is at 1.9 y per x, 5% under the feed
+        let before = (100_000, 190_000);
+        // buying x at about 1.91 brings it closer
+        let toward = Some((before, (99_000, 191_900)));
+        guard
+            .check(&PRICE, 1_000, (6, 6), (1_900, 1_000), false, toward)
+            .unwrap();
+        // selling x at about 1.88 takes it further away
+        let away = Some((before, (101_000, 188_100)));
+        let result = guard.check(&PRICE, 1_000, (6, 6), (1_000, 1_900), true, away);
+        assert_eq!(result, Err(AmmError::OraclePriceDeviation.into()));
+    }
+
+    #[test]
+    fn stale_prices_are_refused() {
+        let result = guard().check(&PRICE, 1_061, (6, 6), (1_000, 2_000), true, None);
+        assert_eq!(result, Err(AmmError::StaleOracle.into()));
+    }
+
+    #[test]
+    fn params_must_be_positive() {
+        for guard in [
+            OracleGuard {
+                max_deviation_bps: 0,
+                ..guard()
+            },
+            OracleGuard {
+                max_staleness: 0,
+                ..guard()
+            },
+        ] {
+            assert!(guard.validate().is_err());
+        }
+    }
+}
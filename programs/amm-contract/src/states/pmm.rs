@@ -0,0 +1,196 @@
+use anchor_lang::prelude::*;
+
+use crate::constant_product_curve::{
+    mul_div, pmm_integrate, pmm_sell_into_short, pmm_target_gap, ConstantProductCurve,
+    RoundDirection, SwapAmounts, FEE_DENOMINATOR,
+};
+use crate::error::AmmError;
+
+/// Proactive market maker state: the pool quotes around its oracle's price,
+/// slipping by slope `k` as either side falls short of its target.
+///
+/// The side in excess keeps its target; the short side's target is derived
+/// on every swap so that buying it back up costs exactly the excess, at the
+/// current oracle price. Both targets are zero until the first swap, which
+/// takes the reserves it finds as balanced.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct Pmm {
+    /// Slope in bps: 10_000 is the constant product at the targets, lower
+    /// values keep the price flatter around the oracle's.
+    pub k: u16,
+    pub target_x: u64,
+    pub target_y: u64,
+}
+
+/// `amount` of x in y at `price` (y per x, Q64.64), or of y in x.
+fn convert(amount: u128, price: u128, x_to_y: bool) -> Result<u128> {
+    match x_to_y {
+        true => mul_div(amount, price, 1 << 64, RoundDirection::Floor),
+        false => mul_div(amount, 1 << 64, price, RoundDirection::Floor),
+    }
+}
+
+fn to_u64(amount: u128) -> Result<u64> {
+    Ok(u64::try_from(amount).map_err(|_| AmmError::Overflow)?)
+}
+
+impl Pmm {
+    pub fn new(k: u16) -> Result<Self> {
+        require!(
+            k > 0 && k as u64 <= FEE_DENOMINATOR,
+            AmmError::InvalidPmmSlope
+        );
+        Ok(Self {
+            k,
+            target_x: 0,
+            target_y: 0,
+        })
+    }
+
+    /// Targets for a pool holding `x` / `y` at `price` (y per x, Q64.64).
+    pub fn targets(&self, x: u64, y: u64, price: u128) -> Result<(u64, u64)> {
+        let (target_x, target_y) = (self.target_x, self.target_y);
+        if target_x == 0 && target_y == 0 {
+            return Ok((x, y));
+        }
+
+        match x.cmp(&target_x) {
+            std::cmp::Ordering::Greater => {
+                let fair = convert((x - target_x) as u128, price, true)?;
+                let gap = pmm_target_gap(y, fair, self.k)?;
+                Ok((target_x, to_u64(y as u128 + gap)?))
+            }
+            std::cmp::Ordering::Less => {
+                let fair = convert(y.saturating_sub(target_y) as u128, price, false)?;
+                let gap = pmm_target_gap(x, fair, self.k)?;
+                Ok((to_u64(x as u128 + gap)?, target_y))
+            }
+            std::cmp::Ordering::Equal => Ok((target_x, target_y.max(y))),
+        }
+    }
+
+    /// Output for swapping `amount_in` into a pool holding `x` / `y` at
+    /// `price` (y per x, Q64.64), saving the targets it trades against. The
+    /// fee is taken from the input as on the constant product curve.
+    pub fn swap(
+        &mut self,
+        x: u64,
+        y: u64,
+        amount_in: u64,
+        fee_bps: u16,
+        is_x_in: bool,
+        price: u128,
+    ) -> Result<SwapAmounts> {
+        require!(x > 0 && y > 0 && price > 0, AmmError::InsufficientLiquidity);
+        let fee = ConstantProductCurve::fee(amount_in, fee_bps)?;
+        let amount_in_after_fee = amount_in - fee;
+
+        let (target_x, target_y) = self.targets(x, y, price)?;
+        let (in_bal, out_bal, in_target, out_target) = match is_x_in {
+            true => (x, y, target_x, target_y),
+            false => (y, x, target_y, target_x),
+        };
+        let worth = |amount: u128| convert(amount, price, is_x_in);
+
+        let amount_out = match in_target.checked_sub(in_bal) {
+            // the output side is short, or about to be
+            None | Some(0) => pmm_sell_into_short(
+                out_target,
+                out_bal,
+                worth(amount_in_after_fee as u128)?,
+                self.k,
+            )?,
+            // the input side is short: back up towards its target first
+            Some(back) if amount_in_after_fee <= back => {
+                let moved = in_bal + amount_in_after_fee;
+                to_u64(worth(pmm_integrate(in_target, moved, in_bal, self.k)?)?)?
+            }
+            Some(back) => {
+                let excess = out_bal.saturating_sub(out_target);
+                let rest = worth((amount_in_after_fee - back) as u128)?;
+                excess + pmm_sell_into_short(out_target, out_target, rest, self.k)?
+            }
+        };
+        require!(amount_out < out_bal, AmmError::InsufficientLiquidity);
+
+        self.target_x = target_x;
+        self.target_y = target_y;
+        Ok(SwapAmounts { fee, amount_out })
+    }
+
+    /// Scales the targets with the LP supply, as deposits and withdrawals
+    /// scale the reserves.
+    pub fn scale(&mut self, supply: u64, new_supply: u64) -> Result<()> {
+        if supply == 0 {
+            return Ok(());
+        }
+        for target in [&mut self.target_x, &mut self.target_y] {
+            *target = to_u64(mul_div(
+                *target as u128,
+                new_supply as u128,
+                supply as u128,
+                RoundDirection::Floor,
+            )?)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ONE: u128 = 1 << 64;
+
+    #[test]
+    fn quotes_follow_the_oracle() {
+        let mut pmm = Pmm::new(1_000).unwrap();
+        let at_one = pmm
+            .swap(1_000_000, 1_000_000, 10_000, 0, true, ONE)
+            .unwrap();
+        assert!(at_one.amount_out >= 9_980);
+
+        // the same pool with x now worth 2 y
+        let mut pmm = Pmm::new(1_000).unwrap();
+        let at_two = pmm
+            .swap(1_000_000, 1_000_000, 10_000, 0, true, 2 * ONE)
+            .unwrap();
+        assert!(at_two.amount_out >= 19_950);
+        let at_two = pmm
+            .swap(1_000_000, 1_000_000, 20_000, 0, false, 2 * ONE)
+            .unwrap();
+        assert!(at_two.amount_out <= 10_000);
+    }
+
+    #[test]
+    fn round_trip_does_not_profit() {
+        let mut pmm = Pmm::new(2_500).unwrap();
+        let (x, y) = (1_000_000, 1_000_000);
+        let sold = pmm.swap(x, y, 200_000, 0, true, ONE).unwrap().amount_out;
+        let (x, y) = (x + 200_000, y - sold);
+        assert_eq!((pmm.target_x, pmm.target_y), (1_000_000, 1_000_000));
+
+        // y is short now, so its target is derived from x's excess
+        let back = pmm.swap(x, y, sold, 0, false, ONE).unwrap().amount_out;
+        assert!(back <= 200_000);
+        assert!(200_000 - back <= 2);
+    }
+
+    #[test]
+    fn slope_must_be_in_range() {
+        assert!(Pmm::new(0).is_err());
+        assert!(Pmm::new(10_001).is_err());
+        Pmm::new(10_000).unwrap();
+    }
+
+    #[test]
+    fn targets_scale_with_supply() {
+        let mut pmm = Pmm {
+            k: 1_000,
+            target_x: 1_000,
+            target_y: 3_000,
+        };
+        pmm.scale(100, 150).unwrap();
+        assert_eq!((pmm.target_x, pmm.target_y), (1_500, 4_500));
+    }
+}
@@ -0,0 +1,22 @@
+use anchor_lang::prelude::*;
+
+pub const TRADER_TIER_SEED: &[u8] = b"tier";
+
+/// Fee ceiling for one trader on one pool, granted by the pool authority,
+/// e.g. to market-making and rebalancing wallets.
+#[account]
+#[derive(InitSpace)]
+pub struct TraderTier {
+    pub config: Pubkey,
+    pub trader: Pubkey,
+    /// Most the trader pays per swap, in bps.
+    pub fee_bps: u16,
+    pub bump: u8,
+}
+
+impl TraderTier {
+    /// The pool's fee, reduced to the tier's when that is lower.
+    pub fn apply(&self, fee_bps: u16) -> u16 {
+        fee_bps.min(self.fee_bps)
+    }
+}
@@ -0,0 +1,502 @@
+use anchor_lang::prelude::*;
+
+use crate::constant_product_curve::{
+    div, mul_div, mul_shr64, twamm_reserves, ConstantProductCurve, RoundDirection,
+};
+use crate::error::AmmError;
+
+pub const TWAMM_ORDER_SEED: &[u8] = b"twamm";
+
+pub const TWAMM_ARCHIVE_SEED: &[u8] = b"twamm_archive";
+
+/// Long-term orders end on a multiple of this many seconds, so orders
+/// ending close together share an expiry slot.
+pub const TWAMM_INTERVAL: i64 = 3600;
+
+/// Distinct end times the pool's long-term orders may have at once.
+pub const MAX_TWAMM_EXPIRIES: usize = 8;
+
+/// Sale rates are tokens per second in Q32.32.
+pub const SALE_RATE_ONE: u128 = 1 << 32;
+
+/// Long-term orders selling one token of the pool.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, InitSpace)]
+pub struct TwammSide {
+    /// Sum of the active orders' sale rates.
+    pub sale_rate: u128,
+    /// Proceeds per unit of sale rate ever paid to this side, in Q64.64.
+    /// Wraps; only differences are meaningful.
+    pub earnings_factor: u128,
+}
+
+impl TwammSide {
+    fn credit(&mut self, proceeds: u64) -> Result<()> {
+        if self.sale_rate > 0 {
+            let per_rate = mul_div(
+                proceeds as u128,
+                1 << 64,
+                self.sale_rate,
+                RoundDirection::Floor,
+            )?;
+            self.earnings_factor = self.earnings_factor.wrapping_add(per_rate);
+        }
+        Ok(())
+    }
+
+    /// Input sold over `seconds`. Rounded down, as the orders' own
+    /// accounting rounds it up.
+    fn sold(&self, seconds: i64) -> Result<u64> {
+        let sold = self
+            .sale_rate
+            .checked_mul(seconds as u128)
+            .ok_or(AmmError::Overflow)?
+            / SALE_RATE_ONE;
+        Ok(u64::try_from(sold).map_err(|_| AmmError::Overflow)?)
+    }
+}
+
+/// Orders ending at `at`, and what both sides had earned by then.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, InitSpace)]
+pub struct TwammExpiry {
+    pub at: i64,
+    pub sale_rate_x: u128,
+    pub sale_rate_y: u128,
+    pub earnings_factor_x: u128,
+    pub earnings_factor_y: u128,
+    /// Orders still open; the slot is free again at zero.
+    pub orders: u32,
+}
+
+/// Expiry moved out of the pool once passed, so the slot can take new end
+/// times while its orders still claim what they earned. Closed with the
+/// last of them, whose owner gets the rent.
+#[account]
+#[derive(InitSpace)]
+pub struct TwammArchive {
+    pub config: Pubkey,
+    pub expiry: TwammExpiry,
+    pub bump: u8,
+}
+
+/// Virtual order pools selling x and y into the pool over time. Their
+/// unsold input and unclaimed proceeds stay in the vaults, set aside in
+/// `reserved_x` / `reserved_y`, and are executed up to the present on
+/// every interaction with the pool.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, InitSpace)]
+pub struct Twamm {
+    /// Orders have been executed up to here.
+    pub last_execution: i64,
+    pub sell_x: TwammSide,
+    pub sell_y: TwammSide,
+    pub reserved_x: u64,
+    pub reserved_y: u64,
+    pub expiries: [TwammExpiry; MAX_TWAMM_EXPIRIES],
+}
+
+impl Twamm {
+    pub fn new(now: i64) -> Self {
+        Self {
+            last_execution: now,
+            ..Default::default()
+        }
+    }
+
+    /// Vault balances less what belongs to long-term orders.
+    pub fn reserves(&self, vault_x: u64, vault_y: u64) -> Result<(u64, u64)> {
+        Ok((
+            vault_x
+                .checked_sub(self.reserved_x)
+                .ok_or(AmmError::Overflow)?,
+            vault_y
+                .checked_sub(self.reserved_y)
+                .ok_or(AmmError::Overflow)?,
+        ))
+    }
+
+    /// Sets `amount` of x (or y) in the vault aside for long-term orders.
+    pub fn reserve(&mut self, is_x: bool, amount: u64) -> Result<()> {
+        let reserved = match is_x {
+            true => &mut self.reserved_x,
+            false => &mut self.reserved_y,
+        };
+        *reserved = reserved.checked_add(amount).ok_or(AmmError::Overflow)?;
+        Ok(())
+    }
+
+    /// Releases `amount` of x (or y) set aside, as it is paid out.
+    pub fn release(&mut self, is_x: bool, amount: u64) -> Result<()> {
+        let reserved = match is_x {
+            true => &mut self.reserved_x,
+            false => &mut self.reserved_y,
+        };
+        *reserved = reserved.checked_sub(amount).ok_or(AmmError::Overflow)?;
+        Ok(())
+    }
+
+    /// Executes every order up to `now`, one period per expiry crossed.
+    /// Stalls, to resume later, while the pool has no liquidity.
+    pub fn execute(
+        &mut self,
+        vault_x: u64,
+        vault_y: u64,
+        fees: (u16, u16),
+        now: i64,
+    ) -> Result<()> {
+        loop {
+            let next = self
+                .expiries
+                .iter()
+                .enumerate()
+                .filter(|(_, e)| e.orders > 0 && e.at > self.last_execution && e.at <= now)
+                .min_by_key(|(_, e)| e.at)
+                .map(|(i, e)| (i, e.at));
+            let until = next.map_or(now, |(_, at)| at);
+            if !self.execute_until(vault_x, vault_y, fees, until)? {
+                return Ok(());
+            }
+
+            let Some((i, _)) = next else {
+                return Ok(());
+            };
+            let expiry = &mut self.expiries[i];
+            expiry.earnings_factor_x = self.sell_x.earnings_factor;
+            expiry.earnings_factor_y = self.sell_y.earnings_factor;
+            self.sell_x.sale_rate -= expiry.sale_rate_x;
+            self.sell_y.sale_rate -= expiry.sale_rate_y;
+        }
+    }
+
+    /// Sells both sides' flow from `last_execution` to `until` at constant
+    /// rates. Returns false, leaving everything as is, when it cannot.
+    fn execute_until(
+        &mut self,
+        vault_x: u64,
+        vault_y: u64,
+        (fee_x, fee_y): (u16, u16),
+        until: i64,
+    ) -> Result<bool> {
+        let seconds = until - self.last_execution;
+        if seconds <= 0 {
+            return Ok(true);
+        }
+        let x_in = self.sell_x.sold(seconds)?;
+        let y_in = self.sell_y.sold(seconds)?;
+        let (x, y) = self.reserves(vault_x, vault_y)?;
+        if (x_in > 0 || y_in > 0) && (x == 0 || y == 0) {
+            return Ok(false);
+        }
+
+        // the fees stay in the pool, on top of the curve's end point
+        let x_fee = ConstantProductCurve::fee(x_in, fee_x)?;
+        let y_fee = ConstantProductCurve::fee(y_in, fee_y)?;
+        let end = twamm_reserves(x, y, x_in - x_fee, y_in - y_fee)?;
+        let x_out = (x as u128 + (x_in - x_fee) as u128)
+            .checked_sub(end.x as u128)
+            .and_then(|out| u64::try_from(out).ok())
+            .ok_or(AmmError::Overflow)?;
+        let y_out = (y as u128 + (y_in - y_fee) as u128)
+            .checked_sub(end.y as u128)
+            .and_then(|out| u64::try_from(out).ok())
+            .ok_or(AmmError::Overflow)?;
+
+        self.release(true, x_in)?;
+        self.release(false, y_in)?;
+        self.reserve(true, x_out)?;
+        self.reserve(false, y_out)?;
+        self.sell_x.credit(y_out)?;
+        self.sell_y.credit(x_out)?;
+        self.last_execution = until;
+        Ok(true)
+    }
+
+    /// Adds a sale rate ending at `end`, in the slot of orders ending then or
+    /// a free one.
+    pub fn add_order(&mut self, is_x_in: bool, sale_rate: u128, end: i64) -> Result<()> {
+        let slot = match self
+            .expiries
+            .iter()
+            .position(|e| e.orders > 0 && e.at == end)
+        {
+            Some(i) => i,
+            None => {
+                let i = self
+                    .expiries
+                    .iter()
+                    .position(|e| e.orders == 0)
+                    .ok_or(AmmError::TwammExpiriesFull)?;
+                self.expiries[i] = TwammExpiry {
+                    at: end,
+                    ..Default::default()
+                };
+                i
+            }
+        };
+
+        let expiry = &mut self.expiries[slot];
+        expiry.orders += 1;
+        match is_x_in {
+            true => {
+                expiry.sale_rate_x += sale_rate;
+                self.sell_x.sale_rate += sale_rate;
+            }
+            false => {
+                expiry.sale_rate_y += sale_rate;
+                self.sell_y.sale_rate += sale_rate;
+            }
+        }
+        Ok(())
+    }
+
+    /// Frees the slot of orders that ended at `at`, returning it to be
+    /// archived. Running slots drive execution, so only passed ones go.
+    pub fn archive_expiry(&mut self, at: i64) -> Result<TwammExpiry> {
+        require!(at <= self.last_execution, AmmError::TwammExpiryRunning);
+        Ok(std::mem::take(self.expiry_mut(at)?))
+    }
+
+    /// Takes an order out of its slot, or `archived` once the slot was
+    /// archived, and out of the flow if it is still running.
+    pub fn remove_order(
+        &mut self,
+        order: &TwammOrder,
+        archived: Option<&mut TwammExpiry>,
+    ) -> Result<()> {
+        let running = order.end > self.last_execution;
+        let side = match order.is_x_in {
+            true => &mut self.sell_x,
+            false => &mut self.sell_y,
+        };
+        if running {
+            side.sale_rate -= order.sale_rate;
+        }
+
+        let expiry = match archived {
+            Some(expiry) => expiry,
+            None => self.expiry_mut(order.end)?,
+        };
+        expiry.orders -= 1;
+        if running {
+            match order.is_x_in {
+                true => expiry.sale_rate_x -= order.sale_rate,
+                false => expiry.sale_rate_y -= order.sale_rate,
+            }
+        }
+        Ok(())
+    }
+
+    fn expiry_mut(&mut self, end: i64) -> Result<&mut TwammExpiry> {
+        self.expiries
+            .iter_mut()
+            .find(|e| e.orders > 0 && e.at == end)
+            .ok_or(AmmError::InvalidTwammOrder.into())
+    }
+
+    /// Earnings factor of `order`'s side, frozen at its end once passed, in
+    /// its slot or `archived`.
+    pub fn earnings_factor(
+        &self,
+        order: &TwammOrder,
+        archived: Option<&TwammExpiry>,
+    ) -> Result<u128> {
+        if order.end > self.last_execution {
+            return Ok(match order.is_x_in {
+                true => self.sell_x.earnings_factor,
+                false => self.sell_y.earnings_factor,
+            });
+        }
+        let expiry = archived
+            .or_else(|| {
+                self.expiries
+                    .iter()
+                    .find(|e| e.orders > 0 && e.at == order.end)
+            })
+            .ok_or(AmmError::InvalidTwammOrder)?;
+        Ok(match order.is_x_in {
+            true => expiry.earnings_factor_x,
+            false => expiry.earnings_factor_y,
+        })
+    }
+}
+
+/// Long-term order selling `amount` evenly from `start` to `end`.
+#[account]
+#[derive(InitSpace)]
+pub struct TwammOrder {
+    pub config: Pubkey,
+    pub owner: Pubkey,
+    /// Chosen by the owner, so one owner can run several orders.
+    pub order_id: u64,
+    /// Sells x for y when set, y for x otherwise.
+    pub is_x_in: bool,
+    pub amount: u64,
+    pub sale_rate: u128,
+    pub start: i64,
+    pub end: i64,
+    /// Side's earnings factor as of the last withdrawal.
+    pub earnings_factor: u128,
+    pub bump: u8,
+}
+
+impl TwammOrder {
+    /// Sale rate selling `amount` over `seconds`. Rounded down.
+    pub fn sale_rate(amount: u64, seconds: i64) -> Result<u128> {
+        div(
+            amount as u128 * SALE_RATE_ONE,
+            seconds as u128,
+            RoundDirection::Floor,
+        )
+    }
+
+    /// Proceeds earned since the last withdrawal, given the side's earnings
+    /// factor now. Rounded down.
+    pub fn proceeds(&self, earnings_factor: u128) -> Result<u64> {
+        let proceeds = mul_shr64(
+            self.sale_rate,
+            earnings_factor.wrapping_sub(self.earnings_factor),
+        )
+        .ok_or(AmmError::Overflow)?;
+        Ok(u64::try_from(proceeds).map_err(|_| AmmError::Overflow)?)
+    }
+
+    /// Input not sold by `last_execution`. Sales are rounded up, so this
+    /// never counts on more than the pool left unsold.
+    pub fn unsold(&self, last_execution: i64) -> Result<u64> {
+        let seconds = last_execution.min(self.end) - self.start;
+        let sold = div(
+            self.sale_rate * seconds as u128,
+            SALE_RATE_ONE,
+            RoundDirection::Ceiling,
+        )?;
+        Ok(self.amount - sold as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(is_x_in: bool, amount: u64, start: i64, end: i64) -> TwammOrder {
+        TwammOrder {
+            config: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            order_id: 0,
+            is_x_in,
+            amount,
+            sale_rate: TwammOrder::sale_rate(amount, end - start).unwrap(),
+            start,
+            end,
+            earnings_factor: 0,
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn one_sided_order_sells_like_swaps() {
+        let mut twamm = Twamm::new(0);
+        let sell = order(true, 1_000_000, 0, 100);
+        twamm.add_order(true, sell.sale_rate, 100).unwrap();
+        twamm.reserved_x = 1_000_000;
+
+        // half way, half is sold
+        let (vault_x, vault_y) = (11_000_000, 10_000_000);
+        twamm.execute(vault_x, vault_y, (0, 0), 50).unwrap();
+        assert_eq!(twamm.reserved_x, 500_000);
+        let paid = twamm.reserved_y;
+        assert_eq!(
+            paid,
+            10_000_000 - (100_000_000_000_000u64).div_ceil(10_500_000)
+        );
+        assert_eq!(sell.unsold(50).unwrap(), 500_000);
+        assert_eq!(
+            sell.proceeds(twamm.earnings_factor(&sell, None).unwrap())
+                .unwrap(),
+            paid - 1
+        );
+    }
+
+    #[test]
+    fn expiry_freezes_earnings_and_stops_the_flow() {
+        let mut twamm = Twamm::new(0);
+        let sell = order(true, 1_000, 0, 100);
+        twamm.add_order(true, sell.sale_rate, 100).unwrap();
+        twamm.reserved_x = 1_000;
+
+        twamm
+            .execute(1_000_000 + 1_000, 1_000_000, (30, 30), 500)
+            .unwrap();
+        assert_eq!(twamm.last_execution, 500);
+        assert_eq!(twamm.sell_x.sale_rate, 0);
+        assert_eq!(twamm.reserved_x, 0);
+        assert_eq!(sell.unsold(500).unwrap(), 0);
+        let earned = sell
+            .proceeds(twamm.earnings_factor(&sell, None).unwrap())
+            .unwrap();
+        assert!(earned > 0 && earned <= twamm.reserved_y);
+    }
+
+    #[test]
+    fn execution_waits_for_liquidity() {
+        let mut twamm = Twamm::new(0);
+        twamm.add_order(false, SALE_RATE_ONE, 100).unwrap();
+        twamm.reserved_y = 100;
+        twamm.execute(0, 100, (0, 0), 10).unwrap();
+        assert_eq!(twamm.last_execution, 0);
+    }
+
+    #[test]
+    fn vaults_short_of_the_reserved_balance_are_refused() {
+        let mut twamm = Twamm::new(0);
+        twamm.reserve(true, 100).unwrap();
+        assert_eq!(twamm.reserves(99, 0), Err(AmmError::Overflow.into()));
+        assert_eq!(twamm.release(true, 101), Err(AmmError::Overflow.into()));
+        assert_eq!(
+            twamm.reserve(true, u64::MAX),
+            Err(AmmError::Overflow.into())
+        );
+        assert_eq!(twamm.reserves(100, 0), Ok((0, 0)));
+    }
+
+    #[test]
+    fn slots_are_shared_and_limited() {
+        let mut twamm = Twamm::new(0);
+        for i in 0..MAX_TWAMM_EXPIRIES as i64 {
+            twamm.add_order(true, 1, (i + 1) * TWAMM_INTERVAL).unwrap();
+        }
+        twamm.add_order(false, 1, TWAMM_INTERVAL).unwrap();
+        assert_eq!(twamm.expiries[0].orders, 2);
+        assert!(twamm.add_order(true, 1, 100 * TWAMM_INTERVAL).is_err());
+    }
+
+    #[test]
+    fn archived_slots_take_new_orders_and_keep_their_earnings() {
+        let mut twamm = Twamm::new(0);
+        let sell = order(true, 1_000, 0, TWAMM_INTERVAL);
+        twamm
+            .add_order(true, sell.sale_rate, TWAMM_INTERVAL)
+            .unwrap();
+        for i in 1..MAX_TWAMM_EXPIRIES as i64 {
+            twamm
+                .add_order(false, 1, (i + 10) * TWAMM_INTERVAL)
+                .unwrap();
+        }
+        twamm.reserved_x = 1_000;
+        assert!(twamm.archive_expiry(TWAMM_INTERVAL).is_err());
+
+        twamm
+            .execute(1_000_000 + 1_000, 1_000_000, (30, 30), 2 * TWAMM_INTERVAL)
+            .unwrap();
+        let earned = twamm.earnings_factor(&sell, None).unwrap();
+        assert!(twamm.add_order(true, 1, 20 * TWAMM_INTERVAL).is_err());
+        let mut archived = twamm.archive_expiry(TWAMM_INTERVAL).unwrap();
+        assert_eq!(archived.orders, 1);
+        twamm.add_order(true, 1, 20 * TWAMM_INTERVAL).unwrap();
+
+        assert!(twamm.earnings_factor(&sell, None).is_err());
+        assert_eq!(
+            twamm.earnings_factor(&sell, Some(&archived)).unwrap(),
+            earned
+        );
+        twamm.remove_order(&sell, Some(&mut archived)).unwrap();
+        assert_eq!(archived.orders, 0);
+    }
+}